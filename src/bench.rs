@@ -0,0 +1,93 @@
+// ==================== 依赖库导入 ====================
+// 导入区块、区块链、链规范和链元数据
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::chainspec::ChainSpec;
+use crate::storage::ChainMetadata;
+// std::time: 计时
+use std::time::{Duration, Instant};
+
+/// 基准测试生成区块时使用的难度，足够低以便快速生成大量区块
+pub const BENCH_DIFFICULTY: u32 = 1;
+
+/// # 验证基准测试结果 (ValidationBench)
+#[derive(Debug, Clone)]
+pub struct ValidationBench {
+    /// 参与验证的区块数
+    pub blocks: usize,
+    /// 并行验证使用的线程数
+    pub threads: usize,
+    /// 单线程验证耗时
+    pub sequential: Duration,
+    /// 多线程验证耗时
+    pub parallel: Duration,
+}
+
+impl ValidationBench {
+    /// 多线程相对单线程的加速比
+    pub fn speedup(&self) -> f64 {
+        self.sequential.as_secs_f64() / self.parallel.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// # 生成指定长度的合成区块链
+///
+/// 使用 `BENCH_DIFFICULTY` 静默挖出 `blocks` 个区块（不含创世区块），
+/// 生成的区块链可以通过完整的验证。
+pub fn synthetic_chain(blocks: usize) -> Blockchain {
+    let mut chain = Vec::with_capacity(blocks + 1);
+    chain.push(ChainSpec::default().genesis_block());
+
+    for i in 1..=blocks as u64 {
+        let previous_hash = chain.last().map(|b: &Block| b.hash.clone()).unwrap_or_default();
+        let mut block = Block::new(i, format!("基准测试区块 #{}", i), previous_hash, BENCH_DIFFICULTY);
+        block.mine_block_silent();
+        chain.push(block);
+    }
+
+    Blockchain::from_parts(
+        chain,
+        ChainMetadata {
+            difficulty: BENCH_DIFFICULTY,
+            mining_reward: ChainSpec::default().reward_at(blocks as u64 + 1),
+            pending_transactions: Vec::new(),
+        },
+    )
+}
+
+/// # 对比单线程与多线程验证
+///
+/// 分别用1个线程和 `threads` 个线程验证同一条链并计时。
+/// 两次验证都必须通过，否则说明并行验证的结果与顺序验证不一致。
+pub fn bench_validation(blockchain: &Blockchain, threads: usize) -> ValidationBench {
+    let time = |threads: usize| {
+        let start = Instant::now();
+        blockchain.validate_parallel(threads).expect("基准测试的区块链应该有效");
+        start.elapsed()
+    };
+
+    ValidationBench {
+        blocks: blockchain.chain.len(),
+        threads,
+        sequential: time(1),
+        parallel: time(threads),
+    }
+}
+
+// ==================== 单元测试 ====================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synthetic_chain_is_valid() {
+        let blockchain = synthetic_chain(50);
+        assert_eq!(blockchain.chain.len(), 51);
+        assert!(blockchain.is_chain_valid());
+
+        let result = bench_validation(&blockchain, 4);
+        assert_eq!(result.blocks, 51);
+        assert!(result.speedup() > 0.0);
+    }
+}
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 // sha2: 提供SHA-256哈希算法实现
 use sha2::{Digest,Sha256};
 // std::fmt: 用于自定义显示格式
-use std::fmt::{self, format};
+use std::fmt;
 
 
 /// # 区块结构体 (Block Structure)
@@ -27,6 +27,7 @@ use std::fmt::{self, format};
 /// - `hash`: 区块的"指纹"，用于快速验证完整性
 /// - `nonce`: 挖矿的关键，通过调整这个值来满足难度要求
 /// - `difficulty`: 控制网络的出块速度和安全性
+/// - `chain_id`: 区块所属的链，不同网络的区块无法混用
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Block {
     /// 区块索引 - 从0开始的连续编号
@@ -57,6 +58,18 @@ pub struct Block {
     /// 挖矿难度 - 控制挖矿的困难程度
     /// 数值越大，找到有效哈希值就越困难，挖矿时间越长
     pub difficulty: u32,
+
+    /// 区块所属的链ID - 参与哈希计算
+    /// 把区块绑定到一个网络，复制到其他网络的区块哈希无效；
+    /// 内置链规范的区块没有这个字段，与旧版本的区块保持一致
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<String>,
+
+    /// 原始数据的SHA-256哈希 - 仅在区块数据被裁剪后存在
+    /// 裁剪后 `data` 为空，这个字段保留对原始数据的承诺，
+    /// 持有原始数据的人可以用它确认数据没有被替换
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_hash: Option<String>,
 }
 
 
@@ -87,6 +100,8 @@ impl Block {
             hash: String::new(), //初始化为空，稍后计算
             nonce:0,    //从0开始，挖矿时会递增
             difficulty,
+            chain_id: None,  //默认不绑定链，见 with_chain_id
+            data_hash: None, //新区块保留完整数据
         };
 
         // 计算初始哈希值（但可能不满足难度要求）
@@ -106,13 +121,32 @@ impl Block {
     /// ## 为什么需要创世区块？
     /// 区块链需要一个起点，创世区块就是这个起点
     /// 所有后续区块都直接或间接地连接到创世区块
-    pub fn genesis_block() -> Self {
-        Block::new(
-            0,                                          // 创世区块索引固定为0
-            "创世区块 - Genesis Block".to_string(),      // 创世区块的标识信息
-            "0".to_string(),                           // 没有前置区块，用"0"表示
-            1,                                         // 较低难度，便于快速生成     
-        )
+    /// 
+    /// ## 为什么时间戳是固定的？
+    /// 创世区块的内容完全由链规范决定（见 `ChainSpec::genesis_block`），
+    /// 同样的参数总是得到同样的区块和哈希。验证时据此判断
+    /// 一条区块链是否真的从这个创世区块开始，而不是另一条无关的链。
+    /// 
+    /// ## 参数
+    /// * `data` - 创世信息
+    /// * `timestamp` - 固定的创世时间
+    /// * `difficulty` - 创世区块的难度（创世区块不需要挖矿）
+    pub fn genesis(data: String, timestamp: DateTime<Utc>, difficulty: u32) -> Self {
+        // 没有前置区块，用"0"表示
+        let mut genesis = Block::new(0, data, "0".to_string(), difficulty);
+        // 使用固定时间戳代替当前时间，并重新计算哈希
+        genesis.timestamp = timestamp;
+        genesis.hash = genesis.calculate_hash();
+        genesis
+    }
+
+    /// # 设置区块所属的链ID
+    /// 
+    /// 链ID参与哈希计算，因此设置后会重新计算哈希（挖矿前调用）。
+    pub fn with_chain_id(mut self, chain_id: Option<String>) -> Self {
+        self.chain_id = chain_id;
+        self.hash = self.calculate_hash();
+        self
     }
 
     /// # 计算区块的哈希值
@@ -135,10 +169,11 @@ impl Block {
     /// 4. previous_hash - 前一区块哈希
     /// 5. nonce - 随机数
     /// 6. difficulty - 难度值
+    /// 7. chain_id - 链ID（仅在存在时，以 `/` 分隔）
     pub fn calculate_hash(&self) -> String {
         // 将区块的关键信息按顺序连接成一个字符串
         // timestamp.timestamp() 将DateTime转换为Unix时间戳
-        let data = format!(
+        let mut data = format!(
             "{}{}{}{}{}{}",
             self.index,                          // 区块索引
             self.timestamp.timestamp(),          // Unix时间戳
@@ -147,6 +182,10 @@ impl Block {
             self.nonce,                         // 当前nonce值
             self.difficulty                     // 难度值
         );
+        if let Some(chain_id) = &self.chain_id {
+            data.push('/');
+            data.push_str(chain_id);
+        }
         
         // 创建SHA-256哈希器
         let mut hasher = Sha256::new();
@@ -181,15 +220,52 @@ impl Block {
     pub fn mine_block(&mut self) {
         // 根据难度生成目标字符串，例如难度为3则target="000"
         let target = "0".repeat(self.difficulty as usize);
+
+        println!("🔨 开始挖掘区块 #{} (难度: {})...", self.index, self.difficulty);
+        println!("🎯 目标：找到以 '{}' 开头的哈希值", target);
+        
+        // 每10000次哈希显示一次进度，避免输出过于频繁
+        let (hash_count, elapsed) = self.mine_block_with_progress(10000, |hash_count, hash_rate| {
+            // \r 让光标回到行首，实现原地更新进度
+            print!("\r⛏️  已尝试 {} 次哈希, 速率: {:.0} H/s", hash_count, hash_rate);
+            // 强制刷新输出缓冲区，确保进度实时显示
+            std::io::Write::flush(&mut std::io::stdout()).unwrap();
+        });
+
+        // 挖矿成功！计算统计信息
+        let hash_rate = hash_count as f64 / elapsed.as_secs_f64();
+        
+        // 输出挖矿成功信息
+        println!(); // 换行，避免与进度信息重叠
+        println!("✅ 区块挖掘成功!");
+        println!("🎯 哈希值: {}", self.hash);
+        println!("🔢 Nonce: {}", self.nonce);
+        println!("⏱️  耗时: {:.2}秒", elapsed.as_secs_f64());
+        println!("🚀 哈希率: {:.0} H/s", hash_rate);
+        println!("💎 总尝试次数: {}", hash_count);
+    }
+
+
+    /// # 报告进度的挖矿
+    /// 
+    /// 与 `mine_block` 使用相同的工作量证明算法，不输出任何内容，
+    /// 而是每尝试 `every` 次哈希调用一次 `on_progress(已尝试次数, 哈希率)`，
+    /// 由调用者决定如何展示进度（命令行打印、推送给事件订阅者）。
+    /// 
+    /// ## 返回值
+    /// 总尝试次数和挖矿用时
+    pub fn mine_block_with_progress(
+        &mut self,
+        every: u64,
+        mut on_progress: impl FnMut(u64, f64),
+    ) -> (u64, std::time::Duration) {
+        let target = "0".repeat(self.difficulty as usize);
         
         // 记录挖矿开始时间，用于计算挖矿用时和哈希率
         let start_time = std::time::Instant::now();
         
-        // 记录尝试的哈希次数，用于统计和显示进度
+        // 记录尝试的哈希次数，用于统计和报告进度
         let mut hash_count = 0u64;
-
-        println!("🔨 开始挖掘区块 #{} (难度: {})...", self.index, self.difficulty);
-        println!("🎯 目标：找到以 '{}' 开头的哈希值", target);
         
         // 挖矿主循环：不断尝试不同的nonce值
         loop {
@@ -197,33 +273,16 @@ impl Block {
             self.hash = self.calculate_hash();
             hash_count += 1;
 
-            // 每10000次哈希显示一次进度，避免输出过于频繁
-            if hash_count % 10000 == 0 {
+            // 检查当前哈希值是否满足难度要求
+            if self.hash.starts_with(&target) {
+                return (hash_count, start_time.elapsed());
+            }
+
+            if hash_count.is_multiple_of(every) {
                 // start_time.elapsed() 返回自 start_time 以来的时间,
                 // as_secs_f64() 将时间转换为秒数
                 let elapsed = start_time.elapsed().as_secs_f64();
-                let hash_rate = hash_count as f64 / elapsed;
-                // \r 让光标回到行首，实现原地更新进度
-                print!("\r⛏️  已尝试 {} 次哈希, 速率: {:.0} H/s", hash_count, hash_rate);
-                // 强制刷新输出缓冲区，确保进度实时显示
-                std::io::Write::flush(&mut std::io::stdout()).unwrap();
-            }
-
-            // 检查当前哈希值是否满足难度要求
-            if self.hash.starts_with(&target) {
-                // 挖矿成功！计算统计信息
-                let elapsed = start_time.elapsed();
-                let hash_rate = hash_count as f64 / elapsed.as_secs_f64();
-                
-                // 输出挖矿成功信息
-                println!(); // 换行，避免与进度信息重叠
-                println!("✅ 区块挖掘成功!");
-                println!("🎯 哈希值: {}", self.hash);
-                println!("🔢 Nonce: {}", self.nonce);
-                println!("⏱️  耗时: {:.2}秒", elapsed.as_secs_f64());
-                println!("🚀 哈希率: {:.0} H/s", hash_rate);
-                println!("💎 总尝试次数: {}", hash_count);
-                break; // 退出挖矿循环
+                on_progress(hash_count, hash_count as f64 / elapsed);
             }
             
             // 如果当前哈希不满足要求，增加nonce值继续尝试
@@ -233,6 +292,20 @@ impl Block {
     }
 
 
+    /// # 静默挖矿
+    /// 
+    /// 与 `mine_block` 使用相同的工作量证明算法，但不输出任何进度信息。
+    /// 用于批量生成区块（例如性能测试）时避免大量终端输出拖慢速度。
+    pub fn mine_block_silent(&mut self) {
+        let target = "0".repeat(self.difficulty as usize);
+        self.hash = self.calculate_hash();
+        while !self.hash.starts_with(&target) {
+            self.nonce += 1;
+            self.hash = self.calculate_hash();
+        }
+    }
+
+
     /// # 验证区块的哈希值是否正确
     /// 
     /// 检查区块存储的哈希值是否与重新计算的哈希值一致。
@@ -269,12 +342,55 @@ impl Block {
     /// - `true`: 满足工作量证明要求
     /// - `false`: 不满足难度要求，可能是无效区块
     pub fn has_valid_proof_of_work(&self) -> bool {
-        // 生成目标字符串，例如难度为3则target="000"
-        // String.repeat() 生成重复的字符串
-        let target = "0".repeat(self.difficulty as usize);
-        // 检查当前哈希值是否以足够数量的0开头
-        // String.starts_with() 检查字符串是否以指定前缀开头
-        self.hash.starts_with(&target)
+        // 数出哈希值开头连续的"0"，至少要有 difficulty 个。
+        // 不生成目标字符串：难度可能来自其他节点，u32::MAX 的目标字符串需要 4 GiB 内存
+        let leading_zeros = self.hash.bytes().take_while(|&byte| byte == b'0').count();
+        leading_zeros >= self.difficulty as usize
+    }
+
+
+    /// # 区块的工作量
+    /// 
+    /// 难度为 d 的区块平均需要尝试 16^d 次哈希（哈希的每个十六进制前导零把概率降为 1/16）。
+    /// 比较两条链时看的是累计工作量，而不是区块数：付出算力最多的链才是大家认可的链。
+    pub fn work(&self) -> u128 {
+        // 难度可能来自未经验证的区块，乘法和移位都不能溢出
+        self.difficulty
+            .checked_mul(4)
+            .and_then(|shift| 1u128.checked_shl(shift))
+            .unwrap_or(u128::MAX)
+    }
+
+
+    /// # 计算区块数据的SHA-256哈希
+    /// 
+    /// 裁剪区块时用来生成 `data_hash`。
+    pub fn hash_data(data: &str) -> String {
+        format!("{:x}", Sha256::digest(data.as_bytes()))
+    }
+
+
+    /// 区块数据是否已被裁剪
+    pub fn is_pruned(&self) -> bool {
+        self.data_hash.is_some()
+    }
+
+
+    /// # 裁剪区块数据
+    /// 
+    /// 丢弃 `data`，只保留它的哈希。区块头（索引、时间戳、哈希、
+    /// 前一哈希、nonce、难度）保持不变，因此区块仍然可以与前后区块连接。
+    /// 
+    /// ## 代价
+    /// 区块哈希是根据原始数据计算的，裁剪后无法再重新计算哈希来检查篡改，
+    /// 只能检查哈希链接和工作量证明。裁剪前应先完整验证区块链。
+    /// 
+    /// 对已经裁剪的区块调用不会有任何效果。
+    pub fn prune(&mut self) {
+        if !self.is_pruned() {
+            self.data_hash = Some(Self::hash_data(&self.data));
+            self.data = String::new();
+        }
     }
 
 
@@ -321,6 +437,20 @@ impl Block {
 /// 使用树状结构显示区块信息，便于阅读。
 impl fmt::Display for Block {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 如果哈希值太长，只显示开头和结尾，中间用省略号
+        let previous_hash = if self.previous_hash.len() > 16 {
+            format!("{}...{}", &self.previous_hash[..8], &self.previous_hash[self.previous_hash.len()-8..])
+        } else {
+            self.previous_hash.clone()
+        };
+        // 哈希值也只显示开头和结尾
+        let hash = format!("{}...{}", &self.hash[..8], &self.hash[self.hash.len()-8..]);
+        // 已裁剪的区块显示数据哈希
+        let data = match &self.data_hash {
+            Some(data_hash) => format!("（已裁剪，数据哈希 {}）", data_hash),
+            None => self.data.clone(),
+        };
+
         write!(
             f,
             "区块 #{}\n\
@@ -334,15 +464,9 @@ impl fmt::Display for Block {
             self.index,
             // 格式化时间戳为可读格式
             self.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
-            self.data,
-            // 如果哈希值太长，只显示开头和结尾，中间用省略号
-            if self.previous_hash.len() > 16 {
-                format!("{}...{}", &self.previous_hash[..8], &self.previous_hash[self.previous_hash.len()-8..])
-            } else {
-                self.previous_hash.clone()
-            },
-            // 哈希值也只显示开头和结尾
-            format!("{}...{}", &self.hash[..8], &self.hash[self.hash.len()-8..]),
+            data,
+            previous_hash,
+            hash,
             self.nonce,
             self.difficulty,
             self.get_size()
@@ -367,7 +491,9 @@ mod tests {
     /// - 哈希值计算正确
     #[test]
     fn test_genesis_block_creation() {
-        let genesis = Block::genesis_block();
+        let timestamp = DateTime::from_timestamp(1_704_067_200, 0).unwrap();
+        let genesis = Block::genesis("创世".to_string(), timestamp, 1);
+        assert_eq!(genesis, Block::genesis("创世".to_string(), timestamp, 1));
         assert_eq!(genesis.index, 0);
         assert_eq!(genesis.previous_hash, "0");
         assert!(genesis.is_valid());
@@ -398,6 +524,34 @@ mod tests {
         assert!(block.is_valid());
     }
 
+    /// # 测试工作量证明检查：与目标前缀等价，超大难度直接判定无效而不分配目标字符串
+    #[test]
+    fn test_proof_of_work_check() {
+        let mut block = Block::new(1, "pow".to_string(), "prev".to_string(), 2);
+        block.hash = format!("00a{}", "f".repeat(61));
+        assert!(block.has_valid_proof_of_work());
+        block.difficulty = 3;
+        assert!(!block.has_valid_proof_of_work());
+        block.difficulty = u32::MAX;
+        assert!(!block.has_valid_proof_of_work());
+    }
+
+    /// # 测试报告进度的挖矿
+    /// 
+    /// 每尝试 `every` 次哈希报告一次，挖出的区块与 `mine_block` 相同。
+    #[test]
+    fn test_mining_progress() {
+        let mut block = Block::new(1, "test progress".to_string(), "prev_hash".to_string(), 2);
+        let mut expected = block.clone();
+        expected.mine_block_silent();
+
+        let mut reports = Vec::new();
+        let (hash_count, _) = block.mine_block_with_progress(3, |hash_count, _| reports.push(hash_count));
+        assert_eq!(block, expected);
+        assert_eq!(hash_count, block.nonce + 1);
+        assert_eq!(reports, (1..=block.nonce / 3).map(|i| i * 3).collect::<Vec<_>>());
+    }
+
     /// # 测试nonce改变对哈希值的影响
     /// 
     /// 验证改变nonce值会产生不同的哈希值。
@@ -410,4 +564,22 @@ mod tests {
         let new_hash = block.calculate_hash();
         assert_ne!(original_hash, new_hash);  // 哈希值应该不同
     }
-}
\ No newline at end of file
+
+    /// # 测试裁剪区块数据
+    /// 
+    /// 裁剪后数据被丢弃、区块头不变，数据哈希可以用原始数据重新得到。
+    #[test]
+    fn test_prune_block() {
+        let mut block = Block::new(1, "要裁剪的数据".to_string(), "prev".to_string(), 1);
+        let hash = block.hash.clone();
+        block.prune();
+        assert!(block.is_pruned());
+        assert!(block.data.is_empty());
+        assert_eq!(block.hash, hash);
+        assert_eq!(block.data_hash, Some(Block::hash_data("要裁剪的数据")));
+
+        // 重复裁剪不会覆盖数据哈希
+        block.prune();
+        assert_eq!(block.data_hash, Some(Block::hash_data("要裁剪的数据")));
+    }
+}
@@ -1,6 +1,24 @@
 // ==================== 依赖库导入 ====================
 // 导入自定义的Block结构体
 use crate::block::Block;
+// 导入链规范
+use crate::chainspec::ChainSpec;
+// 导入二进制编码和文件格式
+use crate::codec::{self, FileFormat};
+// 导入按哈希、时间和内容查找区块的索引
+use crate::index::{ChainIndex, DataQuery};
+// 导入区块数据的打包格式
+use crate::ingest;
+// 导入交易池
+use crate::mempool::{self, Mempool};
+// 导入存储格式的版本信封和迁移工具
+use crate::migration;
+// 导入存储后端接口、链元数据以及原子写入和备份工具
+use crate::storage::{self, ChainMetadata, ChainStore};
+// 导入外部矿工使用的区块模板
+use crate::template::{self, BlockTemplate, Work};
+// chrono: 按时间范围查找区块
+use chrono::{DateTime, Utc};
 // colored: 用于在终端输出彩色文本，提升用户体验
 use colored::Colorize;
 // serde: 用于序列化和反序列化，支持JSON格式的存储和加载
@@ -10,7 +28,9 @@ use std::fs;
 // std::io: 输入输出操作和错误处理
 use std::io;
 // std::path: 路径操作，用于处理文件路径
-use std::path::Path;
+use std::path::{Path, PathBuf};
+// std::thread: 并行验证区块
+use std::thread;
 
 /// # 区块链统计信息结构体 (BlockchainStatistics)
 /// 
@@ -26,7 +46,7 @@ use std::path::Path;
 /// - `average_block_time`: 平均出块时间（秒），反映网络效率
 /// - `average_hash_rate`: 平均哈希率（哈希/秒），反映网络算力
 /// - `total_attempts`: 总挖矿尝试次数，基于所有区块的nonce值累加
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct BlockchainStatistics {
     /// 区块链中的区块总数
     pub total_blocks: u64,
@@ -46,6 +66,24 @@ pub struct BlockchainStatistics {
     pub total_attempts: u64,
 }
 
+/// # 替换区块链的结果 (ChainReplacement)
+/// 
+/// `Blockchain::consider_chain` 采用了工作量更大的链时返回。
+/// 
+/// ## 字段说明
+/// - `fork_height`: 两条链最后一个共同区块的高度
+/// - `rolled_back`: 从原来的链上移除的区块，按高度排列
+/// - `adopted`: 新链在分叉点之后的区块，按高度排列
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainReplacement {
+    /// 最后一个共同区块的高度
+    pub fork_height: u64,
+    /// 被回滚的区块
+    pub rolled_back: Vec<Block>,
+    /// 新采用的区块
+    pub adopted: Vec<Block>,
+}
+
 /// # 区块链错误类型 (BlockchainError)
 /// 
 /// 定义区块链操作中可能遇到的各种错误类型。
@@ -56,6 +94,11 @@ pub struct BlockchainStatistics {
 /// - `InvalidChain`: 整个区块链验证失败，通常是链式结构被破坏
 /// - `IoError`: 文件输入输出操作失败，如文件读写权限问题
 /// - `SerializationError`: JSON序列化/反序列化失败，通常是数据格式问题
+/// - `StorageError`: 存储后端的数据损坏或不一致
+/// - `LockError`: 数据目录正被其他进程占用
+/// - `FormatError`: 文件格式无法识别或版本过新
+/// - `InvalidTransaction`: 提交的交易被拒绝，例如为空、过大或已经上链
+/// - `InvalidDifficulty`: 手动设置的难度超出范围，或链规范不允许手动设置
 #[derive(Debug)]
 pub enum BlockchainError {
     /// 无效区块错误，包含具体的错误信息
@@ -66,6 +109,16 @@ pub enum BlockchainError {
     IoError(io::Error),
     /// 序列化/反序列化错误，数据格式不正确
     SerializationError(serde_json::Error),
+    /// 存储后端错误，如存储内容损坏、区块高度不连续等
+    StorageError(String),
+    /// 锁错误，另一个进程正在使用同一个数据目录
+    LockError(String),
+    /// 格式错误，文件格式无法识别或版本高于程序支持的版本
+    FormatError(String),
+    /// 无效交易错误，交易没有被加入交易池
+    InvalidTransaction(String),
+    /// 无效难度错误，难度没有被修改
+    InvalidDifficulty(String),
 }
 
 /// # 实现From trait - 错误类型转换
@@ -99,6 +152,11 @@ impl std::fmt::Display for BlockchainError {
             BlockchainError::InvalidChain(msg) => write!(f, "无效区块链: {}", msg),
             BlockchainError::IoError(err) => write!(f, "IO错误: {}", err),
             BlockchainError::SerializationError(err) => write!(f, "序列化错误: {}", err),
+            BlockchainError::StorageError(msg) => write!(f, "存储错误: {}", msg),
+            BlockchainError::LockError(msg) => write!(f, "锁错误: {}", msg),
+            BlockchainError::FormatError(msg) => write!(f, "格式错误: {}", msg),
+            BlockchainError::InvalidTransaction(msg) => write!(f, "无效交易: {}", msg),
+            BlockchainError::InvalidDifficulty(msg) => write!(f, "无效难度: {}", msg),
         }
     }
 }
@@ -143,47 +201,60 @@ pub struct Blockchain {
     pub mining_reward: u64,
     
     /// 待处理交易池 - 等待被打包进区块的交易
-    /// 在当前简化版本中使用String，实际项目中应该是Transaction结构体
-    /// 矿工会从这个池中选择交易打包进新区块
-    pub pending_transactions: Vec<String>,
-}
-
-impl Blockchain {
-    /// # 系统默认配置常量
+    /// 每条交易是一段区块数据，按交易ID去重（见 `mempool.rs`）
+    /// 矿工会从这个池中选择交易打包进新区块，区块被追加后其中的交易自动移出交易池
+    pub pending_transactions: Mempool,
     
-    /// 默认挖矿难度 - 平衡安全性和效率的初始值
-    /// 难度2意味着哈希值需要以"00"开头
-    const DEFAULT_DIFFICULTY: u32 = 2;
+    /// 按哈希和时间查找区块的内存索引
+    /// 不参与序列化，加载区块链后重新建立
+    #[serde(skip)]
+    index: ChainIndex,
     
-    /// 默认挖矿奖励 - 激励矿工的初始奖励金额
-    /// 在真实区块链中，这个值会随着时间推移而调整
-    const DEFAULT_MINING_REWARD: u64 = 100;
+    /// 区块链遵循的链规范（创世区块、共识参数和检查点）
+    /// 不参与序列化，默认为内置链规范，可以用 `set_spec` 替换
+    #[serde(skip)]
+    spec: ChainSpec,
+}
 
+impl Blockchain {
     /// # 创建新的区块链实例
     /// 
-    /// 初始化一个全新的区块链，包含创世区块。
+    /// 按内置链规范初始化一个全新的区块链，等同于 `with_spec(ChainSpec::default())`。
+    /// 
+    /// ## 返回值
+    /// 返回一个包含创世区块的新区块链实例
+    pub fn new() -> Self {
+        Self::with_spec(ChainSpec::default())
+    }
+
+    /// # 按链规范创建新的区块链实例
+    /// 
+    /// 初始化一个全新的区块链，包含链规范定义的创世区块。
     /// 创世区块是区块链的起始点，所有后续区块都直接或间接地链接到它。
     /// 
     /// ## 初始化步骤
     /// 1. 创建空的区块链结构
-    /// 2. 设置默认的系统参数（难度、奖励等）
+    /// 2. 按链规范设置系统参数（初始难度、高度1的奖励等）
     /// 3. 创建并添加创世区块
     /// 4. 返回可用的区块链实例
     /// 
-    /// ## 返回值
-    /// 返回一个包含创世区块的新区块链实例
-    pub fn new() -> Self {
-        // 创建区块链基础结构，使用默认配置
+    /// ## 参数
+    /// * `spec` - 新区块链遵循的链规范
+    pub fn with_spec(spec: ChainSpec) -> Self {
+        // 创建区块链基础结构，使用链规范中的参数
         let mut blockchain = Blockchain {
             chain: Vec::new(),                        // 空的区块链条
-            difficulty: Self::DEFAULT_DIFFICULTY,     // 默认挖矿难度
-            mining_reward: Self::DEFAULT_MINING_REWARD, // 默认挖矿奖励
-            pending_transactions: Vec::new(),         // 空的交易池
+            difficulty: spec.initial_difficulty,      // 初始挖矿难度
+            mining_reward: spec.reward_at(1),         // 下一个区块的挖矿奖励
+            pending_transactions: Mempool::default(), // 空的交易池
+            index: ChainIndex::default(),             // 空的查找索引
+            spec,
         };
         
         // 创建并添加创世区块
-        // 创世区块是区块链的第一个区块，具有特殊的标识
-        let genesis_block = Block::genesis_block();
+        // 创世区块是区块链的第一个区块，内容完全由链规范决定
+        let genesis_block = blockchain.spec.genesis_block();
+        blockchain.index.record(&genesis_block);
         blockchain.chain.push(genesis_block);
         
         blockchain
@@ -230,16 +301,8 @@ impl Blockchain {
     /// * `Ok(())` - 成功添加区块
     /// * `Err(BlockchainError)` - 添加失败，包含具体错误信息
     pub fn add_block(&mut self, data: String) -> Result<(), BlockchainError> {
-        // 获取链上最新区块，作为新区块的前驱
-        let previous_block = self.get_latest_block();
-        
-        // 创建新区块，所有参数都基于当前区块链状态
-        let mut new_block = Block::new(
-            previous_block.index + 1,           // 新区块索引 = 前一区块索引 + 1
-            data,                               // 用户提供的区块数据
-            previous_block.hash.clone(),        // 前一区块的哈希值，建立链接
-            self.difficulty,                    // 当前网络的挖矿难度
-        );
+        // 按当前区块链状态创建新区块（检查数据大小、按链规范调整难度）
+        let mut new_block = self.next_block(data)?;
         
         // 执行挖矿操作 - 这是最耗时的步骤
         // 挖矿会调整nonce值直到找到满足难度要求的哈希值
@@ -258,10 +321,307 @@ impl Blockchain {
         }
         
         // 所有验证通过，将新区块添加到链上
-        self.chain.push(new_block);
+        self.push_block(new_block);
+        Ok(())
+    }
+
+    /// # 创建下一个待挖矿的区块
+    /// 
+    /// 1. 检查数据是否超过链规范的大小限制，超过时不创建区块
+    /// 2. 开启难度调整时，按链规范计算新区块的难度并更新 `difficulty`
+    /// 3. 以最新区块为前驱创建新区块（尚未挖矿），带上链规范要求的链ID
+    fn next_block(&mut self, data: String) -> Result<Block, BlockchainError> {
+        let block = self.candidate_block(data)?;
+        self.difficulty = block.difficulty;
+        Ok(block)
+    }
+
+    /// 与 `next_block` 相同，但不更新 `difficulty`，用于生成还不一定被挖出的区块模板
+    fn candidate_block(&self, data: String) -> Result<Block, BlockchainError> {
+        if let Some(max) = self.spec.limits.max_data_bytes
+            && data.len() > max
+        {
+            return Err(BlockchainError::InvalidBlock(format!(
+                "区块数据 {} 字节，超过链规范的上限 {} 字节",
+                data.len(),
+                max
+            )));
+        }
+        let difficulty = self.spec.next_difficulty(&self.chain, self.difficulty)?;
+        
+        // 创建新区块，所有参数都基于当前区块链状态
+        let previous_block = self.get_latest_block();
+        let block = Block::new(
+            previous_block.index + 1,           // 新区块索引 = 前一区块索引 + 1
+            data,                               // 用户提供的区块数据
+            previous_block.hash.clone(),        // 前一区块的哈希值，建立链接
+            difficulty,                         // 当前网络的挖矿难度
+        );
+        // 按链规范把区块绑定到这条链
+        Ok(block.with_chain_id(self.spec.block_chain_id()))
+    }
+
+    /// 把已验证的区块加入链尾，更新索引和下一个区块的奖励，并从交易池中移除它包含的交易
+    fn push_block(&mut self, block: Block) {
+        self.pending_transactions.remove_included(std::slice::from_ref(&block));
+        self.index.record(&block);
+        self.chain.push(block);
+        self.mining_reward = self.spec.reward_at(self.chain.len() as u64);
+    }
+
+    /// # 静默挖矿并添加新区块
+    /// 
+    /// 与 `add_block` 相同，但挖矿时不输出进度信息，
+    /// 用于批量导入等一次挖出大量区块的场景。
+    pub fn add_block_silent(&mut self, data: String) -> Result<(), BlockchainError> {
+        let mut new_block = self.next_block(data)?;
+        new_block.mine_block_silent();
+        self.append_block(new_block)
+    }
+
+    /// # 报告进度地挖矿并添加新区块
+    /// 
+    /// 与 `add_block_silent` 相同，挖矿时每尝试 `every` 次哈希调用一次
+    /// `on_progress(已尝试次数, 哈希率)`（见 `Block::mine_block_with_progress`）。
+    pub fn add_block_with_progress(
+        &mut self,
+        data: String,
+        every: u64,
+        on_progress: impl FnMut(u64, f64),
+    ) -> Result<(), BlockchainError> {
+        let mut new_block = self.next_block(data)?;
+        new_block.mine_block_with_progress(every, on_progress);
+        self.append_block(new_block)
+    }
+
+    /// # 生成交给外部矿工的区块模板
+    /// 
+    /// 区块数据与 `next_block_data` 相同：`coinbase` 作为第一条记录，之后是交易池中的交易，
+    /// 打包交易时为额外随机数预留 `EXTRA_NONCE_BYTES` 字节（见 `BlockTemplate`）。
+    /// 难度按链规范计算，但 `difficulty` 要等模板的区块被追加后才更新。
+    pub fn block_template(&self, coinbase: String) -> Result<BlockTemplate, BlockchainError> {
+        let max_bytes = self.spec.limits.max_data_bytes.map(|max| max.saturating_sub(template::EXTRA_NONCE_BYTES));
+        let (data, packed) = self
+            .pending_transactions
+            .block_data(Some(coinbase.clone()), max_bytes)
+            .expect("至少包含 coinbase");
+        let transactions = match packed {
+            0 => Vec::new(),
+            _ => ingest::unpack_data(&data).split_off(1),
+        };
+        let candidate = self.candidate_block(data)?;
+        Ok(BlockTemplate::new(&candidate, coinbase, transactions, self.get_latest_block().timestamp))
+    }
+
+    /// # 追加外部矿工按模板挖出的区块
+    /// 
+    /// 模板必须基于当前链尖。用 `BlockTemplate::block` 还原区块并检查工作量证明，
+    /// 再用 `append_block` 完整验证后追加，之后的难度与这个区块相同（同 `next_block`）。
+    /// 
+    /// ## 返回值
+    /// * `Ok(Block)` - 追加的区块
+    /// * `Err(BlockchainError::InvalidBlock)` - 模板已过期、工作无效或区块不符合链规范，链保持不变
+    pub fn submit_work(&mut self, template: &BlockTemplate, work: &Work) -> Result<Block, BlockchainError> {
+        if template.previous_hash != self.get_latest_block().hash {
+            return Err(BlockchainError::InvalidBlock(format!(
+                "区块模板 {} 已过期：链尖已经不是 {}",
+                template.id, template.previous_hash
+            )));
+        }
+        let block = template.block(work)?;
+        self.append_block(block.clone())?;
+        self.difficulty = block.difficulty;
+        Ok(block)
+    }
+
+    /// # 追加一个已经挖好的区块
+    /// 
+    /// 与 `add_block` 自己挖矿不同，这里接收来自外部的区块（例如从导出文件导入），
+    /// 追加前用 `check_successor` 完整验证它与当前最新区块的关系，
+    /// 因此无法通过这个方法把无效或不相连的区块放进链中。
+    /// 
+    /// 已裁剪的区块无法重新计算哈希，`"00…"` 开头的哈希可以随意编造，因此不接受；
+    /// 裁剪只能由本地的 `prune_below` 对已经验证过的区块进行。
+    /// 
+    /// ## 返回值
+    /// * `Ok(())` - 区块有效，已追加到链尾
+    /// * `Err(BlockchainError::InvalidBlock)` - 区块无效、已裁剪或与链尾不相连，链保持不变
+    pub fn append_block(&mut self, block: Block) -> Result<(), BlockchainError> {
+        Self::check_new_block(&self.spec, &self.chain, &block)?;
+        self.push_block(block);
         Ok(())
     }
 
+    /// 验证从外部收到的新区块：必须带有完整数据，才能重新计算哈希；
+    /// 除了 `check_successor_in` 之外，时间戳也不能超前本机时间太多（见 `ChainSpec::check_not_future`）
+    fn check_new_block(spec: &ChainSpec, recent: &[Block], block: &Block) -> Result<(), BlockchainError> {
+        if block.is_pruned() {
+            return Err(BlockchainError::InvalidBlock(format!(
+                "区块 #{}: 已裁剪的区块无法验证哈希，不能从外部接受",
+                block.index
+            )));
+        }
+        Self::check_successor_in(spec, recent, block)?;
+        ChainSpec::check_not_future(block)
+    }
+
+    /// # 考虑一条竞争的区块链
+    /// 
+    /// 节点之间出现分叉时（例如两个节点几乎同时挖出了同一高度的区块），
+    /// 以累计工作量更大的链为准。这个方法比较本地链和候选链，候选链更重时替换本地链。
+    /// 
+    /// ## 处理步骤
+    /// 1. 候选链的创世区块必须与本地的相同，否则不是同一条链
+    /// 2. 找到两条链最后一个哈希相同的区块（分叉点）
+    /// 3. 比较分叉点之后两边的累计工作量（见 `Block::work`），候选链不更重时保持不变
+    /// 4. 用 `check_successor_in` 完整验证候选链在分叉点之后的每个区块，这些区块不能是已裁剪的，
+    ///    时间戳也不能超前本机时间太多
+    /// 5. 移除本地链分叉点之后的区块，接上候选链的区块
+    /// 
+    /// 工作量不比本地链大的候选链不会被验证，因此处理大量无用的候选链开销很小。
+    /// 新采用区块中的交易从交易池中移除；被回滚区块中的交易不会自动放回交易池，
+    /// 见 `restore_pending_transactions`。
+    /// 
+    /// ## 参数
+    /// * `candidate` - 从创世区块开始的完整候选链
+    /// 
+    /// ## 返回值
+    /// * `Ok(Some(ChainReplacement))` - 已替换为候选链，包含被回滚和新采用的区块
+    /// * `Ok(None)` - 候选链的工作量不比本地链大，本地链保持不变
+    /// * `Err(BlockchainError::InvalidChain)` - 候选链为空或创世区块不同
+    /// * `Err(BlockchainError::InvalidBlock)` - 候选链在分叉点之后有无效区块，本地链保持不变
+    pub fn consider_chain(&mut self, candidate: Vec<Block>) -> Result<Option<ChainReplacement>, BlockchainError> {
+        let Some(genesis) = candidate.first() else {
+            return Err(BlockchainError::InvalidChain("候选区块链为空".to_string()));
+        };
+        if genesis.hash != self.chain[0].hash {
+            return Err(BlockchainError::InvalidChain(format!(
+                "候选区块链的创世区块 {} 与本地的 {} 不同",
+                genesis.hash, self.chain[0].hash
+            )));
+        }
+
+        let fork = self
+            .chain
+            .iter()
+            .zip(&candidate)
+            .take_while(|(ours, theirs)| ours.hash == theirs.hash)
+            .count();
+        if Self::work_of(&candidate[fork..]) <= Self::work_of(&self.chain[fork..]) {
+            return Ok(None);
+        }
+
+        for height in fork..candidate.len() {
+            Self::check_new_block(&self.spec, &candidate[..height], &candidate[height])?;
+        }
+
+        let adopted = candidate[fork..].to_vec();
+        self.pending_transactions.remove_included(&adopted);
+        let rolled_back = self.chain.split_off(fork);
+        self.chain.extend(candidate.into_iter().skip(fork));
+        self.reindex();
+        self.mining_reward = self.spec.reward_at(self.chain.len() as u64);
+        Ok(Some(ChainReplacement {
+            fork_height: fork as u64 - 1,
+            rolled_back,
+            adopted,
+        }))
+    }
+
+    /// # 把被回滚区块的交易放回交易池
+    /// 
+    /// 被回滚的区块中、没有出现在新采用区块里的交易（见 `mempool::block_transactions`）
+    /// 重新加入待处理交易池，等待打包进之后的区块。已裁剪的区块没有数据，会被跳过。
+    /// 
+    /// ## 返回值
+    /// 放回交易池的交易数
+    pub fn restore_pending_transactions(&mut self, replacement: &ChainReplacement) -> usize {
+        let adopted: Vec<String> = replacement.adopted.iter().flat_map(mempool::block_transactions).collect();
+        replacement
+            .rolled_back
+            .iter()
+            .flat_map(mempool::block_transactions)
+            .filter(|transaction| !transaction.is_empty() && !adopted.contains(transaction))
+            .filter(|transaction| self.pending_transactions.insert(transaction.clone()))
+            .count()
+    }
+
+    /// # 提交一条交易
+    /// 
+    /// 检查后加入交易池，等待打包进之后的区块：
+    /// - 交易不能为空，也不能超过链规范中单个区块数据的上限
+    /// - 已经包含在链上某个区块中的交易被拒绝
+    /// - 交易池已满时拒绝新交易
+    /// 
+    /// ## 返回值
+    /// * `Ok(true)` - 交易已加入交易池
+    /// * `Ok(false)` - 交易池中已有相同ID的交易（例如其他节点转发回来的）
+    /// * `Err(BlockchainError::InvalidTransaction)` - 交易被拒绝，包含原因
+    pub fn submit_transaction(&mut self, data: String) -> Result<bool, BlockchainError> {
+        if data.trim().is_empty() {
+            return Err(BlockchainError::InvalidTransaction("交易数据为空".to_string()));
+        }
+        if let Some(max) = self.spec.limits.max_data_bytes
+            && data.len() > max
+        {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "交易数据 {} 字节，超过链规范的上限 {} 字节",
+                data.len(),
+                max
+            )));
+        }
+        if self.pending_transactions.contains(&data) {
+            return Ok(false);
+        }
+        if let Some(height) = self.transaction_height(&data) {
+            return Err(BlockchainError::InvalidTransaction(format!("交易已包含在区块 #{} 中", height)));
+        }
+        if self.pending_transactions.is_full() {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "交易池已满（{} 条交易）",
+                mempool::MAX_MEMPOOL_TRANSACTIONS
+            )));
+        }
+        Ok(self.pending_transactions.insert(data))
+    }
+
+    /// # 查找包含这条交易的区块高度
+    /// 
+    /// 索引与链一致时用交易ID查找，否则遍历整条链。
+    pub fn transaction_height(&self, data: &str) -> Option<u64> {
+        let id = mempool::transaction_id(data);
+        if self.index.is_current(&self.chain) {
+            return self.index.height_of_transaction(&id);
+        }
+        self.chain
+            .iter()
+            .find(|block| {
+                block.data_hash.as_deref() == Some(id.as_str())
+                    || mempool::block_transactions(block).iter().any(|transaction| transaction == data)
+            })
+            .map(|block| block.index)
+    }
+
+    /// # 下一个区块的数据
+    /// 
+    /// 把 `data`（可选）和交易池中的交易按 `Mempool::block_data` 打包，不超过链规范的数据上限。
+    /// 
+    /// ## 返回值
+    /// 区块数据和其中来自交易池的交易数；没有数据也没有待处理交易时为 None
+    pub fn next_block_data(&self, data: Option<String>) -> Option<(String, usize)> {
+        self.pending_transactions.block_data(data, self.spec.limits.max_data_bytes)
+    }
+
+    /// 一组区块的累计工作量
+    pub fn work_of(blocks: &[Block]) -> u128 {
+        blocks.iter().map(Block::work).fold(0, u128::saturating_add)
+    }
+
+    /// 整条区块链的累计工作量
+    pub fn total_work(&self) -> u128 {
+        Self::work_of(&self.chain)
+    }
+
     /// # 批量挖矿功能
     /// 
     /// 连续创建指定数量的区块，主要用于：
@@ -356,48 +716,177 @@ impl Blockchain {
     /// * `false` - 发现问题，区块链可能被篡改或损坏
     pub fn is_chain_valid(&self) -> bool {
         // 首先检查区块链是否为空
-        if self.chain.is_empty() {
+        let Some(genesis) = self.chain.first() else {
             return false;
-        }
+        };
         
-        // 验证创世区块的特殊性质
-        let genesis = &self.chain[0];
-        if genesis.index != 0 || genesis.previous_hash != "0" {
+        // 验证创世区块的特殊性质，以及它是否是链规范中的创世区块
+        if Self::check_genesis_in(&self.spec, genesis).is_err() {
             return false;
         }
         
-        // 从第二个区块开始验证每个区块
-        for i in 1..self.chain.len() {
-            let current_block = &self.chain[i];
-            let previous_block = &self.chain[i - 1];
-            
-            // 验证当前区块的哈希值是否正确
-            // 这检查区块内容是否被篡改
-            if !current_block.is_valid() {
-                return false;
-            }
-            
-            // 验证工作量证明是否满足要求
-            // 这检查区块是否经过了正当的挖矿过程
-            if !current_block.has_valid_proof_of_work() {
-                return false;
-            }
-            
-            // 验证区块链的连接性
-            // 当前区块的previous_hash必须等于前一区块的hash
-            if current_block.previous_hash != previous_block.hash {
-                return false;
-            }
-            
-            // 验证区块索引的连续性
-            // 确保区块按正确顺序排列，没有跳跃或重复
-            if current_block.index != previous_block.index + 1 {
-                return false;
-            }
+        // 从第二个区块开始，验证每个区块及其与前一区块的连接
+        (1..self.chain.len()).all(|height| Self::check_successor_in(&self.spec, &self.chain[..height], &self.chain[height]).is_ok())
+    }
+
+    /// # 验证创世区块
+    /// 
+    /// 创世区块的索引必须为0，previous_hash必须为"0"。
+    /// 
+    /// ## 返回值
+    /// * `Ok(())` - 创世区块有效
+    /// * `Err(BlockchainError::InvalidBlock)` - 说明具体哪一项不满足
+    pub fn check_genesis(genesis: &Block) -> Result<(), BlockchainError> {
+        if genesis.index != 0 {
+            return Err(BlockchainError::InvalidBlock(format!(
+                "创世区块的索引应为0，实际为 {}",
+                genesis.index
+            )));
+        }
+        if genesis.previous_hash != "0" {
+            return Err(BlockchainError::InvalidBlock("创世区块的previous_hash应为\"0\"".to_string()));
+        }
+        if genesis.is_pruned() && !genesis.data.is_empty() {
+            return Err(BlockchainError::InvalidBlock("已裁剪的创世区块仍包含数据".to_string()));
         }
+        Ok(())
+    }
+
+    /// # 验证一个区块及其与前一区块的连接
+    /// 
+    /// `is_chain_valid` 对每一对相邻区块执行的检查，单独提取出来后
+    /// 流式验证等场景也可以逐块调用，而不需要把整条链放进内存。
+    /// 
+    /// ## 检查项目
+    /// 1. **哈希正确性**：重新计算哈希值，与存储的值比较，检查区块内容是否被篡改
+    /// 2. **工作量证明**：哈希值满足区块记录的难度，检查区块是否经过了正当的挖矿过程
+    /// 3. **哈希链接**：当前区块的previous_hash必须等于前一区块的hash
+    /// 4. **索引连续性**：确保区块按正确顺序排列，没有跳跃或重复
+    /// 
+    /// ## 已裁剪的区块
+    /// 数据被裁剪的区块无法重新计算哈希，跳过第1项，其余检查照常进行。
+    /// 裁剪只能从链的开头连续进行，因此已裁剪的区块前面不能有未裁剪的区块。
+    /// 
+    /// ## 返回值
+    /// * `Ok(())` - 区块有效
+    /// * `Err(BlockchainError::InvalidBlock)` - 说明区块高度和失败原因
+    pub fn check_successor(previous: &Block, current: &Block) -> Result<(), BlockchainError> {
+        let reason = if current.is_pruned() && !previous.is_pruned() {
+            "已裁剪的区块之前存在未裁剪的区块"
+        } else if current.is_pruned() && !current.data.is_empty() {
+            "已裁剪的区块仍包含数据"
+        } else if !current.is_pruned() && !current.is_valid() {
+            "哈希值与区块内容不符"
+        } else if !current.has_valid_proof_of_work() {
+            "不满足工作量证明难度"
+        } else {
+            return Self::check_link(previous, current);
+        };
+        
+        Err(BlockchainError::InvalidBlock(format!("区块 #{}: {}", current.index, reason)))
+    }
+
+    /// # 只验证区块与前一区块的连接
+    /// 
+    /// `check_successor` 的第3、4项：哈希链接和索引连续性。
+    /// 不重新计算哈希，也不检查工作量证明，开销可以忽略。
+    pub fn check_link(previous: &Block, current: &Block) -> Result<(), BlockchainError> {
+        let reason = if current.previous_hash != previous.hash {
+            "previous_hash与前一区块的哈希不一致"
+        } else if current.index != previous.index + 1 {
+            "区块索引不连续"
+        } else {
+            return Ok(());
+        };
         
-        // 所有验证都通过，区块链是有效的
-        true
+        Err(BlockchainError::InvalidBlock(format!("区块 #{}: {}", current.index, reason)))
+    }
+
+    /// # 按链规范验证创世区块
+    /// 
+    /// 在 `check_genesis` 的基础上，要求创世区块的哈希与链规范一致。
+    pub fn check_genesis_in(spec: &ChainSpec, genesis: &Block) -> Result<(), BlockchainError> {
+        Self::check_genesis(genesis)?;
+        spec.check_block(genesis)
+    }
+
+    /// # 按链规范验证一个区块及其与前一区块的连接
+    /// 
+    /// 链规范开启了 `assume_valid` 且区块不高于最后一个检查点时，只调用 `check_link`；
+    /// 否则调用完整的 `check_successor`。之后再检查区块是否满足链规范的共识参数
+    /// （数据大小、难度范围、难度调整规则和时间戳），以及是否与检查点一致。
+    /// 
+    /// ## 参数
+    /// * `recent` - 区块之前连续的区块，最后一个是前一个区块；
+    ///   至少包含 `ChainSpec::difficulty_window` 个，或者从创世区块开始
+    /// * `current` - 要检查的区块
+    pub fn check_successor_in(spec: &ChainSpec, recent: &[Block], current: &Block) -> Result<(), BlockchainError> {
+        let previous = recent.last().expect("至少包含前一个区块");
+        if spec.skips_work_check(current.index) {
+            Self::check_link(previous, current)?;
+        } else {
+            Self::check_successor(previous, current)?;
+        }
+        spec.check_consensus(recent, current)?;
+        spec.check_block(current)
+    }
+
+    /// # 多线程验证整个区块链
+    /// 
+    /// 验证的绝大部分时间花在重新计算每个区块的SHA-256上。每个区块的哈希、
+    /// 工作量证明和连接检查只需要读取它自己和前一个区块，彼此独立，
+    /// 因此可以把区块分成若干连续的段，交给多个线程同时检查。
+    /// 
+    /// ## 验证过程
+    /// 1. 在当前线程检查创世区块
+    /// 2. 把其余区块平均分成 `threads` 段，每个线程对自己段内的每个区块调用 `check_successor_in`
+    /// 3. 按高度顺序汇总结果，返回高度最低的错误
+    /// 
+    /// 与 `is_chain_valid` 的检查项目完全相同，只是把失败原因返回给调用者。
+    /// 
+    /// ## 参数
+    /// * `threads` - 线程数，为0或1时在当前线程顺序验证
+    /// 
+    /// ## 返回值
+    /// * `Ok(())` - 区块链完全有效
+    /// * `Err(BlockchainError::InvalidBlock)` - 第一个无效区块的高度和原因
+    /// * `Err(BlockchainError::InvalidChain)` - 区块链为空
+    pub fn validate_parallel(&self, threads: usize) -> Result<(), BlockchainError> {
+        let Some(genesis) = self.chain.first() else {
+            return Err(BlockchainError::InvalidChain("区块链为空".to_string()));
+        };
+        Self::check_genesis_in(&self.spec, genesis)?;
+        
+        // 检查高度在 [start, end) 范围内的区块
+        let check_range = |start: usize, end: usize| {
+            (start..end).try_for_each(|i| Self::check_successor_in(&self.spec, &self.chain[..i], &self.chain[i]))
+        };
+        
+        let len = self.chain.len();
+        let threads = threads.clamp(1, len.max(1));
+        if threads == 1 {
+            return check_range(1, len);
+        }
+        
+        let chunk = (len - 1).div_ceil(threads);
+        thread::scope(|scope| {
+            let handles: Vec<_> = (1..len)
+                .step_by(chunk)
+                .map(|start| scope.spawn(move || check_range(start, (start + chunk).min(len))))
+                .collect();
+            
+            // 按段的顺序汇总，保证报告的是高度最低的错误
+            handles
+                .into_iter()
+                .try_for_each(|handle| handle.join().expect("验证线程异常退出"))
+        })
+    }
+
+    /// # 默认的验证线程数
+    /// 
+    /// 使用系统报告的可用并行度（通常是CPU核心数），无法获取时为1。
+    pub fn default_validation_threads() -> usize {
+        thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
     }
 
     /// # 获取指定索引的区块
@@ -433,6 +922,124 @@ impl Blockchain {
         self.chain.get(index as usize)
     }
 
+    /// # 按哈希查找区块
+    /// 
+    /// 通过内存索引直接得到区块高度，时间复杂度为O(1)。
+    /// 如果 `chain` 在索引之外被直接修改过，找到的高度会再次核对哈希，
+    /// 核对失败时回退到遍历整条链，保证结果总是正确的。
+    /// 
+    /// ## 参数
+    /// * `hash` - 完整的64位十六进制区块哈希（不区分大小写）
+    /// 
+    /// ## 返回值
+    /// * `Some(&Block)` - 找到哈希相同的区块
+    /// * `None` - 链上没有该哈希的区块
+    pub fn get_block_by_hash(&self, hash: &str) -> Option<&Block> {
+        let hash = hash.to_ascii_lowercase();
+        let indexed = self
+            .index
+            .height_of(&hash)
+            .and_then(|height| self.get_block(height))
+            .filter(|block| block.hash == hash);
+        
+        indexed.or_else(|| self.chain.iter().find(|block| block.hash == hash))
+    }
+
+    /// # 按时间范围查找区块
+    /// 
+    /// 返回时间戳在 `from` 到 `to` 之间（含两端）的所有区块，按高度排序。
+    /// 索引与链一致时使用二分查找，否则遍历整条链。
+    /// 
+    /// ## 参数
+    /// * `from` - 起始时间，None 表示不限
+    /// * `to` - 结束时间，None 表示不限
+    pub fn blocks_in_time_range(&self, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> Vec<&Block> {
+        let from = from.unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let to = to.unwrap_or(DateTime::<Utc>::MAX_UTC);
+        
+        if self.index.is_current(&self.chain) {
+            self.index
+                .heights_in_time_range(from..=to)
+                .into_iter()
+                .filter_map(|height| self.get_block(height))
+                .collect()
+        } else {
+            self.chain
+                .iter()
+                .filter(|block| (from..=to).contains(&block.timestamp))
+                .collect()
+        }
+    }
+
+    /// # 按数据内容搜索区块
+    /// 
+    /// 返回数据满足查询（包含子串或匹配正则表达式）的所有区块，按高度排序。
+    /// 区块数据没有建立索引，搜索需要遍历整条链。已裁剪的区块不会出现在结果中。
+    pub fn search_data(&self, query: &DataQuery) -> Vec<&Block> {
+        self.chain
+            .iter()
+            .filter(|block| !block.is_pruned() && query.matches(&block.data))
+            .collect()
+    }
+
+    /// # 裁剪区块数据
+    /// 
+    /// 丢弃高度低于 `height` 的所有区块的数据，只保留区块头和数据哈希，
+    /// 以减少内存和磁盘占用。区块哈希不变，因此链接关系和链尖都不受影响，
+    /// 之后仍可以正常挖矿和验证（已裁剪的区块跳过哈希重算，见 `check_successor`）。
+    /// 
+    /// 裁剪前区块链应当已经通过完整验证，因为裁剪后就无法再检查这些区块的数据是否被篡改。
+    /// 裁剪只改变区块内容而不改变哈希，保存时需要使用 `rewrite_store`。
+    /// 
+    /// ## 参数
+    /// * `height` - 裁剪高度，可以等于链长度（裁剪全部区块）
+    /// 
+    /// ## 返回值
+    /// 本次新裁剪的区块数（已经裁剪过的区块不计入）
+    pub fn prune_below(&mut self, height: u64) -> Result<u64, BlockchainError> {
+        if height > self.chain.len() as u64 {
+            return Err(BlockchainError::InvalidBlock(format!(
+                "裁剪高度 {} 超过区块链长度 {}",
+                height,
+                self.chain.len()
+            )));
+        }
+
+        let mut pruned = 0;
+        for block in self.chain[..height as usize].iter_mut().filter(|block| !block.is_pruned()) {
+            block.prune();
+            pruned += 1;
+        }
+        Ok(pruned)
+    }
+
+    /// 链开头已裁剪的区块数
+    pub fn pruned_count(&self) -> u64 {
+        self.chain.iter().take_while(|block| block.is_pruned()).count() as u64
+    }
+
+    /// 区块链遵循的链规范
+    pub fn spec(&self) -> &ChainSpec {
+        &self.spec
+    }
+
+    /// # 替换链规范
+    /// 
+    /// 只影响之后的验证和追加区块，不会重新验证已有的区块。
+    /// 挖矿奖励按新链规范的奖励计划更新为下一个区块的奖励。
+    pub fn set_spec(&mut self, spec: ChainSpec) {
+        self.mining_reward = spec.reward_at(self.chain.len() as u64);
+        self.spec = spec;
+    }
+
+    /// # 重新建立查找索引
+    /// 
+    /// 从文件反序列化得到的区块链没有索引，加载后需要调用一次。
+    /// 直接修改过 `chain` 之后也可以调用它让查询重新使用索引。
+    pub fn reindex(&mut self) {
+        self.index = ChainIndex::build(&self.chain);
+    }
+
     /// # 设置挖矿难度
     /// 
     /// 动态调整网络的挖矿难度，这是区块链网络自我调节的重要机制。
@@ -449,27 +1056,51 @@ impl Blockchain {
     /// - **算力减少时**：降低难度，避免网络卡顿
     /// 
     /// ## 安全限制
-    /// 限制难度范围在1-10之间，原因：
-    /// - **最小值1**：确保基本的工作量证明要求
-    /// - **最大值10**：避免挖矿时间过长，影响用户体验
+    /// 难度必须在链规范的 `limits` 范围内（内置链规范为1-10），原因：
+    /// - **最小值**：确保基本的工作量证明要求
+    /// - **最大值**：避免挖矿时间过长，影响用户体验
     /// - **超出范围**：自动忽略，保护网络稳定性
     /// 
+    /// 链规范开启了难度自动调整时，难度由链规范决定，不能手动设置。
+    /// 
     /// ## 参数
-    /// * `difficulty` - 新的难度值，必须在1-10范围内
+    /// * `difficulty` - 新的难度值，必须在链规范允许的范围内
     /// 
     /// ## 使用场景
     /// - 网络负载调整
     /// - 测试不同难度下的性能
     /// - 适应硬件算力变化
     pub fn set_difficulty(&mut self, difficulty: u32) {
-        // 验证难度值在合理范围内
-        if difficulty > 0 && difficulty <= 10 {
-            self.difficulty = difficulty;
-            println!("✅ 挖矿难度已设置为: {}", difficulty);
-        } else {
-            // 难度值超出范围，不进行修改并给出提示
-            println!("❌ 难度必须在1-10之间");
+        match self.try_set_difficulty(difficulty) {
+            Ok(()) => println!("✅ 挖矿难度已设置为: {}", difficulty),
+            // 难度无效时不进行修改并给出提示
+            Err(e) => println!("❌ {}", e),
+        }
+    }
+
+    /// # 设置挖矿难度，失败时返回错误
+    ///
+    /// 与 `set_difficulty` 的检查相同，但不打印结果，供 RPC 等需要把错误返回给调用者的地方使用。
+    ///
+    /// ## 返回值
+    /// 链规范开启了难度自动调整，或难度超出链规范的范围时返回 `InvalidDifficulty`，难度不变
+    pub fn try_set_difficulty(&mut self, difficulty: u32) -> Result<(), BlockchainError> {
+        // 难度自动调整时不接受手动设置
+        if self.spec.retarget.is_some() {
+            return Err(BlockchainError::InvalidDifficulty(format!(
+                "链 {} 的难度由链规范自动调整，不能手动设置",
+                self.spec.chain_id
+            )));
         }
+        // 验证难度值在链规范允许的范围内
+        if !self.spec.difficulty_in_range(difficulty) {
+            return Err(BlockchainError::InvalidDifficulty(format!(
+                "难度必须在{}-{}之间",
+                self.spec.limits.min_difficulty, self.spec.limits.max_difficulty
+            )));
+        }
+        self.difficulty = difficulty;
+        Ok(())
     }
 
     /// # 获取区块链统计信息
@@ -564,19 +1195,22 @@ impl Blockchain {
     /// Path 是 Rust 标准库中的一个 trait，用于表示文件路径。
     /// 
     /// ## 序列化过程
-    /// 1. 使用serde_json将区块链结构转换为格式化的JSON字符串
+    /// 1. 将区块链包装进带 `format_version` 的版本信封，转换为格式化的JSON字符串
     /// 2. 自动创建必要的目录结构
-    /// 3. 将JSON数据写入指定文件
+    /// 3. 轮换备份：当前文件保存为 `.1`，旧备份依次后移，最多保留 `BACKUP_COUNT` 个
+    /// 4. 先写临时文件并 fsync，再重命名为目标文件
     /// 
     /// ## 文件格式
     /// 保存的JSON文件包含：
+    /// - 格式版本号，用于将来升级文件格式（见 `migration` 模块）
     /// - 所有区块的完整信息（索引、时间戳、数据、哈希值等）
     /// - 区块链配置参数（难度、奖励等）
     /// - 待处理交易池的内容
     /// 
     /// ## 安全特性
     /// - **目录自动创建**：如果目标路径的目录不存在，会自动创建
-    /// - **原子写入**：写入操作要么完全成功，要么完全失败，不会产生部分写入的损坏文件
+    /// - **原子写入**：写入临时文件 + fsync + 重命名，崩溃时目标文件要么是旧内容要么是新内容
+    /// - **历史备份**：保留最近几次保存的结果，供 `recover_from_file` 回退使用
     /// - **错误处理**：文件系统错误和序列化错误都会被妥善处理
     /// 
     /// ## 参数
@@ -593,6 +1227,14 @@ impl Blockchain {
     /// blockchain.save_to_file(Path::new("/tmp/backup.json"))?;
     /// ```
     pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), BlockchainError> {
+        self.save_to_file_as(path, FileFormat::Json)
+    }
+
+    /// # 以指定格式保存区块链到文件
+    /// 
+    /// 与 `save_to_file` 相同的原子写入和备份策略，但可以选择JSON或紧凑的二进制格式。
+    /// 二进制格式见 `codec` 模块，加载时会根据文件开头的魔数自动识别。
+    pub fn save_to_file_as<P: AsRef<Path>>(&self, path: P, format: FileFormat) -> Result<(), BlockchainError> {
         // 确保目标路径的父目录存在
         // 这避免了因为目录不存在而导致的写入失败
         // path.as_ref()返回path的引用,Path::parent()返回path的父目录
@@ -601,13 +1243,18 @@ impl Blockchain {
             fs::create_dir_all(parent)?;
         }
         
-        // 将区块链结构序列化为格式化的JSON字符串
-        // serde_json::to_string_pretty 生成便于阅读的格式化JSON
-        let json = serde_json::to_string_pretty(self)?;
+        let bytes = match format {
+            // 将区块链包装进版本信封并序列化为格式化的JSON字符串
+            // serde_json::to_string_pretty 生成便于阅读的格式化JSON
+            FileFormat::Json => serde_json::to_string_pretty(&migration::Envelope::new(self))?.into_bytes(),
+            FileFormat::Binary => codec::encode_blockchain(self),
+        };
+        
+        // 覆盖之前先把当前文件保存为备份
+        storage::rotate_backups(path.as_ref(), storage::BACKUP_COUNT)?;
         
-        // 将JSON字符串写入文件
-        // 这是一个原子操作，要么完全成功，要么完全失败
-        fs::write(path, json)?;
+        // 写临时文件并重命名，保证目标文件不会只写了一半
+        storage::write_atomic(path.as_ref(), &bytes)?;
         Ok(())
     }
 
@@ -618,9 +1265,11 @@ impl Blockchain {
     /// 
     /// ## 加载过程
     /// 1. 读取JSON文件内容
-    /// 2. 使用serde_json反序列化为Blockchain结构
-    /// 3. 验证加载的区块链完整性
-    /// 4. 返回可用的区块链实例
+    /// 2. 识别格式版本，把旧版本的数据逐步迁移到当前版本
+    ///    （以 `SBCB` 魔数开头的文件按二进制格式解码）
+    /// 3. 反序列化为Blockchain结构
+    /// 4. 验证加载的区块链完整性
+    /// 5. 返回可用的区块链实例
     /// 
     /// ## 完整性验证
     /// 加载完成后会自动调用is_chain_valid()进行验证：
@@ -635,34 +1284,222 @@ impl Blockchain {
     /// 
     /// ## 参数
     /// * `path` - JSON文件的路径，支持任何实现AsRef<Path>的类型
+    /// * `spec` - 验证使用的链规范，加载后的区块链继续使用它
     /// 
     /// ## 返回值
     /// * `Ok(Blockchain)` - 成功加载并验证的区块链实例
     /// * `Err(BlockchainError::IoError)` - 文件读取失败（如文件不存在、权限不足等）
     /// * `Err(BlockchainError::SerializationError)` - JSON格式错误或数据类型不匹配
+    /// * `Err(BlockchainError::FormatError)` - 无法识别的格式或版本过新
     /// * `Err(BlockchainError::InvalidChain)` - 加载的区块链验证失败
     /// 
     /// ## 使用示例
     /// ```rust
-    /// let blockchain = Blockchain::load_from_file("data/saved_blockchain.json")?;
+    /// let blockchain = Blockchain::load_from_file("data/saved_blockchain.json", ChainSpec::default())?;
     /// println!("成功加载区块链，包含 {} 个区块", blockchain.chain.len());
     /// ```
-    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, BlockchainError> {
-        // 读取JSON文件的完整内容
-        let json = fs::read_to_string(path)?;
-        
-        // 将JSON字符串反序列化为Blockchain结构
-        let blockchain: Blockchain = serde_json::from_str(&json)?;
+    pub fn load_from_file<P: AsRef<Path>>(path: P, spec: ChainSpec) -> Result<Self, BlockchainError> {
+        let mut blockchain = Self::read_from_file(path)?;
+        blockchain.set_spec(spec);
         
         // 验证加载的区块链完整性
         // 这是安全的关键步骤，确保加载的数据是可信的
-        if !blockchain.is_chain_valid() {
-            return Err(BlockchainError::InvalidChain("加载的区块链无效".to_string()));
+        blockchain.validate_loaded()?;
+        
+        Ok(blockchain)
+    }
+
+    /// # 读取区块链文件但不验证
+    /// 
+    /// `load_from_file` 的解码部分。调用者需要自行验证，
+    /// 例如 `validate` 命令会用指定的线程数调用 `validate_parallel`。
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self, BlockchainError> {
+        // 读取文件的完整内容
+        let bytes = fs::read(path)?;
+        
+        match FileFormat::detect(&bytes) {
+            FileFormat::Binary => codec::decode_blockchain(&bytes),
+            // 先解析为通用JSON值，按版本迁移后再反序列化为Blockchain结构
+            FileFormat::Json => {
+                let mut blockchain = migration::decode(serde_json::from_slice(&bytes)?)?;
+                // 索引不会被序列化，反序列化后重新建立
+                blockchain.reindex();
+                Ok(blockchain)
+            }
+        }
+    }
+
+    /// 加载时的完整性验证：使用所有CPU核心，并把失败原因附在错误信息中
+    fn validate_loaded(&self) -> Result<(), BlockchainError> {
+        self.validate_parallel(Self::default_validation_threads())
+            .map_err(|e| BlockchainError::InvalidChain(format!("加载的区块链无效（{}）", e)))
+    }
+
+    /// # 从文件或其备份中恢复区块链
+    /// 
+    /// 与 `load_from_file` 不同，主文件无法加载时不会直接失败，
+    /// 而是按从新到旧的顺序尝试 `.1`、`.2`、... 备份文件，
+    /// 返回第一个能按 `spec` 通过完整性验证的区块链。
+    /// 
+    /// ## 恢复过程
+    /// 1. 尝试加载主文件，成功则直接返回
+    /// 2. 依次尝试每个备份文件
+    /// 3. 找到有效备份后，把损坏的主文件重命名为 `.corrupt` 保留现场，
+    ///    避免下一次保存时损坏的文件被轮换进备份中
+    /// 
+    /// 只有文件内容损坏（见 `is_damaged_file`）才会回退到备份。权限不足、打开的文件过多等
+    /// IO错误与文件内容无关，重试可能就会成功，这时直接返回错误，不移动主文件，
+    /// 也不会用更旧的备份代替内容完好的文件。
+    /// 
+    /// ## 返回值
+    /// * `Ok((Blockchain, None))` - 主文件有效
+    /// * `Ok((Blockchain, Some(path)))` - 主文件损坏，从 `path` 备份恢复
+    /// * `Err(BlockchainError::IoError)` - 读取主文件或备份时发生与内容无关的IO错误
+    /// * `Err(BlockchainError)` - 主文件和所有备份都损坏，返回主文件的错误
+    pub fn recover_from_file<P: AsRef<Path>>(
+        path: P,
+        spec: &ChainSpec,
+    ) -> Result<(Self, Option<PathBuf>), BlockchainError> {
+        let path = path.as_ref();
+        let error = match Self::load_from_file(path, spec.clone()) {
+            Ok(blockchain) => return Ok((blockchain, None)),
+            Err(e) if Self::is_damaged_file(&e) => e,
+            Err(e) => return Err(e),
+        };
+        
+        for n in 1..=storage::BACKUP_COUNT {
+            let backup = storage::backup_path(path, n);
+            match Self::load_from_file(&backup, spec.clone()) {
+                Ok(blockchain) => {
+                    if path.exists() {
+                        fs::rename(path, storage::corrupt_path(path))?;
+                    }
+                    return Ok((blockchain, Some(backup)));
+                }
+                Err(e) if Self::is_damaged_file(&e) => continue,
+                Err(e) => return Err(e),
+            }
         }
         
+        Err(error)
+    }
+
+    /// # 加载错误是否说明文件本身不可用
+    /// 
+    /// 解析、格式和验证错误说明文件内容损坏；IO错误中只有文件不存在和内容被截断属于这一类。
+    /// 其他IO错误（权限不足、打开的文件过多等）与文件内容无关。
+    fn is_damaged_file(error: &BlockchainError) -> bool {
+        match error {
+            BlockchainError::IoError(e) => matches!(
+                e.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::UnexpectedEof | io::ErrorKind::InvalidData
+            ),
+            _ => true,
+        }
+    }
+
+    /// # 由区块和元数据组装区块链
+    /// 
+    /// 存储后端分别保存区块和链级别参数，读取时用这个方法把两者重新组合。
+    /// 这里不做完整性验证，调用者需要自行调用 `is_chain_valid()`。
+    pub fn from_parts(chain: Vec<Block>, metadata: ChainMetadata) -> Self {
+        Blockchain {
+            index: ChainIndex::build(&chain),
+            chain,
+            difficulty: metadata.difficulty,
+            mining_reward: metadata.mining_reward,
+            pending_transactions: Mempool::from(metadata.pending_transactions),
+            spec: ChainSpec::default(),
+        }
+    }
+
+    /// # 获取链级别元数据
+    /// 
+    /// 返回区块之外需要持久化的参数（难度、奖励、待处理交易）。
+    pub fn metadata(&self) -> ChainMetadata {
+        ChainMetadata {
+            difficulty: self.difficulty,
+            mining_reward: self.mining_reward,
+            pending_transactions: self.pending_transactions.transactions().to_vec(),
+        }
+    }
+
+    /// # 保存区块链到存储后端
+    /// 
+    /// 与 `save_to_file` 每次重写整个文件不同，这里只写入存储中缺少的区块。
+    /// 
+    /// ## 保存过程
+    /// 1. 从链尾向前查找存储中已有的最高公共区块（按哈希比较）
+    /// 2. 截掉存储中公共区块之后的内容（链可能已被替换）
+    /// 3. 写入公共区块之后的所有区块
+    /// 4. 写入元数据并调用 `flush()` 持久化
+    /// 
+    /// ## 参数
+    /// * `store` - 任意实现了 `ChainStore` 的存储后端
+    pub fn save_to_store(&self, store: &mut dyn ChainStore) -> Result<(), BlockchainError> {
+        // 常见情况：存储的链尖就在当前链上，只需追加后续区块
+        let mut common = match store.tip()? {
+            Some(tip) if self.get_block(tip.index).is_some_and(|block| block.hash == tip.hash) => {
+                tip.index + 1
+            }
+            _ => 0,
+        };
+
+        // 否则从链尾向前查找存储中仍然存在的最高区块
+        if common == 0 {
+            for block in self.chain.iter().rev() {
+                if store.get_block_by_hash(&block.hash)?.is_some_and(|stored| stored.index == block.index) {
+                    common = block.index + 1;
+                    break;
+                }
+            }
+        }
+
+        store.truncate(common)?;
+        for block in &self.chain[common as usize..] {
+            store.put_block(block)?;
+        }
+        store.put_metadata(&self.metadata())?;
+        store.flush()
+    }
+
+    /// # 用当前区块链替换存储的全部内容
+    /// 
+    /// 裁剪区块数据后使用：区块哈希没有变化，`save_to_store` 不会重新写入这些区块。
+    /// 日志存储会借此重写整个文件，被裁剪的数据从磁盘上真正删除。
+    pub fn rewrite_store(&self, store: &mut dyn ChainStore) -> Result<(), BlockchainError> {
+        store.rewrite(&self.chain, &self.metadata())
+    }
+
+    /// # 从存储后端加载区块链
+    /// 
+    /// 读取存储中的所有区块和元数据，组装成区块链后按给定的链规范进行完整性验证，
+    /// 加载后的区块链继续使用这个链规范（自定义检查点、信任最后一个检查点之前的区块等）。
+    /// 
+    /// ## 返回值
+    /// * `Ok(Blockchain)` - 成功加载并验证的区块链实例
+    /// * `Err(BlockchainError::StorageError)` - 存储为空或数据损坏
+    /// * `Err(BlockchainError::InvalidChain)` - 加载的区块链验证失败
+    pub fn load_from_store(store: &dyn ChainStore, spec: ChainSpec) -> Result<Self, BlockchainError> {
+        let mut blockchain = Self::read_from_store(store)?;
+        blockchain.set_spec(spec);
+        blockchain.validate_loaded()?;
         Ok(blockchain)
     }
 
+    /// # 从存储后端读取区块链但不验证
+    /// 
+    /// `load_from_store` 的读取部分，调用者需要自行验证。
+    pub fn read_from_store(store: &dyn ChainStore) -> Result<Self, BlockchainError> {
+        if store.block_count() == 0 {
+            return Err(BlockchainError::StorageError(format!("{} 中没有区块", store.location())));
+        }
+
+        let chain = store.iter_range(0, store.block_count()).collect::<Result<Vec<_>, _>>()?;
+        let metadata = store.metadata()?.unwrap_or_default();
+        Ok(Self::from_parts(chain, metadata))
+    }
+
     /// # 显示完整区块链信息
     /// 
     /// 以用户友好的格式展示整个区块链的详细信息。
@@ -825,13 +1662,356 @@ mod tests {
         blockchain.save_to_file(temp_file.path()).unwrap();
         
         // 从文件加载区块链
-        let loaded_blockchain = Blockchain::load_from_file(temp_file.path()).unwrap();
+        let loaded_blockchain = Blockchain::load_from_file(temp_file.path(), ChainSpec::default()).unwrap();
         
         // 验证加载的区块链与原始区块链一致
         assert_eq!(blockchain.chain.len(), loaded_blockchain.chain.len());
         assert!(loaded_blockchain.is_chain_valid());
     }
 
+    /// # 测试加载已保存的区块链不取决于本机时钟
+    /// 
+    /// 时间戳超前本机时间太多的新区块被拒绝；但已经保存的区块链中有这样的区块时
+    /// （例如本机时钟往回调整之后），区块链仍然可以加载。
+    #[test]
+    fn test_load_ignores_local_clock() {
+        let mut blockchain = Blockchain::new();
+        let previous = blockchain.get_latest_block().hash.clone();
+        let mut future = Block::new(1, "未来的区块".to_string(), previous, blockchain.difficulty);
+        future.timestamp = Utc::now() + chrono::Duration::days(1);
+        future.mine_block_silent();
+        assert!(blockchain.clone().append_block(future.clone()).unwrap_err().to_string().contains("超前"));
+
+        blockchain.chain.push(future);
+        let temp_file = NamedTempFile::new().unwrap();
+        blockchain.save_to_file(temp_file.path()).unwrap();
+        let loaded = Blockchain::load_from_file(temp_file.path(), ChainSpec::default()).unwrap();
+        assert_eq!(loaded.chain, blockchain.chain);
+        assert!(loaded.is_chain_valid());
+    }
+
+    /// # 测试保存时的备份轮换
+    /// 
+    /// 连续保存多次后，最多保留 BACKUP_COUNT 个备份，
+    /// 且 `.1` 是上一次保存的内容。
+    #[test]
+    fn test_save_rotates_backups() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("chain.json");
+        let mut blockchain = Blockchain::new();
+        
+        for i in 0..5 {
+            blockchain.save_to_file(&path).unwrap();
+            blockchain.add_block(format!("备份测试 {}", i)).unwrap();
+        }
+        
+        for n in 1..=storage::BACKUP_COUNT {
+            let backup = Blockchain::load_from_file(storage::backup_path(&path, n), ChainSpec::default()).unwrap();
+            // 主文件有5个区块，.1 有4个，.2 有3个……
+            assert_eq!(backup.chain.len(), 5 - n);
+        }
+        assert!(!storage::backup_path(&path, storage::BACKUP_COUNT + 1).exists());
+        assert!(!storage::temp_path(&path).exists());
+    }
+
+    /// # 测试从被截断的文件中恢复
+    /// 
+    /// 模拟旧版本直接写入目标文件时崩溃：主文件只剩下一半内容。
+    /// 恢复过程应当回退到最新的有效备份，并把损坏的文件移到一边。
+    #[test]
+    fn test_recover_from_truncated_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("chain.json");
+        let mut blockchain = Blockchain::new();
+        blockchain.save_to_file(&path).unwrap();
+        blockchain.add_block("恢复测试".to_string()).unwrap();
+        blockchain.save_to_file(&path).unwrap();
+        
+        // 截断主文件，模拟写入中断
+        let json = fs::read(&path).unwrap();
+        fs::write(&path, &json[..json.len() / 2]).unwrap();
+        assert!(Blockchain::load_from_file(&path, ChainSpec::default()).is_err());
+        
+        let (recovered, source) = Blockchain::recover_from_file(&path, &ChainSpec::default()).unwrap();
+        assert_eq!(source, Some(storage::backup_path(&path, 1)));
+        assert_eq!(recovered.chain.len(), 1);
+        assert!(storage::corrupt_path(&path).exists());
+        
+        // 再次保存不应把损坏的文件轮换进备份
+        recovered.save_to_file(&path).unwrap();
+        assert!(Blockchain::load_from_file(storage::backup_path(&path, 1), ChainSpec::default()).is_ok());
+    }
+
+    /// # 测试写临时文件时中断不影响原文件
+    /// 
+    /// 模拟新的原子写入在重命名之前崩溃：磁盘上留下写了一半的临时文件。
+    /// 主文件应当保持完好，下一次保存会覆盖残留的临时文件。
+    #[test]
+    fn test_interrupted_temp_write_keeps_original() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("chain.json");
+        let mut blockchain = Blockchain::new();
+        blockchain.add_block("原始数据".to_string()).unwrap();
+        blockchain.save_to_file(&path).unwrap();
+        
+        fs::write(storage::temp_path(&path), "{\"chain\": [{\"index\"").unwrap();
+        
+        let (loaded, source) = Blockchain::recover_from_file(&path, &ChainSpec::default()).unwrap();
+        assert_eq!(source, None);
+        assert_eq!(loaded.chain.len(), 2);
+        
+        loaded.save_to_file(&path).unwrap();
+        assert!(!storage::temp_path(&path).exists());
+        assert!(Blockchain::load_from_file(&path, ChainSpec::default()).is_ok());
+    }
+
+    /// # 测试所有备份都不可用时恢复失败
+    #[test]
+    fn test_recover_without_valid_backup_fails() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("chain.json");
+        fs::write(&path, "不是JSON").unwrap();
+        fs::write(storage::backup_path(&path, 1), "也不是JSON").unwrap();
+        
+        assert!(Blockchain::recover_from_file(&path, &ChainSpec::default()).is_err());
+        // 没有恢复成功时不应移动原文件
+        assert!(path.exists());
+    }
+
+    /// # 测试与文件内容无关的IO错误直接返回，不回退到备份
+    /// 
+    /// 用目录代替主文件模拟无法读取（以 root 运行时权限位无法让读取失败）。
+    #[test]
+    fn test_recover_propagates_io_errors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("chain.json");
+        Blockchain::new().save_to_file(storage::backup_path(&path, 1)).unwrap();
+        fs::create_dir(&path).unwrap();
+        
+        let result = Blockchain::recover_from_file(&path, &ChainSpec::default());
+        assert!(matches!(result, Err(BlockchainError::IoError(_))));
+        assert!(path.is_dir() && !storage::corrupt_path(&path).exists());
+        
+        // 备份的IO错误同样直接返回，不会跳到更旧的备份
+        fs::remove_dir(&path).unwrap();
+        fs::write(&path, "不是JSON").unwrap();
+        fs::rename(storage::backup_path(&path, 1), storage::backup_path(&path, 2)).unwrap();
+        fs::create_dir(storage::backup_path(&path, 1)).unwrap();
+        let result = Blockchain::recover_from_file(&path, &ChainSpec::default());
+        assert!(matches!(result, Err(BlockchainError::IoError(_))));
+        assert!(path.is_file());
+    }
+
+    /// # 测试多线程验证与顺序验证结果一致
+    /// 
+    /// 分别篡改不同位置的区块，任意线程数下都应该失败，
+    /// 并且报告的是高度最低的无效区块。
+    #[test]
+    fn test_parallel_validation_matches_sequential() {
+        let mut blockchain = Blockchain::new();
+        blockchain.set_difficulty(1);
+        blockchain.batch_mine(9, "并行验证").unwrap();
+        
+        for threads in [0, 1, 2, 3, 16] {
+            assert!(blockchain.validate_parallel(threads).is_ok());
+        }
+        
+        let mut tampered = blockchain.clone();
+        tampered.chain[7].data = "篡改".to_string();
+        tampered.chain[3].nonce += 1;
+        assert!(!tampered.is_chain_valid());
+        for threads in [1, 2, 4, 16] {
+            let err = tampered.validate_parallel(threads).unwrap_err();
+            assert!(err.to_string().contains("#3"), "{} 线程: {}", threads, err);
+        }
+        
+        assert!(Blockchain::from_parts(Vec::new(), ChainMetadata::default()).validate_parallel(4).is_err());
+    }
+
+    /// # 测试裁剪后的区块链仍然有效
+    /// 
+    /// 裁剪必须从链的开头连续进行，在已裁剪区块之后仍能继续挖矿。
+    #[test]
+    fn test_pruned_chain_validates() {
+        let mut blockchain = Blockchain::new();
+        blockchain.set_difficulty(1);
+        blockchain.batch_mine(5, "裁剪").unwrap();
+        
+        assert_eq!(blockchain.prune_below(3).unwrap(), 3);
+        assert_eq!(blockchain.prune_below(4).unwrap(), 1);
+        assert_eq!(blockchain.pruned_count(), 4);
+        assert!(blockchain.prune_below(100).is_err());
+        assert!(blockchain.is_chain_valid());
+        assert!(blockchain.validate_parallel(3).is_ok());
+        
+        blockchain.add_block("裁剪后的新区块".to_string()).unwrap();
+        assert!(blockchain.is_chain_valid());
+        
+        // 已裁剪的区块前面不能有未裁剪的区块
+        let mut gap = blockchain.clone();
+        gap.chain[1].data_hash = None;
+        gap.chain[1].data = "恢复的数据".to_string();
+        assert!(!gap.is_chain_valid());
+    }
+
+    /// # 测试检查点和信任最后一个检查点之前的区块
+    ///
+    /// - 与检查点不符的链无法通过验证
+    /// - 开启 `assume_valid` 后，检查点及之前的区块只检查哈希链接，
+    ///   检查点之后的区块仍然完整验证
+    #[test]
+    fn test_checkpoints_and_assume_valid() {
+        let mut blockchain = Blockchain::new();
+        blockchain.set_difficulty(1);
+        blockchain.batch_mine(4, "检查点").unwrap();
+
+        let mut spec = ChainSpec::default();
+        spec.add_checkpoint(format!("2:{}", blockchain.chain[2].hash).parse().unwrap()).unwrap();
+        blockchain.set_spec(spec.clone());
+        assert!(blockchain.validate_parallel(2).is_ok());
+
+        let mut wrong = ChainSpec::default();
+        wrong.add_checkpoint("2:0000abc".parse().unwrap()).unwrap();
+        blockchain.set_spec(wrong);
+        assert!(!blockchain.is_chain_valid());
+
+        // 篡改检查点之前的区块数据：完整验证会发现，信任检查点时不再检查
+        blockchain.chain[1].data = "篡改的数据".to_string();
+        blockchain.set_spec(spec.clone());
+        assert!(!blockchain.is_chain_valid());
+        spec.assume_valid = true;
+        blockchain.set_spec(spec.clone());
+        assert!(blockchain.is_chain_valid());
+        assert!(blockchain.validate_parallel(2).is_ok());
+
+        // 检查点之后的区块仍然完整验证
+        blockchain.chain[3].data = "篡改的数据".to_string();
+        assert!(!blockchain.is_chain_valid());
+
+        // 创世区块与链规范不符
+        let mut other = Blockchain::new();
+        other.chain[0] = Block::new(0, "另一条链".to_string(), "0".to_string(), 1);
+        other.chain[0].mine_block_silent();
+        assert!(!other.is_chain_valid());
+    }
+
+    /// # 测试以累计工作量更大的链替换本地链
+    /// 
+    /// 从同一个区块分叉的两条链：较短的链被较长（工作量更大）的链替换，
+    /// 被回滚区块的交易回到交易池；更轻的链、无效的链和创世区块不同的链都不会被采用。
+    #[test]
+    fn test_consider_chain() {
+        let mut local = Blockchain::new();
+        local.set_difficulty(1);
+        local.add_block_silent("共同区块".to_string()).unwrap();
+        let mut remote = local.clone();
+        local.add_block_silent("本地交易A".to_string()).unwrap();
+        local.add_block_silent("两边都有的交易".to_string()).unwrap();
+        remote.add_block_silent("两边都有的交易".to_string()).unwrap();
+        remote.add_block_silent("远端交易B".to_string()).unwrap();
+        remote.add_block_silent("远端交易C".to_string()).unwrap();
+        local.pending_transactions.insert("远端交易B".to_string());
+
+        // 更轻的链（本地链的前缀）不会被采用
+        let lighter = local.chain[..2].to_vec();
+        assert!(local.clone().consider_chain(lighter).unwrap().is_none());
+
+        // 分叉点之后有无效区块的链被拒绝，本地链不变
+        let mut invalid = remote.chain.clone();
+        invalid[3].data = "篡改".to_string();
+        assert!(local.clone().consider_chain(invalid).is_err());
+
+        // 创世区块不同的链被拒绝
+        let mut other = Blockchain::new();
+        other.chain[0].data = "另一个创世区块".to_string();
+        other.chain[0].hash = other.chain[0].calculate_hash();
+        assert!(matches!(local.clone().consider_chain(other.chain), Err(BlockchainError::InvalidChain(_))));
+
+        // 分叉点之后的区块被裁剪（哈希无法重新计算）的链被拒绝，单独追加也不行
+        let mut pruned = remote.chain.clone();
+        pruned[2].prune();
+        assert!(local.clone().consider_chain(pruned.clone()).unwrap_err().to_string().contains("已裁剪"));
+        let mut common = Blockchain::from_parts(remote.chain[..2].to_vec(), ChainMetadata::default());
+        assert!(common.append_block(pruned[2].clone()).unwrap_err().to_string().contains("已裁剪"));
+
+        // 难度大到工作量计算会溢出的候选链不会引起 panic，在验证时被拒绝
+        let mut huge = remote.chain.clone();
+        huge[2].difficulty = u32::MAX;
+        assert!(matches!(local.clone().consider_chain(huge), Err(BlockchainError::InvalidBlock(_))));
+
+        let replacement = local.consider_chain(remote.chain.clone()).unwrap().unwrap();
+        assert_eq!(replacement.fork_height, 1);
+        assert_eq!(replacement.rolled_back.len(), 2);
+        assert_eq!(replacement.adopted.len(), 3);
+        assert_eq!(local.chain, remote.chain);
+        assert_eq!(local.total_work(), remote.total_work());
+        assert!(local.is_chain_valid());
+        assert!(local.get_block_by_hash(&replacement.rolled_back[0].hash).is_none());
+
+        // 只有本地独有的交易回到交易池，已被新链包含的交易移出交易池
+        assert_eq!(local.restore_pending_transactions(&replacement), 1);
+        assert_eq!(local.pending_transactions.transactions(), ["本地交易A".to_string()]);
+    }
+
+    /// # 测试拒绝不按时间戳调整难度的链
+    /// 
+    /// 第一个调整周期出块很快，下一个区块的难度必须升高。对方的链在这里降低了难度，
+    /// 再多挖几个低难度区块让累计工作量超过本地链：它既不能通过验证，也不能替换本地链。
+    #[test]
+    fn test_rejects_chain_lowering_difficulty() {
+        use crate::chainspec::RetargetSpec;
+        let spec = ChainSpec {
+            retarget: Some(RetargetSpec { interval: 3, target_block_secs: 600 }),
+            ..ChainSpec::default()
+        };
+        let mut local = Blockchain::with_spec(spec.clone());
+        for i in 1..=3 {
+            local.add_block_silent(format!("快速区块 #{}", i)).unwrap();
+        }
+        assert_eq!(spec.next_difficulty(&local.chain, local.difficulty).unwrap(), spec.initial_difficulty + 1);
+
+        let mut forged = local.clone();
+        for i in 4..=6 {
+            let tip = forged.get_latest_block();
+            let mut block = Block::new(i, format!("降低难度 #{}", i), tip.hash.clone(), spec.initial_difficulty - 1);
+            block.mine_block_silent();
+            forged.chain.push(block);
+        }
+        assert!(Blockchain::work_of(&forged.chain) > local.total_work());
+        assert!(!forged.is_chain_valid());
+        assert!(forged.validate_parallel(2).is_err());
+        assert!(local.clone().append_block(forged.chain[4].clone()).is_err());
+        assert!(local.consider_chain(forged.chain.clone()).is_err());
+        assert_eq!(local.chain.len(), 4);
+    }
+
+    /// # 测试提交交易：检查、去重，区块追加后移出交易池
+    #[test]
+    fn test_submit_transaction() {
+        let mut blockchain = Blockchain::with_spec(crate::network::Network::Testnet.spec());
+        blockchain.set_difficulty(1);
+        assert!(blockchain.submit_transaction("交易A".to_string()).unwrap());
+        assert!(!blockchain.submit_transaction("交易A".to_string()).unwrap());
+        assert!(blockchain.submit_transaction("交易B".to_string()).unwrap());
+        assert!(matches!(blockchain.submit_transaction("  ".to_string()), Err(BlockchainError::InvalidTransaction(_))));
+        // 测试网的区块数据最多 64 KiB
+        assert!(blockchain.submit_transaction("x".repeat(64 * 1024 + 1)).is_err());
+
+        let (data, count) = blockchain.next_block_data(Some("矿工数据".to_string())).unwrap();
+        assert_eq!(count, 2);
+        blockchain.add_block_silent(data).unwrap();
+        assert!(blockchain.pending_transactions.is_empty());
+
+        // 已上链的交易被拒绝
+        assert!(blockchain.submit_transaction("交易B".to_string()).is_err());
+        assert_eq!(blockchain.transaction_height("矿工数据"), Some(1));
+        // 直接修改 chain 后索引失效，遍历整条链也能找到
+        let copy = blockchain.chain[1].clone();
+        blockchain.chain.push(copy);
+        assert_eq!(blockchain.transaction_height("交易A"), Some(1));
+        assert_eq!(blockchain.transaction_height("交易C"), None);
+    }
+
     /// # 测试难度设置功能
     /// 
     /// 验证难度设置的边界检查：
@@ -910,8 +2090,8 @@ mod tests {
         
         // 验证基础统计信息
         assert_eq!(stats.total_blocks, 3);  // 创世区块 + 2个新区块
-        assert_eq!(stats.current_difficulty, Blockchain::DEFAULT_DIFFICULTY);
-        assert_eq!(stats.mining_reward, Blockchain::DEFAULT_MINING_REWARD);
+        assert_eq!(stats.current_difficulty, ChainSpec::default().initial_difficulty);
+        assert_eq!(stats.mining_reward, ChainSpec::default().reward_at(1));
         
         // 验证计算的统计信息
         assert!(stats.total_size > 0);  // 总大小应该大于0
@@ -0,0 +1,251 @@
+// ==================== 依赖库导入 ====================
+// 导入区块链错误类型
+use crate::blockchain::BlockchainError;
+// 导入数据目录锁，复制和删除时防止其他进程正在写入
+use crate::lock::{self, DataDirLock, LockMode};
+// std::fs: 创建、复制和删除链目录
+use std::fs;
+// std::path: 路径处理
+use std::path::{Path, PathBuf};
+// std::time: 等待锁的超时时间
+use std::time::Duration;
+
+/// 默认区块链的名称。默认区块链直接使用网络的数据目录，与引入命名区块链之前相同
+pub const DEFAULT_CHAIN: &str = "default";
+
+/// 网络数据目录下存放命名区块链的子目录
+pub const CHAINS_DIR_NAME: &str = "chains";
+
+/// 链名称的最大长度
+const MAX_NAME_LEN: usize = 64;
+
+// ==================== 命名区块链 ====================
+//
+// 同一个网络下可以有多条互相独立的区块链，例如团队里不同的实验：
+//
+// ```text
+// data/                      默认区块链（mainnet）
+// ├── blockchain.json
+// └── chains/
+//     ├── experiment-a/      命名区块链，各自有存储文件和 chainspec.json
+//     └── experiment-b/
+// ```
+//
+// 每条命名区块链都是一个完整的数据目录，所有命令（包括文件锁）都可以直接作用于它。
+
+/// # 检查链名称
+///
+/// 名称会成为目录名，因此只允许字母、数字、`-` 和 `_`，
+/// 避免路径穿越（`..`、`/`）和不同文件系统上的大小写、特殊字符问题。
+pub fn check_name(name: &str) -> Result<(), BlockchainError> {
+    let valid_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    if name.is_empty() || name.len() > MAX_NAME_LEN || !name.chars().all(valid_char) {
+        return Err(BlockchainError::StorageError(format!(
+            "无效的区块链名称 '{}'：只能包含字母、数字、- 和 _，最长 {} 个字符",
+            name, MAX_NAME_LEN
+        )));
+    }
+    Ok(())
+}
+
+/// # 区块链的数据目录
+///
+/// 默认区块链就是网络的数据目录本身，其他区块链位于 `chains/<名称>`。
+pub fn chain_dir(network_dir: &Path, name: &str) -> PathBuf {
+    if name == DEFAULT_CHAIN {
+        network_dir.to_path_buf()
+    } else {
+        network_dir.join(CHAINS_DIR_NAME).join(name)
+    }
+}
+
+/// 区块链是否存在。默认区块链总是存在（数据目录会在第一次使用时创建）
+pub fn exists(network_dir: &Path, name: &str) -> bool {
+    name == DEFAULT_CHAIN || chain_dir(network_dir, name).is_dir()
+}
+
+/// # 查找已有的区块链
+///
+/// ## 返回值
+/// 区块链的数据目录；名称不合法或区块链不存在时返回错误
+pub fn resolve(network_dir: &Path, name: &str) -> Result<PathBuf, BlockchainError> {
+    check_name(name)?;
+    if !exists(network_dir, name) {
+        return Err(BlockchainError::StorageError(format!(
+            "区块链 {} 不存在，可以使用 chains create {} 创建",
+            name, name
+        )));
+    }
+    Ok(chain_dir(network_dir, name))
+}
+
+/// # 列出网络下的所有区块链
+///
+/// 默认区块链排在最前面，其余按名称排序。名称不合法的目录（例如手动创建的）会被忽略。
+pub fn list(network_dir: &Path) -> Result<Vec<String>, BlockchainError> {
+    let mut names = Vec::new();
+    let chains_dir = network_dir.join(CHAINS_DIR_NAME);
+    if chains_dir.is_dir() {
+        for entry in fs::read_dir(&chains_dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            if let Some(name) = entry.file_name().to_str()
+                && check_name(name).is_ok()
+                && name != DEFAULT_CHAIN
+            {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names.sort();
+    names.insert(0, DEFAULT_CHAIN.to_string());
+    Ok(names)
+}
+
+/// # 创建空的命名区块链目录
+///
+/// 只创建目录，创世区块和链规范由调用者按 `init` 的流程写入。
+///
+/// ## 返回值
+/// 新区块链的数据目录；名称不合法或已存在时返回错误
+pub fn create(network_dir: &Path, name: &str) -> Result<PathBuf, BlockchainError> {
+    check_new_name(network_dir, name)?;
+    let dir = chain_dir(network_dir, name);
+    fs::create_dir_all(network_dir.join(CHAINS_DIR_NAME))?;
+    // create_dir 在目录已存在时失败，两个进程同时创建同名区块链时只有一个会成功
+    fs::create_dir(&dir).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => already_exists(name),
+        _ => e.into(),
+    })?;
+    Ok(dir)
+}
+
+/// # 复制区块链
+///
+/// 在源区块链的共享锁保护下，把它数据目录中的文件（存储文件、备份、链规范等）
+/// 复制到一个临时目录，完成后再重命名为目标区块链。复制中途失败不会留下不完整的区块链。
+/// 子目录（例如默认区块链下的 `chains/`）和锁文件不会被复制。
+///
+/// ## 返回值
+/// 复制的字节数
+pub fn copy(network_dir: &Path, from: &str, to: &str, lock_timeout: Duration) -> Result<u64, BlockchainError> {
+    let source = resolve(network_dir, from)?;
+    check_new_name(network_dir, to)?;
+
+    let _lock = DataDirLock::acquire(&source, LockMode::Shared, lock_timeout)?;
+
+    let chains_dir = network_dir.join(CHAINS_DIR_NAME);
+    fs::create_dir_all(&chains_dir)?;
+    let staging = chains_dir.join(format!(".{}.tmp", to));
+    if staging.exists() {
+        // 上次复制中断留下的临时目录
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir(&staging)?;
+
+    let result = copy_files(&source, &staging).and_then(|bytes| {
+        let target = chain_dir(network_dir, to);
+        if target.exists() {
+            return Err(already_exists(to));
+        }
+        fs::rename(&staging, &target)?;
+        Ok(bytes)
+    });
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    result
+}
+
+/// # 删除命名区块链
+///
+/// 先获取独占锁，确认没有其他进程正在使用，再删除整个数据目录。
+/// 默认区块链不能删除。
+pub fn delete(network_dir: &Path, name: &str, lock_timeout: Duration) -> Result<(), BlockchainError> {
+    if name == DEFAULT_CHAIN {
+        return Err(BlockchainError::StorageError("不能删除默认区块链".to_string()));
+    }
+
+    let dir = resolve(network_dir, name)?;
+    let _lock = DataDirLock::acquire(&dir, LockMode::Exclusive, lock_timeout)?;
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+/// 检查新区块链的名称：合法、不是默认区块链、尚不存在
+fn check_new_name(network_dir: &Path, name: &str) -> Result<(), BlockchainError> {
+    check_name(name)?;
+    if name == DEFAULT_CHAIN || chain_dir(network_dir, name).exists() {
+        return Err(already_exists(name));
+    }
+    Ok(())
+}
+
+/// 复制目录中的普通文件（不含锁文件），返回复制的字节数
+fn copy_files(source: &Path, target: &Path) -> Result<u64, BlockchainError> {
+    let mut bytes = 0;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() || entry.file_name() == lock::LOCK_FILE_NAME {
+            continue;
+        }
+        bytes += fs::copy(entry.path(), target.join(entry.file_name()))?;
+    }
+    Ok(bytes)
+}
+
+fn already_exists(name: &str) -> BlockchainError {
+    BlockchainError::StorageError(format!("区块链 {} 已存在", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// # 测试链名称的检查
+    #[test]
+    fn test_check_name() {
+        assert!(check_name("experiment-a_1").is_ok());
+        for name in ["", "..", "a/b", "实验", "a b", &"x".repeat(MAX_NAME_LEN + 1)] {
+            assert!(check_name(name).is_err(), "{:?} 应当不合法", name);
+        }
+    }
+
+    /// # 测试创建、复制、列出和删除区块链
+    #[test]
+    fn test_create_copy_list_delete() {
+        let dir = TempDir::new().unwrap();
+        let network_dir = dir.path();
+        assert_eq!(list(network_dir).unwrap(), vec![DEFAULT_CHAIN]);
+        assert_eq!(chain_dir(network_dir, DEFAULT_CHAIN), network_dir);
+
+        let created = create(network_dir, "alpha").unwrap();
+        fs::write(created.join("blockchain.json"), "{}").unwrap();
+        assert!(create(network_dir, "alpha").is_err());
+        assert!(create(network_dir, DEFAULT_CHAIN).is_err());
+
+        // 复制时跳过锁文件和子目录
+        fs::write(network_dir.join("blockchain.json"), "[]").unwrap();
+        assert_eq!(copy(network_dir, DEFAULT_CHAIN, "beta", Duration::ZERO).unwrap(), 2);
+        let beta = chain_dir(network_dir, "beta");
+        assert_eq!(fs::read_to_string(beta.join("blockchain.json")).unwrap(), "[]");
+        assert!(!beta.join(CHAINS_DIR_NAME).exists());
+        assert!(!beta.join(lock::LOCK_FILE_NAME).exists());
+        assert!(copy(network_dir, "alpha", "beta", Duration::ZERO).is_err());
+        assert!(copy(network_dir, "missing", "gamma", Duration::ZERO).is_err());
+
+        assert_eq!(list(network_dir).unwrap(), vec![DEFAULT_CHAIN, "alpha", "beta"]);
+
+        // 正在使用的区块链不能删除
+        let busy = DataDirLock::acquire(&created, LockMode::Shared, Duration::ZERO).unwrap();
+        assert!(matches!(delete(network_dir, "alpha", Duration::ZERO), Err(BlockchainError::LockError(_))));
+        drop(busy);
+        delete(network_dir, "alpha", Duration::ZERO).unwrap();
+        assert!(!exists(network_dir, "alpha"));
+        assert!(delete(network_dir, DEFAULT_CHAIN, Duration::ZERO).is_err());
+        assert_eq!(list(network_dir).unwrap(), vec![DEFAULT_CHAIN, "beta"]);
+    }
+}
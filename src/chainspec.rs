@@ -0,0 +1,641 @@
+// ==================== 依赖库导入 ====================
+// 导入区块结构体和错误类型
+use crate::block::Block;
+use crate::blockchain::BlockchainError;
+// 导入原子写入工具
+use crate::storage;
+// chrono: 创世区块时间戳
+use chrono::{DateTime, Utc};
+// serde: 链规范文件的序列化
+use serde::{Deserialize, Serialize};
+// std::fs: 读取链规范文件
+use std::fs;
+// std::path: 路径处理
+use std::path::Path;
+// std::str::FromStr: 从命令行字符串解析检查点
+use std::str::FromStr;
+
+/// 数据目录中记录当前链规范的文件名（由 `init` 写入，或为旧版本创建的区块链自动写入）
+pub const SPEC_FILE_NAME: &str = "chainspec.json";
+
+/// 新区块的时间戳最多比本机时间超前的秒数，超过的区块被拒绝（见 `ChainSpec::check_not_future`）
+pub const MAX_FUTURE_BLOCK_SECS: i64 = 2 * 60 * 60;
+
+/// 内置链规范的链ID
+const BUILTIN_CHAIN_ID: &str = "simplied-blockchain";
+
+/// 内置创世区块的信息
+const BUILTIN_GENESIS_MESSAGE: &str = "创世区块 - Genesis Block";
+
+/// 内置创世区块的固定时间戳：2024-01-01 00:00:00 UTC
+const BUILTIN_GENESIS_TIMESTAMP: i64 = 1_704_067_200;
+
+/// # 内置检查点
+///
+/// 内置链规范随程序发布的 (高度, 哈希) 列表。默认的链是在本地从创世区块开始挖出的，
+/// 除创世区块外没有公认的历史，因此目前为空；其他链可以通过 `--checkpoint` 添加。
+const BUILTIN_CHECKPOINTS: &[(u64, &str)] = &[];
+
+/// # 检查点 (Checkpoint)
+///
+/// 可信的 (高度, 哈希) 对：该高度的区块必须具有这个哈希。
+/// 由于每个区块都通过 previous_hash 指向前一个区块，
+/// 固定一个区块的哈希就固定了它之前的整条区块哈希链。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Checkpoint {
+    pub height: u64,
+    pub hash: String,
+}
+
+impl FromStr for Checkpoint {
+    type Err = String;
+
+    /// 解析 `<高度>:<哈希>` 格式的检查点
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("无效的检查点 '{}'，格式应为 <高度>:<哈希>", s);
+        let (height, hash) = s.split_once(':').ok_or_else(invalid)?;
+        let height = height.trim().parse().map_err(|_| invalid())?;
+        let hash = hash.trim().to_lowercase();
+        if hash.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Checkpoint { height, hash })
+    }
+}
+
+/// # 创世区块参数 (GenesisSpec)
+///
+/// 创世区块的全部内容。同样的参数总是得到同样的创世区块和哈希。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GenesisSpec {
+    /// 创世区块的数据
+    pub message: String,
+    /// 创世区块的时间戳
+    pub timestamp: DateTime<Utc>,
+    /// 创世区块记录的难度（创世区块不需要挖矿）
+    pub difficulty: u32,
+}
+
+/// # 难度调整参数 (RetargetSpec)
+///
+/// 从高度1开始，每 `interval` 个区块为一个调整周期。挖出下一个周期的第一个区块前，
+/// 比较上一个周期的实际耗时与 `target_block_secs × (interval - 1)`：
+/// 快于目标的一半时难度加1，慢于目标的两倍时难度减1，结果限制在 `limits` 的范围内。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetargetSpec {
+    /// 调整周期的区块数，至少为2
+    pub interval: u64,
+    /// 目标出块时间（秒）
+    pub target_block_secs: u64,
+}
+
+impl RetargetSpec {
+    /// 该高度的区块是否是一个新调整周期的第一个区块
+    pub fn is_boundary(&self, height: u64) -> bool {
+        height > 1 && (height - 1).is_multiple_of(self.interval)
+    }
+}
+
+/// # 挖矿奖励计划 (RewardSchedule)
+///
+/// 高度为 h 的区块的奖励为 `initial`，每经过 `halving_interval` 个区块减半。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RewardSchedule {
+    /// 初始奖励
+    pub initial: u64,
+    /// 减半间隔（区块数），为 `None` 时奖励不变
+    #[serde(default)]
+    pub halving_interval: Option<u64>,
+}
+
+impl RewardSchedule {
+    /// 高度为 `height` 的区块的挖矿奖励
+    pub fn at(&self, height: u64) -> u64 {
+        match self.halving_interval {
+            Some(interval) => self.initial.checked_shr((height / interval) as u32).unwrap_or(0),
+            None => self.initial,
+        }
+    }
+}
+
+/// # 共识限制 (Limits)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Limits {
+    /// 允许的最低难度
+    pub min_difficulty: u32,
+    /// 允许的最高难度
+    pub max_difficulty: u32,
+    /// 单个区块数据的最大字节数，为 `None` 时不限制
+    #[serde(default)]
+    pub max_data_bytes: Option<usize>,
+}
+
+/// # 链规范 (ChainSpec)
+///
+/// 描述一条链"应该是什么样子"：创世区块、共识参数，以及验证时用来拒绝看似有效、
+/// 实际上是另一条链的数据（例如从其他地方复制来的区块链文件）的可信信息。
+/// 可以从JSON文件读取（`init --spec <文件>`）。
+///
+/// ## 字段说明
+/// - `chain_id`: 链的名称，用于区分不同的链
+/// - `embed_chain_id`: 是否把 `chain_id` 写入每个区块（参与哈希计算），
+///   开启后其他链的区块在这条链上无效；内置链规范为了与旧区块兼容没有开启
+/// - `genesis`: 创世区块的内容，创世区块的哈希由它决定
+/// - `initial_difficulty`: 创世区块之后第一个区块的挖矿难度
+/// - `retarget`: 难度自动调整参数，为 `None` 时难度由用户设置
+/// - `reward`: 挖矿奖励计划
+/// - `limits`: 难度范围和区块数据大小限制
+/// - `checkpoints`: 按高度排序的检查点，链上对应高度的区块哈希必须一致
+/// - `assume_valid`: 是否信任最后一个检查点及之前的区块（本地策略，不写入文件，需要用户主动开启）
+///
+/// ## 信任最后一个检查点之前的区块
+/// 完整验证的主要开销是为每个区块重新计算哈希和检查工作量证明。
+/// 开启 `assume_valid` 后，高度不超过最后一个检查点的区块只检查哈希链接和索引连续性，
+/// 由检查点保证这段哈希链就是可信的那一条；这些区块的数据是否被篡改则不再检查。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChainSpec {
+    pub chain_id: String,
+    #[serde(default)]
+    pub embed_chain_id: bool,
+    pub genesis: GenesisSpec,
+    pub initial_difficulty: u32,
+    #[serde(default)]
+    pub retarget: Option<RetargetSpec>,
+    pub reward: RewardSchedule,
+    pub limits: Limits,
+    #[serde(default)]
+    pub checkpoints: Vec<Checkpoint>,
+    #[serde(skip)]
+    pub assume_valid: bool,
+}
+
+impl Default for ChainSpec {
+    /// 内置链规范：固定的创世区块、难度2、奖励100、不自动调整难度，不信任任何区块
+    fn default() -> Self {
+        ChainSpec {
+            chain_id: BUILTIN_CHAIN_ID.to_string(),
+            embed_chain_id: false,
+            genesis: GenesisSpec {
+                message: BUILTIN_GENESIS_MESSAGE.to_string(),
+                timestamp: DateTime::from_timestamp(BUILTIN_GENESIS_TIMESTAMP, 0).expect("创世时间戳有效"),
+                difficulty: 1,
+            },
+            initial_difficulty: 2,
+            retarget: None,
+            reward: RewardSchedule {
+                initial: 100,
+                halving_interval: None,
+            },
+            limits: Limits {
+                min_difficulty: 1,
+                max_difficulty: 10,
+                max_data_bytes: None,
+            },
+            checkpoints: BUILTIN_CHECKPOINTS
+                .iter()
+                .map(|&(height, hash)| Checkpoint { height, hash: hash.to_string() })
+                .collect(),
+            assume_valid: false,
+        }
+    }
+}
+
+impl ChainSpec {
+    /// # 从JSON文本解析链规范
+    ///
+    /// 解析后检查参数是否合理，并把检查点按高度排序。
+    pub fn from_json(json: &str) -> Result<Self, BlockchainError> {
+        let mut spec: ChainSpec = serde_json::from_str(json)?;
+        let checkpoints = std::mem::take(&mut spec.checkpoints);
+        for mut checkpoint in checkpoints {
+            checkpoint.hash = checkpoint.hash.to_lowercase();
+            spec.add_checkpoint(checkpoint).map_err(BlockchainError::FormatError)?;
+        }
+        spec.check_params()?;
+        Ok(spec)
+    }
+
+    /// 读取链规范文件
+    pub fn load(path: &Path) -> Result<Self, BlockchainError> {
+        Self::from_json(&fs::read_to_string(path)?).map_err(|e| {
+            let reason = match e {
+                BlockchainError::FormatError(reason) => reason,
+                other => other.to_string(),
+            };
+            BlockchainError::FormatError(format!("链规范文件 {} 无效: {}", path.display(), reason))
+        })
+    }
+
+    /// 转换为格式化的JSON文本
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("链规范总是可以序列化")
+    }
+
+    /// 原子写入链规范文件
+    pub fn save(&self, path: &Path) -> Result<(), BlockchainError> {
+        storage::write_atomic(path, self.to_json().as_bytes())?;
+        Ok(())
+    }
+
+    /// # 检查参数是否合理
+    ///
+    /// 难度范围有效，创世难度和初始难度都在范围内，调整周期至少为2，减半间隔不为0。
+    fn check_params(&self) -> Result<(), BlockchainError> {
+        let limits = &self.limits;
+        let reason = if self.chain_id.trim().is_empty() {
+            "chain_id 不能为空".to_string()
+        } else if limits.min_difficulty == 0 || limits.min_difficulty > limits.max_difficulty {
+            format!("难度范围 {}-{} 无效", limits.min_difficulty, limits.max_difficulty)
+        } else if !self.difficulty_in_range(self.initial_difficulty) || !self.difficulty_in_range(self.genesis.difficulty) {
+            "创世难度和初始难度必须在难度范围内".to_string()
+        } else if self.retarget.as_ref().is_some_and(|r| r.interval < 2 || r.target_block_secs == 0) {
+            "调整周期至少为2个区块，目标出块时间必须大于0".to_string()
+        } else if self.retarget.as_ref().is_some_and(|r| {
+            r.target_block_secs
+                .checked_mul(r.interval - 1)
+                .is_none_or(|secs| secs > i64::MAX as u64)
+        }) {
+            "调整周期的目标耗时（目标出块时间 × (调整周期 - 1)）超出范围".to_string()
+        } else if self.reward.halving_interval == Some(0) {
+            "减半间隔必须大于0".to_string()
+        } else {
+            return Ok(());
+        };
+        Err(BlockchainError::FormatError(reason))
+    }
+
+    /// 难度是否在 `limits` 允许的范围内
+    pub fn difficulty_in_range(&self, difficulty: u32) -> bool {
+        (self.limits.min_difficulty..=self.limits.max_difficulty).contains(&difficulty)
+    }
+
+    /// 按链规范创建创世区块
+    pub fn genesis_block(&self) -> Block {
+        Block::genesis(self.genesis.message.clone(), self.genesis.timestamp, self.genesis.difficulty)
+            .with_chain_id(self.block_chain_id())
+    }
+
+    /// 这条链的区块应当携带的链ID，没有开启 `embed_chain_id` 时为 `None`
+    pub fn block_chain_id(&self) -> Option<String> {
+        self.embed_chain_id.then(|| self.chain_id.clone())
+    }
+
+    /// 创世区块的哈希
+    pub fn genesis_hash(&self) -> String {
+        self.genesis_block().hash
+    }
+
+    /// # 为旧版本创建的区块链推导链规范
+    ///
+    /// 引入链规范之前的版本用创建链时的当前时间作为创世区块的时间戳，其他内容与本链规范的创世区块相同。
+    /// `genesis` 与本链规范的创世区块只有时间戳不同时，返回以它的时间戳为创世时间、其余参数不变的链规范；
+    /// 创世区块本来就相同，或者属于另一条链时返回 None。
+    pub fn for_legacy_genesis(&self, genesis: &Block) -> Option<ChainSpec> {
+        if genesis.index != 0 || genesis.hash == self.genesis_hash() {
+            return None;
+        }
+        let mut spec = self.clone();
+        spec.genesis.timestamp = genesis.timestamp;
+        (spec.genesis_hash() == genesis.hash).then_some(spec)
+    }
+
+    /// 高度为 `height` 的区块的挖矿奖励
+    pub fn reward_at(&self, height: u64) -> u64 {
+        self.reward.at(height)
+    }
+
+    /// # 计算下一个区块的难度
+    ///
+    /// 没有开启难度调整时沿用 `current`（用户设置的难度）。开启时：
+    /// 高度1使用 `initial_difficulty`，调整周期的第一个区块按上一个周期的耗时调整，
+    /// 其余区块沿用前一个区块的难度。
+    ///
+    /// ## 参数
+    /// * `chain` - 当前的区块链，下一个区块接在它的最后一个区块后面
+    /// * `current` - 当前设置的难度
+    ///
+    /// ## 返回值
+    /// * `Ok(u32)` - 下一个区块的难度
+    /// * `Err(BlockchainError::InvalidChain)` - 需要调整难度而 `chain` 既不从创世区块开始，也没有包含整个调整周期
+    pub fn next_difficulty(&self, chain: &[Block], current: u32) -> Result<u32, BlockchainError> {
+        self.expected_difficulty(chain, current).ok_or_else(|| {
+            BlockchainError::InvalidChain(format!("计算下一个区块的难度需要前 {} 个区块", self.difficulty_window()))
+        })
+    }
+
+    /// # 检查共识时需要的前序区块数
+    ///
+    /// 验证一个区块需要它之前连续的这么多个区块（不足时需要从创世区块开始）：
+    /// 开启难度调整时是一个调整周期，用来重新计算难度；否则只需要前一个区块。
+    pub fn difficulty_window(&self) -> usize {
+        self.retarget.as_ref().map_or(1, |retarget| retarget.interval as usize)
+    }
+
+    /// # 按前序区块计算下一个区块应有的难度
+    ///
+    /// 与 `next_difficulty` 相同，`recent` 可以只是最近的连续若干个区块。
+    /// 需要调整难度而 `recent` 没有包含整个调整周期时返回 None。
+    fn expected_difficulty(&self, recent: &[Block], current: u32) -> Option<u32> {
+        let Some(retarget) = &self.retarget else {
+            return Some(current);
+        };
+        let Some(previous) = recent.last().filter(|previous| previous.index > 0) else {
+            return Some(self.initial_difficulty);
+        };
+        let height = previous.index + 1;
+        if !retarget.is_boundary(height) {
+            return Some(previous.difficulty);
+        }
+
+        // 上一个周期的第一个区块，高度为 height - interval
+        let first = &recent[recent.len().checked_sub(retarget.interval as usize)?];
+        let actual = (previous.timestamp - first.timestamp).num_seconds();
+        // 参数已由 `check_params` 检查，这里仍然不让手工构造的链规范溢出
+        let expected = retarget
+            .target_block_secs
+            .saturating_mul(retarget.interval.saturating_sub(1))
+            .min(i64::MAX as u64) as i64;
+        let next = if actual < expected / 2 {
+            previous.difficulty.saturating_add(1)
+        } else if actual > expected.saturating_mul(2) {
+            previous.difficulty.saturating_sub(1)
+        } else {
+            previous.difficulty
+        };
+        Some(next.clamp(self.limits.min_difficulty, self.limits.max_difficulty))
+    }
+
+    /// # 添加检查点
+    ///
+    /// 保持检查点按高度排序；同一高度已有检查点时，哈希必须相同。
+    pub fn add_checkpoint(&mut self, checkpoint: Checkpoint) -> Result<(), String> {
+        match self.checkpoints.binary_search_by_key(&checkpoint.height, |c| c.height) {
+            Ok(position) if self.checkpoints[position].hash == checkpoint.hash => Ok(()),
+            Ok(_) => Err(format!("高度 {} 已有不同哈希的检查点", checkpoint.height)),
+            Err(position) => {
+                self.checkpoints.insert(position, checkpoint);
+                Ok(())
+            }
+        }
+    }
+
+    /// 最后（最高）一个检查点
+    pub fn last_checkpoint(&self) -> Option<&Checkpoint> {
+        self.checkpoints.last()
+    }
+
+    /// 指定高度的检查点
+    pub fn checkpoint_at(&self, height: u64) -> Option<&Checkpoint> {
+        self.checkpoints
+            .binary_search_by_key(&height, |c| c.height)
+            .ok()
+            .map(|position| &self.checkpoints[position])
+    }
+
+    /// # 验证时是否可以跳过该高度区块的哈希重算和工作量证明检查
+    ///
+    /// 仅在开启 `assume_valid` 且高度不超过最后一个检查点时成立。
+    pub fn skips_work_check(&self, height: u64) -> bool {
+        self.assume_valid && self.last_checkpoint().is_some_and(|checkpoint| height <= checkpoint.height)
+    }
+
+    /// # 检查区块是否符合链规范
+    ///
+    /// 创世区块的哈希必须与链规范的创世区块一致，检查点高度上的区块哈希必须等于检查点的哈希。
+    /// 只比较哈希，不涉及区块内容，因此对已裁剪的区块同样适用。
+    pub fn check_block(&self, block: &Block) -> Result<(), BlockchainError> {
+        if block.index == 0 {
+            let genesis_hash = self.genesis_hash();
+            if block.hash != genesis_hash {
+                return Err(BlockchainError::InvalidBlock(format!(
+                    "创世区块的哈希 {} 与链规范 {} 不符（应为 {}），这不是同一条链",
+                    block.hash, self.chain_id, genesis_hash
+                )));
+            }
+        }
+        if let Some(checkpoint) = self.checkpoint_at(block.index)
+            && block.hash != checkpoint.hash
+        {
+            return Err(BlockchainError::InvalidBlock(format!(
+                "区块 #{}: 哈希 {} 与检查点不符（应为 {}）",
+                block.index, block.hash, checkpoint.hash
+            )));
+        }
+        Ok(())
+    }
+
+    /// # 检查区块是否满足共识参数
+    ///
+    /// ## 检查项目
+    /// 1. 区块携带的链ID与 `block_chain_id` 一致
+    /// 2. 区块数据不超过 `max_data_bytes`（已裁剪的区块跳过）
+    /// 3. 难度在 `limits` 的范围内
+    /// 4. 开启难度调整时：难度与按前序区块重新计算的 `next_difficulty` 完全相同
+    /// 5. 时间戳（按秒）不早于前一个区块
+    ///
+    /// 检查结果只取决于区块本身，不取决于本机时钟，重新验证已保存的区块链时结果总是相同。
+    /// 第4项依赖整个调整周期的时间戳；新收到的区块还要用 `check_not_future` 限制时间戳的上限，
+    /// 对方无法任意设置时间戳来操纵难度调整。
+    ///
+    /// ## 参数
+    /// * `recent` - 区块之前连续的区块，最后一个是前一个区块；至少包含 `difficulty_window` 个，
+    ///   或者从创世区块开始
+    /// * `current` - 要检查的区块
+    pub fn check_consensus(&self, recent: &[Block], current: &Block) -> Result<(), BlockchainError> {
+        let previous = recent.last().expect("至少包含前一个区块");
+        let reason = if current.chain_id != self.block_chain_id() {
+            format!(
+                "区块属于链 {}，不属于链 {}",
+                current.chain_id.as_deref().unwrap_or("（无链ID）"),
+                self.chain_id
+            )
+        } else if let Some(max) = self.limits.max_data_bytes
+            && current.data.len() > max
+        {
+            format!("数据大小 {} 字节超过上限 {} 字节", current.data.len(), max)
+        } else if !self.difficulty_in_range(current.difficulty) {
+            format!(
+                "难度 {} 超出允许范围 {}-{}",
+                current.difficulty, self.limits.min_difficulty, self.limits.max_difficulty
+            )
+        } else if let Some(expected) = self.check_retarget(recent, current)? {
+            format!("难度 {} 不符合难度调整规则（应为 {}）", current.difficulty, expected)
+        } else if current.timestamp.timestamp() < previous.timestamp.timestamp() {
+            format!("时间戳 {} 早于前一个区块的 {}", current.timestamp, previous.timestamp)
+        } else {
+            return Ok(());
+        };
+        Err(BlockchainError::InvalidBlock(format!("区块 #{}: {}", current.index, reason)))
+    }
+
+    /// # 检查新区块的时间戳没有超前本机时间太多
+    ///
+    /// 只用于新收到或新提交的区块（追加的区块、分叉链上的新区块和同步的区块头）。
+    /// 结果取决于本机时钟，因此重新验证已保存的区块链时不检查：
+    /// 本机时钟往回调整后，之前接受的区块仍然可以加载。
+    ///
+    /// ## 返回值
+    /// * `Ok(())` - 时间戳不晚于本机时间 `MAX_FUTURE_BLOCK_SECS` 之后
+    /// * `Err(BlockchainError::InvalidBlock)` - 时间戳超前本机时间太多
+    pub fn check_not_future(block: &Block) -> Result<(), BlockchainError> {
+        if block.timestamp.timestamp() > Utc::now().timestamp() + MAX_FUTURE_BLOCK_SECS {
+            return Err(BlockchainError::InvalidBlock(format!(
+                "区块 #{}: 时间戳 {} 超前本机时间超过 {} 秒",
+                block.index, block.timestamp, MAX_FUTURE_BLOCK_SECS
+            )));
+        }
+        Ok(())
+    }
+
+    /// 开启难度调整时检查区块的难度，不相符时返回应有的难度
+    fn check_retarget(&self, recent: &[Block], current: &Block) -> Result<Option<u32>, BlockchainError> {
+        if self.retarget.is_none() {
+            return Ok(None);
+        }
+        match self.expected_difficulty(recent, current.difficulty) {
+            Some(expected) => Ok((expected != current.difficulty).then_some(expected)),
+            None => Err(BlockchainError::InvalidBlock(format!(
+                "区块 #{}: 缺少计算难度所需的前 {} 个区块",
+                current.index,
+                self.difficulty_window()
+            ))),
+        }
+    }
+}
+
+// ==================== 单元测试 ====================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// # 测试内置创世区块与之前的版本一致
+    #[test]
+    fn test_builtin_genesis() {
+        let spec = ChainSpec::default();
+        let genesis = spec.genesis_block();
+        assert_eq!(genesis.hash, "d36bef52c7e940311bbc201130e09423368dac34a4ad91d5d28274af6bacb4e9");
+        assert_eq!(spec.genesis_block(), genesis);
+        assert!(spec.check_block(&genesis).is_ok());
+
+        let mut other = Block::new(0, "另一条链".to_string(), "0".to_string(), 1);
+        other.mine_block_silent();
+        assert!(spec.check_block(&other).is_err());
+    }
+
+    /// # 测试为旧版本的创世区块（创建时的当前时间）推导链规范
+    #[test]
+    fn test_legacy_genesis() {
+        let spec = ChainSpec::default();
+        let created = Utc::now() - chrono::Duration::days(400);
+        let legacy = Block::genesis(BUILTIN_GENESIS_MESSAGE.to_string(), created, 1);
+        let adopted = spec.for_legacy_genesis(&legacy).unwrap();
+        assert_eq!(adopted.genesis_hash(), legacy.hash);
+        assert!(adopted.check_block(&legacy).is_ok());
+        assert_eq!(ChainSpec { genesis: spec.genesis.clone(), ..adopted }, spec);
+
+        // 创世区块相同，或者内容不同（另一条链）时不推导
+        assert!(spec.for_legacy_genesis(&spec.genesis_block()).is_none());
+        let other = Block::genesis("另一条链".to_string(), created, 1);
+        assert!(spec.for_legacy_genesis(&other).is_none());
+    }
+
+    #[test]
+    fn test_checkpoints() {
+        let mut spec = ChainSpec::default();
+        spec.add_checkpoint("10:ABC".parse().unwrap()).unwrap();
+        spec.add_checkpoint("5:def".parse().unwrap()).unwrap();
+        assert!(spec.add_checkpoint("5:def".parse().unwrap()).is_ok());
+        assert!(spec.add_checkpoint("5:123".parse().unwrap()).is_err());
+        assert!("abc".parse::<Checkpoint>().is_err());
+        assert!("x:abc".parse::<Checkpoint>().is_err());
+
+        assert_eq!(spec.last_checkpoint().map(|c| c.height), Some(10));
+        assert_eq!(spec.checkpoint_at(10).map(|c| c.hash.as_str()), Some("abc"));
+        assert!(!spec.skips_work_check(3));
+        spec.assume_valid = true;
+        assert!(spec.skips_work_check(10));
+        assert!(!spec.skips_work_check(11));
+    }
+
+    /// # 测试链规范文件的读写和参数检查
+    #[test]
+    fn test_spec_json() {
+        let mut spec = ChainSpec {
+            chain_id: "测试链".to_string(),
+            ..ChainSpec::default()
+        };
+        spec.genesis.message = "测试链的创世区块".to_string();
+        spec.add_checkpoint("3:abc".parse().unwrap()).unwrap();
+        spec.assume_valid = true;
+
+        let parsed = ChainSpec::from_json(&spec.to_json()).unwrap();
+        assert_eq!(parsed.checkpoints, spec.checkpoints);
+        assert!(!parsed.assume_valid, "assume_valid 是本地策略，不写入文件");
+        assert_ne!(parsed.genesis_hash(), ChainSpec::default().genesis_hash());
+
+        let invalid = spec.to_json().replace("\"initial_difficulty\": 2", "\"initial_difficulty\": 20");
+        assert!(ChainSpec::from_json(&invalid).is_err());
+        let unknown = spec.to_json().replacen('{', "{\"difficulty\": 3,", 1);
+        assert!(ChainSpec::from_json(&unknown).is_err());
+
+        // 调整周期的目标耗时溢出时拒绝加载
+        let mut overflow = spec.clone();
+        overflow.retarget = Some(RetargetSpec { interval: u64::MAX, target_block_secs: 60 });
+        assert!(ChainSpec::from_json(&overflow.to_json()).unwrap_err().to_string().contains("超出范围"));
+    }
+
+    /// # 测试奖励减半和难度调整
+    #[test]
+    fn test_reward_and_retarget() {
+        let reward = RewardSchedule { initial: 100, halving_interval: Some(10) };
+        assert_eq!(reward.at(9), 100);
+        assert_eq!(reward.at(10), 50);
+        assert_eq!(reward.at(25), 25);
+        assert_eq!(reward.at(10 * 64), 0);
+
+        let spec = ChainSpec {
+            retarget: Some(RetargetSpec { interval: 3, target_block_secs: 60 }),
+            ..ChainSpec::default()
+        };
+        let mut chain = vec![spec.genesis_block()];
+        assert_eq!(spec.next_difficulty(&chain, 5).unwrap(), 2);
+        // 高度1-3是第一个周期，每个区块间隔1秒，远快于目标
+        for height in 1..=3 {
+            let mut block = Block::new(height, String::new(), chain.last().unwrap().hash.clone(), 2);
+            block.timestamp = spec.genesis.timestamp + chrono::Duration::seconds(height as i64);
+            chain.push(block);
+        }
+        assert_eq!(spec.next_difficulty(&chain[..3], 5).unwrap(), 2);
+        assert_eq!(spec.next_difficulty(&chain, 5).unwrap(), 3);
+        assert!(spec.next_difficulty(&chain[2..], 5).is_err(), "缺少整个调整周期时返回错误而不是崩溃");
+
+        let mut next = Block::new(4, String::new(), chain[3].hash.clone(), 3);
+        assert!(spec.check_consensus(&chain, &next).is_ok());
+        // 调整周期的第一个区块必须按周期的耗时调整：出块很快时不能保持或降低难度
+        for difficulty in [1, 2, 4] {
+            next.difficulty = difficulty;
+            assert!(spec.check_consensus(&chain, &next).is_err());
+        }
+        next.difficulty = 3;
+        assert!(spec.check_consensus(&chain[2..], &next).unwrap_err().to_string().contains("缺少"));
+        assert!(spec.check_consensus(&chain[..2], &chain[2]).is_ok());
+        chain[2].difficulty = 3;
+        assert!(spec.check_consensus(&chain[..2], &chain[2]).is_err());
+
+        // 时间戳不能早于前一个区块；超前本机时间的上限只对新区块检查，不影响共识检查
+        next.timestamp = chain[3].timestamp - chrono::Duration::seconds(1);
+        assert!(spec.check_consensus(&chain, &next).unwrap_err().to_string().contains("早于"));
+        next.timestamp = Utc::now() + chrono::Duration::seconds(MAX_FUTURE_BLOCK_SECS + 60);
+        assert!(spec.check_consensus(&chain, &next).is_ok());
+        assert!(ChainSpec::check_not_future(&next).unwrap_err().to_string().contains("超前"));
+        next.timestamp = Utc::now();
+        assert!(ChainSpec::check_not_future(&next).is_ok());
+    }
+}
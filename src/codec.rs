@@ -0,0 +1,437 @@
+// ==================== 依赖库导入 ====================
+// 导入区块、区块链以及元数据类型
+use crate::block::Block;
+use crate::blockchain::{Blockchain, BlockchainError};
+use crate::storage::ChainMetadata;
+// chrono: 时间戳与 (秒, 纳秒) 之间的转换
+use chrono::DateTime;
+// std::io: 基于 Read/Write 的编解码，便于流式处理
+use std::io::{self, Read, Write};
+// std::path: 根据扩展名推断文件格式
+use std::path::Path;
+// std::str::FromStr: 从命令行字符串解析文件格式
+use std::str::FromStr;
+
+/// 二进制区块链文件的魔数，用于和JSON文件区分
+pub const MAGIC: &[u8; 4] = b"SBCB";
+
+/// # 当前的二进制格式版本
+///
+/// ## 版本历史
+/// - 版本1：最初的格式
+/// - 版本2：区块数据之后增加可选的数据哈希（用于已裁剪的区块）
+/// - 版本3：数据哈希之后增加可选的链ID（区分不同网络的区块）
+///
+/// 读取时兼容所有旧版本，写入时总是使用当前版本。
+pub const BINARY_FORMAT_VERSION: u8 = 3;
+
+/// 可选字段标记：字段不存在
+const ABSENT: u8 = 0;
+/// 可选字段标记：字段存在，后面紧跟字段内容
+const PRESENT: u8 = 1;
+
+/// 哈希编码标记：32字节原始哈希
+const HASH_RAW: u8 = 0;
+/// 哈希编码标记：任意字符串（如创世区块的 "0"）
+const HASH_TEXT: u8 = 1;
+
+/// # 区块链文件格式 (FileFormat)
+///
+/// - `Json`: 格式化的JSON，便于阅读和调试
+/// - `Binary`: 紧凑的二进制编码，体积更小、加载更快
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Json,
+    Binary,
+}
+
+impl FileFormat {
+    /// 根据文件开头的魔数识别格式
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(MAGIC) {
+            FileFormat::Binary
+        } else {
+            FileFormat::Json
+        }
+    }
+
+    /// 根据扩展名推断格式：`.bin` 为二进制，其他为JSON
+    pub fn from_extension(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("bin") => FileFormat::Binary,
+            _ => FileFormat::Json,
+        }
+    }
+}
+
+impl FromStr for FileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(FileFormat::Json),
+            "binary" | "bin" => Ok(FileFormat::Binary),
+            other => Err(format!("未知的文件格式 '{}'，可选: json, binary", other)),
+        }
+    }
+}
+
+// ==================== 基础类型编码 ====================
+
+/// # 写入变长整数 (LEB128)
+///
+/// 每个字节的低7位存放数据，最高位表示后面是否还有字节。
+/// 小数字只占1个字节，`u64::MAX` 最多占10个字节。
+pub fn write_varint<W: Write>(w: &mut W, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return w.write_all(&[byte]);
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+/// 读取变长整数
+pub fn read_varint<R: Read>(r: &mut R) -> Result<u64, BlockchainError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_u8(r)?;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(BlockchainError::FormatError("变长整数过长".to_string()))
+}
+
+fn read_u8<R: Read>(r: &mut R) -> Result<u8, BlockchainError> {
+    let mut buf = [0u8; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_array<R: Read, const N: usize>(r: &mut R) -> Result<[u8; N], BlockchainError> {
+    let mut buf = [0u8; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// 写入带长度前缀的UTF-8字符串
+fn write_string<W: Write>(w: &mut W, s: &str) -> io::Result<()> {
+    write_varint(w, s.len() as u64)?;
+    w.write_all(s.as_bytes())
+}
+
+/// 读取带长度前缀的UTF-8字符串
+fn read_string<R: Read>(r: &mut R) -> Result<String, BlockchainError> {
+    let len = read_varint(r)? as usize;
+    let mut buf = Vec::new();
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    String::from_utf8(buf).map_err(|_| BlockchainError::FormatError("字符串不是有效的UTF-8".to_string()))
+}
+
+/// # 写入哈希值
+///
+/// 标准的64位小写十六进制哈希以32字节原始形式存储，比文本节省一半空间；
+/// 其他内容（例如创世区块的 `previous_hash = "0"`）按原样存储为字符串，
+/// 保证解码后与原始值完全一致。
+fn write_hash<W: Write>(w: &mut W, hash: &str) -> io::Result<()> {
+    match hex_to_bytes(hash) {
+        Some(bytes) => {
+            w.write_all(&[HASH_RAW])?;
+            w.write_all(&bytes)
+        }
+        None => {
+            w.write_all(&[HASH_TEXT])?;
+            write_string(w, hash)
+        }
+    }
+}
+
+/// 读取哈希值
+fn read_hash<R: Read>(r: &mut R) -> Result<String, BlockchainError> {
+    match read_u8(r)? {
+        HASH_RAW => {
+            let bytes: [u8; 32] = read_array(r)?;
+            Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+        }
+        HASH_TEXT => read_string(r),
+        tag => Err(BlockchainError::FormatError(format!("未知的哈希编码标记 {}", tag))),
+    }
+}
+
+/// 把64位小写十六进制字符串转换为32字节，格式不符时返回None
+fn hex_to_bytes(hex: &str) -> Option<[u8; 32]> {
+    let is_lower_hex = |c: u8| c.is_ascii_digit() || (b'a'..=b'f').contains(&c);
+    if hex.len() != 64 || !hex.bytes().all(is_lower_hex) {
+        return None;
+    }
+
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+// ==================== 区块编码 ====================
+
+/// # 编码单个区块
+///
+/// ## 字段布局
+/// | 字段 | 编码 |
+/// |------|------|
+/// | index | 变长整数 |
+/// | timestamp | 秒数 i64 LE（8字节）+ 纳秒 u32 LE（4字节） |
+/// | data | 变长长度 + UTF-8 |
+/// | data_hash | 标记字节，存在时后跟哈希编码（版本2起） |
+/// | chain_id | 标记字节，存在时后跟字符串（版本3起） |
+/// | previous_hash | 哈希编码 |
+/// | hash | 哈希编码 |
+/// | nonce | 变长整数 |
+/// | difficulty | 变长整数 |
+pub fn write_block<W: Write>(w: &mut W, block: &Block) -> io::Result<()> {
+    write_varint(w, block.index)?;
+    w.write_all(&block.timestamp.timestamp().to_le_bytes())?;
+    w.write_all(&block.timestamp.timestamp_subsec_nanos().to_le_bytes())?;
+    write_string(w, &block.data)?;
+    match &block.data_hash {
+        Some(data_hash) => {
+            w.write_all(&[PRESENT])?;
+            write_hash(w, data_hash)?;
+        }
+        None => w.write_all(&[ABSENT])?,
+    }
+    match &block.chain_id {
+        Some(chain_id) => {
+            w.write_all(&[PRESENT])?;
+            write_string(w, chain_id)?;
+        }
+        None => w.write_all(&[ABSENT])?,
+    }
+    write_hash(w, &block.previous_hash)?;
+    write_hash(w, &block.hash)?;
+    write_varint(w, block.nonce)?;
+    write_varint(w, block.difficulty as u64)
+}
+
+/// 读取以标记字节开头的可选字段
+fn read_optional<R: Read, T>(
+    r: &mut R,
+    read_value: fn(&mut R) -> Result<T, BlockchainError>,
+) -> Result<Option<T>, BlockchainError> {
+    match read_u8(r)? {
+        ABSENT => Ok(None),
+        PRESENT => Ok(Some(read_value(r)?)),
+        tag => Err(BlockchainError::FormatError(format!("未知的可选字段标记 {}", tag))),
+    }
+}
+
+/// # 解码单个区块
+///
+/// `version` 是文件头中的格式版本，决定区块包含哪些字段。
+pub fn read_block<R: Read>(r: &mut R, version: u8) -> Result<Block, BlockchainError> {
+    let index = read_varint(r)?;
+    let seconds = i64::from_le_bytes(read_array(r)?);
+    let nanos = u32::from_le_bytes(read_array(r)?);
+    let timestamp = DateTime::from_timestamp(seconds, nanos)
+        .ok_or_else(|| BlockchainError::FormatError(format!("无效的时间戳 {}.{}", seconds, nanos)))?;
+    let data = read_string(r)?;
+    let data_hash = match version {
+        1 => None,
+        _ => read_optional(r, read_hash)?,
+    };
+    let chain_id = match version {
+        1 | 2 => None,
+        _ => read_optional(r, read_string)?,
+    };
+
+    Ok(Block {
+        index,
+        timestamp,
+        data,
+        data_hash,
+        chain_id,
+        previous_hash: read_hash(r)?,
+        hash: read_hash(r)?,
+        nonce: read_varint(r)?,
+        difficulty: u32::try_from(read_varint(r)?)
+            .map_err(|_| BlockchainError::FormatError("难度值超出范围".to_string()))?,
+    })
+}
+
+// ==================== 区块链编码 ====================
+
+/// # 写入文件头和元数据
+///
+/// ```text
+/// [魔数 "SBCB"][格式版本 u8][难度][奖励][待处理交易数 + 交易字符串...][区块数]
+/// ```
+/// 之后紧跟 `block_count` 个区块。把区块数放在头部，
+/// 读取方可以在不读完整个文件的情况下知道链的长度。
+pub fn write_header<W: Write>(w: &mut W, metadata: &ChainMetadata, block_count: u64) -> io::Result<()> {
+    w.write_all(MAGIC)?;
+    w.write_all(&[BINARY_FORMAT_VERSION])?;
+    write_varint(w, metadata.difficulty as u64)?;
+    write_varint(w, metadata.mining_reward)?;
+    write_varint(w, metadata.pending_transactions.len() as u64)?;
+    for transaction in &metadata.pending_transactions {
+        write_string(w, transaction)?;
+    }
+    write_varint(w, block_count)
+}
+
+/// # 二进制文件头 (Header)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    /// 文件的格式版本，解码区块时需要
+    pub version: u8,
+    /// 链元数据
+    pub metadata: ChainMetadata,
+    /// 文件中的区块数
+    pub block_count: u64,
+}
+
+/// 读取并校验文件头
+pub fn read_header<R: Read>(r: &mut R) -> Result<Header, BlockchainError> {
+    let magic: [u8; 4] = read_array(r)?;
+    if &magic != MAGIC {
+        return Err(BlockchainError::FormatError("不是二进制区块链文件".to_string()));
+    }
+    let version = read_u8(r)?;
+    if version == 0 || version > BINARY_FORMAT_VERSION {
+        return Err(BlockchainError::FormatError(format!(
+            "不支持的二进制格式版本 {}（当前支持 1 到 {}）",
+            version, BINARY_FORMAT_VERSION
+        )));
+    }
+
+    let difficulty = u32::try_from(read_varint(r)?)
+        .map_err(|_| BlockchainError::FormatError("难度值超出范围".to_string()))?;
+    let mining_reward = read_varint(r)?;
+    let pending_count = read_varint(r)?;
+    let pending_transactions = (0..pending_count).map(|_| read_string(r)).collect::<Result<_, _>>()?;
+    let block_count = read_varint(r)?;
+
+    Ok(Header {
+        version,
+        metadata: ChainMetadata { difficulty, mining_reward, pending_transactions },
+        block_count,
+    })
+}
+
+/// 把整个区块链编码为二进制
+pub fn encode_blockchain(blockchain: &Blockchain) -> Vec<u8> {
+    let mut buf = Vec::new();
+    // 写入 Vec<u8> 不会失败
+    write_header(&mut buf, &blockchain.metadata(), blockchain.chain.len() as u64).expect("写入内存缓冲区");
+    for block in &blockchain.chain {
+        write_block(&mut buf, block).expect("写入内存缓冲区");
+    }
+    buf
+}
+
+/// 从二进制数据解码区块链（不做完整性验证）
+pub fn decode_blockchain(mut bytes: &[u8]) -> Result<Blockchain, BlockchainError> {
+    let header = read_header(&mut bytes)?;
+    let chain = (0..header.block_count)
+        .map(|_| read_block(&mut bytes, header.version))
+        .collect::<Result<Vec<_>, _>>()?;
+    if !bytes.is_empty() {
+        return Err(BlockchainError::FormatError(format!("文件末尾有 {} 字节多余数据", bytes.len())));
+    }
+    Ok(Blockchain::from_parts(chain, header.metadata))
+}
+
+// ==================== 单元测试 ====================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::chain_of;
+
+    /// 包含非ASCII和空数据的区块，以及一条待处理交易
+    fn chain_with_pending() -> Blockchain {
+        let mut blockchain = chain_of(&["二进制编码测试 🦀", ""]);
+        blockchain.pending_transactions.insert("待处理交易".to_string());
+        blockchain
+    }
+
+    #[test]
+    fn test_varint_roundtrip() {
+        for value in [0, 1, 127, 128, 300, 16_384, u32::MAX as u64, u64::MAX] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, value).unwrap();
+            assert_eq!(read_varint(&mut buf.as_slice()).unwrap(), value);
+        }
+
+        let mut buf = Vec::new();
+        write_varint(&mut buf, 127).unwrap();
+        assert_eq!(buf.len(), 1);
+    }
+
+    /// # 测试哈希的两种编码方式都能还原
+    #[test]
+    fn test_hash_encoding() {
+        let raw = "00ab".repeat(16);
+        for hash in [raw.as_str(), "0", "00AB", ""] {
+            let mut buf = Vec::new();
+            write_hash(&mut buf, hash).unwrap();
+            assert_eq!(read_hash(&mut buf.as_slice()).unwrap(), hash);
+        }
+
+        // 标准哈希只占 1 + 32 字节
+        let mut buf = Vec::new();
+        write_hash(&mut buf, &raw).unwrap();
+        assert_eq!(buf.len(), 33);
+    }
+
+    /// # 测试二进制与JSON往返得到完全相同的区块链
+    #[test]
+    fn test_roundtrip_matches_json() {
+        let blockchain = chain_with_pending();
+
+        let from_json: Blockchain =
+            serde_json::from_str(&serde_json::to_string(&blockchain).unwrap()).unwrap();
+        let binary = encode_blockchain(&blockchain);
+        let from_binary = decode_blockchain(&binary).unwrap();
+
+        assert_eq!(from_binary.chain, blockchain.chain);
+        assert_eq!(from_binary.chain, from_json.chain);
+        assert_eq!(from_binary.metadata(), from_json.metadata());
+        assert!(from_binary.is_chain_valid());
+
+        // 二进制编码应该明显小于格式化JSON
+        let json = serde_json::to_string_pretty(&blockchain).unwrap();
+        assert!(binary.len() * 2 < json.len());
+    }
+
+    /// # 测试损坏的二进制数据会报错而不是panic
+    #[test]
+    fn test_reject_corrupt_input() {
+        let binary = encode_blockchain(&chain_with_pending());
+
+        assert!(decode_blockchain(&binary[..binary.len() - 5]).is_err());
+        assert!(decode_blockchain(b"SBCB\x09").is_err());
+        assert!(decode_blockchain(b"{}").is_err());
+
+        let mut trailing = binary.clone();
+        trailing.push(0);
+        assert!(decode_blockchain(&trailing).is_err());
+    }
+
+    #[test]
+    fn test_file_format_detection() {
+        let blockchain = chain_with_pending();
+        assert_eq!(FileFormat::detect(&encode_blockchain(&blockchain)), FileFormat::Binary);
+        assert_eq!(FileFormat::detect(b"{\"format_version\": 1}"), FileFormat::Json);
+        assert_eq!(FileFormat::from_extension(Path::new("chain.bin")), FileFormat::Binary);
+        assert_eq!(FileFormat::from_extension(Path::new("chain.json")), FileFormat::Json);
+        assert_eq!("binary".parse::<FileFormat>().unwrap(), FileFormat::Binary);
+    }
+}
@@ -0,0 +1,370 @@
+// ==================== 依赖库导入 ====================
+// 导入节点事件，订阅者收到的事件由它转换而来
+use crate::node::NodeEvent;
+// chrono: 事件发生的时间
+use chrono::{DateTime, Utc};
+// serde: 事件序列化为 JSON
+use serde::Serialize;
+// serde_json: 事件的内容
+use serde_json::{json, Value};
+// std::collections: 订阅的主题集合
+use std::collections::HashSet;
+// std::sync: 订阅者列表和向订阅者发送事件的通道
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Mutex, PoisonError};
+// std::time: 挖矿进度的推送间隔
+use std::time::{Duration, Instant};
+
+/// 每个订阅者最多积压的事件数，处理不过来的订阅者被断开
+const SUBSCRIBER_BUFFER: usize = 1024;
+
+/// `validation` 主题下可以单独订阅的事件类型
+const VALIDATION_KINDS: [&str; 2] = ["block_rejected", "validation_failed"];
+
+// ==================== 主题 ====================
+
+/// # 事件主题 (Topic)
+///
+/// 订阅时按主题过滤事件。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Topic {
+    /// 新区块：本地挖出或从其他节点接受
+    Block,
+    /// 本地挖矿的进度：已尝试的哈希次数和哈希率
+    Mining,
+    /// 挖矿难度改变
+    Difficulty,
+    /// 验证失败：收到的区块被拒绝，或整条链的验证没有通过
+    Validation,
+    /// 切换到了工作量更大的分叉链
+    Reorg,
+}
+
+impl Topic {
+    /// 所有主题，没有指定主题时订阅全部
+    pub const ALL: [Topic; 5] = [Topic::Block, Topic::Mining, Topic::Difficulty, Topic::Validation, Topic::Reorg];
+
+    /// 主题名，与 JSON 中的写法相同
+    pub fn name(self) -> &'static str {
+        match self {
+            Topic::Block => "block",
+            Topic::Mining => "mining",
+            Topic::Difficulty => "difficulty",
+            Topic::Validation => "validation",
+            Topic::Reorg => "reorg",
+        }
+    }
+
+    /// # 解析逗号分隔的主题列表
+    ///
+    /// 例如 `block,reorg`。空字符串表示所有主题。
+    ///
+    /// ## 返回值
+    /// 主题集合；有不认识的主题时返回错误说明
+    pub fn parse_list(list: &str) -> Result<HashSet<Topic>, String> {
+        if list.trim().is_empty() {
+            return Ok(Topic::ALL.into_iter().collect());
+        }
+        list.split(',')
+            .map(str::trim)
+            .map(|name| {
+                Topic::ALL.into_iter().find(|topic| topic.name() == name).ok_or_else(|| {
+                    let names: Vec<&str> = Topic::ALL.iter().map(|topic| topic.name()).collect();
+                    format!("未知的主题 {}，可用的主题: {}", name, names.join(", "))
+                })
+            })
+            .collect()
+    }
+}
+
+// ==================== 事件 ====================
+
+/// # 推送给订阅者的事件
+///
+/// 序列化为 `{"topic", "type", "time", "data"}`，`type` 区分同一主题下的不同事件，
+/// 例如 `validation` 主题下的 `block_rejected` 和 `validation_failed`。
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Event {
+    pub topic: Topic,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub time: DateTime<Utc>,
+    pub data: Value,
+}
+
+impl Event {
+    /// # 由节点事件生成
+    ///
+    /// 只有属于某个主题的节点事件才会推送，连接、握手、交易等事件返回 None。
+    pub fn from_node_event(event: &NodeEvent) -> Option<Event> {
+        let (topic, kind, data) = match event {
+            NodeEvent::BlockAccepted { height, hash, from } => (
+                Topic::Block,
+                "block_accepted",
+                json!({ "height": height, "hash": hash, "from": from.map(|addr| addr.to_string()) }),
+            ),
+            NodeEvent::MiningProgress { height, hashes, hash_rate } => (
+                Topic::Mining,
+                "mining_progress",
+                json!({ "height": height, "hashes": hashes, "hash_rate": hash_rate }),
+            ),
+            NodeEvent::DifficultyChanged { from, to } => {
+                (Topic::Difficulty, "difficulty_changed", json!({ "from": from, "to": to }))
+            }
+            NodeEvent::BlockRejected { height, from, reason } => (
+                Topic::Validation,
+                "block_rejected",
+                json!({ "height": height, "from": from.to_string(), "reason": reason }),
+            ),
+            NodeEvent::ValidationFailed { height, reason } => (
+                Topic::Validation,
+                "validation_failed",
+                json!({ "height": height, "reason": reason }),
+            ),
+            NodeEvent::Reorganized { fork_height, rolled_back, height, restored } => (
+                Topic::Reorg,
+                "reorganized",
+                json!({ "fork_height": fork_height, "rolled_back": rolled_back, "height": height, "restored": restored }),
+            ),
+            _ => return None,
+        };
+        Some(Event {
+            topic,
+            kind,
+            time: Utc::now(),
+            data,
+        })
+    }
+}
+
+// ==================== 过滤条件 ====================
+
+/// # 订阅的过滤条件 (EventFilter)
+///
+/// 先按主题选择事件，再按各主题自己的条件过滤：
+///
+/// | 参数 | 主题 | 作用 |
+/// |------|------|------|
+/// | `topics` | 全部 | 逗号分隔的主题，默认全部 |
+/// | `min_height` | `block`、`reorg` | 只推送高度（`reorg` 为切换后的高度）不低于它的事件 |
+/// | `mining_interval` | `mining` | 两次挖矿进度之间至少间隔的毫秒数，之间的进度被丢弃 |
+/// | `validation` | `validation` | 只推送 `block_rejected` 或只推送 `validation_failed` |
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventFilter {
+    pub topics: HashSet<Topic>,
+    pub min_height: Option<u64>,
+    pub mining_interval: Option<Duration>,
+    pub validation: Option<&'static str>,
+}
+
+impl Default for EventFilter {
+    /// 所有主题的所有事件
+    fn default() -> Self {
+        EventFilter {
+            topics: Topic::ALL.into_iter().collect(),
+            min_height: None,
+            mining_interval: None,
+            validation: None,
+        }
+    }
+}
+
+impl EventFilter {
+    /// # 由查询参数生成过滤条件
+    ///
+    /// ## 参数
+    /// * `param` - 按名称读取查询参数，参数名见 `EventFilter`
+    ///
+    /// ## 返回值
+    /// 过滤条件；参数无效时返回错误说明
+    pub fn from_query<'a>(param: impl Fn(&str) -> Option<&'a str>) -> Result<Self, String> {
+        let number = |name: &str| {
+            param(name)
+                .map(|value| value.parse::<u64>().map_err(|_| format!("{} 必须是非负整数: {}", name, value)))
+                .transpose()
+        };
+        let validation = match param("validation") {
+            None => None,
+            Some(kind) => Some(VALIDATION_KINDS.into_iter().find(|known| *known == kind).ok_or_else(|| {
+                format!("validation 只能是 {}: {}", VALIDATION_KINDS.join(" 或 "), kind)
+            })?),
+        };
+        Ok(EventFilter {
+            topics: Topic::parse_list(param("topics").unwrap_or(""))?,
+            min_height: number("min_height")?,
+            mining_interval: number("mining_interval")?.map(Duration::from_millis),
+            validation,
+        })
+    }
+
+    /// 事件是否满足主题和主题自己的条件（不含 `mining_interval`，它取决于上一次推送的时间）
+    fn matches(&self, event: &Event) -> bool {
+        if !self.topics.contains(&event.topic) {
+            return false;
+        }
+        match event.topic {
+            Topic::Block | Topic::Reorg => self
+                .min_height
+                .is_none_or(|min| event.data["height"].as_u64().is_some_and(|height| height >= min)),
+            Topic::Validation => self.validation.is_none_or(|kind| kind == event.kind),
+            Topic::Mining | Topic::Difficulty => true,
+        }
+    }
+}
+
+// ==================== 订阅 ====================
+
+/// 一个订阅者：过滤条件、上一次推送挖矿进度的时间和发送事件的通道
+struct Subscriber {
+    filter: EventFilter,
+    last_mining: Option<Instant>,
+    sender: SyncSender<Event>,
+}
+
+impl Subscriber {
+    /// 是否把事件发给这个订阅者，按 `mining_interval` 限制挖矿进度的频率
+    fn wants(&mut self, event: &Event) -> bool {
+        if !self.filter.matches(event) {
+            return false;
+        }
+        if event.topic == Topic::Mining
+            && let Some(interval) = self.filter.mining_interval
+        {
+            if self.last_mining.is_some_and(|last| last.elapsed() < interval) {
+                return false;
+            }
+            self.last_mining = Some(Instant::now());
+        }
+        true
+    }
+}
+
+/// # 事件中心 (EventHub)
+///
+/// 把节点事件分发给所有订阅者。节点的事件回调调用 `publish`，
+/// 事件流（例如 REST 服务的 `/events`）用 `subscribe` 得到自己的接收端。
+/// 每个订阅者的过滤条件（见 `EventFilter`）在发送之前应用，被过滤掉的事件不占用它的积压空间。
+///
+/// 发布从不阻塞节点：每个订阅者最多积压 `SUBSCRIBER_BUFFER` 个事件，
+/// 积压满了或接收端已经丢弃的订阅者被移除，它的接收端随之结束。
+#[derive(Default)]
+pub struct EventHub {
+    subscribers: Mutex<Vec<Subscriber>>,
+}
+
+impl EventHub {
+    pub fn new() -> Self {
+        EventHub::default()
+    }
+
+    /// 订阅满足过滤条件的事件
+    pub fn subscribe(&self, filter: EventFilter) -> Receiver<Event> {
+        let (sender, receiver) = mpsc::sync_channel(SUBSCRIBER_BUFFER);
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Subscriber {
+                filter,
+                last_mining: None,
+                sender,
+            });
+        receiver
+    }
+
+    /// 把节点事件发送给过滤条件接受它的订阅者，不属于任何主题的事件被忽略
+    pub fn publish(&self, event: &NodeEvent) {
+        let Some(event) = Event::from_node_event(event) else { return };
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(PoisonError::into_inner);
+        subscribers.retain_mut(|subscriber| {
+            if !subscriber.wants(&event) {
+                return true;
+            }
+            match subscriber.sender.try_send(event.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => false,
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// # 测试解析主题列表
+    #[test]
+    fn test_parse_topics() {
+        assert_eq!(Topic::parse_list("").unwrap().len(), Topic::ALL.len());
+        assert_eq!(Topic::parse_list("block, reorg").unwrap(), HashSet::from([Topic::Block, Topic::Reorg]));
+        assert!(Topic::parse_list("block,blocks").unwrap_err().contains("blocks"));
+    }
+
+    /// # 测试按主题分发，丢弃接收端的订阅者被移除
+    #[test]
+    fn test_publish_by_topic() {
+        let hub = EventHub::new();
+        let topics = |topics: &[Topic]| EventFilter {
+            topics: topics.iter().copied().collect(),
+            ..EventFilter::default()
+        };
+        let blocks = hub.subscribe(topics(&[Topic::Block]));
+        let all = hub.subscribe(EventFilter::default());
+        let dropped = hub.subscribe(topics(&[Topic::Difficulty]));
+        drop(dropped);
+
+        hub.publish(&NodeEvent::DifficultyChanged { from: 1, to: 2 });
+        hub.publish(&NodeEvent::SaveFailed("不推送".to_string()));
+        hub.publish(&NodeEvent::BlockAccepted {
+            height: 1,
+            hash: "00ab".to_string(),
+            from: None,
+        });
+
+        let event = blocks.try_recv().unwrap();
+        assert_eq!((event.topic, event.kind, &event.data["hash"]), (Topic::Block, "block_accepted", &json!("00ab")));
+        assert!(blocks.try_recv().is_err());
+        let kinds: Vec<&str> = all.try_iter().map(|event| event.kind).collect();
+        assert_eq!(kinds, ["difficulty_changed", "block_accepted"]);
+        assert_eq!(hub.subscribers.lock().unwrap().len(), 2);
+
+        let json = serde_json::to_value(Event::from_node_event(&NodeEvent::DifficultyChanged { from: 1, to: 2 })).unwrap();
+        assert_eq!((&json["topic"], &json["type"], &json["data"]["to"]), (&json!("difficulty"), &json!("difficulty_changed"), &json!(2)));
+    }
+
+    /// # 测试各主题自己的过滤条件：最低高度、挖矿进度间隔和验证事件类型
+    #[test]
+    fn test_topic_filters() {
+        let query = |pairs: &'static [(&'static str, &'static str)]| {
+            EventFilter::from_query(|name| pairs.iter().find(|(key, _)| *key == name).map(|(_, value)| *value))
+        };
+        assert_eq!(query(&[]).unwrap(), EventFilter::default());
+        assert!(query(&[("min_height", "-1")]).unwrap_err().contains("min_height"));
+        assert!(query(&[("validation", "valid")]).unwrap_err().contains("validation"));
+        assert!(query(&[("topics", "block,nothing")]).is_err());
+
+        let hub = EventHub::new();
+        let filter = query(&[
+            ("topics", "block,reorg,mining,validation"),
+            ("min_height", "5"),
+            ("mining_interval", "60000"),
+            ("validation", "validation_failed"),
+        ])
+        .unwrap();
+        assert_eq!(filter.mining_interval, Some(Duration::from_secs(60)));
+        let receiver = hub.subscribe(filter);
+
+        let accepted = |height| NodeEvent::BlockAccepted { height, hash: "00ab".to_string(), from: None };
+        let progress = NodeEvent::MiningProgress { height: 6, hashes: 10_000, hash_rate: 1.0 };
+        hub.publish(&accepted(4));
+        hub.publish(&accepted(5));
+        hub.publish(&NodeEvent::Reorganized { fork_height: 2, rolled_back: 1, height: 3, restored: 0 });
+        hub.publish(&progress);
+        hub.publish(&progress);
+        hub.publish(&NodeEvent::BlockRejected { height: 7, from: "127.0.0.1:1".parse().unwrap(), reason: "无效".to_string() });
+        hub.publish(&NodeEvent::ValidationFailed { height: 7, reason: "无效".to_string() });
+
+        let kinds: Vec<&str> = receiver.try_iter().map(|event| event.kind).collect();
+        assert_eq!(kinds, ["block_accepted", "mining_progress", "validation_failed"]);
+    }
+}
@@ -0,0 +1,432 @@
+// ==================== 依赖库导入 ====================
+// 导入区块、区块链和错误类型
+use crate::block::Block;
+use crate::blockchain::{Blockchain, BlockchainError};
+// 导入验证使用的链规范
+use crate::chainspec::ChainSpec;
+use crate::storage::ChainMetadata;
+// chrono: 时间戳的文本格式
+use chrono::{DateTime, SecondsFormat, Utc};
+// serde: NDJSON 行的结构
+use serde::{Deserialize, Serialize};
+// std::io: 逐行读写
+use std::io::{BufRead, Write};
+// std::path: 根据扩展名推断格式
+use std::path::Path;
+// std::str::FromStr: 从命令行字符串解析格式
+use std::str::FromStr;
+
+/// CSV 文件的列，同时也是 NDJSON 每行对象的字段顺序
+pub const COLUMNS: [&str; 7] = ["index", "timestamp", "hash", "previous_hash", "nonce", "difficulty", "data"];
+
+/// 已裁剪区块的数据哈希列。导出时总是写在最后，导入时可以没有（早期导出的文件）
+pub const DATA_HASH_COLUMN: &str = "data_hash";
+
+/// 区块所属链的链ID列。与数据哈希列一样写在最后，导入时可以没有
+pub const CHAIN_ID_COLUMN: &str = "chain_id";
+
+/// # 导出格式 (ExportFormat)
+///
+/// - `Ndjson`: 每行一个JSON对象，适合 jq、pandas 等数据工具
+/// - `Csv`: 带表头的CSV，适合电子表格
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Ndjson,
+    Csv,
+}
+
+impl ExportFormat {
+    /// 根据扩展名推断格式：`.csv` 为CSV，其他为NDJSON
+    pub fn from_extension(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => ExportFormat::Csv,
+            _ => ExportFormat::Ndjson,
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ndjson" | "jsonl" => Ok(ExportFormat::Ndjson),
+            "csv" => Ok(ExportFormat::Csv),
+            other => Err(format!("未知的导出格式 '{}'，可选: ndjson, csv", other)),
+        }
+    }
+}
+
+/// # 导出的一行 (Row)
+///
+/// 字段顺序与 `COLUMNS` 一致。时间戳使用带纳秒的 RFC 3339 文本，
+/// 保证导入后与原区块完全相同。
+#[derive(Debug, Serialize, Deserialize)]
+struct Row {
+    index: u64,
+    timestamp: DateTime<Utc>,
+    hash: String,
+    previous_hash: String,
+    nonce: u64,
+    difficulty: u32,
+    data: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chain_id: Option<String>,
+}
+
+impl From<&Block> for Row {
+    fn from(block: &Block) -> Self {
+        Row {
+            index: block.index,
+            timestamp: block.timestamp,
+            hash: block.hash.clone(),
+            previous_hash: block.previous_hash.clone(),
+            nonce: block.nonce,
+            difficulty: block.difficulty,
+            data: block.data.clone(),
+            data_hash: block.data_hash.clone(),
+            chain_id: block.chain_id.clone(),
+        }
+    }
+}
+
+impl From<Row> for Block {
+    fn from(row: Row) -> Self {
+        Block {
+            index: row.index,
+            timestamp: row.timestamp,
+            data: row.data,
+            previous_hash: row.previous_hash,
+            hash: row.hash,
+            nonce: row.nonce,
+            difficulty: row.difficulty,
+            data_hash: row.data_hash,
+            chain_id: row.chain_id,
+        }
+    }
+}
+
+// ==================== 导出 ====================
+
+/// # 导出区块
+///
+/// ## 参数
+/// * `blocks` - 要导出的区块，可以直接来自存储后端的 `iter_range`
+/// * `format` - 导出格式
+/// * `writer` - 输出目标（文件或标准输出）
+///
+/// ## 返回值
+/// 导出的区块数
+pub fn export_blocks<I, W>(blocks: I, format: ExportFormat, mut writer: W) -> Result<u64, BlockchainError>
+where
+    I: IntoIterator<Item = Result<Block, BlockchainError>>,
+    W: Write,
+{
+    if format == ExportFormat::Csv {
+        writeln!(writer, "{},{},{}", COLUMNS.join(","), DATA_HASH_COLUMN, CHAIN_ID_COLUMN)?;
+    }
+
+    let mut count = 0;
+    for block in blocks {
+        let block = block?;
+        match format {
+            ExportFormat::Ndjson => {
+                serde_json::to_writer(&mut writer, &Row::from(&block))?;
+                writeln!(writer)?;
+            }
+            ExportFormat::Csv => {
+                let fields = [
+                    block.index.to_string(),
+                    block.timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+                    block.hash,
+                    block.previous_hash,
+                    block.nonce.to_string(),
+                    block.difficulty.to_string(),
+                    block.data,
+                    block.data_hash.unwrap_or_default(),
+                    block.chain_id.unwrap_or_default(),
+                ];
+                let line: Vec<String> = fields.iter().map(|field| csv_escape(field)).collect();
+                writeln!(writer, "{}", line.join(","))?;
+            }
+        }
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+/// 按 RFC 4180 转义CSV字段：包含逗号、引号或换行时加引号，内部引号加倍
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// ==================== 导入 ====================
+
+/// # 读取导出文件中的区块
+///
+/// 只负责解析，不做验证。CSV 按表头中的列名取值，
+/// 因此在电子表格中调整过列顺序的文件也能导入。
+///
+/// ## 返回值
+/// 按文件顺序排列的区块；格式错误时指出行号
+pub fn read_blocks<R: BufRead>(reader: R, format: ExportFormat) -> Result<Vec<Block>, BlockchainError> {
+    match format {
+        ExportFormat::Ndjson => read_ndjson(reader),
+        ExportFormat::Csv => read_csv(reader),
+    }
+}
+
+fn read_ndjson<R: BufRead>(reader: R) -> Result<Vec<Block>, BlockchainError> {
+    let mut blocks = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let row: Row = serde_json::from_str(&line)
+            .map_err(|e| BlockchainError::FormatError(format!("第 {} 行: {}", number + 1, e)))?;
+        blocks.push(row.into());
+    }
+    Ok(blocks)
+}
+
+fn read_csv<R: BufRead>(mut reader: R) -> Result<Vec<Block>, BlockchainError> {
+    let header = read_csv_record(&mut reader)?
+        .ok_or_else(|| BlockchainError::FormatError("CSV 文件为空".to_string()))?;
+    let positions = COLUMNS
+        .iter()
+        .map(|column| {
+            header
+                .iter()
+                .position(|name| name.trim() == *column)
+                .ok_or_else(|| BlockchainError::FormatError(format!("CSV 表头缺少 {} 列", column)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let optional_position = |column: &str| header.iter().position(|name| name.trim() == column);
+    let data_hash_position = optional_position(DATA_HASH_COLUMN);
+    let chain_id_position = optional_position(CHAIN_ID_COLUMN);
+
+    let mut blocks = Vec::new();
+    let mut record_number = 1;
+    while let Some(record) = read_csv_record(&mut reader)? {
+        record_number += 1;
+        if record.len() == 1 && record[0].is_empty() {
+            continue;
+        }
+        let field = |column: usize| record.get(positions[column]).map(String::as_str).unwrap_or_default();
+        let optional_field = |position: Option<usize>| {
+            position
+                .and_then(|position| record.get(position))
+                .filter(|value| !value.is_empty())
+                .cloned()
+        };
+        let error = |what: &str| BlockchainError::FormatError(format!("第 {} 条记录: 无效的 {}", record_number, what));
+
+        let timestamp = DateTime::parse_from_rfc3339(field(1)).map_err(|_| error("timestamp"))?;
+        blocks.push(Block {
+            index: field(0).parse().map_err(|_| error("index"))?,
+            timestamp: timestamp.with_timezone(&Utc),
+            hash: field(2).to_string(),
+            previous_hash: field(3).to_string(),
+            nonce: field(4).parse().map_err(|_| error("nonce"))?,
+            difficulty: field(5).parse().map_err(|_| error("difficulty"))?,
+            data: field(6).to_string(),
+            data_hash: optional_field(data_hash_position),
+            chain_id: optional_field(chain_id_position),
+        });
+    }
+    Ok(blocks)
+}
+
+/// 读取一条CSV记录。引号内的字段可以跨越多行。
+fn read_csv_record<R: BufRead>(reader: &mut R) -> Result<Option<Vec<String>>, BlockchainError> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    loop {
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, in_quotes) {
+                ('"', true) if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                ('"', true) => in_quotes = false,
+                ('"', false) if field.is_empty() => in_quotes = true,
+                (',', false) => fields.push(std::mem::take(&mut field)),
+                ('\n' | '\r', false) => {}
+                (c, _) => field.push(c),
+            }
+        }
+        if !in_quotes {
+            break;
+        }
+        // 引号未闭合，字段中包含换行，继续读取下一行
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(BlockchainError::FormatError("CSV 文件在引号内结束".to_string()));
+        }
+    }
+    fields.push(field);
+    Ok(Some(fields))
+}
+
+/// # 导入结果 (ImportReport)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportReport {
+    /// 与现有区块完全相同而跳过的区块数
+    pub skipped: u64,
+    /// 新追加的区块数
+    pub appended: u64,
+}
+
+/// # 把导入的区块合并到区块链
+///
+/// ## 合并规则
+/// - 没有现有区块链时，第一个区块必须是有效的创世区块
+/// - 高度已存在的区块必须与现有区块完全相同（哈希一致），否则拒绝导入
+/// - 超出现有高度的区块逐个用 `append_block` 验证后追加，
+///   任何一个区块无效、已裁剪或不相连（包括高度跳跃）都会拒绝整个导入
+///
+/// 合并在内存中完成，失败时调用者手中的数据不受影响。
+///
+/// ## 参数
+/// * `existing` - 现有区块链，None 表示从导入的数据创建新链
+/// * `blocks` - 按高度顺序排列的导入区块
+/// * `spec` - 创建新链时使用的链规范（现有区块链沿用自己的链规范）
+pub fn merge_blocks(
+    existing: Option<Blockchain>,
+    blocks: Vec<Block>,
+    spec: &ChainSpec,
+) -> Result<(Blockchain, ImportReport), BlockchainError> {
+    let mut blocks = blocks.into_iter();
+    let mut report = ImportReport { skipped: 0, appended: 0 };
+    let mut blockchain = match existing {
+        Some(blockchain) => blockchain,
+        None => {
+            let genesis = blocks
+                .next()
+                .ok_or_else(|| BlockchainError::InvalidChain("导入文件中没有区块".to_string()))?;
+            Blockchain::check_genesis_in(spec, &genesis)?;
+            report.appended += 1;
+            let mut blockchain = Blockchain::from_parts(vec![genesis], ChainMetadata::default());
+            blockchain.set_spec(spec.clone());
+            blockchain
+        }
+    };
+
+    for block in blocks {
+        match blockchain.get_block(block.index) {
+            Some(current) if current.hash == block.hash => report.skipped += 1,
+            Some(_) => {
+                return Err(BlockchainError::InvalidBlock(format!(
+                    "区块 #{} 与现有区块链中的同高度区块不同",
+                    block.index
+                )));
+            }
+            None => {
+                blockchain.append_block(block)?;
+                report.appended += 1;
+            }
+        }
+    }
+    Ok((blockchain, report))
+}
+
+// ==================== 单元测试 ====================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::chain_of;
+
+    /// 导出测试的区块数据，包含CSV需要转义的字符
+    const RECORDS: [&str; 3] = ["普通数据", "包含,逗号和\"引号\"\n以及换行", "第三个区块"];
+
+    fn roundtrip(blockchain: &Blockchain, format: ExportFormat) -> Vec<Block> {
+        let mut buf = Vec::new();
+        let blocks = blockchain.chain.iter().cloned().map(Ok);
+        assert_eq!(export_blocks(blocks, format, &mut buf).unwrap(), blockchain.chain.len() as u64);
+        read_blocks(buf.as_slice(), format).unwrap()
+    }
+
+    /// # 测试两种格式导出后导入得到完全相同的区块
+    #[test]
+    fn test_export_import_roundtrip() {
+        let blockchain = chain_of(&RECORDS);
+        for format in [ExportFormat::Ndjson, ExportFormat::Csv] {
+            let blocks = roundtrip(&blockchain, format);
+            assert_eq!(blocks, blockchain.chain);
+
+            let (imported, report) = merge_blocks(None, blocks, &ChainSpec::default()).unwrap();
+            assert!(imported.is_chain_valid());
+            assert_eq!(report, ImportReport { skipped: 0, appended: 4 });
+        }
+
+        // 已裁剪的区块连同数据哈希一起往返
+        let mut pruned = chain_of(&RECORDS);
+        pruned.prune_below(2).unwrap();
+        for format in [ExportFormat::Ndjson, ExportFormat::Csv] {
+            assert_eq!(roundtrip(&pruned, format), pruned.chain);
+        }
+    }
+
+    /// # 测试CSV按表头列名读取
+    #[test]
+    fn test_csv_column_order() {
+        let blockchain = chain_of(&RECORDS);
+        let genesis = &blockchain.chain[0];
+        let csv = format!(
+            "data,index,hash,previous_hash,nonce,difficulty,timestamp\n{},{},{},{},{},{},{}\n",
+            csv_escape(&genesis.data),
+            genesis.index,
+            genesis.hash,
+            genesis.previous_hash,
+            genesis.nonce,
+            genesis.difficulty,
+            genesis.timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+        );
+        assert_eq!(read_blocks(csv.as_bytes(), ExportFormat::Csv).unwrap(), vec![genesis.clone()]);
+        assert!(read_blocks("index,hash\n".as_bytes(), ExportFormat::Csv).is_err());
+    }
+
+    /// # 测试导入到现有区块链时跳过已有区块、拒绝冲突和断链
+    #[test]
+    fn test_merge_rules() {
+        let blockchain = chain_of(&RECORDS);
+        let mut prefix = blockchain.clone();
+        prefix.chain.truncate(2);
+        prefix.reindex();
+
+        // 与已有区块重叠的部分被跳过，其余追加
+        let (merged, report) = merge_blocks(Some(prefix.clone()), blockchain.chain.clone(), &ChainSpec::default()).unwrap();
+        assert_eq!(merged.chain, blockchain.chain);
+        assert_eq!(report, ImportReport { skipped: 2, appended: 2 });
+
+        // 高度跳跃
+        let gap = vec![blockchain.chain[3].clone()];
+        assert!(merge_blocks(Some(prefix.clone()), gap, &ChainSpec::default()).is_err());
+
+        // 同高度区块不同
+        let mut conflicting = blockchain.chain.clone();
+        conflicting[1].hash = "0".repeat(64);
+        assert!(merge_blocks(Some(prefix), conflicting, &ChainSpec::default()).is_err());
+
+        // 被篡改的区块
+        let mut tampered = blockchain.chain.clone();
+        tampered[2].data = "篡改".to_string();
+        assert!(merge_blocks(None, tampered, &ChainSpec::default()).is_err());
+    }
+}
@@ -0,0 +1,542 @@
+// ==================== 依赖库导入 ====================
+// 导入区块链错误类型，格式错误的请求和响应用 FormatError 表示
+use crate::blockchain::BlockchainError;
+// std::io: 按行读取请求头，写出响应
+use std::io::{self, BufRead, BufReader, Read, Write};
+// std::net: TCP 监听和连接
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+// std::sync: 在连接线程之间共享请求处理函数、停止标志和连接计数
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+// std::thread: 每个连接一个线程
+use std::thread::{self, JoinHandle};
+// std::time: 读写超时
+use std::time::Duration;
+
+/// 请求体的最大字节数，更大的请求被拒绝
+pub const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// 请求行和所有请求头的最大总字节数
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+
+/// 连接上读写的超时，客户端迟迟不发完请求时关闭连接
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 同时处理的最大连接数（包括事件流这样长时间保持的连接），超出的连接直接回复 503
+pub const MAX_CONNECTIONS: usize = 64;
+
+// ==================== HTTP ====================
+//
+// 本地服务（JSON-RPC、REST 等）使用的最小 HTTP/1.1 实现，只依赖标准库：
+// 每个连接处理一个请求，响应后关闭连接（`Connection: close`），
+// 请求体必须用 `Content-Length` 给出长度，不支持分块传输。
+
+/// # HTTP 请求
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    /// 请求方法，例如 `GET`、`POST`
+    pub method: String,
+    /// 请求路径，不含查询字符串，例如 `/blocks`
+    pub path: String,
+    /// `?` 之后的查询字符串，没有时为空
+    query: String,
+    /// 请求头，名称统一为小写
+    headers: Vec<(String, String)>,
+    /// 请求体
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// # 从连接中读取一个请求
+    ///
+    /// ## 返回值
+    /// 请求行或请求头格式错误、使用分块传输、请求体超过 `MAX_BODY_BYTES` 时返回 `FormatError`
+    pub fn read_from(reader: &mut impl BufRead) -> Result<Request, BlockchainError> {
+        let (start, headers) = read_head(reader)?;
+        let mut parts = start.split_whitespace();
+        let (Some(method), Some(path), Some(version)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(BlockchainError::FormatError(format!("无效的请求行: {}", start)));
+        };
+        if !version.starts_with("HTTP/1.") {
+            return Err(BlockchainError::FormatError(format!("不支持的 HTTP 版本: {}", version)));
+        }
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        let mut request = Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: query.to_string(),
+            headers,
+            body: Vec::new(),
+        };
+        request.body = read_body(reader, &request.headers)?;
+        Ok(request)
+    }
+
+    /// 请求头的值，名称不区分大小写
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// 查询参数的值，例如 `/blocks?from=10` 中 `from` 的值为 `10`。值不做百分号解码
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .split('&')
+            .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// 流式响应体：写出响应头之后在连接线程中调用，一直写到返回为止
+pub type StreamBody = Box<dyn FnOnce(&mut dyn Write) -> io::Result<()> + Send>;
+
+/// # HTTP 响应
+pub struct Response {
+    /// 状态码
+    pub status: u16,
+    /// 除 `Content-Type`、`Content-Length` 和 `Connection` 以外的响应头
+    headers: Vec<(String, String)>,
+    content_type: String,
+    /// 响应体
+    pub body: Vec<u8>,
+    /// 流式响应体，有它时忽略 `body`，响应不带 `Content-Length`，写完后关闭连接
+    stream: Option<StreamBody>,
+}
+
+impl Response {
+    /// JSON 响应
+    pub fn json(status: u16, value: &serde_json::Value) -> Self {
+        Response {
+            status,
+            headers: Vec::new(),
+            content_type: "application/json".to_string(),
+            body: value.to_string().into_bytes(),
+            stream: None,
+        }
+    }
+
+    /// 纯文本响应，用于请求本身有问题（路径、方法、认证）的情况
+    pub fn text(status: u16, text: &str) -> Self {
+        Response {
+            status,
+            headers: Vec::new(),
+            content_type: "text/plain; charset=utf-8".to_string(),
+            body: text.as_bytes().to_vec(),
+            stream: None,
+        }
+    }
+
+    /// # 流式响应
+    ///
+    /// 用于服务器推送事件（`text/event-stream`）等长时间保持的响应：
+    /// 先写出状态 200 和响应头，再调用 `body` 持续写出内容，`body` 返回（例如客户端断开导致写入失败）后关闭连接。
+    pub fn stream(content_type: &str, body: impl FnOnce(&mut dyn Write) -> io::Result<()> + Send + 'static) -> Self {
+        Response {
+            status: 200,
+            headers: Vec::new(),
+            content_type: content_type.to_string(),
+            body: Vec::new(),
+            stream: Some(Box::new(body)),
+        }
+    }
+
+    /// 没有内容的响应
+    pub fn empty(status: u16) -> Self {
+        Response::text(status, "")
+    }
+
+    /// 添加一个响应头
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// 写出完整的响应，流式响应会一直写到流结束
+    pub fn write_to(self, writer: &mut impl Write) -> io::Result<()> {
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, reason_phrase(self.status));
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str(&format!("Content-Type: {}\r\n", self.content_type));
+        if self.stream.is_none() {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        head.push_str("Connection: close\r\n\r\n");
+        writer.write_all(head.as_bytes())?;
+        match self.stream {
+            Some(stream) => {
+                writer.flush()?;
+                stream(writer)
+            }
+            None => {
+                writer.write_all(&self.body)?;
+                writer.flush()
+            }
+        }
+    }
+}
+
+/// 常用状态码的原因短语
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        415 => "Unsupported Media Type",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+/// 读取起始行（请求行或状态行）和所有头部，到空行为止
+fn read_head(reader: &mut impl BufRead) -> Result<(String, Vec<(String, String)>), BlockchainError> {
+    let mut total = 0;
+    let mut next_line = |reader: &mut dyn BufRead| -> Result<String, BlockchainError> {
+        let mut line = String::new();
+        let read = reader.take((MAX_HEAD_BYTES - total) as u64 + 1).read_line(&mut line)?;
+        total += read;
+        if total > MAX_HEAD_BYTES {
+            return Err(BlockchainError::FormatError("HTTP 头部过长".to_string()));
+        }
+        if read == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    };
+
+    let start = next_line(reader)?;
+    let mut headers = Vec::new();
+    loop {
+        let line = next_line(reader)?;
+        if line.is_empty() {
+            return Ok((start, headers));
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(BlockchainError::FormatError(format!("无效的 HTTP 头: {}", line)));
+        };
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+}
+
+/// 按 `Content-Length` 读取消息体，没有这个头时消息体为空
+fn read_body(reader: &mut impl BufRead, headers: &[(String, String)]) -> Result<Vec<u8>, BlockchainError> {
+    if find_header(headers, "transfer-encoding").is_some() {
+        return Err(BlockchainError::FormatError("不支持分块传输".to_string()));
+    }
+    let length = match find_header(headers, "content-length") {
+        Some(value) => value
+            .parse::<usize>()
+            .map_err(|_| BlockchainError::FormatError(format!("无效的 Content-Length: {}", value)))?,
+        None => 0,
+    };
+    if length > MAX_BODY_BYTES {
+        return Err(BlockchainError::FormatError(format!("消息体超过 {} 字节", MAX_BODY_BYTES)));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(body)
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+// ==================== 服务端 ====================
+
+/// 处理一个请求，返回响应。在连接线程中调用，可能同时被多个线程调用
+pub type Handler = dyn Fn(Request) -> Response + Send + Sync;
+
+/// # HTTP 服务 (Server)
+///
+/// 在后台线程中接受连接，每个连接一个线程，读取一个请求、调用处理函数、写出响应后关闭
+/// （流式响应在流结束后关闭）。
+/// 无法解析的请求（包括请求体过大）直接回复 400，不会调用处理函数。
+/// 同时处理的连接达到 `max_connections` 后，新连接直接回复 503 并关闭，不会创建线程。
+/// 停止（`shutdown` 或被丢弃）后不再接受新连接，正在处理的请求会继续完成。
+pub struct Server {
+    local_addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    accept_thread: Option<JoinHandle<()>>,
+}
+
+impl Server {
+    /// # 启动服务
+    ///
+    /// ## 参数
+    /// * `addr` - 监听地址，端口为0时由操作系统分配（见 `local_addr`）
+    /// * `handler` - 请求处理函数
+    pub fn start<A: ToSocketAddrs>(addr: A, handler: Arc<Handler>) -> Result<Self, BlockchainError> {
+        Self::start_with_limit(addr, handler, MAX_CONNECTIONS)
+    }
+
+    /// # 启动服务，指定同时处理的最大连接数
+    ///
+    /// ## 参数
+    /// * `addr` - 监听地址
+    /// * `handler` - 请求处理函数
+    /// * `max_connections` - 同时处理的最大连接数
+    pub fn start_with_limit<A: ToSocketAddrs>(
+        addr: A,
+        handler: Arc<Handler>,
+        max_connections: usize,
+    ) -> Result<Self, BlockchainError> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let accept_shutdown = Arc::clone(&shutdown);
+        let active = Arc::new(AtomicUsize::new(0));
+        let accept_thread = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if accept_shutdown.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(mut stream) = stream else { continue };
+                if active.fetch_add(1, Ordering::SeqCst) >= max_connections {
+                    active.fetch_sub(1, Ordering::SeqCst);
+                    // 响应很短，写入套接字缓冲区即返回；超时保证不会卡住接受线程
+                    let _ = stream.set_write_timeout(Some(IO_TIMEOUT));
+                    let _ = Response::text(503, "连接数已达上限，请稍后重试").write_to(&mut stream);
+                    continue;
+                }
+                let handler = Arc::clone(&handler);
+                let slot = ConnectionSlot(Arc::clone(&active));
+                thread::spawn(move || {
+                    let _slot = slot;
+                    serve_connection(stream, handler.as_ref());
+                });
+            }
+        });
+        Ok(Server {
+            local_addr,
+            shutdown,
+            accept_thread: Some(accept_thread),
+        })
+    }
+
+    /// 实际监听的地址
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// 停止接受新连接。可以重复调用
+    pub fn shutdown(&mut self) {
+        if self.shutdown.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        // 连接一次自己，让阻塞在 accept 上的线程醒来并退出
+        let _ = TcpStream::connect(self.local_addr);
+        if let Some(handle) = self.accept_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// 占用的一个连接名额，连接线程结束（包括处理函数 panic）时归还
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 处理一个连接上的一个请求
+fn serve_connection(stream: TcpStream, handler: &Handler) {
+    let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(IO_TIMEOUT));
+    let Ok(mut writer) = stream.try_clone() else { return };
+    let response = match Request::read_from(&mut BufReader::new(stream)) {
+        Ok(request) => handler(request),
+        Err(BlockchainError::FormatError(reason)) => Response::text(400, &reason),
+        // 连接在请求完整之前关闭或超时，无法回复
+        Err(_) => return,
+    };
+    let _ = response.write_to(&mut writer);
+}
+
+// ==================== 客户端 ====================
+
+/// # 发送一个请求并读取响应
+///
+/// ## 参数
+/// * `addr` - 服务地址
+/// * `method` - 请求方法
+/// * `path` - 请求路径
+/// * `headers` - 额外的请求头
+/// * `body` - 请求体，非空时以 JSON 发送
+///
+/// ## 返回值
+/// 状态码和响应体；无法连接或响应格式错误时返回错误
+pub fn request(
+    addr: &str,
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> Result<(u16, Vec<u8>), BlockchainError> {
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    let mut head = format!("{} {} HTTP/1.1\r\nHost: {}\r\n", method, path, addr);
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if !body.is_empty() {
+        head.push_str("Content-Type: application/json\r\n");
+    }
+    head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", body.len()));
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)?;
+
+    let mut reader = BufReader::new(stream);
+    let (status_line, response_headers) = read_head(&mut reader)?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| BlockchainError::FormatError(format!("无效的状态行: {}", status_line)))?;
+    Ok((status, read_body(&mut reader, &response_headers)?))
+}
+
+/// # HTTP 基本认证的凭据
+///
+/// 把 `用户名:密码` 编码为 `Authorization` 头的值：`Basic <Base64>`
+pub fn basic_auth(credentials: &str) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::from("Basic ");
+    for chunk in credentials.as_bytes().chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let group = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// # 请求是否带有期望的认证头
+///
+/// ## 参数
+/// * `request` - 收到的请求
+/// * `expected_auth` - 期望的 `Authorization` 头，见 `basic_auth`
+pub fn is_authorized(request: &Request, expected_auth: &str) -> bool {
+    request
+        .header("authorization")
+        .is_some_and(|auth| constant_time_eq(auth.as_bytes(), expected_auth.as_bytes()))
+}
+
+/// # 比较凭据是否相同
+///
+/// 比较时间只与长度有关，与第一个不同字节的位置无关，不会通过响应时间泄露口令的前缀。
+/// 长度不同时直接返回 `false`（凭据的长度是固定的，不是秘密）
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// # 测试解析请求：查询参数，请求头不区分大小写，按 Content-Length 读取请求体
+    #[test]
+    fn test_read_request() {
+        let raw = b"POST /blocks?from=3&flag&limit= HTTP/1.1\r\nHost: x\r\nContent-LENGTH: 4\r\n\r\nbodyextra";
+        let request = Request::read_from(&mut &raw[..]).unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/blocks"));
+        assert_eq!(request.query_param("from"), Some("3"));
+        assert_eq!(request.query_param("flag"), Some(""));
+        assert_eq!(request.query_param("limit"), Some(""));
+        assert_eq!(request.query_param("to"), None);
+        assert_eq!(request.header("content-length"), Some("4"));
+        assert_eq!(request.body, b"body");
+
+        let chunked = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n";
+        assert!(matches!(Request::read_from(&mut &chunked[..]), Err(BlockchainError::FormatError(_))));
+        let oversized = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY_BYTES + 1);
+        assert!(matches!(Request::read_from(&mut oversized.as_bytes()), Err(BlockchainError::FormatError(_))));
+    }
+
+    /// # 测试基本认证的 Base64 编码（含各种补齐长度）
+    #[test]
+    fn test_basic_auth() {
+        assert_eq!(basic_auth("user:pass"), "Basic dXNlcjpwYXNz");
+        assert_eq!(basic_auth("a:b1"), "Basic YTpiMQ==");
+        assert_eq!(basic_auth("a:b12"), "Basic YTpiMTI=");
+    }
+
+    /// # 测试凭据比较
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"Basic abc", b"Basic abc"));
+        assert!(!constant_time_eq(b"Basic abc", b"Basic abd"));
+        assert!(!constant_time_eq(b"Basic abc", b"Basic ab"));
+        assert!(constant_time_eq(b"", b""));
+    }
+
+    /// # 测试服务端和客户端：处理函数收到请求，格式错误的请求直接回复 400
+    #[test]
+    fn test_server_roundtrip() {
+        let handler: Arc<Handler> = Arc::new(|request: Request| {
+            Response::text(200, &format!("{} {} {}", request.method, request.path, request.body.len()))
+        });
+        let server = Server::start("127.0.0.1:0", handler).unwrap();
+        let addr = server.local_addr().to_string();
+        let (status, body) = request(&addr, "POST", "/echo", &[], b"{}").unwrap();
+        assert_eq!((status, body.as_slice()), (200, b"POST /echo 2".as_slice()));
+
+        let mut stream = TcpStream::connect(&addr).unwrap();
+        stream.write_all(b"garbage\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+    }
+
+    /// # 测试连接数上限：名额占满时新连接收到 503，连接结束后名额归还
+    #[test]
+    fn test_connection_limit() {
+        let handler: Arc<Handler> = Arc::new(|_: Request| Response::text(200, "ok"));
+        let server = Server::start_with_limit("127.0.0.1:0", handler, 1).unwrap();
+        let addr = server.local_addr().to_string();
+
+        // 不发送请求的连接一直占用唯一的名额
+        let idle = TcpStream::connect(&addr).unwrap();
+        let mut rejected = TcpStream::connect(&addr).unwrap();
+        let mut response = String::new();
+        rejected.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable"));
+
+        // 关闭后连接线程退出，名额归还
+        drop(idle);
+        let status = (0..50)
+            .map(|_| {
+                let status = request(&addr, "GET", "/", &[], b"").map(|(status, _)| status).unwrap_or(0);
+                if status != 200 {
+                    thread::sleep(Duration::from_millis(20));
+                }
+                status
+            })
+            .find(|&status| status == 200);
+        assert_eq!(status, Some(200));
+    }
+}
@@ -0,0 +1,190 @@
+// ==================== 依赖库导入 ====================
+// 导入区块结构体
+use crate::block::Block;
+// 导入交易ID和区块中的交易
+use crate::mempool;
+// chrono: 时间范围查询
+use chrono::{DateTime, Utc};
+// regex: 按正则表达式搜索区块数据
+use regex::Regex;
+// std::collections: 哈希到高度的映射
+use std::collections::HashMap;
+// std::ops: 时间范围
+use std::ops::RangeInclusive;
+
+/// # 区块链内存索引 (ChainIndex)
+///
+/// 为按哈希和按时间查找区块维护的内存索引，避免每次查询都遍历整条链。
+/// 索引不会被持久化，加载区块链后重新构建。
+///
+/// ## 索引内容
+/// - `by_hash`: 区块哈希 → 区块高度
+/// - `by_time`: 按时间戳排序的 (时间戳, 高度) 列表，用于二分查找时间范围。
+///   区块时间戳通常是递增的，但不做保证（例如系统时钟被回拨），
+///   因此单独排序而不是直接对链做二分查找。
+/// - `transactions`: 交易ID → 包含它的区块高度，用于拒绝已经上链的交易。
+///   已裁剪的区块只有数据哈希，只包含一条交易时它就是交易ID
+///
+/// ## 一致性
+/// 区块链的 `chain` 字段是公开的，可能在索引不知情的情况下被修改。
+/// 因此索引记录了建立时的区块数和最后一个区块的哈希，
+/// 查询前用 `is_current` 确认索引与链一致，不一致时调用者应回退到遍历。
+#[derive(Debug, Clone, Default)]
+pub struct ChainIndex {
+    by_hash: HashMap<String, u64>,
+    by_time: Vec<(DateTime<Utc>, u64)>,
+    transactions: HashMap<String, u64>,
+    /// 已建立索引的区块数
+    indexed: usize,
+    /// 最后一个已建立索引的区块的哈希
+    tip_hash: String,
+}
+
+impl ChainIndex {
+    /// 为整条链建立索引
+    pub fn build(chain: &[Block]) -> Self {
+        let mut index = ChainIndex::default();
+        for block in chain {
+            index.record(block);
+        }
+        index
+    }
+
+    /// # 把一个新追加到链尾的区块加入索引
+    ///
+    /// 时间戳列表使用插入排序：时间戳递增时新元素直接放在末尾，代价为 O(1)。
+    pub fn record(&mut self, block: &Block) {
+        self.by_hash.insert(block.hash.clone(), block.index);
+        let position = self.by_time.partition_point(|(time, _)| *time <= block.timestamp);
+        self.by_time.insert(position, (block.timestamp, block.index));
+        match &block.data_hash {
+            Some(data_hash) => {
+                self.transactions.insert(data_hash.clone(), block.index);
+            }
+            None => {
+                for transaction in mempool::block_transactions(block) {
+                    self.transactions.insert(mempool::transaction_id(&transaction), block.index);
+                }
+            }
+        }
+        self.indexed += 1;
+        self.tip_hash = block.hash.clone();
+    }
+
+    /// 索引是否与给定的链一致
+    pub fn is_current(&self, chain: &[Block]) -> bool {
+        chain.len() == self.indexed && chain.last().is_none_or(|tip| tip.hash == self.tip_hash)
+    }
+
+    /// 按哈希查找区块高度
+    pub fn height_of(&self, hash: &str) -> Option<u64> {
+        self.by_hash.get(hash).copied()
+    }
+
+    /// 按交易ID查找包含它的区块高度
+    pub fn height_of_transaction(&self, id: &str) -> Option<u64> {
+        self.transactions.get(id).copied()
+    }
+
+    /// # 查找时间戳在指定范围内（含两端）的区块高度
+    ///
+    /// 返回的高度按从小到大排序。
+    pub fn heights_in_time_range(&self, range: RangeInclusive<DateTime<Utc>>) -> Vec<u64> {
+        let start = self.by_time.partition_point(|(time, _)| time < range.start());
+        let end = self.by_time.partition_point(|(time, _)| time <= range.end());
+        let mut heights: Vec<u64> = self.by_time[start..end.max(start)].iter().map(|&(_, height)| height).collect();
+        heights.sort_unstable();
+        heights
+    }
+}
+
+/// # 区块数据查询 (DataQuery)
+///
+/// - `Substring`: 区块数据包含指定文本（区分大小写）
+/// - `Regex`: 区块数据匹配正则表达式
+#[derive(Debug, Clone)]
+pub enum DataQuery {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl DataQuery {
+    /// # 创建查询
+    ///
+    /// ## 参数
+    /// * `pattern` - 查询文本或正则表达式
+    /// * `regex` - 是否按正则表达式解析 `pattern`
+    ///
+    /// ## 返回值
+    /// 正则表达式语法错误时返回错误说明
+    pub fn new(pattern: &str, regex: bool) -> Result<Self, String> {
+        if regex {
+            Regex::new(pattern)
+                .map(DataQuery::Regex)
+                .map_err(|e| format!("无效的正则表达式: {}", e))
+        } else {
+            Ok(DataQuery::Substring(pattern.to_string()))
+        }
+    }
+
+    /// 区块数据是否满足查询
+    pub fn matches(&self, data: &str) -> bool {
+        match self {
+            DataQuery::Substring(text) => data.contains(text.as_str()),
+            DataQuery::Regex(regex) => regex.is_match(data),
+        }
+    }
+}
+
+// ==================== 单元测试 ====================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn block_at(index: u64, timestamp: DateTime<Utc>) -> Block {
+        let mut block = Block::new(index, format!("区块 {}", index), "0".to_string(), 0);
+        block.timestamp = timestamp;
+        block.hash = block.calculate_hash();
+        block
+    }
+
+    /// # 测试时间范围查询（包括时间戳不递增的情况）
+    #[test]
+    fn test_time_range() {
+        let base = Utc::now();
+        let offsets = [0, 10, 5, 20, 30];
+        let chain: Vec<Block> = offsets
+            .iter()
+            .enumerate()
+            .map(|(i, &secs)| block_at(i as u64, base + Duration::seconds(secs)))
+            .collect();
+        let index = ChainIndex::build(&chain);
+
+        let range = base + Duration::seconds(5)..=base + Duration::seconds(20);
+        assert_eq!(index.heights_in_time_range(range), vec![1, 2, 3]);
+        let empty = base + Duration::seconds(31)..=base + Duration::seconds(40);
+        assert!(index.heights_in_time_range(empty).is_empty());
+    }
+
+    /// # 测试索引能察觉链被直接修改
+    #[test]
+    fn test_detects_stale_index() {
+        let mut chain = vec![block_at(0, Utc::now())];
+        let index = ChainIndex::build(&chain);
+        assert!(index.is_current(&chain));
+        assert_eq!(index.height_of(&chain[0].hash), Some(0));
+
+        chain.push(block_at(1, Utc::now()));
+        assert!(!index.is_current(&chain));
+    }
+
+    #[test]
+    fn test_data_query() {
+        assert!(DataQuery::new("转账", false).unwrap().matches("Alice 转账 10"));
+        assert!(!DataQuery::new("a.c", false).unwrap().matches("abc"));
+        assert!(DataQuery::new(r"^a.c$", true).unwrap().matches("abc"));
+        assert!(DataQuery::new("(", true).is_err());
+    }
+}
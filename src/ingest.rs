@@ -0,0 +1,446 @@
+// ==================== 依赖库导入 ====================
+// 导入区块链、错误类型和存储后端
+use crate::blockchain::{Blockchain, BlockchainError};
+use crate::storage::{self, ChainStore};
+// serde: 进度状态文件
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+// std::fs / std::io: 读取输入和状态文件
+use std::fs;
+use std::io::{self, BufRead};
+// std::iter: 预读下一条记录以决定是否放进当前区块
+use std::iter::Peekable;
+// std::path: 根据扩展名推断格式
+use std::path::Path;
+// std::str::FromStr: 从命令行字符串解析格式
+use std::str::FromStr;
+
+/// 数据目录中导入进度状态文件的文件名
+pub const STATE_FILE_NAME: &str = "ingest.state";
+
+/// 状态文件中保留的检查点数量
+const CHECKPOINTS_KEPT: usize = 2;
+
+/// # 输入格式 (InputFormat)
+///
+/// - `Lines`: 每个非空行是一条记录
+/// - `Json`: 一个JSON数组，每个元素是一条记录
+/// - `Ndjson`: 每行一个JSON值，每行是一条记录
+///
+/// JSON 格式中字符串元素按原文作为记录，其他值（对象、数字等）使用紧凑的JSON文本。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    Lines,
+    Json,
+    Ndjson,
+}
+
+impl InputFormat {
+    /// 根据扩展名推断格式：`.json` 为JSON数组，`.ndjson`/`.jsonl` 为NDJSON，其他按行读取
+    pub fn from_extension(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => InputFormat::Json,
+            Some("ndjson" | "jsonl") => InputFormat::Ndjson,
+            _ => InputFormat::Lines,
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lines" => Ok(InputFormat::Lines),
+            "json" => Ok(InputFormat::Json),
+            "ndjson" | "jsonl" => Ok(InputFormat::Ndjson),
+            other => Err(format!("未知的输入格式 '{}'，可选: lines, json, ndjson", other)),
+        }
+    }
+}
+
+/// 把JSON值转换为记录文本
+fn value_to_record(value: Value) -> String {
+    match value {
+        Value::String(text) => text,
+        other => other.to_string(),
+    }
+}
+
+/// 逐行读取，跳过空白行
+fn non_empty_lines<R: BufRead>(reader: R) -> impl Iterator<Item = io::Result<String>> {
+    reader.lines().filter(|line| !matches!(line, Ok(l) if l.trim().is_empty()))
+}
+
+/// # 读取输入中的记录
+///
+/// 按行读取的格式是流式的，JSON数组需要整体解析。
+pub fn read_records<'a, R: BufRead + 'a>(
+    reader: R,
+    format: InputFormat,
+) -> Result<Box<dyn Iterator<Item = Result<String, BlockchainError>> + 'a>, BlockchainError> {
+    Ok(match format {
+        InputFormat::Lines => Box::new(non_empty_lines(reader).map(|line| Ok(line?))),
+        InputFormat::Ndjson => Box::new(non_empty_lines(reader).enumerate().map(|(number, line)| {
+            let value = serde_json::from_str(&line?)
+                .map_err(|e| BlockchainError::FormatError(format!("第 {} 条记录: {}", number + 1, e)))?;
+            Ok(value_to_record(value))
+        })),
+        InputFormat::Json => {
+            let values: Vec<Value> = serde_json::from_reader(reader)?;
+            Box::new(values.into_iter().map(|value| Ok(value_to_record(value))))
+        }
+    })
+}
+
+/// # 打包规则 (Packing)
+///
+/// 决定多少条记录放进同一个区块：达到 `max_records` 条，
+/// 或者再加一条就会超过 `max_bytes` 字节时，开始下一个区块。
+/// 单条记录超过字节预算时单独成块。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packing {
+    pub max_records: usize,
+    pub max_bytes: Option<usize>,
+}
+
+impl Default for Packing {
+    /// 默认每条记录一个区块
+    fn default() -> Self {
+        Packing { max_records: 1, max_bytes: None }
+    }
+}
+
+/// # 按打包规则分组的记录 (Packs)
+///
+/// 把记录流转换为"每个区块的记录"流。读取记录出错时，
+/// 先交出已经分好的组，下一次再返回错误。
+pub struct Packs<I: Iterator> {
+    records: Peekable<I>,
+    packing: Packing,
+}
+
+/// 按打包规则对记录分组
+pub fn pack_records<I>(records: I, packing: Packing) -> Packs<I>
+where
+    I: Iterator<Item = Result<String, BlockchainError>>,
+{
+    Packs { records: records.peekable(), packing }
+}
+
+impl<I> Iterator for Packs<I>
+where
+    I: Iterator<Item = Result<String, BlockchainError>>,
+{
+    type Item = Result<Vec<String>, BlockchainError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut pack: Vec<String> = Vec::new();
+        let mut bytes = 0;
+        while pack.len() < self.packing.max_records.max(1) {
+            let fits = match self.records.peek() {
+                None => break,
+                Some(Err(_)) if pack.is_empty() => return self.records.next().and_then(Result::err).map(Err),
+                Some(Err(_)) => break,
+                Some(Ok(record)) => pack.is_empty() || self.packing.max_bytes.is_none_or(|max| bytes + record.len() <= max),
+            };
+            if !fits {
+                break;
+            }
+            if let Some(Ok(record)) = self.records.next() {
+                bytes += record.len();
+                pack.push(record);
+            }
+        }
+        (!pack.is_empty()).then_some(Ok(pack))
+    }
+}
+
+/// # 把一组记录编码为区块数据
+///
+/// 只有一条记录时原样作为区块数据，多条记录时编码为JSON字符串数组，
+/// 便于之后从区块中取回每条原始记录。
+pub fn pack_data(records: &[String]) -> String {
+    match records {
+        [single] => single.clone(),
+        many => serde_json::to_string(many).expect("字符串数组可以序列化"),
+    }
+}
+
+/// # 从区块数据中取回每条记录
+///
+/// `pack_data` 的逆操作：至少两个元素的JSON字符串数组按多条记录解开，其他数据整体是一条记录。
+/// 一条本身就是这种JSON数组的记录会被当作多条记录，与 `pack_data` 的编码无法区分。
+pub fn unpack_data(data: &str) -> Vec<String> {
+    if data.starts_with('[')
+        && let Ok(records) = serde_json::from_str::<Vec<String>>(data)
+        && records.len() >= 2
+    {
+        return records;
+    }
+    vec![data.to_string()]
+}
+
+/// # 导入进度状态 (IngestState)
+///
+/// 保存在数据目录的 `ingest.state` 中，用于中断后继续导入。
+///
+/// ## 检查点
+/// 每个检查点记录"导入了多少条记录"以及此时的最新区块哈希。
+/// 保存区块链之前先写入新的检查点，所以无论在哪一步中断，
+/// 存储中的最新区块总是与其中一个检查点对应：
+/// - 区块链保存成功：对应最新的检查点
+/// - 区块链保存前中断：对应上一个检查点
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IngestState {
+    /// 输入来源（文件路径或 `-` 表示标准输入）
+    pub source: String,
+    /// 从旧到新排列的检查点
+    pub checkpoints: Vec<Checkpoint>,
+}
+
+/// 一个导入检查点
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// 已经写入区块的记录数
+    pub records: u64,
+    /// 写入这些记录后的最新区块哈希
+    pub tip_hash: String,
+}
+
+impl IngestState {
+    /// 读取状态文件，不存在时返回 None
+    pub fn load(path: &Path) -> Result<Option<Self>, BlockchainError> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 原子写入状态文件
+    pub fn save(&self, path: &Path) -> Result<(), BlockchainError> {
+        storage::write_atomic(path, serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// 追加检查点，只保留最近的几个
+    fn push(&mut self, checkpoint: Checkpoint) {
+        self.checkpoints.push(checkpoint);
+        let excess = self.checkpoints.len().saturating_sub(CHECKPOINTS_KEPT);
+        self.checkpoints.drain(..excess);
+    }
+
+    /// # 根据区块链的最新区块确定继续导入的位置
+    ///
+    /// ## 返回值
+    /// 已经写入区块链的记录数；没有检查点与最新区块对应时返回 None，
+    /// 说明区块链在导入之外被修改过，无法安全地继续
+    pub fn resume_point(&self, blockchain: &Blockchain) -> Option<u64> {
+        let tip = &blockchain.get_latest_block().hash;
+        self.checkpoints.iter().rev().find(|c| &c.tip_hash == tip).map(|c| c.records)
+    }
+}
+
+/// # 导入设置 (IngestOptions)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IngestOptions {
+    /// 打包规则
+    pub packing: Packing,
+    /// 每挖出多少个区块保存一次
+    pub save_every: u64,
+    /// 输入来源，写入状态文件用于确认继续导入的是同一个输入
+    pub source: String,
+    /// 忽略已有的进度，从第一条记录开始
+    pub restart: bool,
+}
+
+/// # 导入结果 (IngestReport)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IngestReport {
+    /// 因为之前已经导入而跳过的记录数
+    pub resumed_from: u64,
+    /// 本次导入的记录数
+    pub records: u64,
+    /// 本次挖出的区块数
+    pub blocks: u64,
+}
+
+/// # 导入记录并挖矿
+///
+/// ## 流程
+/// 1. 读取状态文件，如果来源相同则跳过已经导入的记录
+/// 2. 按打包规则把记录分组，每组静默挖出一个区块
+/// 3. 每 `save_every` 个区块写入检查点并保存区块链
+/// 4. 全部完成后保存区块链并删除状态文件
+///
+/// ## 参数
+/// * `blockchain` - 要追加区块的区块链
+/// * `store` - 存储后端，定期保存到这里
+/// * `records` - 输入记录
+/// * `options` - 导入设置
+/// * `state_path` - 状态文件路径
+/// * `on_checkpoint` - 每次保存后调用，参数为本次已导入的记录数和区块数
+pub fn ingest<I, F>(
+    blockchain: &mut Blockchain,
+    store: &mut dyn ChainStore,
+    records: I,
+    options: &IngestOptions,
+    state_path: &Path,
+    mut on_checkpoint: F,
+) -> Result<IngestReport, BlockchainError>
+where
+    I: Iterator<Item = Result<String, BlockchainError>>,
+    F: FnMut(u64, u64),
+{
+    let previous = if options.restart { None } else { IngestState::load(state_path)? };
+    let resumed_from = match previous.as_ref().filter(|state| state.source == options.source) {
+        Some(state) => state.resume_point(blockchain).ok_or_else(|| {
+            BlockchainError::StorageError(format!(
+                "区块链在上次导入之后被修改过，无法继续导入；使用 --restart 从头开始（{}）",
+                state_path.display()
+            ))
+        })?,
+        None => 0,
+    };
+
+    let mut state = IngestState {
+        source: options.source.clone(),
+        checkpoints: vec![Checkpoint { records: resumed_from, tip_hash: blockchain.get_latest_block().hash.clone() }],
+    };
+    let mut report = IngestReport { resumed_from, records: 0, blocks: 0 };
+    for pack in pack_records(records.skip(resumed_from as usize), options.packing) {
+        let pack = pack?;
+        blockchain.add_block_silent(pack_data(&pack))?;
+        report.records += pack.len() as u64;
+        report.blocks += 1;
+
+        if report.blocks.is_multiple_of(options.save_every.max(1)) {
+            // 先写检查点再保存区块链，保证两者中断后总能对上
+            state.push(Checkpoint {
+                records: resumed_from + report.records,
+                tip_hash: blockchain.get_latest_block().hash.clone(),
+            });
+            state.save(state_path)?;
+            blockchain.save_to_store(store)?;
+            on_checkpoint(report.records, report.blocks);
+        }
+    }
+
+    blockchain.save_to_store(store)?;
+    if state_path.exists() {
+        fs::remove_file(state_path)?;
+    }
+    Ok(report)
+}
+
+// ==================== 单元测试 ====================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chainspec::ChainSpec;
+    use crate::storage::MemoryStore;
+    use tempfile::TempDir;
+
+    fn records(items: &[&str]) -> Vec<Result<String, BlockchainError>> {
+        items.iter().map(|s| Ok(s.to_string())).collect()
+    }
+
+    fn options(max_records: usize, save_every: u64) -> IngestOptions {
+        IngestOptions {
+            packing: Packing { max_records, max_bytes: None },
+            save_every,
+            source: "test".to_string(),
+            restart: false,
+        }
+    }
+
+    fn small_chain() -> Blockchain {
+        let mut blockchain = Blockchain::new();
+        blockchain.set_difficulty(1);
+        blockchain
+    }
+
+    #[test]
+    fn test_read_formats() {
+        let lines: Vec<_> = read_records("a\n\nb\n".as_bytes(), InputFormat::Lines).unwrap().collect();
+        assert_eq!(lines.into_iter().collect::<Result<Vec<_>, _>>().unwrap(), ["a", "b"]);
+
+        let json: Vec<_> = read_records(r#"["a", {"k": 1}, 2]"#.as_bytes(), InputFormat::Json).unwrap().collect();
+        assert_eq!(json.into_iter().collect::<Result<Vec<_>, _>>().unwrap(), ["a", r#"{"k":1}"#, "2"]);
+
+        let mut ndjson = read_records("\"a\"\nnot json\n".as_bytes(), InputFormat::Ndjson).unwrap();
+        assert_eq!(ndjson.next().unwrap().unwrap(), "a");
+        assert!(ndjson.next().unwrap().is_err());
+    }
+
+    /// # 测试按条数和字节预算打包
+    #[test]
+    fn test_packing() {
+        let input = ["aaaa", "bb", "cc", "dddddddd", "e"];
+        let by_count: Vec<_> = pack_records(records(&input).into_iter(), Packing { max_records: 2, max_bytes: None })
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(by_count, [vec!["aaaa", "bb"], vec!["cc", "dddddddd"], vec!["e"]]);
+
+        let by_bytes: Vec<_> = pack_records(records(&input).into_iter(), Packing { max_records: usize::MAX, max_bytes: Some(6) })
+            .map(Result::unwrap)
+            .collect();
+        // 超过预算的单条记录单独成块
+        assert_eq!(by_bytes, [vec!["aaaa", "bb"], vec!["cc"], vec!["dddddddd"], vec!["e"]]);
+
+        assert_eq!(pack_data(&["x".to_string()]), "x");
+        assert_eq!(pack_data(&["x".to_string(), "y".to_string()]), r#"["x","y"]"#);
+    }
+
+    /// # 测试中断后从最后一个检查点继续
+    #[test]
+    fn test_resume_after_interruption() {
+        let dir = TempDir::new().unwrap();
+        let state_path = dir.path().join(STATE_FILE_NAME);
+        let mut store = MemoryStore::new();
+        let all = ["r1", "r2", "r3", "r4", "r5", "r6", "r7"];
+
+        // 第一次导入在读到第6条记录时出错，只有前两次检查点（4条记录）被保存
+        let mut interrupted = records(&all[..5]);
+        interrupted.push(Err(BlockchainError::StorageError("输入中断".to_string())));
+        let mut blockchain = small_chain();
+        let result = ingest(&mut blockchain, &mut store, interrupted.into_iter(), &options(1, 2), &state_path, |_, _| {});
+        assert!(result.is_err());
+        assert!(state_path.exists());
+
+        // 重新打开存储中保存的区块链，再次导入完整输入
+        let mut blockchain = Blockchain::load_from_store(&store, ChainSpec::default()).unwrap();
+        assert_eq!(blockchain.chain.len(), 5);
+        let report = ingest(&mut blockchain, &mut store, records(&all).into_iter(), &options(1, 2), &state_path, |_, _| {})
+            .unwrap();
+        assert_eq!(report.resumed_from, 4);
+        assert_eq!(report.records, 3);
+        assert!(!state_path.exists());
+
+        let saved = Blockchain::load_from_store(&store, ChainSpec::default()).unwrap();
+        let data: Vec<_> = saved.chain[1..].iter().map(|b| b.data.as_str()).collect();
+        assert_eq!(data, all);
+    }
+
+    /// # 测试区块链在导入之外被修改时拒绝继续
+    #[test]
+    fn test_refuse_resume_after_external_change() {
+        let dir = TempDir::new().unwrap();
+        let state_path = dir.path().join(STATE_FILE_NAME);
+        let mut store = MemoryStore::new();
+        let mut blockchain = small_chain();
+        let mut interrupted = records(&["r1", "r2"]);
+        interrupted.push(Err(BlockchainError::StorageError("输入中断".to_string())));
+        assert!(ingest(&mut blockchain, &mut store, interrupted.into_iter(), &options(1, 1), &state_path, |_, _| {}).is_err());
+
+        blockchain.add_block_silent("其他区块".to_string()).unwrap();
+        let input = || records(&["r1", "r2", "r3"]).into_iter();
+        assert!(ingest(&mut blockchain, &mut store, input(), &options(1, 1), &state_path, |_, _| {}).is_err());
+
+        let restart = IngestOptions { restart: true, ..options(3, 1) };
+        let report = ingest(&mut blockchain, &mut store, input(), &restart, &state_path, |_, _| {}).unwrap();
+        assert_eq!((report.resumed_from, report.records, report.blocks), (0, 3, 1));
+    }
+}
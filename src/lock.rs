@@ -0,0 +1,206 @@
+// ==================== 依赖库导入 ====================
+// 导入区块链错误类型
+use crate::blockchain::BlockchainError;
+// std::fs: 创建和打开锁文件
+use std::fs::{self, File, OpenOptions, TryLockError};
+// std::io: 读写锁文件中的进程信息
+use std::io::{Read, Seek, SeekFrom, Write};
+// std::path: 路径处理
+use std::path::Path;
+// std::time: 等待锁时的超时控制
+use std::time::{Duration, Instant};
+
+/// 数据目录中锁文件的文件名
+pub const LOCK_FILE_NAME: &str = ".lock";
+
+/// 等待锁时两次尝试之间的间隔
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// # 锁模式 (LockMode)
+///
+/// - `Shared`: 共享锁（读锁），多个只读进程（display、stats、validate）可以同时持有
+/// - `Exclusive`: 独占锁（写锁），会修改区块链的进程（mine、交互模式）必须独占数据目录
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+/// # 数据目录锁 (DataDirLock)
+///
+/// 防止多个进程同时读写同一个数据目录。例如在两个终端同时运行
+/// `blockchain mine`，如果没有锁，两个进程会加载同一份文件、在同一高度
+/// 各挖出一个区块，最后写入的进程会悄悄覆盖另一个的结果。
+///
+/// ## 实现方式
+/// 使用操作系统提供的文件锁（`File::lock` / `File::lock_shared`）锁住数据目录下的
+/// `.lock` 文件。文件锁在进程退出（包括崩溃）时由操作系统自动释放，
+/// 不会留下需要手动清理的"僵尸锁"。
+///
+/// 持有独占锁的进程会把自己的进程号写入锁文件，其他进程获取失败时
+/// 可以在错误信息中指出是谁占用了目录。
+///
+/// ## 生命周期
+/// 锁在 `DataDirLock` 被丢弃时释放，因此调用者只需在操作期间持有该值。
+#[derive(Debug)]
+pub struct DataDirLock {
+    file: File,
+    mode: LockMode,
+}
+
+impl DataDirLock {
+    /// # 获取数据目录锁
+    ///
+    /// ## 参数
+    /// * `dir` - 要锁定的数据目录，不存在时会自动创建
+    /// * `mode` - 共享锁或独占锁
+    /// * `timeout` - 锁被占用时最多等待的时间，为零时立即失败
+    ///
+    /// ## 返回值
+    /// * `Ok(DataDirLock)` - 成功获取锁
+    /// * `Err(BlockchainError::LockError)` - 超时后仍被其他进程占用
+    /// * `Err(BlockchainError::IoError)` - 无法创建或打开锁文件
+    pub fn acquire<P: AsRef<Path>>(dir: P, mode: LockMode, timeout: Duration) -> Result<Self, BlockchainError> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let path = dir.join(LOCK_FILE_NAME);
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let attempt = match mode {
+                LockMode::Shared => file.try_lock_shared(),
+                LockMode::Exclusive => file.try_lock(),
+            };
+
+            match attempt {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    std::thread::sleep(RETRY_INTERVAL);
+                }
+                Err(TryLockError::WouldBlock) => {
+                    return Err(BlockchainError::LockError(busy_message(dir, &path, timeout)));
+                }
+                Err(TryLockError::Error(e)) => return Err(e.into()),
+            }
+        }
+
+        let mut lock = DataDirLock { file, mode };
+        if mode == LockMode::Exclusive {
+            lock.record_owner()?;
+        }
+        Ok(lock)
+    }
+
+    /// 把当前进程号写入锁文件，供其他进程报错时显示
+    fn record_owner(&mut self) -> Result<(), BlockchainError> {
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        write!(self.file, "{}", std::process::id())?;
+        Ok(())
+    }
+}
+
+impl Drop for DataDirLock {
+    fn drop(&mut self) {
+        if self.mode == LockMode::Exclusive {
+            // 清除进程号，避免误导后来的读者
+            let _ = self.file.set_len(0);
+        }
+        let _ = self.file.unlock();
+    }
+}
+
+/// 生成锁被占用时的错误信息，尽量指出占用者的进程号
+fn busy_message(dir: &Path, path: &Path, timeout: Duration) -> String {
+    let mut owner = String::new();
+    if let Ok(mut file) = File::open(path) {
+        let _ = file.read_to_string(&mut owner);
+    }
+    let owner = match owner.trim() {
+        "" => "另一个进程".to_string(),
+        pid => format!("进程 {} ", pid),
+    };
+
+    if timeout.is_zero() {
+        format!(
+            "数据目录 {} 正被{}使用，请等待其结束，或使用 --lock-timeout <秒> 等待锁释放",
+            dir.display(),
+            owner
+        )
+    } else {
+        format!(
+            "等待 {:.1} 秒后数据目录 {} 仍被{}占用",
+            timeout.as_secs_f64(),
+            dir.display(),
+            owner
+        )
+    }
+}
+
+// ==================== 单元测试 ====================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// # 测试多个共享锁可以同时持有
+    #[test]
+    fn test_shared_locks_coexist() {
+        let dir = TempDir::new().unwrap();
+        let _first = DataDirLock::acquire(dir.path(), LockMode::Shared, Duration::ZERO).unwrap();
+        assert!(DataDirLock::acquire(dir.path(), LockMode::Shared, Duration::ZERO).is_ok());
+    }
+
+    /// # 测试独占锁与其他锁互斥，并在错误信息中给出进程号
+    #[test]
+    fn test_exclusive_lock_excludes_others() {
+        let dir = TempDir::new().unwrap();
+        let writer = DataDirLock::acquire(dir.path(), LockMode::Exclusive, Duration::ZERO).unwrap();
+
+        let err = DataDirLock::acquire(dir.path(), LockMode::Shared, Duration::ZERO).unwrap_err();
+        assert!(err.to_string().contains(&std::process::id().to_string()));
+        assert!(DataDirLock::acquire(dir.path(), LockMode::Exclusive, Duration::ZERO).is_err());
+
+        // 释放后可以重新获取
+        drop(writer);
+        assert!(DataDirLock::acquire(dir.path(), LockMode::Exclusive, Duration::ZERO).is_ok());
+    }
+
+    /// # 测试共享锁会阻止写入者
+    #[test]
+    fn test_shared_lock_blocks_writer() {
+        let dir = TempDir::new().unwrap();
+        let _reader = DataDirLock::acquire(dir.path(), LockMode::Shared, Duration::ZERO).unwrap();
+        assert!(DataDirLock::acquire(dir.path(), LockMode::Exclusive, Duration::ZERO).is_err());
+    }
+
+    /// # 测试等待超时
+    #[test]
+    fn test_wait_times_out() {
+        let dir = TempDir::new().unwrap();
+        let _writer = DataDirLock::acquire(dir.path(), LockMode::Exclusive, Duration::ZERO).unwrap();
+
+        let start = Instant::now();
+        let result = DataDirLock::acquire(dir.path(), LockMode::Exclusive, Duration::from_millis(300));
+        assert!(result.is_err());
+        assert!(start.elapsed() >= Duration::from_millis(300));
+    }
+
+    /// # 测试等待期间锁被释放后能够获取
+    #[test]
+    fn test_wait_succeeds_after_release() {
+        let dir = TempDir::new().unwrap();
+        let writer = DataDirLock::acquire(dir.path(), LockMode::Exclusive, Duration::ZERO).unwrap();
+
+        let releaser = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            drop(writer);
+        });
+
+        let lock = DataDirLock::acquire(dir.path(), LockMode::Exclusive, Duration::from_secs(5));
+        assert!(lock.is_ok());
+        releaser.join().unwrap();
+    }
+}
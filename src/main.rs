@@ -1,27 +1,288 @@
 // ==================== 模块声明 ====================
 // 声明项目的核心模块
 mod block;       // 区块结构体和相关功能
+mod bench;       // 验证性能基准测试
 mod blockchain;  // 区块链核心逻辑
+mod chains;      // 同一网络下的多条命名区块链
+mod chainspec;   // 链规范：创世区块哈希和检查点
+mod codec;       // 紧凑的二进制编码格式
+mod events;      // 推送给订阅者的节点事件
+mod export;      // NDJSON/CSV 导出与导入
+mod http;        // 本地服务使用的最小 HTTP/1.1 实现
+mod index;       // 按哈希、时间和内容查找区块
+mod ingest;      // 从文件或标准输入批量导入记录
+mod lock;        // 数据目录的进程间文件锁
+mod mempool;     // 交易池：待打包的交易
+mod migration;   // 存储格式版本和迁移
+mod network;     // 主网、测试网和回归测试网的预设
+mod node;        // 通过TCP互相同步区块的P2P节点
+mod peers;       // 节点地址簿和不良行为评分
+mod protocol;    // 节点通信协议：消息帧和版本握手
+mod rest;        // 给网页面板使用的 REST 接口
+mod rpc;         // JSON-RPC 服务和客户端
+mod snapshot;    // 区块链快照
+mod storage;     // 可插拔的存储后端
+mod stream;      // 大型区块链的流式验证
+mod sync;        // 区块头优先同步：区块头检查和并行下载
+mod template;    // 交给外部矿工的区块模板
+#[cfg(test)]
+mod test_util;   // 测试共用的区块链
 mod utils;       // 工具函数和用户交互
 
 // ==================== 依赖库导入 ====================
 // 导入区块链核心结构体
-use blockchain::Blockchain;
+use block::Block;
+use blockchain::{Blockchain, BlockchainError};
+// 导入链规范和检查点
+use chainspec::{ChainSpec, Checkpoint};
+// 导入区块链文件格式
+use codec::FileFormat;
+// 导入导出格式
+use export::ExportFormat;
+// 导入批量导入的设置
+use ingest::{IngestOptions, InputFormat, Packing};
+// 导入区块数据查询
+use index::DataQuery;
+// 导入数据目录锁
+use lock::{DataDirLock, LockMode};
+// 导入网络预设
+use network::Network;
+// 导入P2P节点
+use node::{Node, NodeConfig, NodeEvent};
+// 导入节点地址簿
+use peers::AddressBook;
+// 导入事件中心，REST 服务的事件流从这里订阅节点事件
+use events::EventHub;
+// 导入 JSON-RPC 和 REST 服务
+use rest::RestServer;
+use rpc::RpcServer;
+// 导入区块链快照
+use snapshot::Snapshot;
+// 导入存储后端接口和类型
+use storage::{ChainStore, StoreKind};
+// chrono: 解析时间范围参数
+use chrono::{DateTime, Utc};
 // colored: 用于在终端输出彩色文本，提升用户体验
 use colored::*;
 // std::env: 用于获取命令行参数和环境变量
 use std::env;
+// std::fs: 查询文件大小
+use std::fs;
+// std::io: 刷新进度输出，节点按行读取标准输入
+use std::io::{self, BufRead, Write};
+// std::path: 拼接数据文件路径
+use std::path::{Path, PathBuf};
 // std::process: 用于程序退出控制
 use std::process;
+// std::sync: 节点和 RPC 服务共享同一个节点
+use std::sync::Arc;
+// std::time: 等待数据目录锁的超时时间
+use std::time::{Duration, Instant};
 // 导入所有工具函数，包括用户输入处理和界面显示
 use utils::*;
 
-/// # 区块链数据文件路径常量
-/// 
-/// 定义区块链数据持久化存储的默认文件路径。
-/// 使用相对路径，在项目根目录下的data文件夹中存储区块链数据。
-/// JSON格式便于人类阅读和调试，同时保持跨平台兼容性。
-const BLOCKCHAIN_FILE: &str = "data/blockchain.json";
+/// 查询结果每页默认显示的区块数
+const DEFAULT_PAGE_SIZE: usize = 20;
+
+/// 批量导入时默认每挖出多少个区块保存一次
+const DEFAULT_INGEST_SAVE_EVERY: usize = 10;
+
+/// # 命令行全局选项
+/// 
+/// 可以出现在任意命令前后的选项，在分发命令之前统一解析并从参数中移除。
+/// 
+/// ## 支持的选项
+/// - `--network <mainnet|testnet|regtest>`: 选择网络，决定数据目录和预设链规范，默认为mainnet
+/// - `--data-dir <目录>`: 数据根目录，默认为 `data`；测试网和回归测试网使用其中的子目录
+/// - `--chain <名称>`: 选择网络下的命名区块链（见 `chains` 命令），默认为 `default`
+/// - `--store <json|log|memory>`: 选择存储后端，默认为json
+/// - `--lock-timeout <秒>`: 数据目录被其他进程占用时最多等待的秒数，默认不等待
+/// - `--checkpoint <高度>:<哈希>`: 在内置链规范之外添加一个检查点，可以重复使用
+/// - `--assume-valid`: 信任最后一个检查点及之前的区块，验证时跳过它们的哈希重算和工作量证明检查
+/// 
+/// 链规范来自所选区块链数据目录中的 `chainspec.json`（由 `init` 写入，旧版本创建的主网区块链会自动写入，见 `adopt_legacy_spec`），不存在时使用网络的预设链规范，
+/// 再加上 `--checkpoint` 和 `--assume-valid` 的设置。
+/// 
+/// 选项既可以写成 `--store log`，也可以写成 `--store=log`。
+#[derive(Clone)]
+struct CliOptions {
+    /// 所选的网络
+    network: Network,
+    /// 所选网络的数据目录，默认区块链和 `chains/` 子目录都在这里
+    network_dir: PathBuf,
+    /// 所选区块链的名称
+    chain: String,
+    /// 所选区块链的数据目录，具体的文件名由存储后端决定（如 blockchain.json、blockchain.log）
+    data_dir: PathBuf,
+    /// 区块链使用的存储后端
+    store: StoreKind,
+    /// 等待数据目录锁的最长时间
+    lock_timeout: Duration,
+    /// 命令行添加的检查点，切换区块链时重新应用
+    checkpoints: Vec<Checkpoint>,
+    /// 区块链遵循的链规范
+    spec: ChainSpec,
+}
+
+impl CliOptions {
+    /// # 解析全局选项
+    /// 
+    /// 从参数列表中取出全局选项，返回解析结果和剩余的参数。
+    /// 剩余参数保留程序名，便于后续按位置匹配命令。
+    fn parse(args: &[String]) -> Result<(Self, Vec<String>), String> {
+        let mut network = Network::Mainnet;
+        let mut data_root = PathBuf::from(network::DEFAULT_DATA_DIR);
+        let mut chain = chains::DEFAULT_CHAIN.to_string();
+        let mut store = StoreKind::Json;
+        let mut lock_timeout = Duration::ZERO;
+        let mut assume_valid = false;
+        let mut checkpoints = Vec::new();
+        let mut rest = Vec::with_capacity(args.len());
+        let mut iter = args.iter();
+
+        while let Some(arg) = iter.next() {
+            // 拆分 --name=value 形式的选项
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+
+            // --assume-valid 是不带参数的开关
+            if name == "--assume-valid" && inline_value.is_none() {
+                assume_valid = true;
+                continue;
+            }
+            if !matches!(
+                name,
+                "--network" | "--data-dir" | "--chain" | "--store" | "--lock-timeout" | "--checkpoint"
+            ) {
+                rest.push(arg.clone());
+                continue;
+            }
+
+            let value = match inline_value {
+                Some(value) => value,
+                None => iter.next().ok_or(format!("{} 需要一个参数", name))?.clone(),
+            };
+            match name {
+                "--network" => network = value.parse()?,
+                "--data-dir" => data_root = PathBuf::from(value),
+                "--chain" => chain = value,
+                "--store" => store = value.parse()?,
+                "--checkpoint" => checkpoints.push(value.parse::<Checkpoint>()?),
+                _ => {
+                    let seconds: f64 = value
+                        .parse()
+                        .ok()
+                        .filter(|s: &f64| s.is_finite() && *s >= 0.0)
+                        .ok_or(format!("无效的等待时间 '{}'", value))?;
+                    lock_timeout = Duration::from_secs_f64(seconds);
+                }
+            }
+        }
+
+        let network_dir = network.data_dir(&data_root);
+        let mut spec = network.spec();
+        spec.assume_valid = assume_valid;
+        let options = CliOptions {
+            network,
+            data_dir: network_dir.clone(),
+            network_dir,
+            chain: chains::DEFAULT_CHAIN.to_string(),
+            store,
+            lock_timeout,
+            checkpoints,
+            spec,
+        };
+        Ok((options.for_chain(&chain)?, rest))
+    }
+
+    /// # 切换到网络下的另一条区块链
+    /// 
+    /// 返回使用该区块链数据目录的选项，其他选项保持不变。
+    /// 数据目录中的链规范优先于网络的预设，命令行的检查点和 `--assume-valid` 在此基础上重新应用。
+    fn for_chain(&self, name: &str) -> Result<CliOptions, String> {
+        let data_dir = chains::resolve(&self.network_dir, name).map_err(|e| e.to_string())?;
+        let spec_path = data_dir.join(chainspec::SPEC_FILE_NAME);
+        let mut spec = if spec_path.exists() {
+            ChainSpec::load(&spec_path).map_err(|e| e.to_string())?
+        } else {
+            self.adopt_legacy_spec(&data_dir, &spec_path)?.unwrap_or_else(|| self.network.spec())
+        };
+        spec.assume_valid = self.spec.assume_valid;
+        for checkpoint in &self.checkpoints {
+            spec.add_checkpoint(checkpoint.clone())?;
+        }
+
+        Ok(CliOptions {
+            chain: name.to_string(),
+            data_dir,
+            spec,
+            ..self.clone()
+        })
+    }
+
+    /// # 为旧版本创建的区块链记录链规范
+    /// 
+    /// 引入链规范之前，主网的区块链用创建时的当前时间作为创世区块时间戳，与内置链规范的创世区块不同。
+    /// 数据目录中没有 `chainspec.json` 时，读取已有区块链的创世区块：如果它与内置链规范只有时间戳不同，
+    /// 就把以它为创世区块的链规范写入 `chainspec.json`，之后的命令都按它加载，旧数据不需要重新创建。
+    /// 读取失败时不做任何事，由之后的加载报告原因。
+    fn adopt_legacy_spec(&self, data_dir: &Path, spec_path: &Path) -> Result<Option<ChainSpec>, String> {
+        // 测试网络是和链规范一起引入的，从一开始就使用固定的创世区块
+        if self.network != Network::Mainnet {
+            return Ok(None);
+        }
+        let preset = self.network.spec();
+        let Some(spec) = self
+            .store
+            .read_genesis(data_dir)
+            .ok()
+            .flatten()
+            .and_then(|genesis| preset.for_legacy_genesis(&genesis))
+        else {
+            return Ok(None);
+        };
+        spec.save(spec_path)
+            .map_err(|e| format!("无法为旧版本的区块链写入链规范 {}: {}", spec_path.display(), e))?;
+        show_info(&format!(
+            "区块链由旧版本创建（创世区块时间 {}），已把它的链规范写入 {}",
+            spec.genesis.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+            spec_path.display()
+        ));
+        Ok(Some(spec))
+    }
+
+    /// # 锁定数据目录
+    /// 
+    /// 读操作获取共享锁，写操作获取独占锁；锁在返回值被丢弃时释放。
+    /// 内存存储不访问数据目录，因此不需要加锁。获取失败时直接退出程序。
+    fn lock(&self, mode: LockMode) -> Option<DataDirLock> {
+        self.try_lock(mode).unwrap_or_else(|e| {
+            show_error(&e.to_string());
+            process::exit(1);
+        })
+    }
+
+    /// 与 `lock` 相同，但获取失败时返回错误，供交互模式切换区块链时使用
+    fn try_lock(&self, mode: LockMode) -> Result<Option<DataDirLock>, BlockchainError> {
+        if self.store == StoreKind::Memory {
+            return Ok(None);
+        }
+        DataDirLock::acquire(&self.data_dir, mode, self.lock_timeout).map(Some)
+    }
+
+    /// 打开所选的存储后端，失败时直接退出程序
+    fn open_store(&self) -> Box<dyn ChainStore> {
+        match self.store.open(&self.data_dir, &self.spec) {
+            Ok(store) => store,
+            Err(e) => {
+                show_error(&format!("无法打开存储: {}", e));
+                process::exit(1);
+            }
+        }
+    }
+}
 
 /// # 程序主入口函数
 /// 
@@ -61,10 +322,19 @@ fn main() {
     // example: ./blockchain mine "Hello, World!" -> args = ["./blockchain", "mine", "Hello, World!"]
     let args: Vec<String> = env::args().collect();
     
+    // 先取出 --store 等全局选项，剩下的才是命令和命令参数
+    let (mut options, args) = match CliOptions::parse(&args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            show_error(&e);
+            process::exit(1);
+        }
+    };
+    
     // 检查是否有命令行参数（除了程序名本身）
     if args.len() > 1 {
         // 有参数则进入CLI模式，执行完毕后退出程序
-        handle_cli_args(&args);
+        handle_cli_args(&args, &options);
         return;
     }
     
@@ -73,33 +343,42 @@ fn main() {
     // 显示程序启动横幅，展示程序信息和版本
     display_banner();
     
+    // 交互模式随时可能修改区块链，因此在整个会话期间独占数据目录
+    // 切换区块链时换成新区块链的锁
+    let mut lock = options.lock(LockMode::Exclusive);
+    
+    // 打开存储后端，整个交互会话共用同一个存储实例
+    // 这样即使是内存存储，保存和加载也能在会话内生效
+    let mut store = options.open_store();
+    
     // 尝试加载现有区块链，如果不存在则创建新的
     // 这确保程序总是有一个可用的区块链实例
-    let mut blockchain = load_or_create_blockchain();
+    let mut blockchain = load_or_create_blockchain(store.as_ref(), &options.spec);
     
     // 进入主事件循环，提供持续的用户交互
     loop {
         // 显示主菜单选项
-        display_main_menu();
+        display_main_menu(&options.chain);
         
         // 获取用户输入并执行对应操作
         // 使用字符串匹配进行命令分发
         match get_user_input().as_str() {
-            "1" => mine_new_block(&mut blockchain),           // 挖掘新区块
-            "2" => display_blockchain(&blockchain),           // 显示完整区块链
-            "3" => validate_blockchain(&blockchain),          // 验证区块链完整性
-            "4" => save_blockchain(&blockchain),              // 保存区块链到文件
-            "5" => blockchain = load_blockchain(),            // 从文件加载区块链
-            "6" => set_difficulty(&mut blockchain),           // 设置挖矿难度
-            "7" => display_statistics(&blockchain),           // 显示统计信息
-            "8" => batch_mining(&mut blockchain),             // 批量挖矿
+            "1" => mine_new_block(&mut blockchain, store.as_mut()),   // 挖掘新区块
+            "2" => display_blockchain(&blockchain),                   // 显示完整区块链
+            "3" => validate_blockchain(&blockchain),                  // 验证区块链完整性
+            "4" => save_blockchain(&blockchain, store.as_mut()),      // 保存区块链到存储
+            "5" => blockchain = load_blockchain(store.as_ref(), &options.spec, blockchain), // 从存储加载区块链
+            "6" => set_difficulty(&mut blockchain, store.as_mut()),   // 设置挖矿难度
+            "7" => display_statistics(&blockchain),                   // 显示统计信息
+            "8" => batch_mining(&mut blockchain, store.as_mut()),     // 批量挖矿
             "9" => view_block_details(&blockchain),           // 查看区块详情
+            "c" | "C" => switch_chain(&mut options, &mut lock, &mut store, &mut blockchain), // 切换区块链
             "0" => {
                 // 用户选择退出程序
                 show_info("感谢使用 Rust 区块链！再见！👋");
                 process::exit(0);  // 正常退出，返回状态码0
             }
-            _ => show_error("无效选择，请输入 0-9 之间的数字"),
+            _ => show_error("无效选择，请输入 0-9 之间的数字或 c"),
         }
         
         // 操作完成后暂停，让用户查看结果
@@ -168,14 +447,15 @@ fn main() {
 /// - 文件访问：自动处理文件不存在的情况
 /// 
 /// ## 参数
-/// * `args` - 命令行参数切片，包含程序名和用户参数
+/// * `args` - 命令行参数切片，包含程序名和用户参数（已去除全局选项）
+/// * `options` - 解析后的全局选项
 /// 
 /// ## 设计考虑
 /// - **单一职责**：每个命令只做一件事，便于组合使用
 /// - **幂等性**：重复执行相同命令应该产生相同结果
 /// - **错误传播**：适当的退出代码便于shell脚本处理
 /// - **自动保存**：修改操作后自动保存，避免数据丢失
-fn handle_cli_args(args: &[String]) {
+fn handle_cli_args(args: &[String], options: &CliOptions) {
     // 使用模式匹配处理第二个参数（索引1，因为索引0是程序名）
     // get(1) 返回 Option<&String>，map() 转换为 Option<&str>
     match args.get(1).map(|s| s.as_str()) {
@@ -183,39 +463,376 @@ fn handle_cli_args(args: &[String]) {
             // 挖矿命令：mine [数据]
             // 获取第三个参数作为区块数据，如果没有则使用默认值
             let data = args.get(2).unwrap_or(&"命令行挖矿".to_string()).clone();
-            let mut blockchain = load_or_create_blockchain();
+            // 在加载之前获取独占锁，保证"加载-挖矿-保存"整个过程不被其他进程打断
+            let _lock = options.lock(LockMode::Exclusive);
+            let mut store = options.open_store();
+            let mut blockchain = load_or_create_blockchain(store.as_ref(), &options.spec);
             
+            // 交易池中的交易跟在命令行数据后面一起打包
+            let (data, packed) = blockchain.next_block_data(Some(data)).expect("至少包含命令行数据");
             println!("🚀 开始命令行挖矿...");
             match blockchain.add_block(data) {
                 Ok(_) => {
                     show_success("区块挖矿成功！");
+                    if packed > 0 {
+                        show_info(&format!("打包了交易池中的 {} 条交易", packed));
+                    }
                     // 静默保存，避免在CLI模式下显示额外的用户界面
-                    save_blockchain_silent(&blockchain);
+                    save_blockchain_silent(&blockchain, store.as_mut());
                 }
                 Err(e) => show_error(&format!("挖矿失败: {}", e)),
             }
         }
+        Some("init") => {
+            // 初始化命令：init [--spec <链规范文件>]
+            let spec = spec_option(&args[2..], options);
+            let _lock = options.lock(LockMode::Exclusive);
+            let mut store = options.open_store();
+            init_chain(store.as_mut(), spec, options);
+        }
+        Some("chains") => {
+            // 区块链管理：chains [list] | chains create <名称> [--spec <文件>]
+            //             chains copy <源> <目标> | chains delete <名称>
+            let rest = &args[2..];
+            let positional = positional_args(rest, &["--spec"]);
+            match positional.as_slice() {
+                [] | ["list"] => list_chains(options),
+                ["create", name] => {
+                    let spec = spec_option(rest, options);
+                    create_chain(name, spec, options);
+                }
+                ["copy", from, to] => match chains::copy(&options.network_dir, from, to, options.lock_timeout) {
+                    Ok(bytes) => show_success(&format!(
+                        "已把区块链 {} 复制为 {}（{}）",
+                        from,
+                        to,
+                        format_file_size(bytes as usize)
+                    )),
+                    Err(e) => exit_with_error(&format!("复制失败: {}", e)),
+                },
+                ["delete", name] => match chains::delete(&options.network_dir, name, options.lock_timeout) {
+                    Ok(()) => show_success(&format!("已删除区块链 {}", name)),
+                    Err(e) => exit_with_error(&format!("删除失败: {}", e)),
+                },
+                _ => exit_with_error(
+                    "用法: chains [list] | chains create <名称> [--spec <文件>] | chains copy <源> <目标> | chains delete <名称>",
+                ),
+            }
+        }
+        Some("spec") => {
+            // 链规范命令：spec，输出当前使用的链规范
+            println!("{}", options.spec.to_json());
+        }
+        Some("validate") if args[2..].iter().any(|arg| arg == "--stream") => {
+            // 流式验证：validate --stream [--until <高度>] [文件]
+            let mut until = None;
+            let mut path = None;
+            let mut rest = args[2..].iter();
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--stream" => {}
+                    "--until" => match rest.next().and_then(|v| v.parse::<u64>().ok()) {
+                        Some(height) => until = Some(height),
+                        None => {
+                            show_error("--until 需要一个区块高度");
+                            process::exit(1);
+                        }
+                    },
+                    _ => path = Some(PathBuf::from(arg)),
+                }
+            }
+            
+            let _lock = options.lock(LockMode::Shared);
+            stream_validate(path.as_deref(), until, options);
+        }
         Some("validate") => {
-            // 验证命令：validate
-            let blockchain = load_or_create_blockchain();
-            if blockchain.is_chain_valid() {
-                show_success("区块链验证通过 ✅");
-            } else {
-                show_error("区块链验证失败 ❌");
-                // 验证失败时使用非零退出代码，便于脚本检测
-                process::exit(1);
+            // 验证命令：validate [--threads <线程数>]
+            let threads = match parse_usize_flag(&args[2..], "--threads") {
+                Ok(threads) => threads.unwrap_or_else(Blockchain::default_validation_threads),
+                Err(e) => {
+                    show_error(&e);
+                    process::exit(1);
+                }
+            };
+            let _lock = options.lock(LockMode::Shared);
+            validate_stored_blockchain(options, threads);
+        }
+        Some("node") => {
+            // 节点命令：node [--listen <地址>] [--peer <地址>]... [--max-inbound <N>] [--max-outbound <N>] [--rpc <地址>] [--rest <地址>]
+            let listen = flag_value(&args[2..], "--listen")
+                .unwrap_or_else(|e| exit_with_error(&e))
+                .unwrap_or(node::DEFAULT_LISTEN_ADDR);
+            let rpc = flag_value(&args[2..], "--rpc").unwrap_or_else(|e| exit_with_error(&e));
+            let rest = flag_value(&args[2..], "--rest").unwrap_or_else(|e| exit_with_error(&e));
+            let peers = flag_values(&args[2..], "--peer").unwrap_or_else(|e| exit_with_error(&e));
+            let limits = parse_usize_flag(&args[2..], "--max-inbound")
+                .and_then(|inbound| Ok((inbound, parse_usize_flag(&args[2..], "--max-outbound")?)))
+                .unwrap_or_else(|e| exit_with_error(&e));
+            
+            let _lock = options.lock(LockMode::Exclusive);
+            let store = options.open_store();
+            // 内存存储不保存任何东西，地址簿也只保存在内存中
+            let address_book = match options.store {
+                StoreKind::Memory => AddressBook::in_memory(),
+                _ => AddressBook::load_from_dir(&options.data_dir)
+                    .unwrap_or_else(|e| exit_with_error(&format!("无法加载地址簿: {}", e))),
+            };
+            let config = NodeConfig {
+                max_inbound: limits.0.unwrap_or(node::DEFAULT_MAX_INBOUND),
+                max_outbound: limits.1.unwrap_or(node::DEFAULT_MAX_OUTBOUND),
+                address_book,
+            };
+            run_node(store, listen, &peers, config, rpc, rest, options);
+        }
+        Some("peers") => {
+            // 节点地址簿：peers，显示运行中节点的连接、已知地址和封禁
+            list_peers(options);
+        }
+        Some("rpc") => {
+            // RPC 客户端：rpc [--rpc-connect <地址>] <方法> [参数]...
+            let rest = &args[2..];
+            let addr = flag_value(rest, "--rpc-connect")
+                .unwrap_or_else(|e| exit_with_error(&e))
+                .unwrap_or(rpc::DEFAULT_RPC_ADDR);
+            let positional = positional_args(rest, &["--rpc-connect"]);
+            let Some((method, params)) = positional.split_first() else {
+                exit_with_error("用法: rpc [--rpc-connect <地址>] <方法> [参数]...");
+            };
+            let params: Vec<String> = params.iter().map(|param| param.to_string()).collect();
+            call_rpc(options, addr, method, &params);
+        }
+        Some("mempool") => {
+            // 交易池：mempool [list] | mempool add <数据>
+            match &args[2..] {
+                [] => list_mempool(options),
+                [command] if command == "list" => list_mempool(options),
+                [command, data] if command == "add" => {
+                    let _lock = options.lock(LockMode::Exclusive);
+                    let mut store = options.open_store();
+                    let mut blockchain = load_or_create_blockchain(store.as_ref(), &options.spec);
+                    match blockchain.submit_transaction(data.clone()) {
+                        Ok(true) => {
+                            save_blockchain_silent(&blockchain, store.as_mut());
+                            show_success(&format!(
+                                "交易 {} 已加入交易池，共 {} 条待打包",
+                                format_hash(&mempool::transaction_id(data), 16),
+                                blockchain.pending_transactions.len()
+                            ));
+                        }
+                        Ok(false) => show_info("交易池中已有这条交易"),
+                        Err(e) => exit_with_error(&format!("交易被拒绝: {}", e)),
+                    }
+                }
+                _ => exit_with_error("用法: mempool [list] | mempool add <数据>"),
+            }
+        }
+        Some("bench") if args.get(2).map(String::as_str) == Some("validate") => {
+            // 基准测试：bench validate [--blocks <区块数>] [--threads <线程数>]
+            let parsed = parse_usize_flag(&args[3..], "--blocks")
+                .and_then(|blocks| Ok((blocks, parse_usize_flag(&args[3..], "--threads")?)));
+            match parsed {
+                Ok((blocks, threads)) => run_validation_bench(
+                    blocks.unwrap_or(100_000),
+                    threads.unwrap_or_else(Blockchain::default_validation_threads),
+                ),
+                Err(e) => {
+                    show_error(&e);
+                    process::exit(1);
+                }
             }
         }
         Some("display") => {
             // 显示命令：display
-            let blockchain = load_or_create_blockchain();
+            let _lock = options.lock(LockMode::Shared);
+            let blockchain = load_or_create_blockchain(options.open_store().as_ref(), &options.spec);
             blockchain.display_chain();
         }
         Some("stats") => {
             // 统计命令：stats
-            let blockchain = load_or_create_blockchain();
+            let _lock = options.lock(LockMode::Shared);
+            let blockchain = load_or_create_blockchain(options.open_store().as_ref(), &options.spec);
             display_pretty_stats(&blockchain);
         }
+        Some("migrate") => {
+            // 迁移命令：migrate [--dry-run] [文件路径]
+            let dry_run = args[2..].iter().any(|arg| arg == "--dry-run");
+            let path = args[2..]
+                .iter()
+                .find(|arg| !arg.starts_with("--"))
+                .map(PathBuf::from)
+                .unwrap_or_else(|| options.data_dir.join(storage::JSON_FILE_NAME));
+            
+            let _lock = options.lock(if dry_run { LockMode::Shared } else { LockMode::Exclusive });
+            migrate_chain_file(&path, dry_run, &options.spec);
+        }
+        Some("get") => {
+            // 查询区块：get <高度> 或 get --hash <哈希>
+            let hash = flag_value(&args[2..], "--hash").unwrap_or_else(|e| exit_with_error(&e));
+            let height = positional_args(&args[2..], &["--hash"]).first().map(|h| h.parse::<u64>());
+            if hash.is_none() && !matches!(height, Some(Ok(_))) {
+                exit_with_error("用法: get <高度> 或 get --hash <哈希>");
+            }
+            
+            let _lock = options.lock(LockMode::Shared);
+            let blockchain = load_or_create_blockchain(options.open_store().as_ref(), &options.spec);
+            let block = match (hash, height) {
+                (Some(hash), _) => blockchain.get_block_by_hash(hash),
+                (None, Some(Ok(height))) => blockchain.get_block(height),
+                _ => None,
+            };
+            match block {
+                Some(block) => println!("{}", block),
+                None => exit_with_error("未找到指定的区块"),
+            }
+        }
+        Some("range") => {
+            // 按时间查询：range [--from <时间>] [--to <时间>] [--page <页码>] [--per-page <条数>]
+            let from = time_flag(&args[2..], "--from", false);
+            let to = time_flag(&args[2..], "--to", true);
+            let (page, per_page) = page_options(&args[2..]);
+            
+            let _lock = options.lock(LockMode::Shared);
+            let blockchain = load_or_create_blockchain(options.open_store().as_ref(), &options.spec);
+            let blocks = blockchain.blocks_in_time_range(from, to);
+            display_block_page("按时间查询", &blocks, page, per_page);
+        }
+        Some("search") => {
+            // 按内容搜索：search <文本或正则> [--regex] [--page <页码>] [--per-page <条数>]
+            let positional = positional_args(&args[2..], &["--page", "--per-page"]);
+            let Some(pattern) = positional.first() else {
+                exit_with_error("用法: search <文本> [--regex] [--page <页码>] [--per-page <条数>]");
+            };
+            let regex = args[2..].iter().any(|arg| arg == "--regex");
+            let query = DataQuery::new(pattern, regex).unwrap_or_else(|e| exit_with_error(&e));
+            let (page, per_page) = page_options(&args[2..]);
+            
+            let _lock = options.lock(LockMode::Shared);
+            let blockchain = load_or_create_blockchain(options.open_store().as_ref(), &options.spec);
+            let blocks = blockchain.search_data(&query);
+            display_block_page(&format!("搜索 \"{}\"", pattern), &blocks, page, per_page);
+        }
+        Some("export") => {
+            // 导出命令：export <文件|-> [--format ndjson|csv] [--from <高度>] [--to <高度>]
+            let valued = ["--format", "--from", "--to"];
+            let Some(target) = positional_args(&args[2..], &valued).first().map(PathBuf::from) else {
+                exit_with_error("用法: export <文件|-> [--format ndjson|csv] [--from <高度>] [--to <高度>]");
+            };
+            let format = export_format(&args[2..], &target);
+            let from = height_flag(&args[2..], "--from").unwrap_or(0);
+            let to = height_flag(&args[2..], "--to");
+            
+            let _lock = options.lock(LockMode::Shared);
+            export_chain(options.open_store().as_ref(), &target, format, from, to);
+        }
+        Some("import") => {
+            // 导入命令：import <文件> [--format ndjson|csv]
+            let Some(source) = positional_args(&args[2..], &["--format"]).first().map(PathBuf::from) else {
+                exit_with_error("用法: import <文件> [--format ndjson|csv]");
+            };
+            let format = export_format(&args[2..], &source);
+            
+            // 导入会修改区块链，需要独占锁
+            let _lock = options.lock(LockMode::Exclusive);
+            let mut store = options.open_store();
+            import_chain(store.as_mut(), &source, format, &options.spec);
+        }
+        Some("ingest") => {
+            // 批量导入：ingest [文件|-] [--format lines|json|ndjson] [--per-block <条数>]
+            //           [--max-bytes <字节>] [--save-every <区块数>] [--restart]
+            let rest = &args[2..];
+            let valued = ["--format", "--per-block", "--max-bytes", "--save-every"];
+            let source = positional_args(rest, &valued).first().copied().unwrap_or("-").to_string();
+            let format = match flag_value(rest, "--format").unwrap_or_else(|e| exit_with_error(&e)) {
+                Some(format) => format.parse().unwrap_or_else(|e: String| exit_with_error(&e)),
+                None if source == "-" => InputFormat::Lines,
+                None => InputFormat::from_extension(Path::new(&source)),
+            };
+            let per_block = parse_usize_flag(rest, "--per-block").unwrap_or_else(|e| exit_with_error(&e));
+            let max_bytes = parse_usize_flag(rest, "--max-bytes").unwrap_or_else(|e| exit_with_error(&e));
+            let save_every = parse_usize_flag(rest, "--save-every").unwrap_or_else(|e| exit_with_error(&e));
+            
+            let ingest_options = IngestOptions {
+                packing: Packing {
+                    // 只指定字节预算时不限制条数
+                    max_records: per_block.unwrap_or(if max_bytes.is_some() { usize::MAX } else { 1 }),
+                    max_bytes,
+                },
+                save_every: save_every.unwrap_or(DEFAULT_INGEST_SAVE_EVERY) as u64,
+                source,
+                restart: rest.iter().any(|arg| arg == "--restart"),
+            };
+            
+            let _lock = options.lock(LockMode::Exclusive);
+            let mut store = options.open_store();
+            ingest_records(store.as_mut(), format, &ingest_options, &options.spec, &options.data_dir);
+        }
+        Some("prune") => {
+            // 裁剪命令：prune --below <高度>
+            let height = match parse_usize_flag(&args[2..], "--below") {
+                Ok(Some(height)) => height as u64,
+                Ok(None) => exit_with_error("用法: prune --below <高度>"),
+                Err(e) => exit_with_error(&e),
+            };
+            
+            let _lock = options.lock(LockMode::Exclusive);
+            let mut store = options.open_store();
+            prune_chain(store.as_mut(), height, &options.spec);
+        }
+        Some("snapshot") => {
+            // 快照命令：snapshot create <文件> [--height <高度>]
+            //           snapshot restore <文件> --expect <承诺>
+            let rest = &args[2..];
+            let positional = positional_args(rest, &["--height", "--expect"]);
+            match (positional.first().copied(), positional.get(1).map(PathBuf::from)) {
+                (Some("create"), Some(path)) => {
+                    let height = parse_usize_flag(rest, "--height").unwrap_or_else(|e| exit_with_error(&e));
+                    let _lock = options.lock(LockMode::Shared);
+                    create_snapshot(options.open_store().as_ref(), &path, height.map(|h| h as u64), &options.spec);
+                }
+                (Some("restore"), Some(path)) => {
+                    let expect = flag_value(rest, "--expect").unwrap_or_else(|e| exit_with_error(&e));
+                    let _lock = options.lock(LockMode::Exclusive);
+                    let mut store = options.open_store();
+                    restore_snapshot(store.as_mut(), &path, expect, &options.spec);
+                }
+                _ => exit_with_error("用法: snapshot create <文件> [--height <高度>] | snapshot restore <文件> --expect <承诺>"),
+            }
+        }
+        Some("convert") => {
+            // 转换命令：convert <输入文件> <输出文件> [--to json|binary]
+            let mut format = None;
+            let mut paths = Vec::new();
+            let mut rest = args[2..].iter();
+            while let Some(arg) = rest.next() {
+                let value = match arg.strip_prefix("--to=") {
+                    Some(value) => Some(value.to_string()),
+                    None if arg == "--to" => rest.next().cloned(),
+                    None => {
+                        paths.push(PathBuf::from(arg));
+                        continue;
+                    }
+                };
+                match value.as_deref().map(str::parse::<FileFormat>) {
+                    Some(Ok(f)) => format = Some(f),
+                    Some(Err(e)) => {
+                        show_error(&e);
+                        process::exit(1);
+                    }
+                    None => {
+                        show_error("--to 需要一个参数: json 或 binary");
+                        process::exit(1);
+                    }
+                }
+            }
+            
+            let [input, output] = paths.as_slice() else {
+                show_error("用法: convert <输入文件> <输出文件> [--to json|binary]");
+                process::exit(1);
+            };
+            // 未指定 --to 时按输出文件的扩展名决定格式
+            let format = format.unwrap_or_else(|| FileFormat::from_extension(output));
+            convert_chain_file(input, output, format, &options.spec);
+        }
         Some("help") | Some("-h") | Some("--help") => {
             // 帮助命令：支持多种常见的帮助参数格式
             display_help();
@@ -269,15 +886,57 @@ fn display_help() {
     
     // 显示基本使用语法
     println!("用法:");
-    println!("  {} [命令] [参数]", env::args().next().unwrap_or_else(|| "blockchain".to_string()));
+    println!("  {} [选项] [命令] [参数]", env::args().next().unwrap_or_else(|| "blockchain".to_string()));
+    println!();
+    
+    // 全局选项
+    println!("选项:");
+    println!("  --network <网络>       网络: mainnet（默认，data）、testnet（data/testnet）、regtest（data/regtest）");
+    println!("  --data-dir <目录>      数据根目录（默认 data）");
+    println!("  --chain <名称>         使用网络下的命名区块链（默认 default）");
+    println!("  --store <类型>         存储后端: json（默认）、log、memory");
+    println!("  --lock-timeout <秒>    数据目录被其他进程占用时的最长等待时间（默认不等待）");
+    println!("  --checkpoint <高度>:<哈希>  添加一个检查点，对应高度的区块哈希必须一致（可重复）");
+    println!("  --assume-valid         信任最后一个检查点及之前的区块，跳过它们的工作量检查");
     println!();
     
     // 详细列出所有可用命令
     println!("命令:");
-    println!("  mine [数据]     挖掘包含指定数据的新区块");
-    println!("  validate        验证整个区块链的完整性");
+    println!("  init [--spec <文件>]  按链规范（默认为所选网络的预设）在空的数据目录中创建区块链");
+    println!("  spec            输出当前使用的链规范（JSON，可作为 init --spec 的模板）");
+    println!("  chains [list]   列出网络下的所有区块链");
+    println!("  chains create <名称> [--spec <文件>]  创建命名区块链（默认使用网络的预设链规范）");
+    println!("  chains copy <源> <目标>  复制区块链，例如从默认区块链分出一条实验链");
+    println!("  chains delete <名称>  删除命名区块链");
+    println!("  node [--listen <地址>] [--peer <地址>]...  运行P2P节点（默认监听 {}），标准输入的每行挖出一个区块，\"tx <数据>\" 提交交易", node::DEFAULT_LISTEN_ADDR);
+    println!("       [--max-inbound <N>] [--max-outbound <N>]  最多接受的入站连接数（默认 {}）和自动维持的出站连接数（默认 {}）",
+        node::DEFAULT_MAX_INBOUND, node::DEFAULT_MAX_OUTBOUND);
+    println!("       [--rpc <地址>]  同时在该地址上开启 JSON-RPC 服务（例如 {}），认证 cookie 写入数据目录", rpc::DEFAULT_RPC_ADDR);
+    println!("       [--rest <地址>]  同时在该地址上开启 REST 接口（例如 {}），提供 /blocks、/stats、/validate、事件流 /events?topics= 和需要 RPC cookie 认证的 POST /data", rest::DEFAULT_REST_ADDR);
+    println!("  rpc [--rpc-connect <地址>] <方法> [参数]...  调用运行中节点的 RPC 方法（默认连接 {}），结果输出为 JSON", rpc::DEFAULT_RPC_ADDR);
+    println!("  peers           显示运行中节点的连接、地址簿中的地址和被封禁的IP");
+    println!("  mempool [list]  显示交易池中等待打包的交易");
+    println!("  mempool add <数据>  提交一条交易到交易池，下一个挖出的区块会打包它");
+    println!("  mine [数据]     挖掘包含指定数据（和交易池中的交易）的新区块");
+    println!("  validate [--threads <N>]  验证整个区块链的完整性（默认使用所有CPU核心）");
+    println!("  validate --stream [--until <高度>] [文件]  边读边验证，内存占用与链长度无关");
     println!("  display         显示完整的区块链");
     println!("  stats           显示区块链统计信息");
+    println!("  bench validate [--blocks <N>] [--threads <N>]  对比单线程和多线程验证的速度");
+    println!("  get <高度> | get --hash <哈希>  显示指定区块");
+    println!("  range [--from <时间>] [--to <时间>]  列出时间范围内的区块");
+    println!("  search <文本> [--regex]  搜索区块数据（range/search 支持 --page、--per-page 分页）");
+    println!("  migrate [--dry-run] [文件]  把区块链文件升级到当前格式版本");
+    println!("  export <文件|-> [--format ndjson|csv] [--from <高度>] [--to <高度>]  导出区块");
+    println!("  import <文件> [--format ndjson|csv]  导入区块（逐块验证后追加）");
+    println!("  ingest [文件|-] [--format lines|json|ndjson] [--per-block <N>] [--max-bytes <N>] [--save-every <N>] [--restart]");
+    println!("                  从文件或标准输入批量导入记录，每组记录挖出一个区块，中断后可继续");
+    println!("  prune --below <高度>      裁剪低于指定高度的区块数据，只保留区块头");
+    println!("  snapshot create <文件> [--height <高度>]");
+    println!("                  创建区块链快照（区块头和数据哈希）");
+    println!("  snapshot restore <文件> --expect <承诺>");
+    println!("                  从快照恢复区块链到空的数据目录");
+    println!("  convert <输入> <输出> [--to json|binary]  在JSON和二进制格式之间转换区块链文件");
     println!("  help            显示此帮助信息");
     println!("  version         显示版本信息");
     println!();
@@ -287,6 +946,949 @@ fn display_help() {
     println!("  {} mine \"我的第一笔交易\"", env::args().next().unwrap_or_else(|| "blockchain".to_string()));
     println!("  {} validate", env::args().next().unwrap_or_else(|| "blockchain".to_string()));
     println!("  {} display", env::args().next().unwrap_or_else(|| "blockchain".to_string()));
+    println!("  {} --store log mine \"写入日志存储\"", env::args().next().unwrap_or_else(|| "blockchain".to_string()));
+}
+
+/// # 迁移区块链文件格式
+/// 
+/// 识别文件的格式版本并升级到当前版本。`--dry-run` 模式下只显示
+/// 将要执行的迁移步骤，并确认迁移后的数据能通过验证，不修改文件。
+/// 
+/// ## 参数
+/// * `path` - 要迁移的JSON文件
+/// * `dry_run` - 是否只预演
+/// * `spec` - 验证迁移结果使用的链规范
+fn migrate_chain_file(path: &Path, dry_run: bool, spec: &ChainSpec) {
+    match migration::migrate_file(path, dry_run, spec) {
+        Ok(report) if report.is_up_to_date() => {
+            show_success(&format!("{} 已是最新格式（版本 {}）", path.display(), report.to_version));
+        }
+        Ok(report) => {
+            println!("格式版本: {} → {}", report.from_version, report.to_version);
+            for (i, step) in report.steps.iter().enumerate() {
+                println!("  {}. {}", i + 1, step);
+            }
+            if dry_run {
+                show_info("预演模式：迁移后的数据验证通过，文件未修改");
+            } else {
+                show_success(&format!(
+                    "迁移完成，原文件已备份为 {}",
+                    storage::backup_path(path, 1).display()
+                ));
+            }
+        }
+        Err(e) => {
+            show_error(&format!("迁移失败: {}", e));
+            process::exit(1);
+        }
+    }
+}
+
+/// # 流式验证区块链
+/// 
+/// 不把整条链加载到内存，边读边验证，适合非常大的区块链。
+/// 验证过程中在同一行刷新进度。
+/// 
+/// ## 数据来源
+/// - 指定了文件时验证该文件（JSON或二进制格式）
+/// - JSON存储直接流式读取数据目录中的文件
+/// - 其他存储后端通过 `iter_range` 逐块读取
+/// 
+/// ## 参数
+/// * `path` - 要验证的文件，None 表示使用当前存储后端
+/// * `until` - 验证到该高度后停止
+/// * `options` - 全局选项，用于选择存储后端和链规范
+fn stream_validate(path: Option<&Path>, until: Option<u64>, options: &CliOptions) {
+    let progress = |p: stream::Progress| {
+        match p.percent {
+            Some(percent) => print!("\r⏳ 已验证 {} 个区块 ({:.1}%)", p.blocks, percent),
+            None => print!("\r⏳ 已验证 {} 个区块", p.blocks),
+        }
+        let _ = io::stdout().flush();
+    };
+    
+    let json_path = options.data_dir.join(storage::JSON_FILE_NAME);
+    let result = match (path, options.store) {
+        (Some(path), _) => stream::validate_file(path, &options.spec, until, progress),
+        (None, StoreKind::Json) => stream::validate_file(&json_path, &options.spec, until, progress),
+        (None, _) => {
+            let store = options.open_store();
+            let total = store.block_count();
+            stream::validate_blocks(store.iter_range(0, total), total, &options.spec, until, progress)
+        }
+    };
+    println!();
+    
+    match result {
+        Ok(report) if report.stopped_early => show_success(&format!(
+            "前 {} 个区块验证通过（在高度 {} 停止）✅",
+            report.blocks_checked, report.tip.index
+        )),
+        Ok(report) => show_success(&format!(
+            "区块链验证通过 ✅ 共 {} 个区块，最新哈希 {}",
+            report.blocks_checked,
+            format_hash(&report.tip.hash, 16)
+        )),
+        Err(e) => {
+            show_error(&format!("区块链验证失败 ❌ {}", e));
+            process::exit(1);
+        }
+    }
+}
+
+/// # 读取形如 `--name <值>` 或 `--name=<值>` 的命令参数
+/// 
+/// ## 返回值
+/// * `Ok(None)` - 没有出现该参数
+/// * `Ok(Some(value))` - 参数的值
+/// * `Err(String)` - 出现了参数但缺少值
+fn flag_value<'a>(args: &'a [String], name: &str) -> Result<Option<&'a str>, String> {
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        return match arg.strip_prefix(name) {
+            Some("") => match rest.next() {
+                Some(value) => Ok(Some(value)),
+                None => Err(format!("{} 需要一个参数", name)),
+            },
+            Some(value) if value.starts_with('=') => Ok(Some(&value[1..])),
+            _ => continue,
+        };
+    }
+    Ok(None)
+}
+
+/// # 读取可以重复出现的命令参数，例如 `--peer a --peer b`
+/// 
+/// ## 返回值
+/// 按出现顺序排列的所有值；某次出现缺少值时返回错误
+fn flag_values<'a>(args: &'a [String], name: &str) -> Result<Vec<&'a str>, String> {
+    let mut values = Vec::new();
+    let mut rest = args;
+    while let Some(position) = rest.iter().position(|arg| arg == name || arg.starts_with(&format!("{}=", name))) {
+        let tail = &rest[position..];
+        match flag_value(tail, name)? {
+            Some(value) => values.push(value),
+            None => break,
+        }
+        // 跳过本次出现的选项（以及单独的值）
+        rest = &tail[if tail[0] == name { 2 } else { 1 }..];
+    }
+    Ok(values)
+}
+
+/// # 解析形如 `--name <数字>` 或 `--name=<数字>` 的命令参数
+/// 
+/// ## 返回值
+/// * `Ok(None)` - 没有出现该参数
+/// * `Ok(Some(n))` - 解析成功
+/// * `Err(String)` - 缺少值或值不是正整数
+fn parse_usize_flag(args: &[String], name: &str) -> Result<Option<usize>, String> {
+    match flag_value(args, name) {
+        Ok(Some(value)) => match value.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(Some(n)),
+            _ => Err(format!("{} 需要一个正整数", name)),
+        },
+        Ok(None) => Ok(None),
+        Err(_) => Err(format!("{} 需要一个正整数", name)),
+    }
+}
+
+/// # 取出不属于任何选项的位置参数
+/// 
+/// `valued_flags` 中的选项会连同其后的值一起跳过，其他以 `--` 开头的参数视为开关。
+fn positional_args<'a>(args: &'a [String], valued_flags: &[&str]) -> Vec<&'a str> {
+    let mut positional = Vec::new();
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        if valued_flags.contains(&arg.as_str()) {
+            rest.next();
+        } else if !arg.starts_with("--") {
+            positional.push(arg.as_str());
+        }
+    }
+    positional
+}
+
+/// 显示错误信息并以退出代码1结束程序
+fn exit_with_error(message: &str) -> ! {
+    show_error(message);
+    process::exit(1);
+}
+
+/// 解析时间参数，格式错误时退出程序
+fn time_flag(args: &[String], name: &str, end_of_day: bool) -> Option<DateTime<Utc>> {
+    let value = flag_value(args, name).unwrap_or_else(|e| exit_with_error(&e))?;
+    Some(parse_time(value, end_of_day).unwrap_or_else(|e| exit_with_error(&e)))
+}
+
+/// 解析分页参数 `--page`（默认1）和 `--per-page`（默认20），格式错误时退出程序
+fn page_options(args: &[String]) -> (usize, usize) {
+    let page = parse_usize_flag(args, "--page").unwrap_or_else(|e| exit_with_error(&e));
+    let per_page = parse_usize_flag(args, "--per-page").unwrap_or_else(|e| exit_with_error(&e));
+    (page.unwrap_or(1), per_page.unwrap_or(DEFAULT_PAGE_SIZE))
+}
+
+/// 解析区块高度参数，格式错误时退出程序
+fn height_flag(args: &[String], name: &str) -> Option<u64> {
+    let value = flag_value(args, name).unwrap_or_else(|e| exit_with_error(&e))?;
+    match value.parse::<u64>() {
+        Ok(height) => Some(height),
+        Err(_) => exit_with_error(&format!("{} 需要一个区块高度", name)),
+    }
+}
+
+/// 解析 `--format` 参数，未指定时按文件扩展名推断，格式错误时退出程序
+fn export_format(args: &[String], path: &Path) -> ExportFormat {
+    match flag_value(args, "--format").unwrap_or_else(|e| exit_with_error(&e)) {
+        Some(format) => format.parse().unwrap_or_else(|e: String| exit_with_error(&e)),
+        None => ExportFormat::from_extension(path),
+    }
+}
+
+/// # 分页显示区块列表
+/// 
+/// 每个区块显示一行：高度、时间、简化的哈希和截断的数据，
+/// 末尾显示页码和结果总数。
+/// 
+/// ## 参数
+/// * `title` - 列表标题
+/// * `blocks` - 全部查询结果
+/// * `page` - 要显示的页码（从1开始）
+/// * `per_page` - 每页显示的区块数
+fn display_block_page(title: &str, blocks: &[&Block], page: usize, per_page: usize) {
+    println!("\n{}", format!("🔍 ===== {} =====", title).bright_yellow());
+    if blocks.is_empty() {
+        show_info("没有找到匹配的区块");
+        return;
+    }
+    
+    let (items, total_pages) = paginate(blocks, page, per_page);
+    if items.is_empty() {
+        show_warning(&format!("页码超出范围，共 {} 页", total_pages));
+        return;
+    }
+    
+    for block in items {
+        println!(
+            "#{:<6} {}  {}  {}",
+            block.index,
+            block.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            format_hash(&block.hash, 16).bright_green(),
+            truncate_text(&block.data, 40)
+        );
+    }
+    println!("第 {}/{} 页，共 {} 个区块", page, total_pages, blocks.len());
+    if page < total_pages {
+        show_info(&format!("使用 --page {} 查看下一页", page + 1));
+    }
+}
+
+/// # 验证区块链
+/// 
+/// 读取区块链（不经过加载时的自动验证），再用指定的线程数完整验证一次，
+/// 显示验证耗时。验证失败时以非零退出代码退出，便于脚本检测。
+/// 
+/// ## 参数
+/// * `options` - 全局选项，用于选择存储后端和链规范
+/// * `threads` - 验证使用的线程数
+fn validate_stored_blockchain(options: &CliOptions, threads: usize) {
+    let json_path = options.data_dir.join(storage::JSON_FILE_NAME);
+    let read = match options.store {
+        StoreKind::Json if !json_path.exists() => Err(None),
+        StoreKind::Json => Blockchain::read_from_file(&json_path).map_err(Some),
+        _ => {
+            let store = options.open_store();
+            // 只有真正为空的存储才视为没有区块链，读取错误照常报告
+            if store.block_count() == 0 {
+                Err(None)
+            } else {
+                Blockchain::read_from_store(store.as_ref()).map_err(Some)
+            }
+        }
+    };
+    let mut blockchain = match read {
+        Ok(blockchain) => blockchain,
+        Err(None) => {
+            // 与其他命令一致：没有数据时验证一条新的区块链
+            show_info("未找到现有区块链，创建新的区块链");
+            Blockchain::with_spec(options.spec.clone())
+        }
+        Err(Some(e)) => {
+            show_error(&format!("无法读取区块链: {}", e));
+            process::exit(1);
+        }
+    };
+    
+    blockchain.set_spec(options.spec.clone());
+    if let Some(checkpoint) = options.spec.last_checkpoint()
+        && options.spec.assume_valid
+    {
+        show_info(&format!("信任检查点 #{} 及之前的区块，跳过它们的哈希重算和工作量证明检查", checkpoint.height));
+    }
+    
+    let start = Instant::now();
+    match blockchain.validate_parallel(threads) {
+        Ok(()) => show_success(&format!(
+            "区块链验证通过 ✅ 共 {} 个区块，{} 个线程，耗时 {}",
+            blockchain.chain.len(),
+            threads,
+            format_duration(start.elapsed().as_secs_f64())
+        )),
+        Err(e) => {
+            show_error(&format!("区块链验证失败 ❌ {}", e));
+            // 验证失败时使用非零退出代码，便于脚本检测
+            process::exit(1);
+        }
+    }
+}
+
+/// # 验证性能基准测试
+/// 
+/// 在内存中生成一条指定长度的区块链，对比单线程和多线程验证的耗时。
+/// 不读写数据目录。
+/// 
+/// ## 参数
+/// * `blocks` - 生成的区块数
+/// * `threads` - 并行验证使用的线程数
+fn run_validation_bench(blocks: usize, threads: usize) {
+    println!("⛏️  正在生成 {} 个区块（难度 {}）...", blocks, bench::BENCH_DIFFICULTY);
+    let start = Instant::now();
+    let blockchain = bench::synthetic_chain(blocks);
+    println!("   生成耗时 {}", format_duration(start.elapsed().as_secs_f64()));
+    
+    let result = bench::bench_validation(&blockchain, threads);
+    println!("🔍 验证 {} 个区块（可用CPU核心: {}）:", result.blocks, Blockchain::default_validation_threads());
+    println!("   1 个线程:  {}", format_duration(result.sequential.as_secs_f64()));
+    println!("   {} 个线程: {}", result.threads, format_duration(result.parallel.as_secs_f64()));
+    show_success(&format!("加速比 {:.2}x", result.speedup()));
+}
+
+/// # 导出区块
+/// 
+/// 直接从存储后端逐块读取并写出，不需要先把整条链加载为 `Blockchain`。
+/// 目标为 `-` 时写到标准输出，便于接入管道（此时不输出任何提示信息）。
+/// 
+/// ## 参数
+/// * `store` - 存储后端
+/// * `target` - 输出文件路径，或 `-`
+/// * `format` - 导出格式
+/// * `from` / `to` - 导出的高度范围（含两端），`to` 为 None 表示到最新区块
+fn export_chain(store: &dyn ChainStore, target: &Path, format: ExportFormat, from: u64, to: Option<u64>) {
+    let end = to.map_or(store.block_count(), |to| to.saturating_add(1).min(store.block_count()));
+    let blocks = store.iter_range(from, end);
+    
+    let result = if target == Path::new("-") {
+        export::export_blocks(blocks, format, io::stdout().lock())
+    } else {
+        fs::File::create(target)
+            .map_err(Into::into)
+            .and_then(|file| export::export_blocks(blocks, format, io::BufWriter::new(file)))
+    };
+    
+    match result {
+        Ok(_) if target == Path::new("-") => {}
+        Ok(count) => show_success(&format!("已导出 {} 个区块到 {}", count, target.display())),
+        Err(e) => exit_with_error(&format!("导出失败: {}", e)),
+    }
+}
+
+/// # 导入区块
+/// 
+/// 读取导出文件，逐个验证区块并合并到当前区块链，全部通过后才保存。
+/// 合并规则见 `export::merge_blocks`：已有的区块必须完全一致，
+/// 新区块必须与链尾相连，任何无效区块都会使整个导入失败。
+/// 
+/// ## 参数
+/// * `store` - 存储后端，导入结果会保存到这里
+/// * `source` - 导入文件路径
+/// * `format` - 导入文件的格式
+/// * `spec` - 验证导入区块使用的链规范
+fn import_chain(store: &mut dyn ChainStore, source: &Path, format: ExportFormat, spec: &ChainSpec) {
+    let existing = if store.block_count() == 0 {
+        None
+    } else {
+        match Blockchain::load_from_store(store, spec.clone()) {
+            Ok(blockchain) => Some(blockchain),
+            Err(e) => exit_with_error(&format!("无法加载现有区块链: {}", e)),
+        }
+    };
+    
+    let result = fs::File::open(source)
+        .map_err(Into::into)
+        .and_then(|file| export::read_blocks(io::BufReader::new(file), format))
+        .and_then(|blocks| export::merge_blocks(existing, blocks, spec));
+    
+    match result {
+        Ok((blockchain, report)) => {
+            if let Err(e) = blockchain.save_to_store(store) {
+                exit_with_error(&format!("保存失败: {}", e));
+            }
+            show_success(&format!(
+                "导入完成：新增 {} 个区块，跳过 {} 个已有区块，当前共 {} 个区块",
+                report.appended,
+                report.skipped,
+                blockchain.chain.len()
+            ));
+        }
+        Err(e) => exit_with_error(&format!("导入失败，区块链未修改: {}", e)),
+    }
+}
+
+/// # 按链规范初始化区块链
+/// 
+/// 只能在空的数据目录中执行。链规范写入所选网络数据目录的 `chainspec.json`，
+/// 之后的命令都按它加载和验证区块链；然后创建创世区块并保存。
+/// 
+/// ## 参数
+/// * `store` - 存储后端，必须为空
+/// * `spec` - 新区块链的链规范，见 `spec_option`
+/// * `options` - 全局选项，提供网络和数据目录
+fn init_chain(store: &mut dyn ChainStore, spec: ChainSpec, options: &CliOptions) {
+    if store.block_count() > 0 {
+        exit_with_error(&format!("{} 中已有区块链，只能在空的数据目录中初始化", store.location()));
+    }
+    
+    let spec_path = options.data_dir.join(chainspec::SPEC_FILE_NAME);
+    if let Err(e) = fs::create_dir_all(&options.data_dir).map_err(Into::into).and_then(|_| spec.save(&spec_path)) {
+        exit_with_error(&format!("无法写入链规范: {}", e));
+    }
+    
+    let blockchain = Blockchain::with_spec(spec);
+    if let Err(e) = blockchain.save_to_store(store) {
+        exit_with_error(&format!("保存失败: {}", e));
+    }
+    
+    let spec = blockchain.spec();
+    show_success(&format!(
+        "已在 {} 网络创建区块链 {}（链规范已写入 {}）",
+        options.network,
+        spec.chain_id,
+        spec_path.display()
+    ));
+    println!("🔗 创世区块哈希: {}", blockchain.get_latest_block().hash);
+    println!("⛏️  初始难度: {}，初始奖励: {}", spec.initial_difficulty, spec.reward_at(1));
+}
+
+/// 读取 `--spec <文件>` 指定的链规范，没有指定时使用所选网络的预设，文件无效时退出
+fn spec_option(args: &[String], options: &CliOptions) -> ChainSpec {
+    match flag_value(args, "--spec").unwrap_or_else(|e| exit_with_error(&e)) {
+        Some(path) => ChainSpec::load(Path::new(path)).unwrap_or_else(|e| exit_with_error(&e.to_string())),
+        None => options.network.spec(),
+    }
+}
+
+/// # 创建命名区块链
+/// 
+/// 在网络数据目录的 `chains/` 下创建新的数据目录，再按 `init` 的流程写入链规范和创世区块。
+/// 
+/// ## 参数
+/// * `name` - 新区块链的名称
+/// * `spec` - 新区块链的链规范
+/// * `options` - 全局选项，提供网络、存储后端等设置
+fn create_chain(name: &str, spec: ChainSpec, options: &CliOptions) {
+    if let Err(e) = chains::create(&options.network_dir, name) {
+        exit_with_error(&format!("创建失败: {}", e));
+    }
+    let chain_options = options.for_chain(name).unwrap_or_else(|e| exit_with_error(&e));
+    let _lock = chain_options.lock(LockMode::Exclusive);
+    let mut store = chain_options.open_store();
+    init_chain(store.as_mut(), spec, &chain_options);
+    println!("💡 使用 --chain {} 在这条区块链上执行命令", name);
+}
+
+/// # 列出网络下的所有区块链
+/// 
+/// 显示每条区块链的区块数、链ID和数据目录，当前选择的区块链用 `*` 标出。
+/// 列出时不加锁，正在被其他进程写入的区块链可能显示为无法读取。
+fn list_chains(options: &CliOptions) {
+    let names = chains::list(&options.network_dir).unwrap_or_else(|e| exit_with_error(&format!("无法列出区块链: {}", e)));
+    
+    println!("{}", format!("🗂️  {} 网络的区块链（{}）", options.network, options.network_dir.display()).bright_cyan());
+    for name in names {
+        let marker = if name == options.chain { "*" } else { " " };
+        let summary = options
+            .for_chain(&name)
+            .and_then(|chain| {
+                let store = chain.store.open(&chain.data_dir, &chain.spec).map_err(|e| e.to_string())?;
+                Ok(format!("{} 个区块，链ID {}", store.block_count(), chain.spec.chain_id))
+            })
+            .unwrap_or_else(|e| format!("无法读取: {}", e));
+        println!(
+            "{} {:<20} {:<40} {}",
+            marker,
+            name,
+            summary,
+            chains::chain_dir(&options.network_dir, &name).display()
+        );
+    }
+}
+
+/// # 交互模式下切换区块链
+/// 
+/// 列出可用的区块链，让用户输入名称，然后锁定并加载新的区块链。
+/// 新区块链的锁、存储和数据都准备好之后才替换当前的，任何一步失败都会留在原来的区块链上。
+fn switch_chain(
+    options: &mut CliOptions,
+    lock: &mut Option<DataDirLock>,
+    store: &mut Box<dyn ChainStore>,
+    blockchain: &mut Blockchain,
+) {
+    list_chains(options);
+    let name = get_string_input("\n请输入要切换到的区块链名称: ");
+    if name == options.chain {
+        show_info(&format!("已经在使用区块链 {}", name));
+        return;
+    }
+    
+    let result = options.for_chain(&name).and_then(|chain_options| {
+        let new_lock = chain_options.try_lock(LockMode::Exclusive).map_err(|e| e.to_string())?;
+        let new_store = chain_options.store.open(&chain_options.data_dir, &chain_options.spec).map_err(|e| e.to_string())?;
+        let new_blockchain = try_load_or_create_blockchain(new_store.as_ref(), &chain_options.spec).map_err(|e| e.to_string())?;
+        Ok((chain_options, new_lock, new_store, new_blockchain))
+    });
+    match result {
+        Ok((chain_options, new_lock, new_store, new_blockchain)) => {
+            *blockchain = new_blockchain;
+            *lock = new_lock;
+            *store = new_store;
+            *options = chain_options;
+            show_success(&format!("已切换到区块链 {}", name));
+        }
+        Err(e) => show_error(&format!("无法切换到区块链 {}: {}", name, e)),
+    }
+}
+
+/// # 运行P2P节点
+/// 
+/// 加载（或创建）区块链后在 `listen` 上监听，并连接 `peers` 中的每个节点。
+/// 标准输入的每一行都会被挖成一个新区块（同时打包交易池中的交易）并通告给所有连接，
+/// 以 `tx ` 开头的行作为交易加入交易池并转发给所有连接；
+/// 标准输入结束后节点继续运行，按 Ctrl+C 退出。每个区块在接受后立即保存。
+/// 
+/// ## 参数
+/// * `store` - 存储后端，交给节点在整个运行期间使用
+/// * `listen` - 监听地址
+/// * `peers` - 启动时主动连接的节点地址，连接失败只显示警告
+/// * `config` - 连接数量的限制和地址簿
+/// * `rpc` - JSON-RPC 服务的监听地址，None 表示不开启
+/// * `rest` - REST 服务的监听地址，None 表示不开启
+/// * `options` - 全局选项：加载区块链的链规范，写入RPC认证 cookie 的数据目录
+fn run_node(
+    mut store: Box<dyn ChainStore>,
+    listen: &str,
+    peers: &[&str],
+    config: NodeConfig,
+    rpc: Option<&str>,
+    rest: Option<&str>,
+    options: &CliOptions,
+) {
+    let blockchain = load_or_create_blockchain(store.as_ref(), &options.spec);
+    save_blockchain_silent(&blockchain, store.as_mut());
+    let known = config.address_book.addresses().len();
+    // 节点事件既打印在命令行，也推送给事件流的订阅者
+    let hub = Arc::new(EventHub::new());
+    let publisher = Arc::clone(&hub);
+    let on_event = move |event: NodeEvent| {
+        publisher.publish(&event);
+        print_node_event(event);
+    };
+    let node = Node::start(listen, blockchain, store, config, on_event)
+        .map(Arc::new)
+        .unwrap_or_else(|e| exit_with_error(&format!("无法在 {} 上启动节点: {}", listen, e)));
+    show_success(&format!("节点已启动，监听 {}，当前高度 {}", node.local_addr(), node.tip().index));
+    if known > 0 {
+        show_info(&format!("地址簿中有 {} 个已知节点，将自动连接", known));
+    }
+    // RPC 服务在节点退出时随之停止并删除 cookie
+    let rpc_server = rpc.map(|addr| {
+        let server = RpcServer::start(addr, Arc::clone(&node), &options.data_dir)
+            .unwrap_or_else(|e| exit_with_error(&format!("无法在 {} 上启动 RPC 服务: {}", addr, e)));
+        show_success(&format!(
+            "RPC 服务监听 {}，认证 cookie 保存在 {}",
+            server.local_addr(),
+            options.data_dir.join(rpc::COOKIE_FILE_NAME).display()
+        ));
+        server
+    });
+    // REST 的写入接口使用 RPC 的 cookie 认证，没有开启 RPC 时只读
+    let _rest_server = rest.map(|addr| {
+        let write_credentials = rpc_server.as_ref().map(RpcServer::credentials);
+        let server = RestServer::start(addr, Arc::clone(&node), Arc::clone(&hub), write_credentials)
+            .unwrap_or_else(|e| exit_with_error(&format!("无法在 {} 上启动 REST 服务: {}", addr, e)));
+        show_success(&format!(
+            "REST 服务监听 http://{0}/blocks，事件流 http://{0}/events{1}",
+            server.local_addr(),
+            if write_credentials.is_some() { "" } else { "（只读：开启 --rpc 后 POST /data 才可用）" }
+        ));
+        server
+    });
+    
+    let mut connected = 0;
+    for peer in peers {
+        match node.connect(peer) {
+            Ok(_) => connected += 1,
+            Err(e) => show_warning(&format!("无法连接 {}: {}", peer, e)),
+        }
+    }
+    if !peers.is_empty() {
+        // 握手在后台进行，稍等片刻再报告，握手失败的节点不会计入
+        let deadline = Instant::now() + Duration::from_secs(3);
+        while node.peer_count() < connected && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        // 地址交换可能很快带来命令行没有指定的节点，因此分别报告
+        show_info(&format!("已连接 {} 个节点（命令行指定了 {} 个）", node.peer_count(), peers.len()));
+    }
+    
+    println!("💡 输入一行文字挖出新区块（以 tx 开头的行作为交易提交），按 Ctrl+C 退出");
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        let data = line.trim();
+        if data.is_empty() {
+            continue;
+        }
+        if let Some(transaction) = data.strip_prefix("tx ") {
+            match node.submit_transaction(transaction.trim().to_string()) {
+                Ok(true) => show_success("交易已加入交易池并转发"),
+                Ok(false) => show_info("交易池中已有这条交易"),
+                Err(e) => show_error(&format!("交易被拒绝: {}", e)),
+            }
+            continue;
+        }
+        if let Err(e) = node.mine(data.to_string()) {
+            show_error(&format!("挖矿失败: {}", e));
+        }
+    }
+    node.wait();
+}
+
+/// 在命令行显示节点事件
+fn print_node_event(event: NodeEvent) {
+    match event {
+        NodeEvent::PeerConnected(addr) => show_info(&format!("已连接 {}", addr)),
+        NodeEvent::PeerDisconnected(addr) => show_info(&format!("{} 已断开", addr)),
+        NodeEvent::HandshakeFailed { addr, reason } => {
+            show_warning(&format!("与 {} 握手失败: {}", addr, reason))
+        }
+        NodeEvent::BlockAccepted { height, hash, from } => {
+            let source = from.map_or_else(|| "本地挖出".to_string(), |addr| format!("来自 {}", addr));
+            show_success(&format!("区块 #{} {}（{}）", height, format_hash(&hash, 16), source));
+        }
+        NodeEvent::BlockRejected { height, from, reason } => {
+            show_warning(&format!("拒绝来自 {} 的区块 #{}: {}", from, height, reason));
+        }
+        NodeEvent::SyncProgress { height, target } => {
+            let percent = height as f64 * 100.0 / target.max(1) as f64;
+            print!("\r🔄 正在同步区块 {}/{} ({:.1}%)", height, target, percent);
+            let _ = io::stdout().flush();
+            if height == target {
+                println!();
+                show_success(&format!("同步完成，当前高度 {}", height));
+            }
+        }
+        NodeEvent::Reorganized { fork_height, rolled_back, height, restored } => show_warning(&format!(
+            "切换到工作量更大的链：从区块 #{} 分叉，回滚 {} 个区块，{} 个交易回到交易池，当前高度 {}",
+            fork_height, rolled_back, restored, height
+        )),
+        NodeEvent::PeerBanned { addr, reason } => {
+            show_warning(&format!("封禁 {} {} 小时: {}", addr.ip(), peers::BAN_HOURS, reason))
+        }
+        NodeEvent::TransactionsReceived { count, from } => {
+            show_info(&format!("收到来自 {} 的 {} 条新交易", from, count))
+        }
+        // 挖矿进度只推送给事件流的订阅者，不在命令行刷屏
+        NodeEvent::MiningProgress { .. } => {}
+        NodeEvent::DifficultyChanged { from, to } => show_info(&format!("挖矿难度从 {} 调整为 {}", from, to)),
+        NodeEvent::ValidationFailed { height, reason } => {
+            show_error(&format!("区块链验证失败（链尖高度 {}）: {}", height, reason))
+        }
+        NodeEvent::SaveFailed(reason) => show_warning(&format!("自动保存失败: {}", reason)),
+    }
+}
+
+/// # 显示节点地址簿
+///
+/// 地址簿由运行中的节点定期保存，读取时不需要锁定数据目录。
+/// 数据目录没有被节点锁定时，文件中的连接列表是上一次运行遗留的，不显示。
+fn list_peers(options: &CliOptions) {
+    let book = AddressBook::load_from_dir(&options.data_dir)
+        .unwrap_or_else(|e| exit_with_error(&format!("无法加载地址簿: {}", e)));
+    let now = Utc::now();
+    let running = DataDirLock::acquire(&options.data_dir, LockMode::Shared, Duration::ZERO).is_err();
+    
+    println!("{}", format!("🌐 节点地址簿（{}）", options.data_dir.join(peers::ADDRESS_BOOK_FILE_NAME).display()).bright_cyan());
+    if running {
+        println!("当前连接 ({}):", book.connections().len());
+        for connection in book.connections() {
+            println!(
+                "  {:<22} {:<4} 高度 {:<8} 分数 {:<4} 连接于 {}  {}",
+                connection.addr,
+                if connection.inbound { "入站" } else { "出站" },
+                connection.best_height,
+                connection.score,
+                connection.since.format("%Y-%m-%d %H:%M:%S"),
+                connection.user_agent
+            );
+        }
+    } else {
+        println!("当前连接: 节点未运行");
+    }
+    
+    println!("已知地址 ({}):", book.addresses().len());
+    for (addr, known) in book.addresses() {
+        let connected = known
+            .last_connected
+            .map_or_else(|| "从未连接".to_string(), |time| format!("最后连接于 {}", time.format("%Y-%m-%d %H:%M:%S")));
+        let failures = if known.failures > 0 { format!("，连续失败 {} 次", known.failures) } else { String::new() };
+        println!("  {:<22} {}{}", addr, connected, failures);
+    }
+    
+    let bans = book.active_bans(now);
+    println!("已封禁 ({}):", bans.len());
+    for (ip, ban) in bans {
+        println!("  {:<22} 至 {}  {}", ip, ban.until.format("%Y-%m-%d %H:%M:%S UTC"), ban.reason);
+    }
+}
+
+/// # 调用运行中节点的 RPC 方法
+///
+/// 从数据目录读取节点写入的认证 cookie，调用方法后把结果以格式化的 JSON 输出到标准输出，
+/// 便于脚本处理；RPC 错误显示错误码和说明，并以退出代码1结束。
+fn call_rpc(options: &CliOptions, addr: &str, method: &str, params: &[String]) {
+    let credentials = rpc::read_cookie(&options.data_dir).unwrap_or_else(|e| exit_with_error(&e.to_string()));
+    match rpc::call(addr, &credentials, method, rpc::cli_params(method, params)) {
+        Ok(Ok(result)) => {
+            println!("{}", serde_json::to_string_pretty(&result).expect("JSON 值总能序列化"));
+        }
+        Ok(Err(error)) => exit_with_error(&error.to_string()),
+        Err(e) => exit_with_error(&format!("无法调用 {} 上的 RPC: {}", addr, e)),
+    }
+}
+
+/// # 显示交易池
+///
+/// 交易池随区块链元数据一起保存，这里用 `StoreKind::read_metadata` 只读地读取，不锁定数据目录，
+/// 节点运行时也能查看；节点收到的交易在下一次自动保存后才能在这里看到。
+fn list_mempool(options: &CliOptions) {
+    let metadata = options
+        .store
+        .read_metadata(&options.data_dir)
+        .unwrap_or_else(|e| exit_with_error(&format!("无法读取交易池: {}", e)));
+    let transactions = metadata.map(|metadata| metadata.pending_transactions).unwrap_or_default();
+    println!("{}", format!("📥 交易池 ({} 条待打包)", transactions.len()).bright_cyan());
+    for data in &transactions {
+        println!("  {}  {}", format_hash(&mempool::transaction_id(data), 16), truncate_text(data, 60));
+    }
+}
+
+/// # 加载已有的区块链，失败时退出
+/// 
+/// 与 `load_or_create_blockchain` 不同，裁剪、快照等操作没有意义的情况下
+/// 不会创建新的区块链。
+fn load_existing_blockchain(store: &dyn ChainStore, spec: &ChainSpec) -> Blockchain {
+    if store.block_count() == 0 {
+        exit_with_error(&format!("{} 中没有区块链", store.location()));
+    }
+    Blockchain::load_from_store(store, spec.clone())
+        .unwrap_or_else(|e| exit_with_error(&format!("无法加载现有区块链: {}", e)))
+}
+
+/// # 裁剪区块数据
+/// 
+/// 加载并完整验证区块链后，丢弃低于指定高度的区块数据，然后重写存储。
+/// 输出已裁剪部分的区块头承诺，便于与快照或其他节点核对。
+/// 
+/// ## 参数
+/// * `store` - 存储后端
+/// * `height` - 裁剪高度，低于这个高度的区块只保留区块头
+/// * `spec` - 加载区块链时使用的链规范
+fn prune_chain(store: &mut dyn ChainStore, height: u64, spec: &ChainSpec) {
+    let mut blockchain = load_existing_blockchain(store, spec);
+    let bytes_before: usize = blockchain.chain.iter().map(|block| block.data.len()).sum();
+    
+    let pruned = blockchain.prune_below(height).unwrap_or_else(|e| exit_with_error(&e.to_string()));
+    if pruned == 0 {
+        show_info(&format!("高度 {} 以下的区块已经全部裁剪", height));
+        return;
+    }
+    if let Err(e) = blockchain.rewrite_store(store) {
+        exit_with_error(&format!("保存失败: {}", e));
+    }
+    
+    let bytes_after: usize = blockchain.chain.iter().map(|block| block.data.len()).sum();
+    let pruned_count = blockchain.pruned_count();
+    show_success(&format!(
+        "已裁剪 {} 个区块的数据，释放 {} 字节（共 {} 个区块已裁剪）",
+        pruned,
+        bytes_before - bytes_after,
+        pruned_count
+    ));
+    println!(
+        "🔏 区块头承诺 (#0 - #{}): {}",
+        pruned_count - 1,
+        snapshot::commitment(&blockchain.chain[..pruned_count as usize])
+    );
+}
+
+/// # 创建快照
+/// 
+/// ## 参数
+/// * `store` - 存储后端（只读）
+/// * `path` - 快照文件路径
+/// * `height` - 快照高度，默认为当前链尖
+/// * `spec` - 加载区块链时使用的链规范
+fn create_snapshot(store: &dyn ChainStore, path: &Path, height: Option<u64>, spec: &ChainSpec) {
+    let blockchain = load_existing_blockchain(store, spec);
+    let height = height.unwrap_or(blockchain.get_latest_block().index);
+    
+    let result = Snapshot::create(&blockchain, height).and_then(|snapshot| {
+        snapshot.save(path)?;
+        Ok(snapshot)
+    });
+    match result {
+        Ok(snapshot) => {
+            show_success(&format!("已创建快照 {}（高度 {}）", path.display(), snapshot.height));
+            println!("🔗 链尖哈希: {}", snapshot.tip_hash);
+            println!("🔏 区块头承诺: {}", snapshot.commitment);
+        }
+        Err(e) => exit_with_error(&format!("创建快照失败: {}", e)),
+    }
+}
+
+/// # 从快照恢复区块链
+/// 
+/// 只能恢复到空的数据目录。快照会先通过自洽性检查，但已裁剪的区块无法重新计算哈希，
+/// 编造的区块头同样可以自洽，因此还必须有可信的来源：承诺与 `expect`（来自可信来源的承诺）一致，
+/// 或者链规范在快照的最高区块上有检查点。两者都没有时拒绝恢复。
+/// 
+/// ## 参数
+/// * `store` - 存储后端，必须为空
+/// * `path` - 快照文件路径
+/// * `expect` - 期望的区块头承诺
+/// * `spec` - 验证快照使用的链规范，快照必须与其中的创世区块和检查点一致
+fn restore_snapshot(store: &mut dyn ChainStore, path: &Path, expect: Option<&str>, spec: &ChainSpec) {
+    if store.block_count() > 0 {
+        exit_with_error(&format!("{} 中已有区块链，只能把快照恢复到空的数据目录", store.location()));
+    }
+    
+    let snapshot = Snapshot::load(path).unwrap_or_else(|e| exit_with_error(&format!("无法读取快照: {}", e)));
+    if let Err(e) = snapshot.verify(spec) {
+        exit_with_error(&e.to_string());
+    }
+    match expect {
+        Some(expected) if !expected.eq_ignore_ascii_case(&snapshot.commitment) => exit_with_error(&format!(
+            "快照的区块头承诺 {} 与期望的 {} 不一致",
+            snapshot.commitment, expected
+        )),
+        Some(_) => show_success("区块头承诺与期望值一致"),
+        // 检查点的哈希已经在 verify 中核对过
+        None if spec.checkpoint_at(snapshot.height).is_some() => {
+            show_success(&format!("快照的最高区块与链规范中高度 {} 的检查点一致", snapshot.height))
+        }
+        None => exit_with_error(&format!(
+            "快照只通过了自洽性检查，无法证明来自真实的链。请与可信来源核对承诺后用 --expect 指定（快照的承诺为 {}）",
+            snapshot.commitment
+        )),
+    }
+    
+    let height = snapshot.height;
+    let commitment = snapshot.commitment.clone();
+    let blockchain = snapshot.into_blockchain(spec.clone());
+    if let Err(e) = blockchain.save_to_store(store) {
+        exit_with_error(&format!("保存失败: {}", e));
+    }
+    show_success(&format!("已从快照恢复区块链（高度 {}，{} 个区块）", height, blockchain.chain.len()));
+    println!("🔏 区块头承诺: {}", commitment);
+}
+
+/// # 批量导入记录
+/// 
+/// 从文件或标准输入读取记录，按打包规则挖出区块并定期保存。
+/// 中断后用相同的参数再次运行会从上次保存的位置继续。
+/// 
+/// ## 参数
+/// * `store` - 存储后端
+/// * `format` - 输入格式
+/// * `ingest_options` - 打包、保存间隔和输入来源等设置
+/// * `spec` - 加载区块链时使用的链规范
+/// * `data_dir` - 数据目录，断点续传的进度文件保存在这里
+fn ingest_records(
+    store: &mut dyn ChainStore,
+    format: InputFormat,
+    ingest_options: &IngestOptions,
+    spec: &ChainSpec,
+    data_dir: &Path,
+) {
+    let mut blockchain = load_or_create_blockchain(store, spec);
+    
+    let reader: Box<dyn io::BufRead> = if ingest_options.source == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        match fs::File::open(&ingest_options.source) {
+            Ok(file) => Box::new(io::BufReader::new(file)),
+            Err(e) => exit_with_error(&format!("无法打开 {}: {}", ingest_options.source, e)),
+        }
+    };
+    let records = ingest::read_records(reader, format).unwrap_or_else(|e| exit_with_error(&format!("读取输入失败: {}", e)));
+    
+    let state_path = data_dir.join(ingest::STATE_FILE_NAME);
+    let start = Instant::now();
+    let result = ingest::ingest(&mut blockchain, store, records, ingest_options, &state_path, |records, blocks| {
+        println!("💾 已导入 {} 条记录，挖出 {} 个区块", records, blocks);
+    });
+    
+    match result {
+        Ok(report) => {
+            if report.resumed_from > 0 {
+                show_info(&format!("从第 {} 条记录继续导入", report.resumed_from + 1));
+            }
+            show_success(&format!(
+                "导入完成：{} 条记录，{} 个区块，耗时 {}，当前共 {} 个区块",
+                report.records,
+                report.blocks,
+                format_duration(start.elapsed().as_secs_f64()),
+                blockchain.chain.len()
+            ));
+        }
+        Err(e) => {
+            show_error(&format!("导入中断: {}", e));
+            show_info("已保存的进度不会丢失，修复问题后使用相同的参数重新运行即可继续");
+            process::exit(1);
+        }
+    }
+}
+
+/// # 转换区块链文件格式
+/// 
+/// 加载输入文件（自动识别JSON或二进制格式，并通过完整性验证），
+/// 再以指定格式原子写入输出文件。
+/// 
+/// ## 参数
+/// * `input` - 输入文件
+/// * `output` - 输出文件，已存在时原内容会被保留为 `.1` 备份
+/// * `format` - 输出格式
+/// * `spec` - 验证输入文件使用的链规范
+fn convert_chain_file(input: &Path, output: &Path, format: FileFormat, spec: &ChainSpec) {
+    let result = Blockchain::load_from_file(input, spec.clone())
+        .and_then(|blockchain| blockchain.save_to_file_as(output, format).map(|_| blockchain));
+    
+    match result {
+        Ok(blockchain) => {
+            let size = |path: &Path| fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            show_success(&format!(
+                "已转换 {} 个区块: {} ({} 字节) → {} ({} 字节)",
+                blockchain.chain.len(),
+                input.display(),
+                size(input),
+                output.display(),
+                size(output)
+            ));
+        }
+        Err(e) => {
+            show_error(&format!("转换失败: {}", e));
+            process::exit(1);
+        }
+    }
 }
 
 /// # 加载或创建区块链实例
@@ -298,43 +1900,50 @@ fn display_help() {
 /// ## 加载策略
 /// 
 /// ### 优先加载现有数据
-/// 1. 尝试从BLOCKCHAIN_FILE指定的文件读取
+/// 1. 尝试从所选的存储后端读取
 /// 2. 验证加载的区块链完整性
 /// 3. 成功加载时显示区块数量等信息
 /// 
-/// ### 备用创建新链
-/// 当加载失败时（文件不存在、格式错误、验证失败等）：
+/// ### 只在存储为空时创建新链
+/// 存储中没有任何区块和元数据时（初次运行）：
 /// 1. 显示友好的提示信息
 /// 2. 创建包含创世区块的新区块链
-/// 3. 不会保存到文件，等待用户操作后再保存
+/// 3. 不会写入存储，等待用户操作后再保存
+/// 
+/// 存储中已有数据但无法加载时（文件损坏、链规范或检查点不符等），显示原因后以退出代码1结束，
+/// 不会用新链代替：调用者随后的保存会把存储截断到创世区块，丢失已有的全部区块。
 /// 
 /// ## 用户体验设计
 /// - **透明性**：清楚告知用户当前操作状态
-/// - **容错性**：各种错误情况都能优雅处理
 /// - **一致性**：无论加载还是创建，都返回可用的区块链
 /// 
 /// ## 安全考虑
 /// - **验证加载的数据**：通过is_chain_valid()确保数据完整性
-/// - **错误隔离**：文件系统错误不会导致程序崩溃
+/// - **不覆盖已有数据**：加载失败时不修改存储
 /// - **默认安全**：新创建的区块链使用安全的默认配置
 /// 
+/// ## 参数
+/// * `store` - 读取区块链的存储后端
+/// * `spec` - 验证和新建区块链使用的链规范
+/// 
 /// ## 返回值
-/// 返回一个有效的Blockchain实例，要么从文件加载，要么新创建
-fn load_or_create_blockchain() -> Blockchain {
-    // 尝试从文件加载区块链
-    match Blockchain::load_from_file(BLOCKCHAIN_FILE) {
-        Ok(blockchain) => {
-            // 加载成功，显示成功信息和基本统计
-            show_success(&format!("成功加载区块链 ({} 个区块)", blockchain.chain.len()));
-            blockchain
-        }
-        Err(_) => {
-            // 加载失败（文件不存在、格式错误等），创建新区块链
-            // 使用show_info而不是show_error，因为这是正常的初次运行情况
-            show_info("未找到现有区块链，创建新的区块链");
-            Blockchain::new()
-        }
+/// 返回一个有效的Blockchain实例，要么从存储加载，要么新创建
+fn load_or_create_blockchain(store: &dyn ChainStore, spec: &ChainSpec) -> Blockchain {
+    try_load_or_create_blockchain(store, spec)
+        .unwrap_or_else(|e| exit_with_error(&format!("无法加载现有区块链（存储没有被修改）: {}", e)))
+}
+
+/// 与 `load_or_create_blockchain` 相同，但加载失败时返回错误，供交互模式切换区块链时使用
+fn try_load_or_create_blockchain(store: &dyn ChainStore, spec: &ChainSpec) -> Result<Blockchain, BlockchainError> {
+    if store.block_count() == 0 && store.metadata()?.is_none() {
+        // 存储为空，这是正常的初次运行情况
+        show_info("未找到现有区块链，创建新的区块链");
+        return Ok(Blockchain::with_spec(spec.clone()));
     }
+    let blockchain = Blockchain::load_from_store(store, spec.clone())?;
+    // 加载成功，显示成功信息和基本统计
+    show_success(&format!("成功加载区块链 ({} 个区块)", blockchain.chain.len()));
+    Ok(blockchain)
 }
 
 /// # 挖掘新区块功能
@@ -377,7 +1986,8 @@ fn load_or_create_blockchain() -> Blockchain {
 /// 
 /// ## 参数
 /// * `blockchain` - 可变区块链引用，用于添加新区块
-fn mine_new_block(blockchain: &mut Blockchain) {
+/// * `store` - 挖矿成功后自动保存的存储后端
+fn mine_new_block(blockchain: &mut Blockchain, store: &mut dyn ChainStore) {
     // 获取用户输入的区块数据
     // 这些数据将被永久存储在区块链中，不可修改
     let data = get_string_input("请输入区块数据: ");
@@ -386,13 +1996,14 @@ fn mine_new_block(blockchain: &mut Blockchain) {
     // 1000ms的延迟让用户感受到系统正在进行重要操作
     show_loading("准备挖矿", 1000);
     
-    // 执行实际的挖矿操作
+    // 执行实际的挖矿操作，交易池中的交易跟在输入的数据后面一起打包
+    let (data, _) = blockchain.next_block_data(Some(data)).expect("至少包含输入的数据");
     match blockchain.add_block(data) {
         Ok(_) => {
             // 挖矿成功
             show_success("区块挖矿成功！");
             // 静默自动保存，避免额外的用户界面干扰
-            save_blockchain_silent(blockchain);
+            save_blockchain_silent(blockchain, store);
         }
         Err(e) => {
             // 挖矿失败，显示具体错误信息
@@ -463,17 +2074,16 @@ fn validate_blockchain(blockchain: &Blockchain) {
     // 显示验证进度，让用户了解系统正在进行重要操作
     show_loading("验证区块链完整性", 1500);
     
-    // 执行全面的区块链验证
-    if blockchain.is_chain_valid() {
-        show_success("区块链验证通过！所有区块都是有效的。");
-    } else {
-        show_error("区块链验证失败！检测到无效区块。");
+    // 执行全面的区块链验证，多线程并行计算哈希
+    match blockchain.validate_parallel(Blockchain::default_validation_threads()) {
+        Ok(()) => show_success("区块链验证通过！所有区块都是有效的。"),
+        Err(e) => show_error(&format!("区块链验证失败！检测到无效区块: {}", e)),
     }
 }
 
-/// # 保存区块链到文件功能
+/// # 保存区块链到存储功能
 /// 
-/// 将当前区块链状态序列化并持久化到JSON文件。
+/// 将当前区块链状态持久化到所选的存储后端（默认是JSON文件）。
 /// 这是数据备份和状态保持的核心功能，确保数据不会因程序关闭而丢失。
 /// 
 /// ## 保存流程
@@ -483,14 +2093,14 @@ fn validate_blockchain(blockchain: &Blockchain) {
 /// - 1000ms延迟给用户明确的操作反馈
 /// 
 /// ### 2. 执行保存
-/// 调用blockchain.save_to_file()执行实际保存：
-/// - **序列化**：将区块链结构转换为JSON格式
-/// - **文件写入**：将JSON数据写入指定文件
+/// 调用blockchain.save_to_store()执行实际保存：
+/// - **增量写入**：只写入存储中还没有的区块
+/// - **元数据写入**：保存难度、奖励等链级别参数
 /// - **目录创建**：如需要会自动创建目录结构
 /// - **原子操作**：确保文件要么完全写入，要么保持原状
 /// 
 /// ### 3. 结果反馈
-/// - **保存成功**：显示文件路径，确认数据已安全存储
+/// - **保存成功**：显示存储位置，确认数据已安全存储
 /// - **保存失败**：显示错误信息，可能的原因包括权限不足、磁盘空间不足等
 /// 
 /// ## 数据格式
@@ -512,13 +2122,14 @@ fn validate_blockchain(blockchain: &Blockchain) {
 /// 
 /// ## 参数
 /// * `blockchain` - 区块链的不可变引用
-fn save_blockchain(blockchain: &Blockchain) {
+/// * `store` - 目标存储后端
+fn save_blockchain(blockchain: &Blockchain, store: &mut dyn ChainStore) {
     // 显示保存进度，给用户明确的操作反馈
-    show_loading("保存区块链到文件", 1000);
+    show_loading("保存区块链到存储", 1000);
     
-    // 执行实际的文件保存操作
-    match blockchain.save_to_file(BLOCKCHAIN_FILE) {
-        Ok(_) => show_success(&format!("区块链已保存到 {}", BLOCKCHAIN_FILE)),
+    // 执行实际的保存操作
+    match blockchain.save_to_store(store) {
+        Ok(_) => show_success(&format!("区块链已保存到 {}", store.location())),
         Err(e) => show_error(&format!("保存失败: {}", e)),
     }
 }
@@ -552,17 +2163,18 @@ fn save_blockchain(blockchain: &Blockchain) {
 /// 
 /// ## 参数
 /// * `blockchain` - 区块链的不可变引用
-fn save_blockchain_silent(blockchain: &Blockchain) {
+/// * `store` - 目标存储后端
+fn save_blockchain_silent(blockchain: &Blockchain, store: &mut dyn ChainStore) {
     // 尝试保存，只在失败时显示提示
-    if let Err(e) = blockchain.save_to_file(BLOCKCHAIN_FILE) {
+    if let Err(e) = blockchain.save_to_store(store) {
         // 使用警告级别而不是错误级别，因为这是后台操作
         show_warning(&format!("自动保存失败: {}", e));
     }
 }
 
-/// # 从文件加载区块链功能
+/// # 从存储加载区块链功能
 /// 
-/// 从持久化文件中读取并恢复区块链状态。
+/// 从所选的存储后端中读取并恢复区块链状态。
 /// 这是数据恢复和状态切换的重要功能，允许用户在不同的区块链版本间切换。
 /// 
 /// ## 加载流程
@@ -572,9 +2184,9 @@ fn save_blockchain_silent(blockchain: &Blockchain) {
 /// - 1000ms延迟提供明确的操作反馈
 /// 
 /// ### 2. 执行加载
-/// 调用Blockchain::load_from_file()进行文件读取：
-/// - **文件读取**：从指定路径读取JSON文件内容
-/// - **反序列化**：将JSON数据转换为区块链结构
+/// 调用Blockchain::load_from_store()进行读取：
+/// - **区块读取**：按高度顺序读取存储中的所有区块
+/// - **元数据读取**：恢复难度、奖励等链级别参数
 /// - **完整性验证**：自动验证加载的区块链是否有效
 /// - **类型检查**：确保数据格式符合程序期望
 /// 
@@ -601,25 +2213,29 @@ fn save_blockchain_silent(blockchain: &Blockchain) {
 /// - **备份恢复**：从备份文件恢复数据
 /// - **测试验证**：加载特定的测试数据
 /// 
+/// ## 参数
+/// * `store` - 读取区块链的存储后端
+/// * `spec` - 验证区块链使用的链规范
+/// * `current` - 当前的区块链，加载失败时原样返回
+/// 
 /// ## 返回值
 /// 返回一个有效的Blockchain实例，要么是加载的数据，要么是当前状态
-fn load_blockchain() -> Blockchain {
+fn load_blockchain(store: &dyn ChainStore, spec: &ChainSpec, current: Blockchain) -> Blockchain {
     // 显示加载进度动画
-    show_loading("从文件加载区块链", 1000);
+    show_loading("从存储加载区块链", 1000);
     
-    // 尝试从文件加载区块链
-    match Blockchain::load_from_file(BLOCKCHAIN_FILE) {
+    // 尝试从存储加载区块链
+    match Blockchain::load_from_store(store, spec.clone()) {
         Ok(blockchain) => {
             // 加载成功，显示统计信息
             show_success(&format!("成功加载区块链 ({} 个区块)", blockchain.chain.len()));
             blockchain
         }
         Err(e) => {
-            // 加载失败，显示错误并返回默认区块链
+            // 加载失败，显示错误并保持当前区块链
             show_error(&format!("加载失败: {}", e));
             show_info("返回当前区块链");
-            // 使用load_or_create_blockchain()作为后备方案
-            load_or_create_blockchain()
+            current
         }
     }
 }
@@ -670,7 +2286,8 @@ fn load_blockchain() -> Blockchain {
 /// 
 /// ## 参数
 /// * `blockchain` - 可变区块链引用，用于修改难度设置
-fn set_difficulty(blockchain: &mut Blockchain) {
+/// * `store` - 保存配置更改的存储后端
+fn set_difficulty(blockchain: &mut Blockchain, store: &mut dyn ChainStore) {
     // 显示当前挖矿难度，让用户了解现状
     println!("当前挖矿难度: {}", blockchain.difficulty);
     
@@ -679,7 +2296,7 @@ fn set_difficulty(blockchain: &mut Blockchain) {
         // 应用新的难度设置（内部会进行范围检查）
         blockchain.set_difficulty(new_difficulty);
         // 静默保存配置更改
-        save_blockchain_silent(blockchain);
+        save_blockchain_silent(blockchain, store);
     } else {
         // 输入无效，显示错误提示
         show_error("无效输入，请输入1-10之间的数字");
@@ -777,7 +2394,8 @@ fn display_statistics(blockchain: &Blockchain) {
 /// 
 /// ## 参数
 /// * `blockchain` - 可变区块链引用，用于添加新区块
-fn batch_mining(blockchain: &mut Blockchain) {
+/// * `store` - 批量挖矿完成后保存的存储后端
+fn batch_mining(blockchain: &mut Blockchain, store: &mut dyn ChainStore) {
     // 获取要挖掘的区块数量
     if let Some(count) = get_number_input("请输入要挖掘的区块数量: ") {
         // 验证数量在合理范围内
@@ -794,7 +2412,7 @@ fn batch_mining(blockchain: &mut Blockchain) {
             Ok(_) => {
                 show_success("批量挖矿完成！");
                 // 批量操作成功后立即保存
-                save_blockchain_silent(blockchain);
+                save_blockchain_silent(blockchain, store);
             }
             Err(e) => show_error(&format!("批量挖矿失败: {}", e)),
         }
@@ -812,7 +2430,7 @@ fn batch_mining(blockchain: &mut Blockchain) {
 /// 
 /// ### 1. 交互式选择
 /// - 显示区块链总长度，帮助用户了解可用范围
-/// - 允许用户输入特定的区块索引
+/// - 允许用户输入区块索引或区块哈希
 /// - 提供友好的输入提示和错误处理
 /// 
 /// ### 2. 详细信息显示
@@ -846,8 +2464,8 @@ fn batch_mining(blockchain: &mut Blockchain) {
 /// - 只对非创世区块执行此检查
 /// 
 /// ## 错误处理
-/// - **无效索引**：友好提示用户索引超出范围
-/// - **输入错误**：处理非数字输入的情况
+/// - **区块不存在**：友好提示用户索引超出范围或哈希不在链上
+/// - **输入方式**：纯数字按索引查找，其他输入按哈希查找
 /// - **系统错误**：处理意外的系统错误
 /// 
 /// ## 使用场景
@@ -862,58 +2480,57 @@ fn view_block_details(blockchain: &Blockchain) {
     // 显示区块链总长度，帮助用户选择合适的索引
     println!("区块链总长度: {}", blockchain.chain.len());
     
-    // 获取用户要查看的区块索引
-    if let Some(index) = get_number_input("请输入要查看的区块索引: ") {
-        // 尝试获取指定索引的区块
-        match blockchain.get_block(index as u64) {
-            Some(block) => {
-                // 找到区块，显示详细信息
-                
-                // 显示区块基本信息（使用区块的Display实现）
-                println!("\n{}", "🔍 ===== 区块详情 =====".bright_yellow());
-                println!("{}", block);
-                
-                // 显示额外的验证和技术信息
-                println!("\n{}", "📊 ===== 额外信息 =====".bright_blue());
-                
-                // 哈希验证状态
-                println!("哈希验证: {}", 
-                    if block.is_valid() { 
-                        "✅ 通过" 
-                    } else { 
-                        "❌ 失败" 
-                    }
-                );
-                
-                // 工作量证明验证状态
-                println!("工作量证明: {}", 
-                    if block.has_valid_proof_of_work() { 
-                        "✅ 有效" 
+    // 获取用户要查看的区块：纯数字按索引查找，否则按哈希查找
+    let input = get_string_input("请输入要查看的区块索引或哈希: ");
+    let block = match input.parse::<u64>() {
+        Ok(index) => blockchain.get_block(index),
+        Err(_) => blockchain.get_block_by_hash(&input),
+    };
+    match block {
+        Some(block) => {
+            // 找到区块，显示详细信息
+            
+            // 显示区块基本信息（使用区块的Display实现）
+            println!("\n{}", "🔍 ===== 区块详情 =====".bright_yellow());
+            println!("{}", block);
+            
+            // 显示额外的验证和技术信息
+            println!("\n{}", "📊 ===== 额外信息 =====".bright_blue());
+            
+            // 哈希验证状态
+            println!("哈希验证: {}", 
+                if block.is_valid() { 
+                    "✅ 通过" 
+                } else { 
+                    "❌ 失败" 
+                }
+            );
+            
+            // 工作量证明验证状态
+            println!("工作量证明: {}", 
+                if block.has_valid_proof_of_work() { 
+                    "✅ 有效" 
+                } else { 
+                    "❌ 无效" 
+                }
+            );
+            
+            // 链接验证状态（仅对非创世区块）
+            if block.index > 0
+                && let Some(prev_block) = blockchain.get_block(block.index - 1)
+            {
+                println!("链接验证: {}", 
+                    if block.previous_hash == prev_block.hash { 
+                        "✅ 正确" 
                     } else { 
-                        "❌ 无效" 
+                        "❌ 错误" 
                     }
                 );
-                
-                // 链接验证状态（仅对非创世区块）
-                if index > 0 {
-                    if let Some(prev_block) = blockchain.get_block(index as u64 - 1) {
-                        println!("链接验证: {}", 
-                            if block.previous_hash == prev_block.hash { 
-                                "✅ 正确" 
-                            } else { 
-                                "❌ 错误" 
-                            }
-                        );
-                    }
-                }
-            }
-            None => {
-                // 区块不存在，显示友好的错误信息
-                show_error(&format!("区块索引 {} 不存在", index));
             }
         }
-    } else {
-        // 输入无效，显示错误提示
-        show_error("无效输入，请输入数字");
+        None => {
+            // 区块不存在，显示友好的错误信息
+            show_error(&format!("区块 {} 不存在", input));
+        }
     }
 }
\ No newline at end of file
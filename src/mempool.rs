@@ -0,0 +1,211 @@
+// ==================== 依赖库导入 ====================
+// 导入区块结构体，计算交易ID和取出区块中的交易
+use crate::block::Block;
+// 导入多条记录在区块数据中的编码
+use crate::ingest;
+// serde: 交易池与待处理交易列表之间的转换，保持文件格式不变
+use serde::{Deserialize, Serialize};
+// std::collections: 按交易ID去重
+use std::collections::HashSet;
+
+/// 交易池最多容纳的交易数，已满时拒绝新交易
+pub const MAX_MEMPOOL_TRANSACTIONS: usize = 10_000;
+
+/// 一个新区块最多打包的交易数
+pub const MAX_TRANSACTIONS_PER_BLOCK: usize = 100;
+
+/// 一条 `Transactions` 消息最多携带的交易数，更多的交易分批发送
+pub const MAX_TRANSACTIONS_PER_MESSAGE: usize = 1000;
+
+// ==================== 交易 ====================
+//
+// 交易就是一段要写进区块的数据。一个区块的数据可以是一条交易，
+// 也可以是用 `ingest::pack_data` 编码的多条交易（JSON字符串数组）。
+// 交易ID是交易数据的 SHA-256（与 `Block::hash_data` 相同），
+// 因此只有一条交易的区块被裁剪后，`data_hash` 仍然就是它的交易ID。
+
+/// 交易ID：交易数据的 SHA-256 十六进制字符串
+pub fn transaction_id(data: &str) -> String {
+    Block::hash_data(data)
+}
+
+/// # 区块包含的交易
+///
+/// 按 `ingest::unpack_data` 解出区块数据中的每条交易。已裁剪的区块没有数据，返回空列表。
+pub fn block_transactions(block: &Block) -> Vec<String> {
+    if block.is_pruned() {
+        return Vec::new();
+    }
+    ingest::unpack_data(&block.data)
+}
+
+// ==================== 交易池 ====================
+
+/// # 交易池 (Mempool)
+///
+/// 等待打包进区块的交易，按到达的顺序排列，相同ID的交易只保留一条。
+/// 交易池是区块链元数据的一部分（`pending_transactions`），随区块链一起保存，重启后仍然存在；
+/// 序列化格式仍然是字符串数组，与引入交易池之前的文件兼容。
+///
+/// 交易池本身不检查交易内容，交易的检查（大小、是否已经上链）见 `Blockchain::submit_transaction`。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<String>", into = "Vec<String>")]
+pub struct Mempool {
+    transactions: Vec<String>,
+    ids: HashSet<String>,
+}
+
+impl From<Vec<String>> for Mempool {
+    fn from(transactions: Vec<String>) -> Self {
+        let mut mempool = Mempool::default();
+        for transaction in transactions {
+            mempool.insert(transaction);
+        }
+        mempool
+    }
+}
+
+impl From<Mempool> for Vec<String> {
+    fn from(mempool: Mempool) -> Self {
+        mempool.transactions
+    }
+}
+
+impl Mempool {
+    /// 交易数
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    /// 交易池是否为空
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// 是否已满
+    pub fn is_full(&self) -> bool {
+        self.transactions.len() >= MAX_MEMPOOL_TRANSACTIONS
+    }
+
+    /// 按到达顺序排列的所有交易
+    pub fn transactions(&self) -> &[String] {
+        &self.transactions
+    }
+
+    /// 是否已有这条交易
+    pub fn contains(&self, data: &str) -> bool {
+        self.ids.contains(&transaction_id(data))
+    }
+
+    /// 加入一条交易，已有相同ID的交易时返回 false
+    pub fn insert(&mut self, data: String) -> bool {
+        if !self.ids.insert(transaction_id(&data)) {
+            return false;
+        }
+        self.transactions.push(data);
+        true
+    }
+
+    /// # 移除这些区块已经包含的交易
+    ///
+    /// ## 返回值
+    /// 移除的交易数
+    pub fn remove_included(&mut self, blocks: &[Block]) -> usize {
+        let included: HashSet<String> = blocks
+            .iter()
+            .flat_map(block_transactions)
+            .map(|data| transaction_id(&data))
+            .filter(|id| self.ids.contains(id))
+            .collect();
+        if included.is_empty() {
+            return 0;
+        }
+        self.transactions.retain(|data| !included.contains(&transaction_id(data)));
+        self.ids.retain(|id| !included.contains(id));
+        included.len()
+    }
+
+    /// # 选择下一个区块要打包的交易
+    ///
+    /// 按到达顺序选择，最多 `MAX_TRANSACTIONS_PER_BLOCK` 条；`first` 是必须放在最前面的数据
+    /// （例如节点挖矿时输入的一行），不计入交易池。打包后的区块数据超过 `max_bytes` 的交易被跳过。
+    ///
+    /// ## 返回值
+    /// 用 `ingest::pack_data` 编码的区块数据和其中来自交易池的交易数；没有任何数据时为 None
+    pub fn block_data(&self, first: Option<String>, max_bytes: Option<usize>) -> Option<(String, usize)> {
+        let mut records: Vec<String> = first.into_iter().collect();
+        let reserved = records.len();
+        for transaction in &self.transactions {
+            if records.len() - reserved >= MAX_TRANSACTIONS_PER_BLOCK {
+                break;
+            }
+            if records.iter().any(|record| record == transaction) {
+                continue;
+            }
+            records.push(transaction.clone());
+            if max_bytes.is_some_and(|max| ingest::pack_data(&records).len() > max) {
+                records.pop();
+            }
+        }
+        if records.is_empty() {
+            return None;
+        }
+        let packed = records.len() - reserved;
+        Some((ingest::pack_data(&records), packed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_with(data: &str) -> Block {
+        Block::new(1, data.to_string(), "0".repeat(64), 0)
+    }
+
+    /// # 测试交易按ID去重，序列化为字符串数组
+    #[test]
+    fn test_dedup_and_serde() {
+        let mut mempool = Mempool::default();
+        assert!(mempool.insert("交易A".to_string()));
+        assert!(mempool.insert("交易B".to_string()));
+        assert!(!mempool.insert("交易A".to_string()));
+        assert_eq!(mempool.len(), 2);
+        assert!(mempool.contains("交易B"));
+
+        let json = serde_json::to_string(&mempool).unwrap();
+        assert_eq!(json, r#"["交易A","交易B"]"#);
+        // 旧文件中可能有重复的交易，加载时去重
+        let loaded: Mempool = serde_json::from_str(r#"["交易A","交易B","交易A"]"#).unwrap();
+        assert_eq!(loaded, mempool);
+    }
+
+    /// # 测试区块包含的交易（单条和打包的多条）从交易池中移除
+    #[test]
+    fn test_remove_included() {
+        let mut mempool = Mempool::from(vec!["A".to_string(), "B".to_string(), "C".to_string(), "D".to_string()]);
+        let single = block_with("A");
+        let packed = block_with(&ingest::pack_data(&["C".to_string(), "其他".to_string()]));
+        assert_eq!(mempool.remove_included(&[single.clone(), packed]), 2);
+        assert_eq!(mempool.transactions(), ["B".to_string(), "D".to_string()]);
+
+        let mut pruned = single;
+        pruned.prune();
+        assert!(block_transactions(&pruned).is_empty());
+    }
+
+    /// # 测试选择打包的交易：保持顺序，跳过重复和超出大小限制的交易
+    #[test]
+    fn test_block_data() {
+        let mempool = Mempool::from(vec!["短".to_string(), "很长很长很长的交易".to_string(), "行".to_string()]);
+        assert_eq!(Mempool::default().block_data(None, None), None);
+        assert_eq!(
+            Mempool::from(vec!["A".to_string()]).block_data(None, None),
+            Some(("A".to_string(), 1))
+        );
+
+        let (data, count) = mempool.block_data(Some("行".to_string()), Some(24)).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(ingest::unpack_data(&data), vec!["行".to_string(), "短".to_string()]);
+    }
+}
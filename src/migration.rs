@@ -0,0 +1,344 @@
+// ==================== 依赖库导入 ====================
+// 导入区块链结构体和错误类型
+use crate::blockchain::{Blockchain, BlockchainError};
+// 导入验证迁移结果使用的链规范
+use crate::chainspec::ChainSpec;
+// serde: 序列化版本信封
+use serde::Serialize;
+// serde_json: 以通用JSON值的形式读取和改写旧格式
+use serde_json::{Value, json};
+// std::fs: 读取文件
+use std::fs;
+// std::path: 路径处理
+use std::path::Path;
+
+/// # 当前的存储格式版本
+///
+/// 每当 `Block` 或 `Blockchain` 的持久化字段发生不兼容的变化时，
+/// 都需要把这个版本号加一，并在 `MIGRATIONS` 中追加一个迁移步骤。
+///
+/// ## 版本历史
+/// - 版本0：没有版本信息，文件内容直接是序列化后的 `Blockchain`
+/// - 版本1：`{"format_version": 1, "blockchain": {...}}` 版本信封
+/// - 版本2：区块增加可选的 `data_hash` 字段（已裁剪区块的数据哈希）
+/// - 版本3：区块增加可选的 `chain_id` 字段（区块所属的网络）
+pub const CURRENT_FORMAT_VERSION: u32 = 3;
+
+/// 版本信封中记录版本号的字段名
+const VERSION_FIELD: &str = "format_version";
+
+/// 版本信封中存放区块链数据的字段名
+const PAYLOAD_FIELD: &str = "blockchain";
+
+/// # 迁移步骤 (Migration)
+///
+/// 把某个版本的文件内容升级到下一个版本。迁移直接操作通用的
+/// `serde_json::Value`，因此不依赖旧版本的结构体定义。
+struct Migration {
+    /// 迁移前的版本，迁移后为 `from + 1`
+    from: u32,
+    /// 面向用户的迁移说明
+    description: &'static str,
+    /// 执行迁移的函数
+    apply: fn(Value) -> Result<Value, BlockchainError>,
+}
+
+/// # 所有迁移步骤
+///
+/// 按版本顺序排列，第 `n` 项把版本 `n` 升级到版本 `n + 1`。
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "为无版本的区块链数据添加版本信封",
+        apply: wrap_in_envelope,
+    },
+    Migration {
+        from: 1,
+        description: "支持裁剪区块数据（区块可以带有 data_hash 字段）",
+        apply: allow_pruned_blocks,
+    },
+    Migration {
+        from: 2,
+        description: "支持区块链ID（区块可以带有 chain_id 字段）",
+        apply: allow_block_chain_ids,
+    },
+];
+
+/// 版本0 → 版本1：把原始的区块链对象包装进版本信封
+fn wrap_in_envelope(value: Value) -> Result<Value, BlockchainError> {
+    Ok(json!({
+        VERSION_FIELD: 1,
+        PAYLOAD_FIELD: value,
+    }))
+}
+
+/// # 版本1 → 版本2：允许区块带有 `data_hash` 字段
+///
+/// 旧文件中的区块都没有被裁剪，数据本身不需要改动，只更新版本号。
+/// 提升版本号是为了让旧程序拒绝读取含有已裁剪区块的文件，
+/// 而不是忽略 `data_hash` 后把这些区块当作被篡改的区块。
+fn allow_pruned_blocks(mut value: Value) -> Result<Value, BlockchainError> {
+    value[VERSION_FIELD] = json!(2);
+    Ok(value)
+}
+
+/// # 版本2 → 版本3：允许区块带有 `chain_id` 字段
+///
+/// 与版本2相同，旧区块都属于没有链ID的主网，只更新版本号，
+/// 防止旧程序忽略 `chain_id` 后算出不同的区块哈希。
+fn allow_block_chain_ids(mut value: Value) -> Result<Value, BlockchainError> {
+    value[VERSION_FIELD] = json!(3);
+    Ok(value)
+}
+
+/// # 迁移报告 (MigrationReport)
+///
+/// 记录一次迁移（或预演）从哪个版本升级到哪个版本、经过了哪些步骤。
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationReport {
+    /// 文件原来的格式版本
+    pub from_version: u32,
+    /// 迁移后的格式版本
+    pub to_version: u32,
+    /// 依次执行的迁移步骤说明
+    pub steps: Vec<&'static str>,
+}
+
+impl MigrationReport {
+    /// 文件是否已经是最新格式
+    pub fn is_up_to_date(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+/// # 识别文件内容的格式版本
+///
+/// 带有 `format_version` 字段的对象按该字段识别；
+/// 没有该字段但包含 `chain` 数组的对象视为版本0（本功能引入之前的格式）。
+pub fn detect_version(value: &Value) -> Result<u32, BlockchainError> {
+    match value.get(VERSION_FIELD) {
+        Some(version) => version
+            .as_u64()
+            .map(|v| v as u32)
+            .ok_or_else(|| BlockchainError::FormatError(format!("无效的格式版本: {}", version))),
+        None if value.get("chain").is_some_and(Value::is_array) => Ok(0),
+        None => Err(BlockchainError::FormatError("无法识别的区块链文件格式".to_string())),
+    }
+}
+
+/// # 把文件内容逐步升级到当前版本
+///
+/// 从识别出的版本开始，依次执行每个迁移步骤，直到 `CURRENT_FORMAT_VERSION`。
+/// 比当前程序更新的版本无法降级，直接报错。
+///
+/// ## 返回值
+/// 升级后的JSON值（一定是当前版本的信封）和迁移报告
+pub fn upgrade(mut value: Value) -> Result<(Value, MigrationReport), BlockchainError> {
+    let from_version = detect_version(&value)?;
+    if from_version > CURRENT_FORMAT_VERSION {
+        return Err(BlockchainError::FormatError(format!(
+            "文件格式版本 {} 高于当前程序支持的版本 {}，请升级程序",
+            from_version, CURRENT_FORMAT_VERSION
+        )));
+    }
+
+    let mut steps = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= from_version) {
+        value = (migration.apply)(value)?;
+        steps.push(migration.description);
+    }
+
+    Ok((
+        value,
+        MigrationReport {
+            from_version,
+            to_version: CURRENT_FORMAT_VERSION,
+            steps,
+        },
+    ))
+}
+
+/// # 解析任意版本的区块链文件内容
+///
+/// 先升级到当前版本，再从信封中取出区块链数据反序列化。
+/// 这里不做完整性验证，由调用者负责。
+pub fn decode(value: Value) -> Result<Blockchain, BlockchainError> {
+    let (mut envelope, _) = upgrade(value)?;
+    let payload = envelope
+        .get_mut(PAYLOAD_FIELD)
+        .map(Value::take)
+        .ok_or_else(|| BlockchainError::FormatError(format!("版本信封缺少 {} 字段", PAYLOAD_FIELD)))?;
+    Ok(serde_json::from_value(payload)?)
+}
+
+/// # 当前版本的版本信封 (Envelope)
+///
+/// 保存时使用的结构体。使用结构体而不是 `json!` 宏，
+/// 可以让 `format_version` 始终出现在文件开头，便于人工查看。
+#[derive(Serialize)]
+pub struct Envelope<'a> {
+    format_version: u32,
+    blockchain: &'a Blockchain,
+}
+
+impl<'a> Envelope<'a> {
+    /// 把区块链包装进当前版本的信封
+    pub fn new(blockchain: &'a Blockchain) -> Self {
+        Envelope {
+            format_version: CURRENT_FORMAT_VERSION,
+            blockchain,
+        }
+    }
+}
+
+/// # 迁移区块链文件
+///
+/// 读取文件、识别版本并执行所有迁移步骤。迁移后的区块链必须能够按链规范
+/// 通过完整性验证，否则不会写回。无版本的旧文件的创世区块只有时间戳与链规范不同时，
+/// 按它自己的创世区块验证（见 `ChainSpec::for_legacy_genesis`）。
+///
+/// ## 参数
+/// * `path` - 区块链JSON文件路径
+/// * `dry_run` - 为 `true` 时只报告将要执行的步骤，不修改文件
+/// * `spec` - 验证迁移结果使用的链规范
+///
+/// ## 写回方式
+/// 通过 `Blockchain::save_to_file` 写回，因此同样是原子写入，
+/// 迁移前的原文件会被保留为 `.1` 备份。
+pub fn migrate_file<P: AsRef<Path>>(path: P, dry_run: bool, spec: &ChainSpec) -> Result<MigrationReport, BlockchainError> {
+    let path = path.as_ref();
+    let value: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let (_, report) = upgrade(value.clone())?;
+
+    let mut blockchain = decode(value)?;
+    // 无版本的文件来自引入链规范之前的版本，创世区块使用的是创建时的时间
+    let legacy_spec = match (report.from_version, blockchain.chain.first()) {
+        (0, Some(genesis)) => spec.for_legacy_genesis(genesis),
+        _ => None,
+    };
+    blockchain.set_spec(legacy_spec.unwrap_or_else(|| spec.clone()));
+    if !blockchain.is_chain_valid() {
+        return Err(BlockchainError::InvalidChain("迁移后的区块链无效".to_string()));
+    }
+
+    if !dry_run && !report.is_up_to_date() {
+        blockchain.save_to_file(path)?;
+    }
+    Ok(report)
+}
+
+// ==================== 单元测试 ====================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::storage;
+    use chrono::{Duration, Utc};
+    use tempfile::TempDir;
+
+    /// # 写出一个版本0（无版本信封）的区块链文件
+    ///
+    /// 按引入版本信封之前的程序构造：创世区块使用创建时的当前时间（带纳秒），
+    /// 区块只有当时的七个字段，文件顶层就是区块链对象，没有 `format_version`。
+    /// 返回文件中的区块和加载它所需的链规范（见 `ChainSpec::for_legacy_genesis`）。
+    fn write_legacy_file(path: &Path) -> (Vec<Block>, ChainSpec) {
+        let builtin = ChainSpec::default();
+        let genesis = Block::genesis(builtin.genesis.message.clone(), Utc::now() - Duration::days(30), 1);
+        let mut block = Block::new(1, "旧格式区块".to_string(), genesis.hash.clone(), 2);
+        block.mine_block_silent();
+        let chain = vec![genesis, block];
+
+        let legacy_blocks: Vec<Value> = chain
+            .iter()
+            .map(|block| {
+                json!({
+                    "index": block.index,
+                    "timestamp": block.timestamp,
+                    "data": block.data,
+                    "previous_hash": block.previous_hash,
+                    "hash": block.hash,
+                    "nonce": block.nonce,
+                    "difficulty": block.difficulty,
+                })
+            })
+            .collect();
+        let legacy = json!({
+            "chain": legacy_blocks,
+            "difficulty": 2,
+            "mining_reward": 100,
+            "pending_transactions": [],
+        });
+        fs::write(path, serde_json::to_string_pretty(&legacy).unwrap()).unwrap();
+
+        let spec = builtin.for_legacy_genesis(&chain[0]).expect("旧版本的创世区块只有时间戳不同");
+        (chain, spec)
+    }
+
+    #[test]
+    fn test_detect_version() {
+        let blockchain = Blockchain::new();
+        assert_eq!(detect_version(&serde_json::to_value(&blockchain).unwrap()).unwrap(), 0);
+        assert_eq!(
+            detect_version(&serde_json::to_value(Envelope::new(&blockchain)).unwrap()).unwrap(),
+            CURRENT_FORMAT_VERSION
+        );
+        assert!(detect_version(&json!({"something": "else"})).is_err());
+    }
+
+    /// # 测试旧格式文件可以直接加载
+    #[test]
+    fn test_load_legacy_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("legacy.json");
+        let (original, spec) = write_legacy_file(&path);
+
+        // 内置链规范的创世区块是固定的，旧文件需要按它自己的创世区块推导出的链规范加载
+        assert!(Blockchain::load_from_file(&path, ChainSpec::default()).is_err());
+        let loaded = Blockchain::load_from_file(&path, spec).unwrap();
+        assert_eq!(loaded.chain, original);
+        assert!(loaded.chain.iter().all(|block| block.data_hash.is_none() && block.chain_id.is_none()));
+        assert_eq!((loaded.difficulty, loaded.mining_reward), (2, 100));
+    }
+
+    /// # 测试比程序更新的格式会被拒绝
+    #[test]
+    fn test_reject_newer_version() {
+        let value = json!({VERSION_FIELD: CURRENT_FORMAT_VERSION + 1, PAYLOAD_FIELD: {}});
+        assert!(matches!(upgrade(value), Err(BlockchainError::FormatError(_))));
+    }
+
+    /// # 测试预演模式不修改文件
+    #[test]
+    fn test_migrate_dry_run() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("legacy.json");
+        write_legacy_file(&path);
+        let before = fs::read_to_string(&path).unwrap();
+
+        let report = migrate_file(&path, true, &ChainSpec::default()).unwrap();
+        assert_eq!(report.from_version, 0);
+        assert_eq!(report.to_version, CURRENT_FORMAT_VERSION);
+        assert_eq!(report.steps.len(), MIGRATIONS.len());
+        assert_eq!(fs::read_to_string(&path).unwrap(), before);
+    }
+
+    /// # 测试实际迁移会写回新格式并保留原文件备份
+    #[test]
+    fn test_migrate_rewrites_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("legacy.json");
+        let (original, spec) = write_legacy_file(&path);
+
+        // 按内置链规范迁移：旧文件按它自己的创世区块验证
+        migrate_file(&path, false, &ChainSpec::default()).unwrap();
+        let value: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(detect_version(&value).unwrap(), CURRENT_FORMAT_VERSION);
+        assert!(storage::backup_path(&path, 1).exists());
+        // 迁移不改变任何区块，哈希保持不变
+        assert_eq!(Blockchain::load_from_file(&path, spec.clone()).unwrap().chain, original);
+
+        // 已经是最新格式时再次迁移不做任何事
+        assert!(migrate_file(&path, false, &spec).unwrap().is_up_to_date());
+    }
+}
@@ -0,0 +1,179 @@
+// ==================== 依赖库导入 ====================
+// 导入链规范及其组成部分
+use crate::chainspec::{ChainSpec, GenesisSpec, Limits, RetargetSpec, RewardSchedule};
+// chrono: 创世区块的固定时间戳
+use chrono::DateTime;
+// std::fmt: 网络名称的显示
+use std::fmt;
+// std::path: 每个网络的数据目录
+use std::path::{Path, PathBuf};
+// std::str::FromStr: 从命令行字符串解析网络
+use std::str::FromStr;
+
+/// 默认的数据根目录，也是主网的数据目录。与引入网络之前的数据目录相同，已有的区块链无需迁移
+pub const DEFAULT_DATA_DIR: &str = "data";
+
+/// 测试网和回归测试网的创世时间戳：2024-06-01 00:00:00 UTC
+const TEST_GENESIS_TIMESTAMP: i64 = 1_717_200_000;
+
+/// # 网络 (Network)
+///
+/// 预设的网络，每个网络有自己的链规范和数据目录，互不干扰：
+///
+/// | 网络 | 数据目录 | 特点 |
+/// |------|----------|------|
+/// | `mainnet` | `data` | 内置链规范，与引入网络之前的区块链完全相同 |
+/// | `testnet` | `data/testnet` | 难度自动调整、奖励减半，接近真实网络的参数 |
+/// | `regtest` | `data/regtest` | 难度固定为1，挖矿几乎瞬间完成，适合测试和演示 |
+///
+/// 测试网和回归测试网的区块都带有链ID（见 `ChainSpec::embed_chain_id`），
+/// 链ID参与哈希计算，因此把一个网络的区块或文件拿到另一个网络上会被拒绝。
+/// 主网的区块没有链ID，同样不会被其他网络接受。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Regtest,
+}
+
+impl Network {
+    /// 所有网络，按显示顺序排列
+    pub const ALL: [Network; 3] = [Network::Mainnet, Network::Testnet, Network::Regtest];
+
+    /// 网络的名称，也是 `--network` 接受的值
+    pub fn name(self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "testnet",
+            Network::Regtest => "regtest",
+        }
+    }
+
+    /// # 网络的数据目录
+    ///
+    /// 主网直接使用数据根目录（默认为 `data`），其他网络使用 `<根目录>/<网络名>` 子目录。
+    pub fn data_dir(self, root: &Path) -> PathBuf {
+        match self {
+            Network::Mainnet => root.to_path_buf(),
+            other => root.join(other.name()),
+        }
+    }
+
+    /// # 网络的预设链规范
+    ///
+    /// 数据目录中没有 `chainspec.json` 时使用，`init` 也会把它写入数据目录。
+    pub fn spec(self) -> ChainSpec {
+        let test_genesis = |message: &str| GenesisSpec {
+            message: message.to_string(),
+            timestamp: DateTime::from_timestamp(TEST_GENESIS_TIMESTAMP, 0).expect("创世时间戳有效"),
+            difficulty: 1,
+        };
+
+        match self {
+            Network::Mainnet => ChainSpec::default(),
+            Network::Testnet => ChainSpec {
+                chain_id: self.name().to_string(),
+                embed_chain_id: true,
+                genesis: test_genesis("测试网创世区块 - Testnet Genesis Block"),
+                initial_difficulty: 3,
+                retarget: Some(RetargetSpec {
+                    interval: 10,
+                    target_block_secs: 30,
+                }),
+                reward: RewardSchedule {
+                    initial: 50,
+                    halving_interval: Some(1000),
+                },
+                limits: Limits {
+                    min_difficulty: 1,
+                    max_difficulty: 8,
+                    max_data_bytes: Some(64 * 1024),
+                },
+                ..ChainSpec::default()
+            },
+            Network::Regtest => ChainSpec {
+                chain_id: self.name().to_string(),
+                embed_chain_id: true,
+                genesis: test_genesis("回归测试网创世区块 - Regtest Genesis Block"),
+                initial_difficulty: 1,
+                retarget: None,
+                reward: RewardSchedule {
+                    initial: 50,
+                    halving_interval: Some(150),
+                },
+                ..ChainSpec::default()
+            },
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Network::ALL
+            .into_iter()
+            .find(|network| network.name() == s)
+            .ok_or_else(|| format!("未知的网络 '{}'，可选: mainnet, testnet, regtest", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::blockchain::Blockchain;
+
+    /// # 测试每个网络的预设都有效且互不相同
+    #[test]
+    fn test_presets() {
+        assert_eq!(Network::Mainnet.spec(), ChainSpec::default());
+        let root = Path::new(DEFAULT_DATA_DIR);
+        assert_eq!(Network::Mainnet.data_dir(root), PathBuf::from("data"));
+        assert_eq!(Network::Regtest.data_dir(root), PathBuf::from("data/regtest"));
+        assert_eq!("testnet".parse::<Network>().unwrap(), Network::Testnet);
+        assert!("devnet".parse::<Network>().is_err());
+
+        let hashes: Vec<String> = Network::ALL.iter().map(|network| network.spec().genesis_hash()).collect();
+        assert_ne!(hashes[0], hashes[1]);
+        assert_ne!(hashes[1], hashes[2]);
+        assert_ne!(hashes[0], hashes[2]);
+
+        for network in Network::ALL {
+            // 预设要能通过链规范文件的检查
+            let spec = network.spec();
+            assert_eq!(ChainSpec::from_json(&spec.to_json()).unwrap(), spec);
+        }
+    }
+
+    /// # 测试一个网络的区块不能接到另一个网络的链上
+    #[test]
+    fn test_blocks_cannot_cross_networks() {
+        let mut regtest = Blockchain::with_spec(Network::Regtest.spec());
+        regtest.add_block_silent("回归测试网交易".to_string()).unwrap();
+        assert_eq!(regtest.get_latest_block().chain_id.as_deref(), Some("regtest"));
+
+        // 链接和工作量都正确、只有链ID属于另一个网络的区块也会被拒绝
+        let mut foreign = Block::new(2, "测试网交易".to_string(), regtest.get_latest_block().hash.clone(), 1)
+            .with_chain_id(Some("testnet".to_string()));
+        foreign.mine_block_silent();
+        assert!(regtest.clone().append_block(foreign).is_err());
+
+        // 主网的区块没有链ID，同样不能接到回归测试网上
+        let mut mainnet_block = Block::new(2, "主网交易".to_string(), regtest.get_latest_block().hash.clone(), 1);
+        mainnet_block.mine_block_silent();
+        assert!(regtest.clone().append_block(mainnet_block).is_err());
+
+        // 带正确链ID的区块可以正常追加
+        let mut own = Block::new(2, "回归测试网交易".to_string(), regtest.get_latest_block().hash.clone(), 1)
+            .with_chain_id(Some("regtest".to_string()));
+        own.mine_block_silent();
+        regtest.append_block(own).unwrap();
+    }
+}
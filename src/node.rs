@@ -0,0 +1,1511 @@
+// ==================== 依赖库导入 ====================
+// 导入区块和区块链类型
+use crate::block::Block;
+use crate::blockchain::{Blockchain, BlockchainError};
+// 导入节点之间的协议：消息、握手信息和帧编码
+use crate::protocol::{self, BlockRef, Message, Version, ADDR_VERSION, HEADERS_FIRST_VERSION, MAX_FRAME_BYTES};
+// 导入交易消息的大小限制
+use crate::mempool::MAX_TRANSACTIONS_PER_MESSAGE;
+// 导入地址簿和不良行为评分
+use crate::peers::{AddressBook, Connection, Misbehavior, MAX_ADDR_PER_MESSAGE};
+// 导入区块头优先同步的下载状态
+use crate::sync::{self, HeaderSync, HeadersOutcome, MAX_HEADERS_PER_MESSAGE};
+// 导入存储后端接口，收到的区块要立即持久化
+use crate::storage::ChainStore;
+// 导入外部矿工使用的区块模板
+use crate::template::{BlockTemplate, Work};
+// chrono: 地址簿和连接列表中的时间
+use chrono::{DateTime, Utc};
+// std::io: 带缓冲的读取，连接被封禁的节点时的错误
+use std::io::{self, BufReader};
+// std::net: TCP 监听和连接
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+// std::sync: 在网络线程之间共享区块链和连接列表
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+// std::thread: 每个连接一个读线程，另有一个保活线程
+use std::thread::{self, JoinHandle};
+// std::time: 保活和超时
+use std::time::{Duration, Instant};
+
+/// 节点默认的监听地址
+pub const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:8330";
+
+/// 一条 `Blocks` 或 `Bodies` 消息最多携带的区块数，更多的区块分批请求
+const MAX_BLOCKS_PER_MESSAGE: usize = 500;
+
+/// 一条 `Bodies` 消息中区块的大致总字节数上限，区块数据较大时少回复一些，保证不超过帧的上限
+const MAX_BODIES_BYTES: usize = MAX_FRAME_BYTES as usize / 2;
+
+/// 向每个连接发送 `Ping` 的间隔
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 超过这么久没有收到任何消息（包括迟迟不握手）的连接会被断开
+const PEER_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// 保活线程检查的间隔，也决定了停止节点后它多快退出
+const KEEPALIVE_TICK: Duration = Duration::from_millis(200);
+
+/// 默认最多接受的入站连接数
+pub const DEFAULT_MAX_INBOUND: usize = 16;
+
+/// 默认自动维持的出站连接数
+pub const DEFAULT_MAX_OUTBOUND: usize = 8;
+
+/// 检查出站连接是否足够、并保存地址簿的间隔
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 启动后第一次检查出站连接前的等待，让命令行指定的节点先连接
+const FIRST_MAINTENANCE_DELAY: Duration = Duration::from_secs(1);
+
+/// 自动连接地址簿中的地址时的超时
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 挖矿时每尝试这么多次哈希检查一次是否需要报告进度
+const MINING_PROGRESS_HASHES: u64 = 10_000;
+
+/// 两次 `MiningProgress` 事件之间的最短间隔
+const MINING_PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 最多保留的区块模板数，更早的模板被丢弃，提交时找不到
+const MAX_TEMPLATES: usize = 32;
+
+// ==================== 节点事件 ====================
+
+/// # 节点事件 (NodeEvent)
+///
+/// 节点在后台线程中运行，通过 `Node::start` 传入的回调报告发生的事情，
+/// 由调用者决定如何显示（命令行打印，测试中忽略）。
+#[derive(Debug, Clone)]
+pub enum NodeEvent {
+    /// 与另一个节点完成了握手（主动或被动连接）
+    PeerConnected(SocketAddr),
+    /// 与另一个节点的连接断开
+    PeerDisconnected(SocketAddr),
+    /// 握手失败（链不同、协议版本过低或对方拒绝），连接已关闭
+    HandshakeFailed { addr: SocketAddr, reason: String },
+    /// 一个区块被追加到本地区块链，`from` 为 None 表示本地挖出（包括外部矿工按模板提交的区块）
+    BlockAccepted { height: u64, hash: String, from: Option<SocketAddr> },
+    /// 收到的区块无效，没有被接受
+    BlockRejected { height: u64, from: SocketAddr, reason: String },
+    /// 区块头优先同步的进度：本地链尖高度和已知最好区块头的高度，两者相等时同步完成。
+    /// 只在一次同步超过一个区块时报告，单个新区块仍然报告 `BlockAccepted`
+    SyncProgress { height: u64, target: u64 },
+    /// 切换到了一条与本地链分叉、工作量更大的链：回滚了分叉点之后的 `rolled_back` 个区块，
+    /// 其中 `restored` 个交易回到交易池
+    Reorganized { fork_height: u64, rolled_back: usize, height: u64, restored: usize },
+    /// 对方的不良行为分数达到上限，它的IP被封禁，来自这个IP的连接都被断开
+    PeerBanned { addr: SocketAddr, reason: String },
+    /// 从另一个节点收到了 `count` 条新交易，已加入交易池并继续转发
+    TransactionsReceived { count: usize, from: SocketAddr },
+    /// 本地挖矿的进度：正在挖的区块高度、已尝试的哈希次数和哈希率（次/秒），
+    /// 最多每 `MINING_PROGRESS_INTERVAL` 报告一次，很快挖出的区块不会报告
+    MiningProgress { height: u64, hashes: u64, hash_rate: f64 },
+    /// 本地挖矿的难度改变了：手动设置，或挖矿时按链规范自动调整
+    DifficultyChanged { from: u32, to: u32 },
+    /// 验证整条本地区块链（`Node::validate`）没有通过
+    ValidationFailed { height: u64, reason: String },
+    /// 区块链或地址簿保存失败，下一次保存时会一起写入
+    SaveFailed(String),
+}
+
+// ==================== 节点配置 ====================
+
+/// # 节点配置 (NodeConfig)
+///
+/// 连接数量的限制和地址簿。默认值适合在本机运行的节点，地址簿只保存在内存中。
+pub struct NodeConfig {
+    /// 最多接受的入站连接数，超出后新的连接被立即关闭
+    pub max_inbound: usize,
+    /// 自动维持的出站连接数，不足时从地址簿中选择地址连接；`Node::connect` 不受这个限制
+    pub max_outbound: usize,
+    /// 地址簿，节点运行期间更新并保存
+    pub address_book: AddressBook,
+}
+
+impl Default for NodeConfig {
+    fn default() -> Self {
+        NodeConfig {
+            max_inbound: DEFAULT_MAX_INBOUND,
+            max_outbound: DEFAULT_MAX_OUTBOUND,
+            address_book: AddressBook::in_memory(),
+        }
+    }
+}
+
+// ==================== 节点 ====================
+
+/// 区块链和它的存储，总是一起加锁，保证内存和存储中的区块链一致
+struct ChainState {
+    blockchain: Blockchain,
+    store: Box<dyn ChainStore>,
+}
+
+/// 一个已建立的连接。读在连接自己的线程中进行，写可能来自任何线程，因此写端加锁
+struct Peer {
+    addr: SocketAddr,
+    /// 是否由对方发起连接
+    inbound: bool,
+    /// 建立连接的时间
+    since: DateTime<Utc>,
+    writer: Mutex<TcpStream>,
+    /// 握手是否已经完成，完成之前不向它广播区块和交易
+    ready: AtomicBool,
+    /// 最后一次收到消息的时间
+    last_seen: Mutex<Instant>,
+    /// 对方的协议版本，握手完成后有效
+    protocol_version: AtomicU32,
+    /// 对方已知的最高区块高度，来自握手、区块清单和区块头
+    best_height: AtomicU64,
+    /// 对方接受连接的地址：主动连接时就是 `addr`，被动连接时由握手信息中的端口得到
+    listen_addr: Mutex<Option<SocketAddr>>,
+    /// 对方的软件标识，握手完成后有效
+    user_agent: Mutex<String>,
+}
+
+impl Peer {
+    fn send(&self, message: &Message) -> Result<(), BlockchainError> {
+        protocol::write_message(&mut *lock(&self.writer), message)
+    }
+
+    fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::SeqCst)
+    }
+
+    /// 对方是否支持区块头优先同步
+    fn headers_first(&self) -> bool {
+        self.protocol_version.load(Ordering::SeqCst) >= HEADERS_FIRST_VERSION
+    }
+
+    /// 关闭连接，读线程随后退出并清理
+    fn close(&self) {
+        let _ = lock(&self.writer).shutdown(Shutdown::Both);
+    }
+}
+
+/// 一个连接的握手进度，由它的读线程持有
+#[derive(Default)]
+struct Handshake {
+    /// 对方的握手信息，收到并接受后记录
+    theirs: Option<Version>,
+    /// 对方是否已经确认了我们的握手信息
+    acked: bool,
+}
+
+/// 所有网络线程共享的节点状态
+struct Shared {
+    state: Mutex<ChainState>,
+    /// 区块头优先同步的进度。需要同时加锁时总是先锁 `state` 再锁 `sync`
+    sync: Mutex<HeaderSync>,
+    peers: Mutex<Vec<Arc<Peer>>>,
+    /// 地址簿。持有它时不再获取其他锁（`dialing` 除外，它总是先于地址簿加锁）
+    book: Mutex<AddressBook>,
+    /// 正在自动连接的地址
+    dialing: Mutex<Vec<SocketAddr>>,
+    /// 交给外部矿工的区块模板，都基于同一个链尖。持有它时不再获取其他锁
+    templates: Mutex<Vec<BlockTemplate>>,
+    listen_addr: SocketAddr,
+    max_inbound: usize,
+    max_outbound: usize,
+    shutdown: AtomicBool,
+    next_nonce: AtomicU64,
+    on_event: Box<dyn Fn(NodeEvent) + Send + Sync>,
+}
+
+/// 收到一个区块后的处理结果
+enum Received {
+    /// 区块已追加到链尾
+    Accepted,
+    /// 区块不高于本地链尖，已有或属于分叉，忽略
+    Known,
+    /// 区块比链尖高出不止一个，中间缺少区块
+    Gap,
+    /// 区块无效
+    Rejected,
+}
+
+/// # P2P 节点 (Node)
+///
+/// 在TCP端口上监听其他节点的连接，也可以主动连接其他节点。
+/// 相连的节点之间互相通告新区块，从而保持各自的区块链一致。
+/// 消息格式见 `protocol.rs`。
+///
+/// ## 工作方式
+/// - 建立连接后双方互相发送 `Version`，链ID或创世区块不同的节点会收到 `Reject` 并被断开；
+///   双方都回复 `Verack` 后握手完成，对方的链更高时向它请求缺少的区块
+/// - 缺少的区块用区块头优先同步（见 `sync.rs`）：先向链更高的节点请求区块头，
+///   在所有节点提供的区块头中选择累计工作量最大的链，再把区块分段同时向多个节点下载；
+///   这条链与本地链分叉并且更重时，切换过去（`Blockchain::consider_chain`）。
+///   不支持的旧版本节点仍然用 `GetBlocks` 按顺序同步
+/// - 本地挖出或接受了新区块后，向所有连接发送只含链尖的 `Inv`，缺少区块的节点再来请求
+/// - 收到的区块先用 `Block::is_valid` 和 `Block::has_valid_proof_of_work` 检查哈希和工作量，
+///   再用 `Blockchain::append_block` 检查与链尖的连接和链规范，通过后保存
+/// - 收到的新交易经过检查后加入交易池（见 `mempool.rs`）并转发给其他连接；
+///   握手完成后把自己的交易池发给对方
+/// - 每隔 `PING_INTERVAL` 向每个连接发送 `Ping`，超过 `PEER_TIMEOUT` 没有任何消息的连接被断开
+/// - 握手后互相请求已知的节点地址（`GetAddr`/`Addr`），记入地址簿（见 `peers.rs`）；
+///   出站连接少于 `NodeConfig::max_outbound` 时自动连接地址簿中的地址
+/// - 发送无效区块或格式错误消息的节点被扣分，分数达到上限后它的IP被封禁
+///
+/// 每个连接由一个线程读取消息；区块链和存储由一把锁保护，同一时刻只有一个线程修改。
+pub struct Node {
+    shared: Arc<Shared>,
+    local_addr: SocketAddr,
+    /// 接受连接的线程，`wait` 等待它退出。节点可能被多个线程共享（例如RPC服务），因此加锁
+    accept_thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Node {
+    /// # 启动节点
+    ///
+    /// ## 参数
+    /// * `listen` - 监听地址，端口为0时由操作系统分配（见 `local_addr`）
+    /// * `blockchain` - 本地区块链
+    /// * `store` - 保存区块链的存储后端
+    /// * `config` - 连接数量的限制和地址簿
+    /// * `on_event` - 接收节点事件的回调，在网络线程中调用
+    pub fn start<A: ToSocketAddrs>(
+        listen: A,
+        blockchain: Blockchain,
+        store: Box<dyn ChainStore>,
+        config: NodeConfig,
+        on_event: impl Fn(NodeEvent) + Send + Sync + 'static,
+    ) -> Result<Self, BlockchainError> {
+        let listener = TcpListener::bind(listen)?;
+        let local_addr = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            state: Mutex::new(ChainState { blockchain, store }),
+            sync: Mutex::new(HeaderSync::new()),
+            peers: Mutex::new(Vec::new()),
+            book: Mutex::new(config.address_book),
+            dialing: Mutex::new(Vec::new()),
+            templates: Mutex::new(Vec::new()),
+            listen_addr: local_addr,
+            max_inbound: config.max_inbound,
+            max_outbound: config.max_outbound,
+            shutdown: AtomicBool::new(false),
+            next_nonce: AtomicU64::new(0),
+            on_event: Box::new(on_event),
+        });
+
+        let accept_shared = Arc::clone(&shared);
+        let accept_thread = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if accept_shared.shutdown.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    accept_shared.accept(stream);
+                }
+            }
+        });
+        let keepalive_shared = Arc::clone(&shared);
+        thread::spawn(move || keepalive_shared.keepalive());
+
+        Ok(Node {
+            shared,
+            local_addr,
+            accept_thread: Mutex::new(Some(accept_thread)),
+        })
+    }
+
+    /// 实际监听的地址
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// # 连接另一个节点
+    ///
+    /// 建立TCP连接后立即返回，握手在后台完成，结果通过 `PeerConnected` 或 `HandshakeFailed` 事件报告。
+    /// 不受出站连接数的限制；已经连接的地址不会重复连接。
+    ///
+    /// ## 返回值
+    /// 对方的地址；无法连接或对方的IP被封禁时返回IO错误
+    pub fn connect<A: ToSocketAddrs>(&self, addr: A) -> Result<SocketAddr, BlockchainError> {
+        let stream = TcpStream::connect(addr)?;
+        let ip = stream.peer_addr()?.ip();
+        if lock(&self.shared.book).is_banned(ip, Utc::now()) {
+            let reason = format!("{} 已被封禁", ip);
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, reason).into());
+        }
+        spawn_peer(&self.shared, stream, false)
+    }
+
+    /// # 挖出一个新区块并通告给所有连接
+    ///
+    /// 区块数据是 `data` 加上交易池中的交易（见 `Blockchain::next_block_data`），
+    /// 打包的交易随后移出交易池。
+    /// 挖矿期间持有区块链的锁，收到的区块要等挖矿完成后才会处理，
+    /// 因此新区块总是接在最新的链尖上。
+    pub fn mine(&self, data: String) -> Result<Block, BlockchainError> {
+        let (block, difficulty) = {
+            let mut state = lock(&self.shared.state);
+            let (data, _) = state.blockchain.next_block_data(Some(data)).expect("至少包含 data");
+            let difficulty = state.blockchain.difficulty;
+            let height = state.blockchain.get_latest_block().index + 1;
+            let mut last_report: Option<Instant> = None;
+            state.blockchain.add_block_with_progress(data, MINING_PROGRESS_HASHES, |hashes, hash_rate| {
+                if last_report.is_none_or(|last| last.elapsed() >= MINING_PROGRESS_INTERVAL) {
+                    last_report = Some(Instant::now());
+                    self.shared.emit(NodeEvent::MiningProgress { height, hashes, hash_rate });
+                }
+            })?;
+            // 链尖变了，正在下载的区块头接不上了，之后重新请求
+            lock(&self.shared.sync).reset();
+            state.save(&self.shared);
+            (state.blockchain.get_latest_block().clone(), difficulty)
+        };
+        self.shared.mined(&block, difficulty);
+        Ok(block)
+    }
+
+    /// # 生成交给外部矿工的区块模板
+    ///
+    /// 见 `Blockchain::block_template`。模板保存在节点中，之后用它的ID提交工作；
+    /// 链尖改变后，基于旧链尖的模板被丢弃。
+    pub fn block_template(&self, coinbase: String) -> Result<BlockTemplate, BlockchainError> {
+        let state = lock(&self.shared.state);
+        let template = state.blockchain.block_template(coinbase)?;
+        let mut templates = lock(&self.shared.templates);
+        templates.retain(|known| known.previous_hash == template.previous_hash && known.id != template.id);
+        if templates.len() >= MAX_TEMPLATES {
+            templates.remove(0);
+        }
+        templates.push(template.clone());
+        Ok(template)
+    }
+
+    /// # 提交外部矿工按模板完成的工作
+    ///
+    /// 找到ID对应的模板，用 `Blockchain::submit_work` 还原并验证区块，
+    /// 通过后与本地挖出的区块一样保存并通告给所有连接。
+    ///
+    /// ## 返回值
+    /// 追加的区块；模板不存在或已过期、工作无效时返回 `InvalidBlock`
+    pub fn submit_work(&self, template_id: &str, work: &Work) -> Result<Block, BlockchainError> {
+        let (block, difficulty) = {
+            let mut state = lock(&self.shared.state);
+            let template = lock(&self.shared.templates)
+                .iter()
+                .find(|template| template.id == template_id)
+                .cloned()
+                .ok_or_else(|| BlockchainError::InvalidBlock(format!("区块模板 {} 不存在或已过期", template_id)))?;
+            let difficulty = state.blockchain.difficulty;
+            let block = state.blockchain.submit_work(&template, work)?;
+            // 所有模板都基于旧链尖，不再有效
+            lock(&self.shared.templates).clear();
+            lock(&self.shared.sync).reset();
+            state.save(&self.shared);
+            (block, difficulty)
+        };
+        self.shared.mined(&block, difficulty);
+        Ok(block)
+    }
+
+    /// # 提交一条交易
+    ///
+    /// 检查后加入交易池并保存，再转发给所有连接，由任何一个节点打包进区块。
+    ///
+    /// ## 返回值
+    /// 同 `Blockchain::submit_transaction`：交易池中已有这条交易时返回 `Ok(false)`，不再转发
+    pub fn submit_transaction(&self, data: String) -> Result<bool, BlockchainError> {
+        {
+            let mut state = lock(&self.shared.state);
+            if !state.blockchain.submit_transaction(data.clone())? {
+                return Ok(false);
+            }
+            state.save(&self.shared);
+        }
+        self.shared.broadcast(&Message::Transactions { transactions: vec![data] }, None);
+        Ok(true)
+    }
+
+    /// # 设置挖矿难度并保存
+    ///
+    /// 检查同 `Blockchain::try_set_difficulty`，之后本地挖出的区块使用新的难度。
+    pub fn set_difficulty(&self, difficulty: u32) -> Result<(), BlockchainError> {
+        let previous = {
+            let mut state = lock(&self.shared.state);
+            let previous = state.blockchain.difficulty;
+            state.blockchain.try_set_difficulty(difficulty)?;
+            state.save(&self.shared);
+            previous
+        };
+        if previous != difficulty {
+            self.shared.emit(NodeEvent::DifficultyChanged {
+                from: previous,
+                to: difficulty,
+            });
+        }
+        Ok(())
+    }
+
+    /// # 验证整条本地区块链
+    ///
+    /// 验证可能较慢，在区块链的副本上多线程进行，不阻塞节点处理区块。
+    /// 没有通过时报告 `ValidationFailed` 事件。
+    ///
+    /// ## 返回值
+    /// 验证的链尖高度，以及验证结果
+    pub fn validate(&self) -> (u64, Result<(), BlockchainError>) {
+        let blockchain = self.with_blockchain(Blockchain::clone);
+        let height = blockchain.get_latest_block().index;
+        let result = blockchain.validate_parallel(Blockchain::default_validation_threads());
+        if let Err(e) = &result {
+            self.shared.emit(NodeEvent::ValidationFailed {
+                height,
+                reason: e.to_string(),
+            });
+        }
+        (height, result)
+    }
+
+    /// # 读取本地区块链
+    ///
+    /// 在持有区块链的锁时调用 `read`，期间收到的区块要等它返回后才会处理，因此 `read` 应该尽快完成。
+    pub fn with_blockchain<T>(&self, read: impl FnOnce(&Blockchain) -> T) -> T {
+        read(&lock(&self.shared.state).blockchain)
+    }
+
+    /// 本地区块链的最新区块
+    pub fn tip(&self) -> Block {
+        lock(&self.shared.state).blockchain.get_latest_block().clone()
+    }
+
+    /// 已完成握手的连接数
+    pub fn peer_count(&self) -> usize {
+        lock(&self.shared.peers).iter().filter(|peer| peer.is_ready()).count()
+    }
+
+    /// # 停止节点
+    ///
+    /// 不再接受新连接并断开所有连接。可以重复调用。
+    pub fn shutdown(&self) {
+        if self.shared.shutdown.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        // 连接一次自己，让阻塞在 accept 上的线程醒来并退出
+        let _ = TcpStream::connect(self.local_addr);
+        for peer in lock(&self.shared.peers).iter() {
+            peer.close();
+        }
+        // 节点停止后地址簿中不再有当前连接
+        self.shared.save_book();
+    }
+
+    /// 一直运行，直到节点被停止
+    pub fn wait(&self) {
+        let handle = lock(&self.accept_thread).take();
+        if let Some(handle) = handle {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl ChainState {
+    /// 把区块链保存到存储，失败时报告事件而不是中断处理
+    fn save(&mut self, shared: &Shared) {
+        if let Err(e) = self.blockchain.save_to_store(self.store.as_mut()) {
+            shared.emit(NodeEvent::SaveFailed(e.to_string()));
+        }
+    }
+}
+
+impl Shared {
+    fn emit(&self, event: NodeEvent) {
+        (self.on_event)(event);
+    }
+
+    fn height(&self) -> u64 {
+        lock(&self.state).blockchain.get_latest_block().index
+    }
+
+    /// 向所有已握手的连接发送消息，`except` 为消息的来源，不再发回给它
+    fn broadcast(&self, message: &Message, except: Option<SocketAddr>) {
+        let peers = lock(&self.peers).clone();
+        for peer in peers.iter().filter(|peer| peer.is_ready() && Some(peer.addr) != except) {
+            let _ = peer.send(message);
+        }
+    }
+
+    /// 报告本地挖出（或外部矿工提交）的区块并通告给所有连接，`previous_difficulty` 是挖矿前的难度
+    fn mined(&self, block: &Block, previous_difficulty: u32) {
+        // 开启难度调整时，挖矿前会按链规范重新计算难度
+        if block.difficulty != previous_difficulty {
+            self.emit(NodeEvent::DifficultyChanged {
+                from: previous_difficulty,
+                to: block.difficulty,
+            });
+        }
+        self.emit(NodeEvent::BlockAccepted {
+            height: block.index,
+            hash: block.hash.clone(),
+            from: None,
+        });
+        self.announce(block, None);
+    }
+
+    /// 通告新的链尖
+    fn announce(&self, tip: &Block, except: Option<SocketAddr>) {
+        self.broadcast(&Message::Inv { blocks: vec![BlockRef::from(tip)] }, except);
+    }
+
+    /// # 接受一个入站连接
+    ///
+    /// 来自被封禁IP的连接和超出 `max_inbound` 的连接被立即关闭，不发送任何消息。
+    fn accept(self: &Arc<Self>, stream: TcpStream) {
+        let Ok(addr) = stream.peer_addr() else {
+            return;
+        };
+        if lock(&self.book).is_banned(addr.ip(), Utc::now()) {
+            return;
+        }
+        let inbound = lock(&self.peers).iter().filter(|peer| peer.inbound).count();
+        if inbound >= self.max_inbound {
+            return;
+        }
+        let _ = spawn_peer(self, stream, true);
+    }
+
+    /// # 补充出站连接
+    ///
+    /// 出站连接（包括正在建立的）少于 `max_outbound` 时，从地址簿中选择没有连接的地址，
+    /// 在后台线程中连接；连接失败的地址在地址簿中记一次失败。
+    fn maintain_peers(self: &Arc<Self>) {
+        let peers = lock(&self.peers).clone();
+        let mut dialing = lock(&self.dialing);
+        let outbound = peers.iter().filter(|peer| !peer.inbound).count() + dialing.len();
+        let wanted = self.max_outbound.saturating_sub(outbound);
+        if wanted == 0 || self.shutdown.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let mut exclude = vec![self.listen_addr];
+        exclude.extend(dialing.iter().copied());
+        for peer in &peers {
+            exclude.push(peer.addr);
+            exclude.extend(*lock(&peer.listen_addr));
+        }
+        let candidates = lock(&self.book).candidates(wanted, &exclude, Utc::now());
+        for addr in candidates {
+            dialing.push(addr);
+            let shared = Arc::clone(self);
+            thread::spawn(move || {
+                let connected = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)
+                    .map_err(BlockchainError::from)
+                    .and_then(|stream| spawn_peer(&shared, stream, false));
+                if connected.is_err() {
+                    lock(&shared.book).mark_failed(addr, Utc::now());
+                }
+                lock(&shared.dialing).retain(|dialed| *dialed != addr);
+            });
+        }
+    }
+
+    /// 当前所有连接的信息
+    fn connections(&self) -> Vec<Connection> {
+        let peers = lock(&self.peers).clone();
+        let book = lock(&self.book);
+        peers
+            .iter()
+            .map(|peer| Connection {
+                addr: peer.addr,
+                inbound: peer.inbound,
+                since: peer.since,
+                user_agent: lock(&peer.user_agent).clone(),
+                best_height: peer.best_height.load(Ordering::SeqCst),
+                score: book.score(peer.addr.ip()),
+            })
+            .collect()
+    }
+
+    /// 连同当前连接一起保存地址簿，失败时报告事件。节点停止后保存的连接列表为空
+    fn save_book(&self) {
+        let connections = if self.shutdown.load(Ordering::SeqCst) {
+            Vec::new()
+        } else {
+            self.connections()
+        };
+        let mut book = lock(&self.book);
+        book.set_connections(connections);
+        if let Err(e) = book.save() {
+            drop(book);
+            self.emit(NodeEvent::SaveFailed(format!("地址簿: {}", e)));
+        }
+    }
+
+    /// # 记录对方的不良行为
+    ///
+    /// 分数达到上限时封禁它的IP，断开来自这个IP的所有连接并立即保存地址簿。
+    fn misbehaving(&self, peer: &Peer, behavior: Misbehavior, reason: &str) {
+        let ip = peer.addr.ip();
+        if !lock(&self.book).misbehaving(ip, behavior, reason, Utc::now()) {
+            return;
+        }
+        self.emit(NodeEvent::PeerBanned {
+            addr: peer.addr,
+            reason: reason.to_string(),
+        });
+        let peers = lock(&self.peers).clone();
+        for other in peers.iter().filter(|other| other.addr.ip() == ip) {
+            other.close();
+        }
+        self.save_book();
+    }
+
+    /// # 保活
+    ///
+    /// 定期向每个连接发送 `Ping`；长时间没有任何消息的连接（包括一直不握手的）被关闭，
+    /// 由它的读线程完成清理。同时检查区块下载请求是否超时，定期补充出站连接并保存地址簿。
+    fn keepalive(self: &Arc<Self>) {
+        let mut last_ping = Instant::now();
+        let mut next_maintenance = Instant::now() + FIRST_MAINTENANCE_DELAY;
+        while !self.shutdown.load(Ordering::SeqCst) {
+            thread::sleep(KEEPALIVE_TICK);
+            // 超时的下载请求交给其他节点
+            lock(&self.sync).expire(Instant::now());
+            self.schedule();
+            if Instant::now() >= next_maintenance {
+                next_maintenance = Instant::now() + MAINTENANCE_INTERVAL;
+                lock(&self.book).expire_bans(Utc::now());
+                self.maintain_peers();
+                self.save_book();
+            }
+            if last_ping.elapsed() < PING_INTERVAL {
+                continue;
+            }
+            last_ping = Instant::now();
+
+            let peers = lock(&self.peers).clone();
+            for peer in peers {
+                if lock(&peer.last_seen).elapsed() > PEER_TIMEOUT {
+                    peer.close();
+                } else {
+                    let nonce = self.next_nonce.fetch_add(1, Ordering::SeqCst);
+                    let _ = peer.send(&Message::Ping { nonce });
+                }
+            }
+        }
+    }
+
+    /// # 处理收到的一个区块
+    ///
+    /// 先做不需要区块链的检查（哈希、工作量证明），无效的区块不会占用区块链的锁；
+    /// 再检查它能否接到链尖上，通过后追加并保存。
+    fn receive_block(&self, peer: &Peer, block: Block) -> Received {
+        let height = block.index;
+        let reject = |reason: String| {
+            self.emit(NodeEvent::BlockRejected {
+                height,
+                from: peer.addr,
+                reason,
+            });
+            Received::Rejected
+        };
+
+        // 哈希和工作量证明与本地链无关，无效只可能是故意构造的
+        let reason = if !block.is_valid() {
+            Some("区块哈希与内容不符")
+        } else if !block.has_valid_proof_of_work() {
+            Some("不满足工作量证明要求")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            self.misbehaving(peer, Misbehavior::InvalidBlock, reason);
+            return reject(reason.to_string());
+        }
+
+        let mut state = lock(&self.state);
+        let tip = state.blockchain.get_latest_block().index;
+        if block.index <= tip {
+            return Received::Known;
+        }
+        if block.index > tip + 1 {
+            return Received::Gap;
+        }
+        let hash = block.hash.clone();
+        if let Err(e) = state.blockchain.append_block(block) {
+            drop(state);
+            return reject(e.to_string());
+        }
+        lock(&self.sync).reset();
+        state.save(self);
+        drop(state);
+
+        self.emit(NodeEvent::BlockAccepted {
+            height,
+            hash,
+            from: Some(peer.addr),
+        });
+        Received::Accepted
+    }
+
+    /// # 对方的链更高时，请求本地缺少的区块
+    ///
+    /// 支持区块头优先同步的节点用区块定位器请求区块头（对方的链与本地分叉时也能找到分叉点），
+    /// 并让它参与下载区块；旧版本的节点直接请求链尖之后的区块。
+    fn catch_up(&self, peer: &Peer, their_height: u64) -> Result<(), BlockchainError> {
+        peer.best_height.fetch_max(their_height, Ordering::SeqCst);
+        if !peer.headers_first() {
+            let height = self.height();
+            if their_height > height {
+                peer.send(&Message::GetBlocks { from: height + 1 })?;
+            }
+            return Ok(());
+        }
+
+        let locator = {
+            let state = lock(&self.state);
+            let tip = state.blockchain.get_latest_block().index;
+            let sync = lock(&self.sync);
+            (their_height > sync.target(tip)).then(|| sync.locator(&state.blockchain))
+        };
+        if let Some(locator) = locator {
+            peer.send(&Message::GetHeaders { locator })?;
+        }
+        self.schedule();
+        Ok(())
+    }
+
+    /// # 分配区块下载
+    ///
+    /// 给每个空闲的、支持区块头优先同步的节点分配一段要下载的区块。
+    fn schedule(&self) {
+        let peers = lock(&self.peers).clone();
+        let requests: Vec<(Arc<Peer>, u64, u64)> = {
+            let state = lock(&self.state);
+            let tip = state.blockchain.get_latest_block().index;
+            let mut sync = lock(&self.sync);
+            if !sync.is_active() {
+                return;
+            }
+            let now = Instant::now();
+            peers
+                .into_iter()
+                .filter(|peer| peer.is_ready() && peer.headers_first())
+                .filter_map(|peer| {
+                    let height = peer.best_height.load(Ordering::SeqCst);
+                    let (from, to) = sync.next_request(peer.addr, height, tip, now)?;
+                    Some((peer, from, to))
+                })
+                .collect()
+        };
+
+        for (peer, from, to) in requests {
+            if peer.send(&Message::GetBodies { from, count: to - from + 1 }).is_err() {
+                peer.close();
+            }
+        }
+    }
+
+    /// # 使用下载好的区块
+    ///
+    /// 下载好的区块接在链尖上时直接追加；属于一条与本地链分叉、并且更重的链时，
+    /// 用 `Blockchain::consider_chain` 切换过去，被回滚区块的交易放回交易池。之后保存。
+    /// 区块与区块头一致但仍然无法使用（例如数据超过链规范的上限）时，放弃这次同步的区块头，之后重新请求。
+    fn apply_downloaded(&self) {
+        let mut state = lock(&self.state);
+        let mut sync = lock(&self.sync);
+        let start = sync.start();
+        let ready = sync.take_ready(&state.blockchain);
+        let Some(&(ref first, first_from)) = ready.first() else {
+            return;
+        };
+        let fork = first.index;
+
+        let mut accepted = Vec::new();
+        let mut reorganized = None;
+        let mut rejected = None;
+        if fork == state.blockchain.get_latest_block().index + 1 {
+            for (block, from) in ready {
+                let (height, hash) = (block.index, block.hash.clone());
+                if let Err(e) = state.blockchain.append_block(block) {
+                    sync.reset();
+                    rejected = Some(NodeEvent::BlockRejected {
+                        height,
+                        from,
+                        reason: e.to_string(),
+                    });
+                    break;
+                }
+                accepted.push(NodeEvent::BlockAccepted { height, hash, from: Some(from) });
+            }
+        } else {
+            let mut candidate = state.blockchain.chain[..fork as usize].to_vec();
+            candidate.extend(ready.into_iter().map(|(block, _)| block));
+            match state.blockchain.consider_chain(candidate) {
+                Ok(Some(replacement)) => {
+                    let restored = state.blockchain.restore_pending_transactions(&replacement);
+                    reorganized = Some(NodeEvent::Reorganized {
+                        fork_height: replacement.fork_height,
+                        rolled_back: replacement.rolled_back.len(),
+                        height: state.blockchain.get_latest_block().index,
+                        restored,
+                    });
+                }
+                Ok(None) => sync.reset(),
+                Err(e) => {
+                    sync.reset();
+                    rejected = Some(NodeEvent::BlockRejected {
+                        height: fork,
+                        from: first_from,
+                        reason: e.to_string(),
+                    });
+                }
+            }
+        }
+        if !accepted.is_empty() || reorganized.is_some() {
+            state.save(self);
+        }
+        let tip = state.blockchain.get_latest_block().clone();
+        let target = sync.target(tip.index);
+        drop(sync);
+        drop(state);
+
+        if target > start + 1 {
+            self.emit(NodeEvent::SyncProgress { height: tip.index, target });
+        } else {
+            accepted.into_iter().for_each(|event| self.emit(event));
+        }
+        if let Some(event) = reorganized.into_iter().chain(rejected).next() {
+            self.emit(event);
+        }
+        self.announce(&tip, None);
+    }
+
+    /// # 把收到的交易加入交易池
+    ///
+    /// 每条交易都经过 `Blockchain::submit_transaction` 检查。已有的交易和被拒绝的交易
+    /// （例如刚刚上链的）被忽略，不算不良行为：它们可能只是与其他节点的转发交错了。
+    ///
+    /// ## 返回值
+    /// 新加入交易池、需要继续转发的交易
+    fn accept_transactions(&self, transactions: Vec<String>) -> Vec<String> {
+        let mut state = lock(&self.state);
+        let fresh: Vec<String> = transactions
+            .into_iter()
+            .filter(|transaction| state.blockchain.submit_transaction(transaction.clone()).unwrap_or(false))
+            .collect();
+        if !fresh.is_empty() {
+            state.save(self);
+        }
+        fresh
+    }
+
+    /// # 处理收到的区块头
+    ///
+    /// 区块头延长了待下载的链时，满额的回复说明对方还有更多，继续请求。
+    /// 无效的区块头会断开连接。
+    fn receive_headers(&self, peer: &Peer, headers: Vec<Block>) -> Result<(), BlockchainError> {
+        let full = headers.len() == MAX_HEADERS_PER_MESSAGE;
+        let (first, last) = match (headers.first(), headers.last()) {
+            (Some(first), Some(last)) => (first.index, last.index),
+            _ => return Ok(()),
+        };
+        peer.best_height.fetch_max(last, Ordering::SeqCst);
+
+        let (outcome, tip, locator, target, start) = {
+            let state = lock(&self.state);
+            let tip = state.blockchain.get_latest_block().index;
+            let mut sync = lock(&self.sync);
+            let outcome = sync.add_headers(&state.blockchain, headers, full).inspect_err(|e| {
+                self.misbehaving(peer, Misbehavior::InvalidBlock, "无效的区块头");
+                self.emit(NodeEvent::BlockRejected {
+                    height: first,
+                    from: peer.addr,
+                    reason: e.to_string(),
+                });
+            })?;
+            (outcome, tip, sync.locator(&state.blockchain), sync.target(tip), sync.start())
+        };
+
+        if outcome == HeadersOutcome::Extended {
+            if full {
+                peer.send(&Message::GetHeaders { locator })?;
+            }
+            if target > start + 1 {
+                self.emit(NodeEvent::SyncProgress { height: tip, target });
+            }
+        }
+        self.schedule();
+        Ok(())
+    }
+
+    /// # 处理握手消息
+    ///
+    /// 对方的握手信息被接受且对方确认了我们的握手信息后，握手完成。
+    /// 返回错误时连接会被关闭。
+    fn handle_handshake(self: &Arc<Self>, peer: &Peer, handshake: &mut Handshake, message: Message) -> Result<(), BlockchainError> {
+        match message {
+            Message::Version(theirs) => {
+                if handshake.theirs.is_some() {
+                    return Err(BlockchainError::FormatError("重复的握手信息".to_string()));
+                }
+                let ours = Version::local(&lock(&self.state).blockchain);
+                if let Err(reason) = ours.check_compatible(&theirs) {
+                    let _ = peer.send(&Message::Reject { reason: reason.clone() });
+                    return Err(BlockchainError::InvalidChain(reason));
+                }
+                peer.send(&Message::Verack)?;
+                handshake.theirs = Some(theirs);
+            }
+            Message::Verack => handshake.acked = true,
+            Message::Reject { reason } => {
+                return Err(BlockchainError::InvalidChain(format!("对方拒绝连接: {}", reason)));
+            }
+            _ => unreachable!("只处理握手消息"),
+        }
+
+        if let Some(theirs) = &handshake.theirs
+            && handshake.acked
+            && !peer.is_ready()
+        {
+            peer.ready.store(true, Ordering::SeqCst);
+            peer.protocol_version.store(theirs.protocol_version, Ordering::SeqCst);
+            *lock(&peer.user_agent) = theirs.user_agent.clone();
+            // 主动连接的地址就是对方接受连接的地址；被动连接时对方的端口是临时的，用它告诉我们的端口
+            let listen_addr = match peer.inbound {
+                false => Some(peer.addr),
+                true => theirs.listen_port.map(|port| SocketAddr::new(peer.addr.ip(), port)),
+            };
+            *lock(&peer.listen_addr) = listen_addr;
+            if let Some(listen_addr) = listen_addr {
+                lock(&self.book).mark_connected(listen_addr, Utc::now());
+            }
+            self.save_book();
+            self.emit(NodeEvent::PeerConnected(peer.addr));
+
+            if theirs.protocol_version >= ADDR_VERSION {
+                peer.send(&Message::GetAddr)?;
+            }
+            // 把交易池发给新连接，让它也能打包这些交易
+            let pending = lock(&self.state).blockchain.pending_transactions.transactions().to_vec();
+            for chunk in pending.chunks(MAX_TRANSACTIONS_PER_MESSAGE) {
+                peer.send(&Message::Transactions { transactions: chunk.to_vec() })?;
+            }
+            self.catch_up(peer, theirs.best_height)?;
+        }
+        Ok(())
+    }
+
+    /// 处理一条消息，返回错误时连接会被关闭；`FormatError` 还会给对方扣分
+    fn handle_message(self: &Arc<Self>, peer: &Peer, handshake: &mut Handshake, message: Message) -> Result<(), BlockchainError> {
+        if !peer.is_ready() && !message.allowed_before_handshake() {
+            return Err(BlockchainError::FormatError("握手完成之前收到了其他消息".to_string()));
+        }
+
+        match message {
+            Message::Version(_) | Message::Verack | Message::Reject { .. } => {
+                self.handle_handshake(peer, handshake, message)?;
+            }
+            Message::Ping { nonce } => peer.send(&Message::Pong { nonce })?,
+            Message::Pong { .. } => {}
+            Message::Inv { blocks } => {
+                if let Some(their_height) = blocks.iter().map(|block| block.height).max() {
+                    self.catch_up(peer, their_height)?;
+                }
+            }
+            Message::GetBlocks { from } => {
+                let blocks = {
+                    let state = lock(&self.state);
+                    let chain = &state.blockchain.chain;
+                    let start = usize::try_from(from).unwrap_or(usize::MAX).min(chain.len());
+                    let end = start.saturating_add(MAX_BLOCKS_PER_MESSAGE).min(chain.len());
+                    chain[start..end].to_vec()
+                };
+                peer.send(&Message::Blocks { blocks })?;
+            }
+            Message::Blocks { blocks } => {
+                let full = blocks.len() == MAX_BLOCKS_PER_MESSAGE;
+                let mut accepted = None;
+                for block in blocks {
+                    match self.receive_block(peer, block.clone()) {
+                        Received::Accepted => accepted = Some(block),
+                        Received::Known => {}
+                        Received::Gap | Received::Rejected => break,
+                    }
+                }
+                if let Some(tip) = accepted {
+                    self.announce(&tip, Some(peer.addr));
+                    if full {
+                        peer.send(&Message::GetBlocks { from: self.height() + 1 })?;
+                    }
+                }
+            }
+            Message::GetHeaders { locator } => {
+                let headers = {
+                    let state = lock(&self.state);
+                    let chain = &state.blockchain.chain;
+                    let from = sync::locate(&state.blockchain, &locator);
+                    let start = usize::try_from(from).unwrap_or(usize::MAX).min(chain.len());
+                    let end = start.saturating_add(MAX_HEADERS_PER_MESSAGE).min(chain.len());
+                    chain[start..end].iter().map(sync::header).collect()
+                };
+                peer.send(&Message::Headers { headers })?;
+            }
+            Message::Headers { headers } => self.receive_headers(peer, headers)?,
+            Message::GetBodies { from, count } => {
+                let blocks = {
+                    let state = lock(&self.state);
+                    let chain = &state.blockchain.chain;
+                    let start = usize::try_from(from).unwrap_or(usize::MAX).min(chain.len());
+                    let count = usize::try_from(count).unwrap_or(usize::MAX).min(MAX_BLOCKS_PER_MESSAGE);
+                    let mut bytes = 0;
+                    // 已裁剪的区块无法提供完整数据，到此为止
+                    chain[start..]
+                        .iter()
+                        .take(count)
+                        .take_while(|block| !block.is_pruned())
+                        .take_while(|block| {
+                            bytes += block.get_size();
+                            bytes <= MAX_BODIES_BYTES
+                        })
+                        .cloned()
+                        .collect()
+                };
+                peer.send(&Message::Bodies { blocks })?;
+            }
+            Message::Bodies { blocks } => {
+                let first = blocks.first().map_or(0, |block| block.index);
+                let result = lock(&self.sync).receive_bodies(peer.addr, blocks);
+                if let Err(e) = result {
+                    self.misbehaving(peer, Misbehavior::InvalidBlock, "与区块头不一致的区块");
+                    self.emit(NodeEvent::BlockRejected {
+                        height: first,
+                        from: peer.addr,
+                        reason: e.to_string(),
+                    });
+                    return Err(e);
+                }
+                self.apply_downloaded();
+                self.schedule();
+            }
+            Message::Transactions { transactions } => {
+                if transactions.len() > MAX_TRANSACTIONS_PER_MESSAGE {
+                    return Err(BlockchainError::FormatError(format!(
+                        "一条交易消息包含 {} 条交易，超过上限 {}",
+                        transactions.len(),
+                        MAX_TRANSACTIONS_PER_MESSAGE
+                    )));
+                }
+                let fresh = self.accept_transactions(transactions);
+                if !fresh.is_empty() {
+                    self.emit(NodeEvent::TransactionsReceived {
+                        count: fresh.len(),
+                        from: peer.addr,
+                    });
+                    self.broadcast(&Message::Transactions { transactions: fresh }, Some(peer.addr));
+                }
+            }
+            Message::GetAddr => {
+                let exclude: Vec<SocketAddr> = lock(&peer.listen_addr).iter().copied().collect();
+                let addresses = lock(&self.book).shareable(&exclude, Utc::now());
+                peer.send(&Message::Addr { addresses })?;
+            }
+            Message::Addr { addresses } => {
+                if addresses.len() > MAX_ADDR_PER_MESSAGE {
+                    return Err(BlockchainError::FormatError(format!(
+                        "一条地址消息包含 {} 个地址，超过上限 {}",
+                        addresses.len(),
+                        MAX_ADDR_PER_MESSAGE
+                    )));
+                }
+                {
+                    let now = Utc::now();
+                    let mut book = lock(&self.book);
+                    for addr in addresses.into_iter().filter(|addr| *addr != self.listen_addr) {
+                        book.add(addr, now);
+                    }
+                }
+                self.maintain_peers();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// # 为一个新连接启动读线程
+///
+/// 加入连接列表后立即发送自己的握手信息。主动连接一个已经连接的地址时直接关闭新连接。
+fn spawn_peer(shared: &Arc<Shared>, stream: TcpStream, inbound: bool) -> Result<SocketAddr, BlockchainError> {
+    if shared.shutdown.load(Ordering::SeqCst) {
+        return Err(BlockchainError::IoError(io::ErrorKind::NotConnected.into()));
+    }
+    let addr = stream.peer_addr()?;
+    let peer = Arc::new(Peer {
+        addr,
+        inbound,
+        since: Utc::now(),
+        writer: Mutex::new(stream.try_clone()?),
+        ready: AtomicBool::new(false),
+        last_seen: Mutex::new(Instant::now()),
+        protocol_version: AtomicU32::new(0),
+        best_height: AtomicU64::new(0),
+        listen_addr: Mutex::new(None),
+        user_agent: Mutex::new(String::new()),
+    });
+    {
+        let mut peers = lock(&shared.peers);
+        let connected = |other: &Arc<Peer>| other.addr == addr || *lock(&other.listen_addr) == Some(addr);
+        if !inbound && peers.iter().any(connected) {
+            let _ = stream.shutdown(Shutdown::Both);
+            return Ok(addr);
+        }
+        peers.push(Arc::clone(&peer));
+    }
+    let version = Version {
+        listen_port: Some(shared.listen_addr.port()),
+        ..Version::local(&lock(&shared.state).blockchain)
+    };
+    peer.send(&Message::Version(version))?;
+
+    let shared = Arc::clone(shared);
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        let mut handshake = Handshake::default();
+        // 对方关闭连接、读取出错、消息无效或握手失败时断开连接
+        let result = loop {
+            match protocol::read_message(&mut reader) {
+                Ok(Some(message)) => {
+                    *lock(&peer.last_seen) = Instant::now();
+                    if let Err(e) = shared.handle_message(&peer, &mut handshake, message) {
+                        break Err(e);
+                    }
+                }
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+        // 格式错误的帧和违反协议的消息
+        if let Err(BlockchainError::FormatError(reason)) = &result {
+            shared.misbehaving(&peer, Misbehavior::InvalidMessage, reason);
+        }
+
+        peer.close();
+        lock(&shared.peers).retain(|other| !Arc::ptr_eq(other, &peer));
+        // 它没有完成的下载交给其他节点
+        lock(&shared.sync).peer_gone(addr);
+        shared.schedule();
+        if peer.is_ready() {
+            shared.save_book();
+        }
+        match result {
+            _ if peer.is_ready() => shared.emit(NodeEvent::PeerDisconnected(addr)),
+            Err(e) => shared.emit(NodeEvent::HandshakeFailed {
+                addr,
+                reason: e.to_string(),
+            }),
+            Ok(()) => shared.emit(NodeEvent::HandshakeFailed {
+                addr,
+                reason: "握手完成之前连接已关闭".to_string(),
+            }),
+        }
+    });
+    Ok(addr)
+}
+
+/// 获取互斥锁。持锁线程崩溃不影响其他连接继续使用节点
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chainspec::ChainSpec;
+    use crate::mempool;
+    use crate::network::Network;
+    use crate::protocol::{read_message, write_message};
+    use crate::storage::MemoryStore;
+    use std::io::Write;
+    use std::sync::mpsc::{self, Receiver};
+
+    /// 在本机随机端口上启动一个节点，返回节点和它的事件
+    fn start_node_with(spec: ChainSpec, config: NodeConfig) -> (Node, Receiver<NodeEvent>) {
+        let (sender, events) = mpsc::channel();
+        let blockchain = Blockchain::with_spec(spec);
+        let node = Node::start("127.0.0.1:0", blockchain, Box::new(MemoryStore::new()), config, move |event| {
+            let _ = sender.send(event);
+        })
+        .unwrap();
+        (node, events)
+    }
+
+    /// 在本机随机端口上启动一个回归测试网节点
+    fn start_node() -> Node {
+        start_node_with(Network::Regtest.spec(), NodeConfig::default()).0
+    }
+
+    /// 连接是否已被对方关闭（之前收到的消息被跳过）
+    fn closed_by_node(stream: &mut TcpStream) -> bool {
+        loop {
+            match read_message(stream) {
+                Ok(Some(_)) => continue,
+                Ok(None) | Err(_) => return true,
+            }
+        }
+    }
+
+    /// 等待条件成立，最多10秒
+    fn wait_until(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !condition() {
+            assert!(Instant::now() < deadline, "等待超时");
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    /// 等待满足条件的事件，最多10秒
+    fn wait_for_event(events: &Receiver<NodeEvent>, matches: impl Fn(&NodeEvent) -> bool) -> NodeEvent {
+        loop {
+            let event = events.recv_timeout(Duration::from_secs(10)).expect("等待事件超时");
+            if matches(&event) {
+                return event;
+            }
+        }
+    }
+
+    /// 作为回归测试网节点与 `node` 完成握手的原始连接
+    fn handshake_with(node: &Node) -> TcpStream {
+        let mut stream = TcpStream::connect(node.local_addr()).unwrap();
+        let version = Version::local(&Blockchain::with_spec(Network::Regtest.spec()));
+        write_message(&mut stream, &Message::Version(version)).unwrap();
+        write_message(&mut stream, &Message::Verack).unwrap();
+        while read_message(&mut stream).unwrap() != Some(Message::Verack) {}
+        stream
+    }
+
+    /// # 测试新连接的节点会同步已有区块，之后互相通告新区块
+    #[test]
+    fn test_sync_and_announce() {
+        let a = start_node();
+        for i in 0..3 {
+            a.mine(format!("A #{}", i)).unwrap();
+        }
+
+        let b = start_node();
+        b.connect(a.local_addr()).unwrap();
+        wait_until(|| b.tip().index == 3);
+        assert_eq!(b.tip(), a.tip());
+
+        b.mine("B #1".to_string()).unwrap();
+        wait_until(|| a.tip().index == 4);
+        assert_eq!(a.tip(), b.tip());
+    }
+
+    /// # 测试新节点从多个节点同时下载区块，完成后报告同步进度
+    #[test]
+    fn test_initial_block_download() {
+        let mut blockchain = Blockchain::with_spec(Network::Regtest.spec());
+        for i in 0..2 * sync::BODY_BATCH + 10 {
+            blockchain.add_block_silent(format!("区块 #{}", i)).unwrap();
+        }
+        let start_with = |blockchain: Blockchain| {
+            Node::start("127.0.0.1:0", blockchain, Box::new(MemoryStore::new()), NodeConfig::default(), |_| {}).unwrap()
+        };
+        let (a, b) = (start_with(blockchain.clone()), start_with(blockchain.clone()));
+
+        let (c, events) = start_node_with(Network::Regtest.spec(), NodeConfig::default());
+        c.connect(a.local_addr()).unwrap();
+        c.connect(b.local_addr()).unwrap();
+        let done = |event: &NodeEvent| matches!(event, NodeEvent::SyncProgress { height, target } if height == target);
+        let NodeEvent::SyncProgress { height, .. } = wait_for_event(&events, done) else { unreachable!() };
+        assert_eq!(height, blockchain.get_latest_block().index);
+        assert_eq!(c.tip(), *blockchain.get_latest_block());
+    }
+
+    /// # 测试区块经过中间节点转发到没有直接相连的节点
+    #[test]
+    fn test_relay_through_peer() {
+        // A 和 C 不自动连接从 B 得知的对方，区块只能经过 B 转发
+        let no_outbound = || NodeConfig {
+            max_outbound: 0,
+            ..NodeConfig::default()
+        };
+        let (a, _a_events) = start_node_with(Network::Regtest.spec(), no_outbound());
+        let b = start_node();
+        let (c, _c_events) = start_node_with(Network::Regtest.spec(), no_outbound());
+        b.connect(a.local_addr()).unwrap();
+        c.connect(b.local_addr()).unwrap();
+        wait_until(|| a.peer_count() == 1 && b.peer_count() == 2 && c.peer_count() == 1);
+
+        a.mine("经过 B 转发".to_string()).unwrap();
+        wait_until(|| c.tip().index == 1);
+        assert_eq!(c.tip(), a.tip());
+    }
+
+    /// # 测试交易在节点之间转发，新连接的节点在握手后收到交易池，打包后从所有交易池中移除
+    #[test]
+    fn test_transaction_relay_and_mining() {
+        let pending = |node: &Node| lock(&node.shared.state).blockchain.pending_transactions.clone();
+        let a = start_node();
+        let (b, b_events) = start_node_with(Network::Regtest.spec(), NodeConfig::default());
+        b.connect(a.local_addr()).unwrap();
+        wait_until(|| a.peer_count() == 1 && b.peer_count() == 1);
+
+        assert!(a.submit_transaction("转账 10".to_string()).unwrap());
+        assert!(!a.submit_transaction("转账 10".to_string()).unwrap());
+        let received = wait_for_event(&b_events, |event| matches!(event, NodeEvent::TransactionsReceived { .. }));
+        assert!(matches!(received, NodeEvent::TransactionsReceived { count: 1, .. }));
+        assert!(pending(&b).contains("转账 10"));
+
+        let c = start_node();
+        c.connect(b.local_addr()).unwrap();
+        wait_until(|| pending(&c).contains("转账 10"));
+
+        let block = b.mine("B 挖出".to_string()).unwrap();
+        assert_eq!(mempool::block_transactions(&block), vec!["B 挖出".to_string(), "转账 10".to_string()]);
+        wait_until(|| a.tip() == block && c.tip() == block);
+        for node in [&a, &b, &c] {
+            assert!(pending(node).is_empty());
+        }
+        assert!(matches!(
+            a.submit_transaction("转账 10".to_string()),
+            Err(BlockchainError::InvalidTransaction(_))
+        ));
+    }
+
+    /// # 测试分叉的节点连接后切换到工作量更大的链
+    #[test]
+    fn test_switches_to_heavier_fork() {
+        let (a, a_events) = start_node_with(Network::Regtest.spec(), NodeConfig::default());
+        let b = start_node();
+        a.mine("A 的区块 1".to_string()).unwrap();
+        a.mine("A 的区块 2".to_string()).unwrap();
+        for i in 1..=4 {
+            b.mine(format!("B 的区块 {}", i)).unwrap();
+        }
+
+        a.connect(b.local_addr()).unwrap();
+        let reorganized = |event: &NodeEvent| matches!(event, NodeEvent::Reorganized { .. });
+        let event = wait_for_event(&a_events, reorganized);
+        assert!(matches!(
+            event,
+            NodeEvent::Reorganized { fork_height: 0, rolled_back: 2, height: 4, restored: 2 }
+        ));
+        assert_eq!(a.tip(), b.tip());
+    }
+
+    /// # 测试创世区块不同的节点在握手时被拒绝
+    #[test]
+    fn test_handshake_rejects_other_genesis() {
+        let (a, a_events) = start_node_with(Network::Regtest.spec(), NodeConfig::default());
+        let mut other = Network::Regtest.spec();
+        other.genesis.message = "另一个创世区块".to_string();
+        let (b, b_events) = start_node_with(other, NodeConfig::default());
+
+        b.connect(a.local_addr()).unwrap();
+        let failed = |event: &NodeEvent| matches!(event, NodeEvent::HandshakeFailed { .. });
+        let NodeEvent::HandshakeFailed { reason, .. } = wait_for_event(&a_events, failed) else { unreachable!() };
+        assert!(reason.contains("创世区块"), "{}", reason);
+        let NodeEvent::HandshakeFailed { reason, .. } = wait_for_event(&b_events, failed) else { unreachable!() };
+        // 双方都会检查对方的握手信息，先发现不兼容的一方回复 Reject
+        assert!(reason.contains("创世区块"), "{}", reason);
+        assert_eq!((a.peer_count(), b.peer_count()), (0, 0));
+    }
+
+    /// # 测试握手之前发送其他消息会被断开
+    #[test]
+    fn test_message_before_handshake() {
+        let node = start_node();
+        let mut stream = TcpStream::connect(node.local_addr()).unwrap();
+        write_message(&mut stream, &Message::GetBlocks { from: 0 }).unwrap();
+        // 先收到节点的握手信息，然后连接被关闭
+        assert!(matches!(read_message(&mut stream), Ok(Some(Message::Version(_)))));
+        assert!(!matches!(read_message(&mut stream), Ok(Some(_))));
+    }
+
+    /// # 测试接不上链尖的区块被拒绝但不扣分，连接保持可用并响应 Ping
+    #[test]
+    fn test_rejects_invalid_block() {
+        let node = start_node();
+        let mut stream = handshake_with(&node);
+        let genesis = node.tip();
+
+        let mut block = Block::new(1, "分叉".to_string(), "0".repeat(64), 1).with_chain_id(genesis.chain_id.clone());
+        block.mine_block_silent();
+        write_message(&mut stream, &Message::Blocks { blocks: vec![block] }).unwrap();
+
+        let mut valid = Block::new(1, "有效".to_string(), genesis.hash, 1).with_chain_id(genesis.chain_id);
+        valid.mine_block_silent();
+        write_message(&mut stream, &Message::Blocks { blocks: vec![valid.clone()] }).unwrap();
+        wait_until(|| node.tip().index == 1);
+        assert_eq!(node.tip(), valid);
+
+        write_message(&mut stream, &Message::Ping { nonce: 7 }).unwrap();
+        while read_message(&mut stream).unwrap() != Some(Message::Pong { nonce: 7 }) {}
+    }
+
+    /// # 测试发送被篡改区块的节点被封禁，之后的连接被立即关闭
+    #[test]
+    fn test_bans_invalid_block_sender() {
+        let (node, events) = start_node_with(Network::Regtest.spec(), NodeConfig::default());
+        let mut stream = handshake_with(&node);
+        let genesis = node.tip();
+
+        let mut block = Block::new(1, "篡改".to_string(), genesis.hash.clone(), 1)
+            .with_chain_id(genesis.chain_id.clone());
+        block.mine_block_silent();
+        block.data = "篡改后的数据".to_string();
+        write_message(&mut stream, &Message::Blocks { blocks: vec![block] }).unwrap();
+
+        wait_for_event(&events, |event| matches!(event, NodeEvent::PeerBanned { .. }));
+        assert!(closed_by_node(&mut stream));
+        assert_eq!(node.tip(), genesis);
+
+        // 被封禁的IP重新连接时收不到握手信息
+        let mut again = TcpStream::connect(node.local_addr()).unwrap();
+        assert!(!matches!(read_message(&mut again), Ok(Some(_))));
+        assert!(node.connect(stream.local_addr().unwrap()).is_err());
+    }
+
+    /// # 测试多次发送格式错误的消息后被封禁
+    #[test]
+    fn test_bans_after_repeated_malformed_messages() {
+        let (node, events) = start_node_with(Network::Regtest.spec(), NodeConfig::default());
+        let threshold = crate::peers::BAN_THRESHOLD / Misbehavior::InvalidMessage.penalty();
+        for _ in 0..threshold {
+            let mut stream = TcpStream::connect(node.local_addr()).unwrap();
+            stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+            assert!(closed_by_node(&mut stream));
+        }
+        wait_for_event(&events, |event| matches!(event, NodeEvent::PeerBanned { .. }));
+    }
+
+    /// # 测试通过地址交换发现其他节点并自动连接
+    #[test]
+    fn test_discovers_peers_through_addr() {
+        let (a, b, c) = (start_node(), start_node(), start_node());
+        c.connect(b.local_addr()).unwrap();
+        wait_until(|| b.peer_count() == 1 && c.peer_count() == 1);
+
+        // A 只连接 B，从 B 的地址簿中得知 C 的监听地址后主动连接
+        a.connect(b.local_addr()).unwrap();
+        wait_until(|| a.peer_count() == 2 && c.peer_count() == 2);
+        let outbound = a.shared.connections().iter().filter(|connection| !connection.inbound).count();
+        assert_eq!(outbound, 2);
+    }
+
+    /// # 测试超出入站连接数上限的连接被立即关闭
+    #[test]
+    fn test_inbound_limit() {
+        let config = NodeConfig {
+            max_inbound: 1,
+            ..NodeConfig::default()
+        };
+        let (node, _events) = start_node_with(Network::Regtest.spec(), config);
+        let _first = handshake_with(&node);
+        wait_until(|| node.peer_count() == 1);
+
+        let mut second = TcpStream::connect(node.local_addr()).unwrap();
+        assert!(!matches!(read_message(&mut second), Ok(Some(_))));
+        assert_eq!(node.peer_count(), 1);
+    }
+}
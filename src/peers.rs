@@ -0,0 +1,410 @@
+// ==================== 依赖库导入 ====================
+// 导入错误类型
+use crate::blockchain::BlockchainError;
+// 导入原子写入工具
+use crate::storage;
+// chrono: 地址的最后活动时间和封禁期限
+use chrono::{DateTime, TimeDelta, Utc};
+// serde: 地址簿文件的序列化
+use serde::{Deserialize, Serialize};
+// std::collections: 按地址排序的地址簿，按IP记录的不良行为分数
+use std::collections::{BTreeMap, HashMap};
+// std::fs: 读取地址簿文件
+use std::fs;
+// std::io: 地址簿文件不存在时视为空
+use std::io;
+// std::net: 节点地址
+use std::net::{IpAddr, SocketAddr};
+// std::path: 地址簿文件路径
+use std::path::{Path, PathBuf};
+
+/// 数据目录中保存地址簿的文件名
+pub const ADDRESS_BOOK_FILE_NAME: &str = "peers.json";
+
+/// 地址簿最多记录的地址数，超出时忘记最久没有活动的地址
+pub const MAX_ADDRESSES: usize = 1000;
+
+/// 一条 `Addr` 消息最多携带的地址数，超过的消息视为不良行为
+pub const MAX_ADDR_PER_MESSAGE: usize = 250;
+
+/// 不良行为分数达到这个值时封禁对方的IP
+pub const BAN_THRESHOLD: u32 = 100;
+
+/// 封禁时长（小时）
+pub const BAN_HOURS: i64 = 24;
+
+/// 连续连接失败这么多次的地址被忘记
+const MAX_FAILURES: u32 = 5;
+
+/// 连接失败后至少等待这么久（秒）才再次尝试同一个地址
+const RETRY_SECS: i64 = 60;
+
+// ==================== 不良行为 ====================
+
+/// # 不良行为 (Misbehavior)
+///
+/// 每种不良行为给对方的IP增加一定的分数，累计达到 `BAN_THRESHOLD` 时封禁 `BAN_HOURS` 小时。
+/// 分数只保存在内存中；封禁写入地址簿，节点重启后仍然有效。
+///
+/// | 行为 | 分数 | 例子 |
+/// |------|------|------|
+/// | `InvalidBlock` | 100 | 哈希或工作量证明无效的区块、无效的区块头、与区块头不一致的区块 |
+/// | `InvalidMessage` | 20 | 格式错误的帧、握手之前的其他消息、过多的地址 |
+///
+/// 无效的区块只可能是故意构造的，立即封禁；格式错误的消息也可能来自有缺陷的旧版本软件，
+/// 多次出现后才封禁。与本地链分叉或接不上链尖的区块是正常现象，不计分。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Misbehavior {
+    InvalidBlock,
+    InvalidMessage,
+}
+
+impl Misbehavior {
+    /// 这种行为增加的分数
+    pub fn penalty(self) -> u32 {
+        match self {
+            Misbehavior::InvalidBlock => 100,
+            Misbehavior::InvalidMessage => 20,
+        }
+    }
+}
+
+// ==================== 地址簿 ====================
+
+/// 地址簿中一个已知的节点地址
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KnownAddress {
+    /// 最后一次听说或连接上这个地址的时间
+    pub last_seen: DateTime<Utc>,
+    /// 最后一次成功完成握手的时间，从未连接过时为 None
+    #[serde(default)]
+    pub last_connected: Option<DateTime<Utc>>,
+    /// 最后一次主动连接失败的时间
+    #[serde(default)]
+    pub last_failure: Option<DateTime<Utc>>,
+    /// 连续连接失败的次数，成功后清零
+    #[serde(default)]
+    pub failures: u32,
+}
+
+/// 一个被封禁的IP
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ban {
+    /// 封禁到期的时间
+    pub until: DateTime<Utc>,
+    /// 触发封禁的行为
+    pub reason: String,
+}
+
+/// 运行中节点的一个连接，保存在地址簿文件中供 `peers` 命令查看
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Connection {
+    pub addr: SocketAddr,
+    /// 是否由对方发起
+    pub inbound: bool,
+    /// 建立连接的时间
+    pub since: DateTime<Utc>,
+    /// 对方的软件标识，握手完成前为空
+    pub user_agent: String,
+    /// 对方已知的最高区块高度
+    pub best_height: u64,
+    /// 对方当前的不良行为分数
+    pub score: u32,
+}
+
+/// # 地址簿 (AddressBook)
+///
+/// 记录节点知道的其他节点的地址、被封禁的IP，以及运行中节点的当前连接，
+/// 保存在数据目录的 `peers.json` 中：
+///
+/// ```json
+/// {
+///   "addresses": { "127.0.0.1:8331": { "last_seen": "...", "last_connected": "...", "failures": 0 } },
+///   "bans": { "10.0.0.7": { "until": "...", "reason": "无效的区块" } },
+///   "connections": [ { "addr": "127.0.0.1:8331", "inbound": false, ... } ]
+/// }
+/// ```
+///
+/// 地址来自命令行的 `--peer`、成功的连接和其他节点的 `Addr` 消息。
+/// 节点启动后自动连接地址簿中的地址，因此重启后不需要再次指定 `--peer`。
+/// 封禁按IP进行，同一台机器上不同端口的连接一起被拒绝。
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AddressBook {
+    #[serde(default)]
+    addresses: BTreeMap<SocketAddr, KnownAddress>,
+    #[serde(default)]
+    bans: BTreeMap<IpAddr, Ban>,
+    #[serde(default)]
+    connections: Vec<Connection>,
+    /// 各IP的不良行为分数，不保存
+    #[serde(skip)]
+    scores: HashMap<IpAddr, u32>,
+    /// 地址簿文件的路径，为 None 时只保存在内存中
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl AddressBook {
+    /// 只保存在内存中的空地址簿
+    pub fn in_memory() -> Self {
+        AddressBook::default()
+    }
+
+    /// # 从文件加载地址簿
+    ///
+    /// 文件不存在时返回空的地址簿，之后 `save` 会创建它。
+    pub fn load(path: &Path) -> Result<Self, BlockchainError> {
+        let mut book = match fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+                BlockchainError::FormatError(format!("无法解析地址簿 {}: {}", path.display(), e))
+            })?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => AddressBook::default(),
+            Err(e) => return Err(e.into()),
+        };
+        book.path = Some(path.to_path_buf());
+        Ok(book)
+    }
+
+    /// 数据目录中的地址簿
+    pub fn load_from_dir(data_dir: &Path) -> Result<Self, BlockchainError> {
+        Self::load(&data_dir.join(ADDRESS_BOOK_FILE_NAME))
+    }
+
+    /// 把地址簿原子地写入文件；只保存在内存中时什么也不做
+    pub fn save(&self) -> Result<(), BlockchainError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_vec_pretty(self)?;
+        storage::write_atomic(path, &json)?;
+        Ok(())
+    }
+
+    /// 已知的地址，按地址排序
+    pub fn addresses(&self) -> &BTreeMap<SocketAddr, KnownAddress> {
+        &self.addresses
+    }
+
+    /// 保存的连接列表，由运行中的节点更新
+    pub fn connections(&self) -> &[Connection] {
+        &self.connections
+    }
+
+    /// 替换保存的连接列表
+    pub fn set_connections(&mut self, connections: Vec<Connection>) {
+        self.connections = connections;
+    }
+
+    /// # 记录听说的地址
+    ///
+    /// 已知的地址只更新最后活动时间。地址簿已满时忘记最久没有活动的地址。
+    pub fn add(&mut self, addr: SocketAddr, now: DateTime<Utc>) {
+        if addr.port() == 0 || addr.ip().is_unspecified() {
+            return;
+        }
+        if let Some(known) = self.addresses.get_mut(&addr) {
+            known.last_seen = known.last_seen.max(now);
+            return;
+        }
+        if self.addresses.len() >= MAX_ADDRESSES
+            && let Some(oldest) = self.addresses.iter().min_by_key(|(_, known)| known.last_seen).map(|(addr, _)| *addr)
+        {
+            self.addresses.remove(&oldest);
+        }
+        self.addresses.insert(
+            addr,
+            KnownAddress {
+                last_seen: now,
+                last_connected: None,
+                last_failure: None,
+                failures: 0,
+            },
+        );
+    }
+
+    /// 记录与这个地址完成了握手
+    pub fn mark_connected(&mut self, addr: SocketAddr, now: DateTime<Utc>) {
+        self.add(addr, now);
+        if let Some(known) = self.addresses.get_mut(&addr) {
+            known.last_seen = now;
+            known.last_connected = Some(now);
+            known.failures = 0;
+        }
+    }
+
+    /// 记录主动连接这个地址失败，连续失败太多次的地址被忘记
+    pub fn mark_failed(&mut self, addr: SocketAddr, now: DateTime<Utc>) {
+        if let Some(known) = self.addresses.get_mut(&addr) {
+            known.failures += 1;
+            known.last_failure = Some(now);
+            if known.failures >= MAX_FAILURES {
+                self.addresses.remove(&addr);
+            }
+        }
+    }
+
+    /// # 选择要主动连接的地址
+    ///
+    /// 跳过被封禁的、刚刚连接失败的和 `exclude` 中的地址（已连接或正在连接的），
+    /// 优先选择失败次数少、最近活动过的地址。
+    pub fn candidates(&self, count: usize, exclude: &[SocketAddr], now: DateTime<Utc>) -> Vec<SocketAddr> {
+        let retry = TimeDelta::seconds(RETRY_SECS);
+        let mut candidates: Vec<_> = self
+            .addresses
+            .iter()
+            .filter(|(addr, _)| !exclude.contains(addr) && !self.is_banned(addr.ip(), now))
+            .filter(|(_, known)| known.last_failure.is_none_or(|failure| now - failure >= retry))
+            .collect();
+        candidates.sort_by_key(|(_, known)| (known.failures, std::cmp::Reverse(known.last_seen)));
+        candidates.into_iter().take(count).map(|(addr, _)| *addr).collect()
+    }
+
+    /// 告诉其他节点的地址：最近活动过的、未被封禁的地址，最多 `MAX_ADDR_PER_MESSAGE` 个
+    pub fn shareable(&self, exclude: &[SocketAddr], now: DateTime<Utc>) -> Vec<SocketAddr> {
+        let mut addresses: Vec<_> = self
+            .addresses
+            .iter()
+            .filter(|(addr, _)| !exclude.contains(addr) && !self.is_banned(addr.ip(), now))
+            .collect();
+        addresses.sort_by_key(|(_, known)| std::cmp::Reverse(known.last_seen));
+        addresses.into_iter().take(MAX_ADDR_PER_MESSAGE).map(|(addr, _)| *addr).collect()
+    }
+
+    /// 这个IP当前是否被封禁
+    pub fn is_banned(&self, ip: IpAddr, now: DateTime<Utc>) -> bool {
+        self.bans.get(&ip).is_some_and(|ban| ban.until > now)
+    }
+
+    /// 仍然有效的封禁，按IP排序
+    pub fn active_bans(&self, now: DateTime<Utc>) -> Vec<(IpAddr, &Ban)> {
+        self.bans
+            .iter()
+            .filter(|(_, ban)| ban.until > now)
+            .map(|(ip, ban)| (*ip, ban))
+            .collect()
+    }
+
+    /// 这个IP当前的不良行为分数
+    pub fn score(&self, ip: IpAddr) -> u32 {
+        self.scores.get(&ip).copied().unwrap_or(0)
+    }
+
+    /// # 记录一次不良行为
+    ///
+    /// ## 返回值
+    /// 分数因此达到 `BAN_THRESHOLD`、这个IP刚被封禁时返回 true
+    pub fn misbehaving(&mut self, ip: IpAddr, behavior: Misbehavior, reason: &str, now: DateTime<Utc>) -> bool {
+        let score = self.scores.entry(ip).or_insert(0);
+        *score = score.saturating_add(behavior.penalty());
+        if *score < BAN_THRESHOLD || self.is_banned(ip, now) {
+            return false;
+        }
+        self.ban(ip, reason, now);
+        true
+    }
+
+    /// 封禁一个IP `BAN_HOURS` 小时，同时忘记它的地址和分数
+    pub fn ban(&mut self, ip: IpAddr, reason: &str, now: DateTime<Utc>) {
+        self.bans.insert(
+            ip,
+            Ban {
+                until: now + TimeDelta::hours(BAN_HOURS),
+                reason: reason.to_string(),
+            },
+        );
+        self.scores.remove(&ip);
+        self.addresses.retain(|addr, _| addr.ip() != ip);
+    }
+
+    /// 删除已经到期的封禁
+    pub fn expire_bans(&mut self, now: DateTime<Utc>) {
+        self.bans.retain(|_, ban| ban.until > now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    /// # 测试地址簿保存后原样加载，分数不保存
+    #[test]
+    fn test_save_and_load() {
+        let dir = TempDir::new().unwrap();
+        let now = Utc::now();
+        let mut book = AddressBook::load_from_dir(dir.path()).unwrap();
+        assert!(book.addresses().is_empty());
+
+        book.add(addr(8331), now);
+        book.mark_connected(addr(8332), now);
+        book.ban("10.0.0.7".parse().unwrap(), "无效的区块", now);
+        book.misbehaving("10.0.0.8".parse().unwrap(), Misbehavior::InvalidMessage, "格式错误", now);
+        book.save().unwrap();
+
+        let loaded = AddressBook::load_from_dir(dir.path()).unwrap();
+        assert_eq!(loaded.addresses(), book.addresses());
+        assert!(loaded.is_banned("10.0.0.7".parse().unwrap(), now));
+        assert_eq!(loaded.score("10.0.0.8".parse().unwrap()), 0);
+
+        fs::write(dir.path().join(ADDRESS_BOOK_FILE_NAME), "{").unwrap();
+        assert!(AddressBook::load_from_dir(dir.path()).is_err());
+    }
+
+    /// # 测试不良行为累计到阈值后封禁，封禁到期后解除
+    #[test]
+    fn test_misbehavior_and_bans() {
+        let now = Utc::now();
+        let ip: IpAddr = "10.0.0.7".parse().unwrap();
+        let mut book = AddressBook::in_memory();
+        book.add(SocketAddr::new(ip, 8333), now);
+
+        for _ in 0..4 {
+            assert!(!book.misbehaving(ip, Misbehavior::InvalidMessage, "格式错误", now));
+        }
+        assert_eq!(book.score(ip), 80);
+        assert!(book.misbehaving(ip, Misbehavior::InvalidMessage, "格式错误", now));
+        assert!(book.is_banned(ip, now));
+        // 被封禁的IP的地址被忘记
+        assert!(book.addresses().is_empty());
+
+        // 无效的区块立即封禁
+        let other: IpAddr = "10.0.0.8".parse().unwrap();
+        assert!(book.misbehaving(other, Misbehavior::InvalidBlock, "无效的区块", now));
+        assert_eq!(book.active_bans(now).len(), 2);
+
+        let later = now + TimeDelta::hours(BAN_HOURS) + TimeDelta::seconds(1);
+        assert!(!book.is_banned(ip, later));
+        book.expire_bans(later);
+        assert!(book.active_bans(now).is_empty());
+    }
+
+    /// # 测试选择连接地址：跳过已连接、被封禁和刚失败的地址，连续失败的地址被忘记
+    #[test]
+    fn test_candidates() {
+        let now = Utc::now();
+        let mut book = AddressBook::in_memory();
+        book.add(addr(1), now - TimeDelta::seconds(30));
+        book.add(addr(2), now);
+        book.add(addr(3), now);
+        book.add(SocketAddr::from(([0, 0, 0, 0], 4)), now);
+        book.add(SocketAddr::from(([10, 0, 0, 7], 5)), now);
+        book.ban("10.0.0.7".parse().unwrap(), "测试", now);
+
+        assert_eq!(book.candidates(10, &[addr(3)], now), vec![addr(2), addr(1)]);
+        assert_eq!(book.shareable(&[], now).len(), 3);
+
+        book.mark_failed(addr(2), now);
+        assert_eq!(book.candidates(10, &[], now), vec![addr(3), addr(1)]);
+        let later = now + TimeDelta::seconds(RETRY_SECS);
+        assert_eq!(book.candidates(10, &[], later), vec![addr(3), addr(1), addr(2)]);
+
+        for _ in 1..MAX_FAILURES {
+            book.mark_failed(addr(2), now);
+        }
+        assert!(!book.addresses().contains_key(&addr(2)));
+    }
+}
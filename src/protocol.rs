@@ -0,0 +1,297 @@
+// ==================== 依赖库导入 ====================
+// 导入区块和错误类型
+use crate::block::Block;
+use crate::blockchain::{Blockchain, BlockchainError};
+// serde: 消息内容的JSON编码
+use serde::{Deserialize, Serialize};
+// std::io: 帧的读写
+use std::io::{self, Read, Write};
+// std::net: 交换的节点地址
+use std::net::SocketAddr;
+
+/// 当前的协议版本，握手时发送给对方
+pub const PROTOCOL_VERSION: u32 = 3;
+
+/// 支持区块头优先同步（`GetHeaders`/`GetBodies`）的最低协议版本，
+/// 与更早版本的节点仍然用 `GetBlocks` 同步
+pub const HEADERS_FIRST_VERSION: u32 = 2;
+
+/// 支持交换节点地址（`GetAddr`/`Addr`）的最低协议版本，不向更早版本的节点发送这两条消息
+pub const ADDR_VERSION: u32 = 3;
+
+/// 能够通信的最低协议版本，低于它的节点在握手时被拒绝
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// 每一帧开头的魔数，用于尽早发现连到了不是区块链节点的端口
+pub const FRAME_MAGIC: &[u8; 4] = b"SBCN";
+
+/// 单帧内容的最大字节数，防止对方声明一个巨大的长度耗尽内存
+pub const MAX_FRAME_BYTES: u32 = 16 * 1024 * 1024;
+
+/// 节点的软件标识，握手时发送给对方，便于排查问题
+pub const USER_AGENT: &str = concat!("simplied-blockchain-rust/", env!("CARGO_PKG_VERSION"));
+
+// ==================== 消息 ====================
+
+/// # 握手信息 (Version)
+///
+/// 建立连接后双方首先互相发送，用来确认对方和自己在同一条链上：
+/// - `protocol_version`: 协议版本，不低于 `MIN_PROTOCOL_VERSION`
+/// - `chain_id`: 链规范中的链ID
+/// - `genesis_hash`: 创世区块哈希，由链规范唯一确定，同一条链的所有节点都相同
+/// - `best_height`: 发送方的链尖高度，接收方据此决定是否需要同步
+/// - `user_agent`: 软件名称和版本
+/// - `listen_port`: 发送方接受连接的端口。对方主动连接时只看到一个临时端口，
+///   与它的IP组合后才是可以告诉其他节点的地址；旧版本的节点不发送
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Version {
+    pub protocol_version: u32,
+    pub chain_id: String,
+    pub genesis_hash: String,
+    pub best_height: u64,
+    pub user_agent: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listen_port: Option<u16>,
+}
+
+impl Version {
+    /// 根据本地区块链生成握手信息
+    pub fn local(blockchain: &Blockchain) -> Self {
+        Version {
+            protocol_version: PROTOCOL_VERSION,
+            chain_id: blockchain.spec().chain_id.clone(),
+            genesis_hash: blockchain.chain[0].hash.clone(),
+            best_height: blockchain.get_latest_block().index,
+            user_agent: USER_AGENT.to_string(),
+            listen_port: None,
+        }
+    }
+
+    /// # 检查对方的握手信息能否与自己通信
+    ///
+    /// ## 返回值
+    /// 不兼容时返回拒绝原因，会通过 `Reject` 消息告诉对方
+    pub fn check_compatible(&self, theirs: &Version) -> Result<(), String> {
+        if theirs.protocol_version < MIN_PROTOCOL_VERSION {
+            return Err(format!(
+                "协议版本 {} 过低，最低支持 {}",
+                theirs.protocol_version, MIN_PROTOCOL_VERSION
+            ));
+        }
+        if theirs.chain_id != self.chain_id {
+            return Err(format!("链ID {} 与本节点的 {} 不同", theirs.chain_id, self.chain_id));
+        }
+        if theirs.genesis_hash != self.genesis_hash {
+            return Err(format!(
+                "创世区块 {} 与本节点的 {} 不同",
+                theirs.genesis_hash, self.genesis_hash
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 区块清单中的一项：区块的高度和哈希
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockRef {
+    pub height: u64,
+    pub hash: String,
+}
+
+impl From<&Block> for BlockRef {
+    fn from(block: &Block) -> Self {
+        BlockRef {
+            height: block.index,
+            hash: block.hash.clone(),
+        }
+    }
+}
+
+/// # 节点之间的消息 (Message)
+///
+/// | 消息 | 用途 |
+/// |------|------|
+/// | `Version` / `Verack` | 握手：发送自己的信息 / 确认接受对方的信息 |
+/// | `Reject` | 握手失败的原因，发送后断开连接 |
+/// | `Ping` / `Pong` | 检测连接是否存活，`Pong` 原样带回 `Ping` 的 `nonce` |
+/// | `Inv` | 区块清单：通告自己新接受的区块，对方缺少时再来请求 |
+/// | `GetBlocks` | 请求从 `from` 高度开始的区块 |
+/// | `Blocks` | 对 `GetBlocks` 的回复，按高度顺序排列 |
+/// | `Transactions` | 转发待处理的交易 |
+/// | `GetHeaders` | 请求区块头，从 `locator`（区块定位器，见 `sync::locator`）中对方认识的第一个区块之后开始（协议版本 2） |
+/// | `Headers` | 对 `GetHeaders` 的回复：裁剪后的区块，见 `sync.rs` |
+/// | `GetBodies` | 请求从 `from` 高度开始的 `count` 个完整区块（协议版本 2） |
+/// | `Bodies` | 对 `GetBodies` 的回复，可能少于请求的数量 |
+/// | `GetAddr` | 请求对方知道的其他节点的地址（协议版本 3） |
+/// | `Addr` | 节点地址，最多 `peers::MAX_ADDR_PER_MESSAGE` 个（协议版本 3） |
+///
+/// 握手完成之前只允许 `Version`、`Verack`、`Reject`、`Ping` 和 `Pong`。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    Version(Version),
+    Verack,
+    Reject { reason: String },
+    Ping { nonce: u64 },
+    Pong { nonce: u64 },
+    Inv { blocks: Vec<BlockRef> },
+    GetBlocks { from: u64 },
+    Blocks { blocks: Vec<Block> },
+    Transactions { transactions: Vec<String> },
+    GetHeaders { locator: Vec<String> },
+    Headers { headers: Vec<Block> },
+    GetBodies { from: u64, count: u64 },
+    Bodies { blocks: Vec<Block> },
+    GetAddr,
+    Addr { addresses: Vec<SocketAddr> },
+}
+
+impl Message {
+    /// 握手完成之前是否允许收到这条消息
+    pub fn allowed_before_handshake(&self) -> bool {
+        matches!(
+            self,
+            Message::Version(_) | Message::Verack | Message::Reject { .. } | Message::Ping { .. } | Message::Pong { .. }
+        )
+    }
+}
+
+// ==================== 帧 ====================
+
+/// # 写入一帧消息
+///
+/// ## 帧格式
+/// ```text
+/// [魔数 "SBCN"][内容长度 u32 大端序][内容：消息的JSON编码]
+/// ```
+/// 整帧先在内存中组装好再一次写出，多个线程轮流写同一个连接时帧不会交错。
+pub fn write_message<W: Write>(writer: &mut W, message: &Message) -> Result<(), BlockchainError> {
+    let payload = serde_json::to_vec(message)?;
+    let len = u32::try_from(payload.len())
+        .ok()
+        .filter(|len| *len <= MAX_FRAME_BYTES)
+        .ok_or_else(|| BlockchainError::FormatError(format!("消息超过 {} 字节", MAX_FRAME_BYTES)))?;
+
+    let mut frame = Vec::with_capacity(payload.len() + 8);
+    frame.extend_from_slice(FRAME_MAGIC);
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(&payload);
+    writer.write_all(&frame)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// # 读取一帧消息
+///
+/// ## 返回值
+/// * `Ok(Some(Message))` - 读到一条完整的消息
+/// * `Ok(None)` - 对方在两帧之间关闭了连接
+/// * `Err(BlockchainError::FormatError)` - 魔数不符、长度超限或内容不是有效的消息
+/// * `Err(BlockchainError::IoError)` - 读取失败，或连接在一帧中间断开
+pub fn read_message<R: Read>(reader: &mut R) -> Result<Option<Message>, BlockchainError> {
+    let mut magic = [0u8; 4];
+    match reader.read(&mut magic[..1])? {
+        0 => return Ok(None),
+        _ => reader.read_exact(&mut magic[1..])?,
+    }
+    if &magic != FRAME_MAGIC {
+        return Err(BlockchainError::FormatError("帧魔数不符，对方可能不是区块链节点".to_string()));
+    }
+
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len);
+    if len > MAX_FRAME_BYTES {
+        return Err(BlockchainError::FormatError(format!(
+            "帧长度 {} 超过上限 {} 字节",
+            len, MAX_FRAME_BYTES
+        )));
+    }
+
+    let mut payload = Vec::new();
+    reader.by_ref().take(len as u64).read_to_end(&mut payload)?;
+    if payload.len() != len as usize {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    serde_json::from_slice(&payload)
+        .map(Some)
+        .map_err(|e| BlockchainError::FormatError(format!("无效的消息: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Network;
+
+    /// # 测试消息经过帧编码后原样还原，连续的帧可以逐个读出
+    #[test]
+    fn test_frame_roundtrip() {
+        let blockchain = Blockchain::with_spec(Network::Regtest.spec());
+        let messages = vec![
+            Message::Version(Version::local(&blockchain)),
+            Message::Verack,
+            Message::Ping { nonce: 42 },
+            Message::Inv {
+                blocks: vec![BlockRef::from(blockchain.get_latest_block())],
+            },
+            Message::Blocks {
+                blocks: blockchain.chain.clone(),
+            },
+            Message::GetBodies { from: 1, count: 100 },
+            Message::GetAddr,
+            Message::Addr {
+                addresses: vec!["127.0.0.1:8331".parse().unwrap(), "[::1]:8332".parse().unwrap()],
+            },
+        ];
+
+        let mut buffer = Vec::new();
+        for message in &messages {
+            write_message(&mut buffer, message).unwrap();
+        }
+        assert_eq!(&buffer[..4], FRAME_MAGIC);
+
+        let mut reader = buffer.as_slice();
+        for message in &messages {
+            assert_eq!(read_message(&mut reader).unwrap().as_ref(), Some(message));
+        }
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+
+    /// # 测试格式错误的帧被拒绝
+    #[test]
+    fn test_malformed_frames() {
+        // 魔数不符
+        assert!(read_message(&mut &b"GET / HTTP/1.1\r\n"[..]).is_err());
+
+        // 长度超过上限
+        let mut oversized = FRAME_MAGIC.to_vec();
+        oversized.extend_from_slice(&(MAX_FRAME_BYTES + 1).to_be_bytes());
+        assert!(matches!(read_message(&mut oversized.as_slice()), Err(BlockchainError::FormatError(_))));
+
+        // 帧在中间断开
+        let mut truncated = Vec::new();
+        write_message(&mut truncated, &Message::Verack).unwrap();
+        truncated.pop();
+        assert!(matches!(read_message(&mut truncated.as_slice()), Err(BlockchainError::IoError(_))));
+    }
+
+    /// # 测试握手时检查协议版本、链ID和创世区块
+    #[test]
+    fn test_version_compatibility() {
+        let ours = Version::local(&Blockchain::with_spec(Network::Regtest.spec()));
+        assert!(ours.check_compatible(&ours.clone()).is_ok());
+
+        let old = Version { protocol_version: 0, ..ours.clone() };
+        assert!(ours.check_compatible(&old).is_err());
+
+        let other_network = Version::local(&Blockchain::with_spec(Network::Testnet.spec()));
+        assert!(ours.check_compatible(&other_network).is_err());
+
+        // 链ID相同但创世区块不同（例如修改了链规范中的创世信息）
+        let mut spec = Network::Regtest.spec();
+        spec.genesis.message = "另一个创世区块".to_string();
+        let forked = Version::local(&Blockchain::with_spec(spec));
+        assert_eq!(forked.chain_id, ours.chain_id);
+        assert!(ours.check_compatible(&forked).unwrap_err().contains("创世区块"));
+    }
+}
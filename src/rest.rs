@@ -0,0 +1,421 @@
+// ==================== 依赖库导入 ====================
+// 导入区块链类型，读取统计信息
+use crate::blockchain::{Blockchain, BlockchainError};
+// 导入事件中心和过滤条件，`/events` 推送节点事件
+use crate::events::{EventFilter, EventHub};
+// 导入最小 HTTP 实现，写入请求使用基本认证
+use crate::http::{self, Handler, Request, Response, Server};
+// 导入交易ID的计算
+use crate::mempool;
+// 导入P2P节点，所有请求都经过运行中的节点完成
+use crate::node::Node;
+// 导入与 RPC 共用的链验证
+use crate::rpc;
+// serde_json: 响应都是 JSON
+use serde_json::{json, Value};
+// std::io: 写出事件流
+use std::io::Write;
+// std::net: 监听地址
+use std::net::{SocketAddr, ToSocketAddrs};
+// std::sync: 在连接线程之间共享节点和事件中心
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+// std::time: 事件流的保活间隔
+use std::time::Duration;
+
+/// REST 服务默认的监听地址
+pub const DEFAULT_REST_ADDR: &str = "127.0.0.1:8333";
+
+/// `/blocks` 没有给出 `limit` 时每页的区块数
+const DEFAULT_LIMIT: u64 = 20;
+
+/// `/blocks` 每页最多的区块数
+const MAX_LIMIT: u64 = 100;
+
+/// 事件流在没有事件时发送保活注释的间隔，同时用来发现已经断开的客户端
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// # REST 服务 (RestServer)
+///
+/// 给网页面板等使用的 REST 接口，与 JSON-RPC 一样经过运行中的节点完成。
+/// 区块以 `Block` 的 serde 格式输出，与区块链文件中的格式相同。
+///
+/// | 请求 | 结果 |
+/// |------|------|
+/// | `GET /blocks?from=&limit=` | 从高度 `from`（默认0）开始的最多 `limit` 个区块（默认20，最多100） |
+/// | `GET /blocks/{height}` | 指定高度的区块 |
+/// | `GET /blocks/hash/{hash}` | 指定哈希的区块 |
+/// | `GET /stats` | 统计信息（`Blockchain::get_statistics`） |
+/// | `GET /validate` | 验证整条链（同 RPC 的 `validatechain`） |
+/// | `POST /data` | 请求体 `{"data": "..."}`，作为交易加入交易池，等待打包；需要认证 |
+/// | `GET /events?topics=` | 服务器推送事件（SSE），只推送逗号分隔的主题，默认全部；各主题的过滤参数见 `EventFilter` |
+///
+/// 错误以 `{"error": "说明"}` 返回：参数无效为 400，区块或路径不存在为 404，方法不对为 405。
+///
+/// 读取接口不需要认证，响应允许跨域访问，网页面板可以从其他端口的页面直接调用。
+/// 写入接口 `POST /data` 必须用 RPC 的 cookie 凭据做基本认证，请求体必须是 `application/json`，
+/// 响应不允许跨域访问：其他网页无法借用浏览器缓存的凭据或简单表单提交数据。
+/// 没有给出凭据（节点没有开启 RPC）时写入接口关闭，返回 403。
+pub struct RestServer {
+    server: Server,
+}
+
+impl RestServer {
+    /// # 启动 REST 服务
+    ///
+    /// ## 参数
+    /// * `addr` - 监听地址，端口为0时由操作系统分配（见 `local_addr`）
+    /// * `node` - 处理请求的节点
+    /// * `events` - 节点事件的事件中心，`/events` 从这里订阅
+    /// * `write_credentials` - 写入接口的凭据（见 `RpcServer::credentials`），None 表示只读
+    pub fn start<A: ToSocketAddrs>(
+        addr: A,
+        node: Arc<Node>,
+        events: Arc<EventHub>,
+        write_credentials: Option<&str>,
+    ) -> Result<Self, BlockchainError> {
+        let write_auth = write_credentials.map(http::basic_auth);
+        let handler: Arc<Handler> = Arc::new(move |request: Request| {
+            let cross_origin = matches!(request.method.as_str(), "GET" | "OPTIONS");
+            let response = handle(&node, &events, write_auth.as_deref(), request);
+            if cross_origin {
+                response.with_header("Access-Control-Allow-Origin", "*")
+            } else {
+                response
+            }
+        });
+        Ok(RestServer {
+            server: Server::start(addr, handler)?,
+        })
+    }
+
+    /// 实际监听的地址
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server.local_addr()
+    }
+}
+
+/// 错误响应 `{"error": 说明}`
+fn error(status: u16, message: &str) -> Response {
+    Response::json(status, &json!({ "error": message }))
+}
+
+/// 按路径和方法分派请求，`write_auth` 是写入接口期望的认证头
+fn handle(node: &Node, events: &EventHub, write_auth: Option<&str>, request: Request) -> Response {
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    let method = request.method.as_str();
+    if method == "OPTIONS" {
+        // 跨域请求的预检：只允许读取，跨域的 POST /data 在预检阶段就被浏览器拦下
+        return Response::empty(204).with_header("Access-Control-Allow-Methods", "GET");
+    }
+    let allowed = match segments.as_slice() {
+        ["data"] => "POST",
+        ["blocks"] | ["blocks", _] | ["blocks", "hash", _] | ["stats"] | ["validate"] | ["events"] => "GET",
+        _ => return error(404, &format!("没有路径 {}", request.path)),
+    };
+    if method != allowed {
+        return error(405, &format!("{} 只接受 {} 请求", request.path, allowed)).with_header("Allow", allowed);
+    }
+
+    match segments.as_slice() {
+        ["blocks"] => list_blocks(node, &request),
+        ["blocks", "hash", hash] => match node.with_blockchain(|blockchain| blockchain.get_block_by_hash(hash).cloned()) {
+            Some(block) => Response::json(200, &json!(block)),
+            None => error(404, &format!("哈希为 {} 的区块不存在", hash)),
+        },
+        ["blocks", height] => {
+            let Ok(height) = height.parse::<u64>() else {
+                return error(400, &format!("无效的区块高度: {}", height));
+            };
+            match node.with_blockchain(|blockchain| blockchain.get_block(height).cloned()) {
+                Some(block) => Response::json(200, &json!(block)),
+                None => error(404, &format!("区块 #{} 不存在", height)),
+            }
+        }
+        ["stats"] => Response::json(200, &json!(node.with_blockchain(Blockchain::get_statistics))),
+        ["validate"] => Response::json(200, &rpc::validation_report(node)),
+        ["events"] => event_stream(events, &request),
+        _ => submit_data(node, write_auth, &request),
+    }
+}
+
+/// 读取非负整数的查询参数，没有给出时使用默认值
+fn query_u64(request: &Request, name: &str, default: u64) -> Result<u64, Response> {
+    match request.query_param(name) {
+        None | Some("") => Ok(default),
+        Some(value) => value
+            .parse::<u64>()
+            .map_err(|_| error(400, &format!("{} 必须是非负整数: {}", name, value))),
+    }
+}
+
+/// # `GET /blocks?from=&limit=`
+///
+/// 返回 `{"from", "limit", "total", "next", "blocks"}`：`total` 是区块总数，
+/// `next` 是下一页的 `from`，已经是最后一页时为 null。
+fn list_blocks(node: &Node, request: &Request) -> Response {
+    let (from, limit) = match (query_u64(request, "from", 0), query_u64(request, "limit", DEFAULT_LIMIT)) {
+        (Ok(from), Ok(limit)) => (from, limit),
+        (Err(response), _) | (_, Err(response)) => return response,
+    };
+    if limit == 0 || limit > MAX_LIMIT {
+        return error(400, &format!("limit 必须在1-{}之间", MAX_LIMIT));
+    }
+
+    let (blocks, total) = node.with_blockchain(|blockchain| {
+        let total = blockchain.chain.len() as u64;
+        let start = from.min(total) as usize;
+        let end = from.saturating_add(limit).min(total) as usize;
+        (blockchain.chain[start..end].to_vec(), total)
+    });
+    let next = from.saturating_add(limit);
+    Response::json(
+        200,
+        &json!({
+            "from": from,
+            "limit": limit,
+            "total": total,
+            "next": (next < total).then_some(next),
+            "blocks": blocks,
+        }),
+    )
+}
+
+/// # `GET /events?topics=&min_height=&mining_interval=&validation=`
+///
+/// 服务器推送事件（`text/event-stream`），浏览器可以直接用 `EventSource` 订阅。每个事件是一段：
+///
+/// ```text
+/// event: block
+/// data: {"topic":"block","type":"block_accepted","time":"...","data":{"height":5,...}}
+/// ```
+///
+/// 查询参数见 `EventFilter`：`topics` 选择主题，其余参数只作用于各自的主题。
+/// 连接建立后先发送一行注释，之后没有事件时每隔 `KEEPALIVE_INTERVAL` 发送一次保活注释。
+/// 客户端处理不过来时会被断开（见 `EventHub`），重新连接即可。
+fn event_stream(events: &EventHub, request: &Request) -> Response {
+    let filter = match EventFilter::from_query(|name| request.query_param(name)) {
+        Ok(filter) => filter,
+        Err(reason) => return error(400, &reason),
+    };
+    // 在返回之前订阅，响应头发出之后发生的事件都不会错过
+    let receiver = events.subscribe(filter);
+    Response::stream("text/event-stream; charset=utf-8", move |writer: &mut dyn Write| {
+        writer.write_all(": subscribed\n\n".as_bytes())?;
+        writer.flush()?;
+        loop {
+            match receiver.recv_timeout(KEEPALIVE_INTERVAL) {
+                Ok(event) => {
+                    let data = serde_json::to_string(&event).expect("事件总能序列化为 JSON");
+                    write!(writer, "event: {}\ndata: {}\n\n", event.topic.name(), data)?;
+                }
+                Err(RecvTimeoutError::Timeout) => writer.write_all(": keepalive\n\n".as_bytes())?,
+                // 订阅者因为积压过多被移除
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+            writer.flush()?;
+        }
+    })
+    .with_header("Cache-Control", "no-cache")
+}
+
+/// # `POST /data`
+///
+/// 请求体为 `{"data": "..."}`。新交易返回 202 和 `{"txid", "added": true}`，
+/// 交易池中已有时返回 200 和 `"added": false`，被区块链拒绝时返回 400。
+/// 写入接口关闭时返回 403，没有正确的认证时返回 401，请求体不是 `application/json` 时返回 415。
+fn submit_data(node: &Node, write_auth: Option<&str>, request: &Request) -> Response {
+    let Some(expected_auth) = write_auth else {
+        return error(403, "REST 接口是只读的：写入需要节点同时开启 RPC（--rpc），并用 rpc.cookie 中的凭据认证");
+    };
+    if !http::is_authorized(request, expected_auth) {
+        return error(401, "认证失败").with_header("WWW-Authenticate", "Basic realm=\"rest\"");
+    }
+    let media_type = request.header("content-type").and_then(|value| value.split(';').next()).unwrap_or("");
+    if !media_type.trim().eq_ignore_ascii_case("application/json") {
+        return error(415, "请求体必须是 application/json");
+    }
+    let data = match serde_json::from_slice::<Value>(&request.body) {
+        Ok(Value::Object(mut body)) => match body.remove("data") {
+            Some(Value::String(data)) => data,
+            _ => return error(400, "请求体必须包含字符串字段 data"),
+        },
+        _ => return error(400, "请求体必须是 JSON 对象 {\"data\": \"...\"}"),
+    };
+    match node.submit_transaction(data.clone()) {
+        Ok(added) => Response::json(
+            if added { 202 } else { 200 },
+            &json!({ "txid": mempool::transaction_id(&data), "added": added }),
+        ),
+        Err(e) => error(400, &e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::http;
+    use crate::network::Network;
+    use crate::node::NodeConfig;
+    use crate::storage::MemoryStore;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpStream;
+
+    /// 测试中写入接口使用的凭据
+    const CREDENTIALS: &str = "__cookie__:secret";
+
+    /// 启动一个有 `blocks` 个区块（不含创世区块）的回归测试网节点和它的 REST 服务
+    fn start_rest(blocks: usize) -> (Arc<Node>, RestServer) {
+        start_rest_with(blocks, Some(CREDENTIALS))
+    }
+
+    /// 同 `start_rest`，指定写入接口的凭据
+    fn start_rest_with(blocks: usize, write_credentials: Option<&str>) -> (Arc<Node>, RestServer) {
+        let mut blockchain = Blockchain::with_spec(Network::Regtest.spec());
+        for i in 0..blocks {
+            blockchain.add_block_silent(format!("区块 #{}", i + 1)).unwrap();
+        }
+        let hub = Arc::new(EventHub::new());
+        let publisher = Arc::clone(&hub);
+        let node = Node::start("127.0.0.1:0", blockchain, Box::new(MemoryStore::new()), NodeConfig::default(), move |event| {
+            publisher.publish(&event)
+        })
+        .map(Arc::new)
+        .unwrap();
+        let server = RestServer::start("127.0.0.1:0", Arc::clone(&node), hub, write_credentials).unwrap();
+        (node, server)
+    }
+
+    /// 发送请求，返回状态码和解析后的 JSON
+    fn send(server: &RestServer, method: &str, path: &str, body: &str) -> (u16, Value) {
+        send_with(server, method, path, &[], body)
+    }
+
+    /// 同 `send`，带上请求头
+    fn send_with(server: &RestServer, method: &str, path: &str, headers: &[(&str, &str)], body: &str) -> (u16, Value) {
+        let addr = server.local_addr().to_string();
+        let (status, body) = http::request(&addr, method, path, headers, body.as_bytes()).unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    /// 带认证的 `POST /data`
+    fn post_data(server: &RestServer, body: &str) -> (u16, Value) {
+        let auth = http::basic_auth(CREDENTIALS);
+        let headers = [("Authorization", auth.as_str()), ("Content-Type", "application/json")];
+        send_with(server, "POST", "/data", &headers, body)
+    }
+
+    /// 发送原始请求，返回完整的响应头
+    fn raw_head(server: &RestServer, request: &str) -> String {
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response.split("\r\n\r\n").next().unwrap().to_string()
+    }
+
+    /// # 测试分页列出区块
+    #[test]
+    fn test_list_blocks() {
+        let (node, server) = start_rest(25);
+        let (status, page) = send(&server, "GET", "/blocks", "");
+        assert_eq!(status, 200);
+        assert_eq!((&page["total"], &page["next"]), (&json!(26), &json!(20)));
+        let blocks: Vec<Block> = serde_json::from_value(page["blocks"].clone()).unwrap();
+        assert_eq!(blocks.len(), 20);
+        assert_eq!(blocks[0], node.with_blockchain(|blockchain| blockchain.chain[0].clone()));
+
+        let (_, last) = send(&server, "GET", "/blocks?from=20&limit=10", "");
+        assert_eq!((last["blocks"].as_array().unwrap().len(), &last["next"]), (6, &Value::Null));
+        let (_, beyond) = send(&server, "GET", "/blocks?from=100", "");
+        assert!(beyond["blocks"].as_array().unwrap().is_empty());
+
+        assert_eq!(send(&server, "GET", "/blocks?limit=0", "").0, 400);
+        assert_eq!(send(&server, "GET", "/blocks?from=abc", "").0, 400);
+    }
+
+    /// # 测试查询单个区块、统计、验证，以及错误的路径和方法
+    #[test]
+    fn test_block_endpoints() {
+        let (node, server) = start_rest(3);
+        let tip = node.tip();
+        assert_eq!(send(&server, "GET", "/blocks/3", ""), (200, json!(tip)));
+        assert_eq!(send(&server, "GET", &format!("/blocks/hash/{}", tip.hash), ""), (200, json!(tip)));
+        assert_eq!(send(&server, "GET", "/blocks/4", "").0, 404);
+        assert_eq!(send(&server, "GET", "/blocks/hash/abc", "").0, 404);
+        assert_eq!(send(&server, "GET", "/blocks/x", "").0, 400);
+
+        assert_eq!(send(&server, "GET", "/stats", "").1["total_blocks"], json!(4));
+        assert_eq!(send(&server, "GET", "/validate", "").1["valid"], json!(true));
+        assert_eq!(send(&server, "GET", "/nothing", "").0, 404);
+        assert_eq!(send(&server, "POST", "/stats", "").0, 405);
+    }
+
+    /// # 测试事件流：只推送订阅的主题
+    #[test]
+    fn test_event_stream() {
+        let (node, server) = start_rest(0);
+        assert_eq!(send(&server, "GET", "/events?topics=blocks", "").0, 400);
+        assert_eq!(send(&server, "GET", "/events?min_height=x", "").0, 400);
+
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.write_all(b"GET /events?topics=block,difficulty&min_height=2 HTTP/1.1\r\n\r\n").unwrap();
+        let mut lines = BufReader::new(stream).lines().map(Result::unwrap);
+        assert_eq!(lines.next().unwrap(), "HTTP/1.1 200 OK");
+        // 等到订阅确认之后再产生事件
+        assert!(lines.by_ref().any(|line| line == ": subscribed"));
+
+        node.submit_transaction("不推送".to_string()).unwrap();
+        node.set_difficulty(2).unwrap();
+        node.mine("低于 min_height，不推送".to_string()).unwrap();
+        let block = node.mine("推送".to_string()).unwrap();
+        let data: Vec<Value> = lines
+            .filter_map(|line| line.strip_prefix("data: ").map(|data| serde_json::from_str(data).unwrap()))
+            .take(2)
+            .collect();
+        assert_eq!((&data[0]["type"], &data[0]["data"]["to"]), (&json!("difficulty_changed"), &json!(2)));
+        assert_eq!((&data[1]["topic"], &data[1]["data"]["hash"]), (&json!("block"), &json!(block.hash)));
+    }
+
+    /// # 测试提交数据：加入交易池，重复提交不再加入，格式错误返回 400
+    #[test]
+    fn test_submit_data() {
+        let (node, server) = start_rest(0);
+        let (status, body) = post_data(&server, r#"{"data":"面板提交"}"#);
+        assert_eq!((status, &body["added"]), (202, &json!(true)));
+        assert_eq!(body["txid"], json!(mempool::transaction_id("面板提交")));
+        assert_eq!(post_data(&server, r#"{"data":"面板提交"}"#).0, 200);
+        assert!(node.with_blockchain(|blockchain| blockchain.pending_transactions.contains("面板提交")));
+
+        assert_eq!(post_data(&server, r#"{"data":""}"#).0, 400);
+        assert_eq!(post_data(&server, "面板提交").0, 400);
+        assert_eq!(send(&server, "GET", "/data", "").0, 405);
+    }
+
+    /// # 测试写入接口的保护：需要认证和 JSON 请求体，没有凭据时只读，写入的响应不允许跨域
+    #[test]
+    fn test_write_protection() {
+        let (node, server) = start_rest(0);
+        let body = r#"{"data":"未认证"}"#;
+        let json = [("Content-Type", "application/json")];
+        assert_eq!(send_with(&server, "POST", "/data", &json, body).0, 401);
+        let wrong = http::basic_auth("__cookie__:wrong");
+        assert_eq!(send_with(&server, "POST", "/data", &[("Authorization", &wrong), json[0]], body).0, 401);
+        // 简单表单的请求体不是 JSON，即使浏览器带上了缓存的凭据也被拒绝
+        let auth = http::basic_auth(CREDENTIALS);
+        let form = [("Authorization", auth.as_str()), ("Content-Type", "text/plain")];
+        assert_eq!(send_with(&server, "POST", "/data", &form, body).0, 415);
+        assert!(node.with_blockchain(|blockchain| blockchain.pending_transactions.is_empty()));
+
+        // 读取的响应允许跨域，写入的响应和预检都不允许跨域写入
+        assert!(raw_head(&server, "GET /stats HTTP/1.1\r\n\r\n").contains("Access-Control-Allow-Origin: *"));
+        let post = format!("POST /data HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+        assert!(!raw_head(&server, &post).contains("Access-Control-Allow-Origin"));
+        let preflight = raw_head(&server, "OPTIONS /data HTTP/1.1\r\n\r\n");
+        assert!(preflight.contains("Access-Control-Allow-Methods: GET\r\n"));
+
+        let (_, read_only) = start_rest_with(0, None);
+        assert_eq!(send_with(&read_only, "POST", "/data", &[("Authorization", &auth), json[0]], body).0, 403);
+    }
+}
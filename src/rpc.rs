@@ -0,0 +1,557 @@
+// ==================== 依赖库导入 ====================
+// 导入区块链和错误类型
+use crate::blockchain::{Blockchain, BlockchainError};
+// 导入最小 HTTP 实现：服务端、客户端和基本认证
+use crate::http::{self, Handler, Request, Response, Server};
+// 导入交易ID的计算
+use crate::mempool;
+// 导入P2P节点，RPC 调用都经过运行中的节点完成
+use crate::node::Node;
+// 导入外部矿工提交的工作
+use crate::template::Work;
+// serde: RPC 错误对象的序列化
+use serde::{Deserialize, Serialize};
+// serde_json: 请求和响应都是 JSON
+use serde_json::{json, Value};
+// std::fs: 写入和删除认证 cookie 文件
+use std::fs;
+// std::io: 创建 cookie 文件，从操作系统的随机数源读取口令
+use std::io::{self, Read, Write};
+// std::net: 监听地址
+use std::net::{SocketAddr, ToSocketAddrs};
+// std::path: cookie 文件路径
+use std::path::{Path, PathBuf};
+// std::sync: 在连接线程之间共享节点
+use std::sync::Arc;
+
+/// RPC 服务默认的监听地址
+pub const DEFAULT_RPC_ADDR: &str = "127.0.0.1:8332";
+
+/// 数据目录中保存认证 cookie 的文件名
+pub const COOKIE_FILE_NAME: &str = "rpc.cookie";
+
+/// cookie 认证使用的固定用户名
+const COOKIE_USER: &str = "__cookie__";
+
+/// `mine` 和 `getblocktemplate` 没有给出数据时使用的区块数据
+const DEFAULT_MINE_DATA: &str = "RPC挖矿";
+
+// ==================== 错误码 ====================
+//
+// -32700 到 -32600 是 JSON-RPC 2.0 规定的错误码，-32000 以下是本服务自己的错误码。
+
+/// 请求不是有效的 JSON
+pub const PARSE_ERROR: i64 = -32700;
+/// 请求不是有效的 JSON-RPC 请求对象
+pub const INVALID_REQUEST: i64 = -32600;
+/// 没有这个方法
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// 参数缺少、类型错误或超出范围
+pub const INVALID_PARAMS: i64 = -32602;
+/// 要查找的区块不存在
+pub const NOT_FOUND: i64 = -32001;
+/// 区块链拒绝了这个操作，例如无效的交易或难度
+pub const REJECTED: i64 = -32002;
+
+/// # RPC 错误对象
+///
+/// JSON-RPC 2.0 响应中的 `error` 字段：错误码和说明。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<BlockchainError> for RpcError {
+    fn from(error: BlockchainError) -> Self {
+        RpcError::new(REJECTED, error.to_string())
+    }
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RPC错误 {}: {}", self.code, self.message)
+    }
+}
+
+// ==================== 认证 cookie ====================
+//
+// 服务启动时生成一个随机口令，以 `__cookie__:<口令>` 的形式写入数据目录中的 `rpc.cookie`，
+// 停止时删除。客户端读取这个文件，用 HTTP 基本认证发送。能读取数据目录的用户才能调用 RPC。
+
+/// cookie 口令的随机字节数
+const COOKIE_SECRET_BYTES: usize = 32;
+
+/// 从操作系统的密码学安全随机数源读取口令，编码为十六进制
+fn random_secret() -> io::Result<String> {
+    let mut bytes = [0u8; COOKIE_SECRET_BYTES];
+    fs::File::open("/dev/urandom")
+        .and_then(|mut source| source.read_exact(&mut bytes))
+        .map_err(|e| io::Error::new(e.kind(), format!("无法读取 /dev/urandom 生成 RPC cookie: {}", e)))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// 生成新的 cookie 并写入 `dir` 中的 `rpc.cookie`（Unix 上只有所有者可读写），返回凭据
+fn write_cookie(dir: &Path) -> io::Result<String> {
+    let secret = random_secret()?;
+    let credentials = format!("{}:{}", COOKIE_USER, secret);
+
+    fs::create_dir_all(dir)?;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(dir.join(COOKIE_FILE_NAME))?;
+    file.write_all(credentials.as_bytes())?;
+    file.sync_all()?;
+    Ok(credentials)
+}
+
+/// # 读取数据目录中的 cookie
+///
+/// ## 返回值
+/// 凭据 `__cookie__:<口令>`；文件不存在（节点没有运行或没有开启 RPC）时返回IO错误
+pub fn read_cookie(dir: &Path) -> Result<String, BlockchainError> {
+    let path = dir.join(COOKIE_FILE_NAME);
+    let credentials = fs::read_to_string(&path).map_err(|e| {
+        io::Error::new(e.kind(), format!("无法读取 {}（节点是否开启了 RPC？）: {}", path.display(), e))
+    })?;
+    Ok(credentials.trim().to_string())
+}
+
+// ==================== 服务端 ====================
+
+/// # JSON-RPC 服务 (RpcServer)
+///
+/// 通过 HTTP 提供 JSON-RPC 2.0 接口，所有调用都经过运行中的节点完成：
+/// 挖出的区块和提交的交易会像在节点的标准输入中一样转发给其他节点。
+/// 请求必须是 `POST`，并带有 cookie 的基本认证，否则回复 401。
+///
+/// | 方法 | 参数 | 结果 |
+/// |------|------|------|
+/// | `getblockcount` | 无 | 链尖高度 |
+/// | `getblock` | `height` | 区块 |
+/// | `getblockbyhash` | `hash` | 区块 |
+/// | `getstats` | 无 | 统计信息（`Blockchain::get_statistics`） |
+/// | `validatechain` | 无 | `{"valid", "height", "error"}` |
+/// | `submitdata` | `data` | 交易加入交易池：`{"txid", "added"}` |
+/// | `mine` | `data`（可选） | 挖出的区块（同时打包交易池中的交易） |
+/// | `setdifficulty` | `difficulty` | 新的难度 |
+/// | `getblocktemplate` | `data`（可选） | 交给外部矿工的区块模板（见 `BlockTemplate`） |
+/// | `submitwork` | `id`、`nonce`、`timestamp`（可选）、`extra_nonce`（可选） | 按模板追加的区块 |
+///
+/// 参数可以按位置（数组）或按名称（对象）给出，也支持批量请求和通知（没有 `id` 的请求不回复）。
+pub struct RpcServer {
+    server: Server,
+    cookie_path: PathBuf,
+    credentials: String,
+}
+
+impl RpcServer {
+    /// # 启动 RPC 服务
+    ///
+    /// ## 参数
+    /// * `addr` - 监听地址，端口为0时由操作系统分配（见 `local_addr`）
+    /// * `node` - 处理调用的节点
+    /// * `cookie_dir` - 写入认证 cookie 的目录，通常是数据目录
+    pub fn start<A: ToSocketAddrs>(addr: A, node: Arc<Node>, cookie_dir: &Path) -> Result<Self, BlockchainError> {
+        let credentials = write_cookie(cookie_dir)?;
+        let expected = http::basic_auth(&credentials);
+        let handler: Arc<Handler> = Arc::new(move |request: Request| handle_http(&node, &expected, request));
+        let server = Server::start(addr, handler)?;
+        Ok(RpcServer {
+            server,
+            cookie_path: cookie_dir.join(COOKIE_FILE_NAME),
+            credentials,
+        })
+    }
+
+    /// 实际监听的地址
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server.local_addr()
+    }
+
+    /// cookie 中的凭据 `__cookie__:<口令>`，REST 接口的写入请求使用同一个凭据认证
+    pub fn credentials(&self) -> &str {
+        &self.credentials
+    }
+}
+
+impl Drop for RpcServer {
+    fn drop(&mut self) {
+        self.server.shutdown();
+        // 服务停止后 cookie 失效，删除文件让客户端得到明确的错误
+        let _ = fs::remove_file(&self.cookie_path);
+    }
+}
+
+/// 检查 HTTP 层面的要求（方法、认证），再按 JSON-RPC 处理请求体
+fn handle_http(node: &Node, expected_auth: &str, request: Request) -> Response {
+    if request.method != "POST" {
+        return Response::text(405, "JSON-RPC 只接受 POST 请求").with_header("Allow", "POST");
+    }
+    if !http::is_authorized(&request, expected_auth) {
+        return Response::text(401, "认证失败").with_header("WWW-Authenticate", "Basic realm=\"jsonrpc\"");
+    }
+
+    let response = match serde_json::from_slice::<Value>(&request.body) {
+        Err(e) => Some(error_response(Value::Null, RpcError::new(PARSE_ERROR, e.to_string()))),
+        Ok(Value::Array(calls)) if calls.is_empty() => {
+            Some(error_response(Value::Null, RpcError::new(INVALID_REQUEST, "批量请求不能为空")))
+        }
+        Ok(Value::Array(calls)) => {
+            let responses: Vec<Value> = calls.into_iter().filter_map(|call| handle_call(node, call)).collect();
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        Ok(call) => handle_call(node, call),
+    };
+    match response {
+        Some(response) => Response::json(200, &response),
+        // 全部是通知，没有需要回复的内容
+        None => Response::empty(204),
+    }
+}
+
+/// # 处理一个调用
+///
+/// ## 返回值
+/// 响应对象；通知（没有 `id`）返回 None
+fn handle_call(node: &Node, call: Value) -> Option<Value> {
+    let Value::Object(mut call) = call else {
+        return Some(error_response(Value::Null, RpcError::new(INVALID_REQUEST, "请求必须是对象")));
+    };
+    let id = call.remove("id");
+    let method = match (call.get("jsonrpc"), call.get("method")) {
+        (Some(Value::String(version)), Some(Value::String(method))) if version == "2.0" => method.clone(),
+        _ => {
+            let error = RpcError::new(INVALID_REQUEST, "请求必须包含 \"jsonrpc\": \"2.0\" 和字符串 method");
+            return Some(error_response(id.unwrap_or(Value::Null), error));
+        }
+    };
+    let params = Params(call.remove("params").unwrap_or(Value::Null));
+
+    let result = dispatch(node, &method, &params);
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err(error) => error_response(id, error),
+    })
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "error": error, "id": id })
+}
+
+/// 调用参数：按位置的数组或按名称的对象
+struct Params(Value);
+
+impl Params {
+    fn get(&self, position: usize, name: &str) -> Option<&Value> {
+        match &self.0 {
+            Value::Array(values) => values.get(position),
+            Value::Object(values) => values.get(name),
+            _ => None,
+        }
+        .filter(|value| !value.is_null())
+    }
+
+    fn u64(&self, position: usize, name: &str) -> Result<u64, RpcError> {
+        self.get(position, name)
+            .and_then(Value::as_u64)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("{} 必须是非负整数", name)))
+    }
+
+    fn optional_u64(&self, position: usize, name: &str) -> Result<Option<u64>, RpcError> {
+        match self.get(position, name) {
+            None => Ok(None),
+            Some(value) => value
+                .as_u64()
+                .map(Some)
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("{} 必须是非负整数", name))),
+        }
+    }
+
+    fn optional_string(&self, position: usize, name: &str) -> Result<Option<String>, RpcError> {
+        match self.get(position, name) {
+            None => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err(RpcError::new(INVALID_PARAMS, format!("{} 必须是字符串", name))),
+        }
+    }
+
+    fn string(&self, position: usize, name: &str) -> Result<String, RpcError> {
+        self.optional_string(position, name)?
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("缺少参数 {}", name)))
+    }
+}
+
+/// # 验证节点的整条区块链
+///
+/// 见 `Node::validate`。RPC 的 `validatechain` 和 REST 的 `/validate` 共用。
+///
+/// ## 返回值
+/// `{"valid": 是否通过, "height": 验证的链尖高度, "error": 失败原因或 null}`
+pub fn validation_report(node: &Node) -> Value {
+    let (height, result) = node.validate();
+    match result {
+        Ok(()) => json!({ "valid": true, "height": height, "error": null }),
+        Err(e) => json!({ "valid": false, "height": height, "error": e.to_string() }),
+    }
+}
+
+/// 按方法名执行调用
+fn dispatch(node: &Node, method: &str, params: &Params) -> Result<Value, RpcError> {
+    match method {
+        "getblockcount" => Ok(json!(node.tip().index)),
+        "getblock" => {
+            let height = params.u64(0, "height")?;
+            node.with_blockchain(|blockchain| blockchain.get_block(height).cloned())
+                .map(|block| json!(block))
+                .ok_or_else(|| RpcError::new(NOT_FOUND, format!("区块 #{} 不存在", height)))
+        }
+        "getblockbyhash" => {
+            let hash = params.string(0, "hash")?;
+            node.with_blockchain(|blockchain| blockchain.get_block_by_hash(&hash).cloned())
+                .map(|block| json!(block))
+                .ok_or_else(|| RpcError::new(NOT_FOUND, format!("哈希为 {} 的区块不存在", hash)))
+        }
+        "getstats" => Ok(json!(node.with_blockchain(Blockchain::get_statistics))),
+        "validatechain" => Ok(validation_report(node)),
+        "submitdata" => {
+            let data = params.string(0, "data")?;
+            let added = node.submit_transaction(data.clone())?;
+            Ok(json!({ "txid": mempool::transaction_id(&data), "added": added }))
+        }
+        "mine" => {
+            let data = params.optional_string(0, "data")?.unwrap_or_else(|| DEFAULT_MINE_DATA.to_string());
+            Ok(json!(node.mine(data)?))
+        }
+        "setdifficulty" => {
+            let difficulty = u32::try_from(params.u64(0, "difficulty")?)
+                .map_err(|_| RpcError::new(INVALID_PARAMS, "difficulty 超出范围"))?;
+            node.set_difficulty(difficulty)?;
+            Ok(json!(difficulty))
+        }
+        "getblocktemplate" => {
+            let data = params.optional_string(0, "data")?.unwrap_or_else(|| DEFAULT_MINE_DATA.to_string());
+            Ok(json!(node.block_template(data)?))
+        }
+        "submitwork" => {
+            let id = params.string(0, "id")?;
+            let timestamp = params
+                .optional_u64(2, "timestamp")?
+                .map(i64::try_from)
+                .transpose()
+                .map_err(|_| RpcError::new(INVALID_PARAMS, "timestamp 超出范围"))?;
+            let work = Work {
+                nonce: params.u64(1, "nonce")?,
+                timestamp,
+                extra_nonce: params.optional_u64(3, "extra_nonce")?,
+            };
+            Ok(json!(node.submit_work(&id, &work)?))
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("没有方法 {}", method))),
+    }
+}
+
+// ==================== 客户端 ====================
+
+/// 参数为整数的方法和参数位置，命令行客户端据此把参数转换为数字
+const INTEGER_PARAMS: &[(&str, usize)] = &[
+    ("getblock", 0),
+    ("setdifficulty", 0),
+    ("submitwork", 1),
+    ("submitwork", 2),
+    ("submitwork", 3),
+];
+
+/// # 把命令行参数转换为调用参数
+///
+/// 整数参数（例如 `getblock` 的高度）转换为数字，其他参数原样作为字符串，
+/// 因此 `submitdata 123` 提交的是字符串 `"123"`。
+pub fn cli_params(method: &str, args: &[String]) -> Vec<Value> {
+    args.iter()
+        .enumerate()
+        .map(|(position, arg)| match arg.parse::<u64>() {
+            Ok(number) if INTEGER_PARAMS.contains(&(method, position)) => json!(number),
+            _ => json!(arg),
+        })
+        .collect()
+}
+
+/// # 调用一个 RPC 方法
+///
+/// ## 参数
+/// * `addr` - RPC 服务地址
+/// * `credentials` - `read_cookie` 读出的凭据
+/// * `method` - 方法名
+/// * `params` - 按位置的参数
+///
+/// ## 返回值
+/// 外层错误表示无法完成调用（连接失败、认证失败、响应无效），内层错误是服务返回的 RPC 错误
+pub fn call(
+    addr: &str,
+    credentials: &str,
+    method: &str,
+    params: Vec<Value>,
+) -> Result<Result<Value, RpcError>, BlockchainError> {
+    let body = json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 1 });
+    let auth = http::basic_auth(credentials);
+    let (status, body) = http::request(addr, "POST", "/", &[("Authorization", &auth)], body.to_string().as_bytes())?;
+    if status == 401 {
+        let reason = "RPC 认证失败，cookie 可能已经过期（节点重启过？）";
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, reason).into());
+    }
+    let response: Value = serde_json::from_slice(&body)
+        .map_err(|_| BlockchainError::FormatError(format!("无效的 RPC 响应（HTTP {}）", status)))?;
+    if let Some(error) = response.get("error") {
+        return Ok(Err(serde_json::from_value(error.clone())?));
+    }
+    Ok(Ok(response.get("result").cloned().unwrap_or(Value::Null)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::network::Network;
+    use crate::node::NodeConfig;
+    use crate::storage::MemoryStore;
+
+    /// 启动一个回归测试网节点和它的 RPC 服务，返回服务地址和凭据
+    fn start_rpc(dir: &Path) -> (Arc<Node>, RpcServer, String, String) {
+        let blockchain = Blockchain::with_spec(Network::Regtest.spec());
+        let node = Node::start("127.0.0.1:0", blockchain, Box::new(MemoryStore::new()), NodeConfig::default(), |_| {})
+            .map(Arc::new)
+            .unwrap();
+        let server = RpcServer::start("127.0.0.1:0", Arc::clone(&node), dir).unwrap();
+        let credentials = read_cookie(dir).unwrap();
+        let addr = server.local_addr().to_string();
+        (node, server, addr, credentials)
+    }
+
+    /// # 测试各个方法：挖矿、查询区块、提交交易、统计和验证
+    #[test]
+    fn test_methods() {
+        let dir = tempfile::tempdir().unwrap();
+        let (node, _server, addr, credentials) = start_rpc(dir.path());
+        let rpc = |method: &str, params: Vec<Value>| call(&addr, &credentials, method, params).unwrap();
+
+        assert_eq!(rpc("getblockcount", vec![]), Ok(json!(0)));
+        assert_eq!(rpc("submitdata", vec![json!("转账 10")]).unwrap()["added"], json!(true));
+        let mined: Block = serde_json::from_value(rpc("mine", vec![json!("RPC 区块")]).unwrap()).unwrap();
+        assert_eq!(mined, node.tip());
+        assert_eq!(mempool::block_transactions(&mined), vec!["RPC 区块".to_string(), "转账 10".to_string()]);
+
+        assert_eq!(rpc("getblockcount", vec![]), Ok(json!(1)));
+        assert_eq!(rpc("getblock", vec![json!(1)]), Ok(json!(mined)));
+        assert_eq!(rpc("getblockbyhash", vec![]).unwrap_err().code, INVALID_PARAMS);
+        assert_eq!(rpc("getblock", vec![json!(5)]).unwrap_err().code, NOT_FOUND);
+
+        assert_eq!(rpc("getstats", vec![]).unwrap()["total_blocks"], json!(2));
+        assert_eq!(rpc("validatechain", vec![]).unwrap()["valid"], json!(true));
+        assert_eq!(rpc("setdifficulty", vec![json!(2)]), Ok(json!(2)));
+        assert_eq!(node.with_blockchain(|blockchain| blockchain.difficulty), 2);
+        assert_eq!(rpc("setdifficulty", vec![json!(99)]).unwrap_err().code, REJECTED);
+        assert_eq!(rpc("nosuchmethod", vec![]).unwrap_err().code, METHOD_NOT_FOUND);
+    }
+
+    /// # 测试外部矿工获取区块模板、在节点之外挖矿并提交
+    #[test]
+    fn test_block_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let (node, _server, addr, credentials) = start_rpc(dir.path());
+        let rpc = |method: &str, params: Vec<Value>| call(&addr, &credentials, method, params).unwrap();
+
+        assert_eq!(rpc("submitdata", vec![json!("转账 3")]).unwrap()["added"], json!(true));
+        let template = rpc("getblocktemplate", vec![json!("外部矿工")]).unwrap();
+        assert_eq!((&template["index"], &template["previous_hash"]), (&json!(1), &json!(node.tip().hash)));
+        assert_eq!(template["transactions"], json!(["转账 3"]));
+
+        // 只用模板中的字段计算哈希，寻找满足目标的 nonce
+        let field = |name: &str| match &template[name] {
+            Value::String(value) => value.clone(),
+            value => value.to_string(),
+        };
+        let chain_id = template["chain_id"].as_str().map(|id| format!("/{}", id)).unwrap_or_default();
+        let nonce = (0u64..)
+            .find(|nonce| {
+                let header = format!(
+                    "{}{}{}{}{}{}{}",
+                    field("index"), field("timestamp"), field("data"), field("previous_hash"), nonce, field("difficulty"), chain_id
+                );
+                Block::hash_data(&header).starts_with(&field("target"))
+            })
+            .unwrap();
+
+        let id = template["id"].clone();
+        let block: Block = serde_json::from_value(rpc("submitwork", vec![id.clone(), json!(nonce)]).unwrap()).unwrap();
+        assert_eq!(block, node.tip());
+        assert_eq!(mempool::block_transactions(&block), vec!["外部矿工".to_string(), "转账 3".to_string()]);
+        assert!(node.with_blockchain(|blockchain| blockchain.pending_transactions.is_empty()));
+
+        // 链尖改变后模板失效，同样的工作不能再次提交
+        assert_eq!(rpc("submitwork", vec![id, json!(nonce)]).unwrap_err().code, REJECTED);
+        assert_eq!(rpc("submitwork", vec![json!("abc")]).unwrap_err().code, INVALID_PARAMS);
+    }
+
+    /// # 测试认证、按名称的参数、批量请求、通知和格式错误的请求
+    #[test]
+    fn test_protocol() {
+        let dir = tempfile::tempdir().unwrap();
+        let (node, server, addr, credentials) = start_rpc(dir.path());
+        let post = |body: &str, credentials: &str| {
+            let auth = http::basic_auth(credentials);
+            let (status, body) = http::request(&addr, "POST", "/", &[("Authorization", &auth)], body.as_bytes()).unwrap();
+            (status, serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null))
+        };
+
+        assert!(matches!(call(&addr, "__cookie__:wrong", "getblockcount", vec![]), Err(BlockchainError::IoError(_))));
+        assert_eq!(http::request(&addr, "GET", "/", &[], b"").unwrap().0, 405);
+
+        let hash = node.tip().hash;
+        let named = format!(r#"{{"jsonrpc":"2.0","method":"getblockbyhash","params":{{"hash":"{}"}},"id":"a"}}"#, hash);
+        let (status, response) = post(&named, &credentials);
+        assert_eq!((status, &response["id"], &response["result"]["hash"]), (200, &json!("a"), &json!(hash)));
+
+        let batch = r#"[{"jsonrpc":"2.0","method":"getblockcount","id":1},
+                        {"jsonrpc":"2.0","method":"submitdata","params":["通知"]},
+                        {"jsonrpc":"1.0","method":"getblockcount","id":2}]"#;
+        let (_, response) = post(batch, &credentials);
+        assert_eq!(response.as_array().unwrap().len(), 2);
+        assert_eq!(response[0]["result"], json!(0));
+        assert_eq!(response[1]["error"]["code"], json!(INVALID_REQUEST));
+        // 通知也会被执行，只是没有回复
+        assert!(node.with_blockchain(|blockchain| blockchain.pending_transactions.contains("通知")));
+        let notification = r#"{"jsonrpc":"2.0","method":"getblockcount"}"#;
+        assert_eq!(post(notification, &credentials).0, 204);
+        assert_eq!(post("{不是JSON", &credentials).1["error"]["code"], json!(PARSE_ERROR));
+
+        // 口令是 32 个随机字节的十六进制，每次启动都不同
+        let secret = credentials.strip_prefix("__cookie__:").unwrap();
+        assert!(secret.len() == 2 * COOKIE_SECRET_BYTES && secret.bytes().all(|byte| byte.is_ascii_hexdigit()));
+        assert_ne!(write_cookie(dir.path()).unwrap(), credentials);
+
+        // 服务停止后 cookie 被删除
+        drop(server);
+        assert!(read_cookie(dir.path()).is_err());
+    }
+
+    /// # 测试命令行参数的转换
+    #[test]
+    fn test_cli_params() {
+        let args = vec!["12".to_string(), "abc".to_string()];
+        assert_eq!(cli_params("getblock", &args), vec![json!(12), json!("abc")]);
+        assert_eq!(cli_params("submitdata", &args), vec![json!("12"), json!("abc")]);
+        assert_eq!(cli_params("submitwork", &args), vec![json!("12"), json!("abc")]);
+    }
+}
@@ -0,0 +1,219 @@
+// ==================== 依赖库导入 ====================
+// 导入区块、区块链、错误类型和链元数据
+use crate::block::Block;
+use crate::blockchain::{Blockchain, BlockchainError};
+// 导入验证使用的链规范
+use crate::chainspec::ChainSpec;
+use crate::storage::{self, ChainMetadata};
+// chrono: 快照创建时间
+use chrono::{DateTime, Utc};
+// serde: 快照文件的序列化
+use serde::{Deserialize, Serialize};
+// sha2: 计算区块头承诺
+use sha2::{Digest, Sha256};
+// std::fs: 读取快照文件
+use std::fs;
+// std::path: 路径处理
+use std::path::Path;
+
+/// 当前的快照文件格式版本
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// # 计算区块头承诺
+///
+/// 对一组（已裁剪的）区块的全部区块头字段和数据哈希计算一个SHA-256摘要。
+/// 已裁剪的区块无法通过重新计算哈希来检查篡改，承诺把这些区块头作为一个整体固定下来：
+/// 只要承诺与可信来源（例如另一个完整验证过区块链的节点）公布的值一致，
+/// 就可以确认每个区块头和数据哈希都没有被改动。
+///
+/// 每个区块编码为一行 `index,秒.纳秒,previous_hash,hash,nonce,difficulty,data_hash`，
+/// 未裁剪的区块在计算时使用其数据的哈希，因此裁剪前后的承诺相同。
+/// 带有链ID的区块在行尾再加上 `,chain_id`，没有链ID的区块（主网）承诺保持不变。
+pub fn commitment(blocks: &[Block]) -> String {
+    let mut hasher = Sha256::new();
+    for block in blocks {
+        let data_hash = block.data_hash.clone().unwrap_or_else(|| Block::hash_data(&block.data));
+        let chain_id = block.chain_id.as_ref().map(|id| format!(",{}", id)).unwrap_or_default();
+        hasher.update(format!(
+            "{},{}.{},{},{},{},{},{}{}\n",
+            block.index,
+            block.timestamp.timestamp(),
+            block.timestamp.timestamp_subsec_nanos(),
+            block.previous_hash,
+            block.hash,
+            block.nonce,
+            block.difficulty,
+            data_hash,
+            chain_id
+        ));
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// # 区块链快照 (Snapshot)
+///
+/// 包含从创世区块到 `height` 的全部区块头（数据已裁剪）以及链的参数。
+/// 新节点可以从快照启动，而不需要下载完整的区块数据：
+/// 恢复后的区块链与原链有相同的区块哈希，可以直接在其后继续挖矿。
+///
+/// ## 信任模型
+/// 快照中的区块哈希无法重新计算（数据已被裁剪），恢复时只能检查
+/// 哈希链接、工作量证明和承诺是否自洽。要确认快照来自真实的链，
+/// 需要把承诺与可信来源比较（`snapshot restore --expect`）。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// 快照文件格式版本
+    pub format_version: u32,
+    /// 快照包含的最高区块高度
+    pub height: u64,
+    /// 最高区块的哈希
+    pub tip_hash: String,
+    /// 全部区块头的承诺，见 `commitment`
+    pub commitment: String,
+    /// 快照创建时间
+    pub created_at: DateTime<Utc>,
+    /// 链参数（不含待处理交易）
+    pub metadata: ChainMetadata,
+    /// 已裁剪的区块，高度从0到 `height`
+    pub headers: Vec<Block>,
+}
+
+impl Snapshot {
+    /// # 从区块链创建快照
+    ///
+    /// 调用者应确保区块链已经通过完整验证（例如通过 `load_from_store` 加载）。
+    ///
+    /// ## 参数
+    /// * `blockchain` - 来源区块链，不会被修改
+    /// * `height` - 快照包含的最高区块高度
+    pub fn create(blockchain: &Blockchain, height: u64) -> Result<Self, BlockchainError> {
+        let Some(tip) = blockchain.get_block(height) else {
+            return Err(BlockchainError::InvalidBlock(format!(
+                "快照高度 {} 超出区块链范围（最高 {}）",
+                height,
+                blockchain.chain.len().saturating_sub(1)
+            )));
+        };
+
+        let mut headers = blockchain.chain[..=height as usize].to_vec();
+        headers.iter_mut().for_each(Block::prune);
+        Ok(Snapshot {
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            height,
+            tip_hash: tip.hash.clone(),
+            commitment: commitment(&headers),
+            created_at: Utc::now(),
+            metadata: ChainMetadata {
+                pending_transactions: Vec::new(),
+                ..blockchain.metadata()
+            },
+            headers,
+        })
+    }
+
+    /// # 验证快照是否自洽
+    ///
+    /// ## 检查项目
+    /// 1. 格式版本受支持
+    /// 2. 区块头数量、最高区块哈希与 `height`、`tip_hash` 一致
+    /// 3. 所有区块都已裁剪
+    /// 4. 区块头逐个通过创世区块和连接检查（哈希链接、工作量证明、索引连续），
+    ///    并且与链规范中的创世区块和检查点一致
+    /// 5. 重新计算的承诺与 `commitment` 一致
+    pub fn verify(&self, spec: &ChainSpec) -> Result<(), BlockchainError> {
+        let invalid = |reason: String| Err(BlockchainError::InvalidChain(format!("快照无效: {}", reason)));
+
+        if self.format_version != SNAPSHOT_FORMAT_VERSION {
+            return invalid(format!("不支持的快照格式版本 {}", self.format_version));
+        }
+        let Some(tip) = self.headers.last() else {
+            return invalid("没有区块".to_string());
+        };
+        if self.headers.len() as u64 != self.height + 1 || tip.hash != self.tip_hash {
+            return invalid("区块头与快照高度或最高区块哈希不符".to_string());
+        }
+        if let Some(block) = self.headers.iter().find(|block| !block.is_pruned()) {
+            return invalid(format!("区块 #{} 没有裁剪", block.index));
+        }
+
+        Blockchain::check_genesis_in(spec, &self.headers[0])?;
+        for height in 1..self.headers.len() {
+            Blockchain::check_successor_in(spec, &self.headers[..height], &self.headers[height])?;
+        }
+
+        if commitment(&self.headers) != self.commitment {
+            return invalid("区块头与承诺不符".to_string());
+        }
+        Ok(())
+    }
+
+    /// 把快照转换为使用指定链规范的区块链（不做验证，调用前应先调用 `verify`）
+    pub fn into_blockchain(self, spec: ChainSpec) -> Blockchain {
+        let mut blockchain = Blockchain::from_parts(self.headers, self.metadata);
+        blockchain.set_spec(spec);
+        blockchain
+    }
+
+    /// 原子写入快照文件（紧凑JSON）
+    pub fn save(&self, path: &Path) -> Result<(), BlockchainError> {
+        storage::write_atomic(path, &serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    /// 读取快照文件（不做验证）
+    pub fn load(path: &Path) -> Result<Self, BlockchainError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+// ==================== 单元测试 ====================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::sample_chain;
+    use tempfile::TempDir;
+
+    /// # 测试快照保存、恢复后可以继续挖矿
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("snapshot.json");
+        let blockchain = sample_chain(4);
+
+        let snapshot = Snapshot::create(&blockchain, 3).unwrap();
+        // 裁剪不改变承诺
+        assert_eq!(snapshot.commitment, commitment(&blockchain.chain[..=3]));
+        snapshot.save(&path).unwrap();
+
+        let loaded = Snapshot::load(&path).unwrap();
+        assert_eq!(loaded, snapshot);
+        loaded.verify(&ChainSpec::default()).unwrap();
+
+        let mut restored = loaded.into_blockchain(ChainSpec::default());
+        assert_eq!(restored.get_latest_block().hash, blockchain.chain[3].hash);
+        restored.add_block_silent("恢复后的新区块".to_string()).unwrap();
+        restored.validate_parallel(2).unwrap();
+
+        assert!(Snapshot::create(&blockchain, 5).is_err());
+    }
+
+    /// # 测试被篡改的快照无法通过验证
+    #[test]
+    fn test_reject_tampered_snapshot() {
+        let snapshot = Snapshot::create(&sample_chain(4), 4).unwrap();
+
+        // 修改区块头的时间戳：哈希链接和工作量证明仍然成立，但承诺不符
+        let mut tampered = snapshot.clone();
+        tampered.headers[2].timestamp = Utc::now() + chrono::Duration::days(1);
+        assert!(tampered.verify(&ChainSpec::default()).is_err());
+
+        let mut tampered = snapshot.clone();
+        tampered.headers[1].data_hash = Some(Block::hash_data("伪造的数据"));
+        assert!(tampered.verify(&ChainSpec::default()).is_err());
+
+        let mut truncated = snapshot;
+        truncated.headers.pop();
+        assert!(truncated.verify(&ChainSpec::default()).is_err());
+    }
+}
@@ -0,0 +1,1011 @@
+// ==================== 依赖库导入 ====================
+// 导入区块结构体和区块链错误类型
+use crate::block::Block;
+use crate::blockchain::{Blockchain, BlockchainError};
+// 导入内置链规范，提供元数据的默认值
+use crate::chainspec::ChainSpec;
+// colored: 恢复备份时输出彩色警告
+use colored::Colorize;
+// serde: 用于元数据的序列化和反序列化
+use serde::{Deserialize, Serialize};
+// sha2: 为日志记录计算校验和
+use sha2::{Digest, Sha256};
+// std::collections::HashMap: 哈希到高度的索引
+use std::collections::HashMap;
+// std::fs: 文件系统操作
+use std::fs::{self, File, OpenOptions};
+// std::io: 顺序读写和定位
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+// std::path: 路径处理
+use std::path::{Path, PathBuf};
+// std::str::FromStr: 从命令行字符串解析存储类型
+use std::str::FromStr;
+
+/// JSON 文件存储在数据目录中使用的文件名
+pub const JSON_FILE_NAME: &str = "blockchain.json";
+
+/// 日志结构存储在数据目录中使用的文件名
+pub const LOG_FILE_NAME: &str = "blockchain.log";
+
+/// 每次保存时保留的历史备份数量（`.1` 最新，`.3` 最旧）
+pub const BACKUP_COUNT: usize = 3;
+
+/// # 区块链元数据 (ChainMetadata)
+///
+/// 区块之外需要持久化的链级别参数。不同的存储后端都以这个结构体
+/// 保存和读取这些参数，从而与区块本身的存储方式解耦。
+///
+/// ## 字段说明
+/// - `difficulty`: 当前挖矿难度
+/// - `mining_reward`: 当前挖矿奖励
+/// - `pending_transactions`: 尚未打包的交易
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChainMetadata {
+    /// 当前挖矿难度
+    pub difficulty: u32,
+    /// 当前挖矿奖励
+    pub mining_reward: u64,
+    /// 待处理交易池
+    #[serde(default)]
+    pub pending_transactions: Vec<String>,
+}
+
+impl Default for ChainMetadata {
+    /// 内置链规范中新区块链的参数
+    fn default() -> Self {
+        let spec = ChainSpec::default();
+        ChainMetadata {
+            difficulty: spec.initial_difficulty,
+            mining_reward: spec.reward_at(1),
+            pending_transactions: Vec::new(),
+        }
+    }
+}
+
+/// # 区块链存储后端接口 (ChainStore)
+///
+/// 把"区块存在哪里、怎么存"从区块链逻辑中抽离出来。`Blockchain`
+/// 只通过这个 trait 读写数据，因此可以在 JSON 文件、内存和日志结构
+/// 文件之间自由切换。
+///
+/// ## 约定
+/// - 区块按高度（即 `Block::index`）连续存放，高度从0开始
+/// - `put_block` 写入已存在的高度时，会丢弃该高度及之后的所有区块，
+///   这样链被替换时存储能够保持连续
+/// - 写操作可能被缓冲，调用 `flush` 后才保证持久化
+/// - 实现必须是 `Send`，节点（见 `node.rs`）在网络线程之间共享同一个存储
+pub trait ChainStore: Send {
+    /// 写入一个区块，高度由 `block.index` 决定
+    fn put_block(&mut self, block: &Block) -> Result<(), BlockchainError>;
+
+    /// 按高度读取区块
+    fn get_block(&self, height: u64) -> Result<Option<Block>, BlockchainError>;
+
+    /// 按哈希读取区块
+    fn get_block_by_hash(&self, hash: &str) -> Result<Option<Block>, BlockchainError>;
+
+    /// 当前存储的区块数量
+    fn block_count(&self) -> u64;
+
+    /// 读取最新区块（链尖）
+    fn tip(&self) -> Result<Option<Block>, BlockchainError> {
+        match self.block_count() {
+            0 => Ok(None),
+            count => self.get_block(count - 1),
+        }
+    }
+
+    /// 按高度范围 `[from, to)` 顺序遍历区块
+    fn iter_range<'a>(
+        &'a self,
+        from: u64,
+        to: u64,
+    ) -> Box<dyn Iterator<Item = Result<Block, BlockchainError>> + 'a>;
+
+    /// 只保留前 `len` 个区块
+    fn truncate(&mut self, len: u64) -> Result<(), BlockchainError>;
+
+    /// 读取链级别元数据
+    fn metadata(&self) -> Result<Option<ChainMetadata>, BlockchainError>;
+
+    /// 写入链级别元数据
+    fn put_metadata(&mut self, metadata: &ChainMetadata) -> Result<(), BlockchainError>;
+
+    /// 把缓冲的写操作持久化
+    fn flush(&mut self) -> Result<(), BlockchainError> {
+        Ok(())
+    }
+
+    /// # 用给定的区块和元数据替换存储的全部内容
+    ///
+    /// 用于已有区块的内容发生变化而哈希不变的情况（例如裁剪区块数据），
+    /// 此时 `save_to_store` 按哈希比较会认为无需写入。
+    /// 默认实现截断后重新写入所有区块，追加写的后端可以覆盖为重写文件。
+    fn rewrite(&mut self, chain: &[Block], metadata: &ChainMetadata) -> Result<(), BlockchainError> {
+        self.truncate(0)?;
+        for block in chain {
+            self.put_block(block)?;
+        }
+        self.put_metadata(metadata)?;
+        self.flush()
+    }
+
+    /// 用于界面显示的存储位置描述
+    fn location(&self) -> String;
+}
+
+/// # 存储后端类型 (StoreKind)
+///
+/// 命令行 `--store` 选项可选的存储后端：
+/// - `json`: 现有的格式化JSON文件，便于阅读和调试
+/// - `log`: 紧凑的追加写日志文件，每次保存只追加新区块
+/// - `memory`: 纯内存存储，进程退出后数据消失，主要用于测试
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreKind {
+    Json,
+    Log,
+    Memory,
+}
+
+impl StoreKind {
+    /// 在指定数据目录下打开对应类型的存储，`spec` 用于JSON存储从备份恢复时的验证
+    pub fn open<P: AsRef<Path>>(&self, data_dir: P, spec: &ChainSpec) -> Result<Box<dyn ChainStore>, BlockchainError> {
+        let data_dir = data_dir.as_ref();
+        Ok(match self {
+            StoreKind::Json => Box::new(JsonFileStore::open(data_dir.join(JSON_FILE_NAME), spec)?),
+            StoreKind::Log => Box::new(LogStore::open(data_dir.join(LOG_FILE_NAME))?),
+            StoreKind::Memory => Box::new(MemoryStore::new()),
+        })
+    }
+
+    /// # 只读地读取数据目录中保存的创世区块
+    ///
+    /// 不验证区块链，也不会修改任何文件，用于在按链规范打开存储之前识别旧版本创建的区块链。
+    /// 存储中还没有数据（或是内存存储）时返回 None。
+    pub fn read_genesis<P: AsRef<Path>>(&self, data_dir: P) -> Result<Option<Block>, BlockchainError> {
+        let data_dir = data_dir.as_ref();
+        match self {
+            StoreKind::Json => {
+                let path = data_dir.join(JSON_FILE_NAME);
+                if !path.exists() {
+                    return Ok(None);
+                }
+                Ok(Blockchain::read_from_file(path)?.chain.into_iter().next())
+            }
+            StoreKind::Log => {
+                let path = data_dir.join(LOG_FILE_NAME);
+                if !path.exists() {
+                    return Ok(None);
+                }
+                LogStore::open(path)?.get_block(0)
+            }
+            StoreKind::Memory => Ok(None),
+        }
+    }
+
+    /// # 只读地读取数据目录中最近一次保存的链元数据
+    ///
+    /// 不需要锁定数据目录，节点运行时也可以调用：不会修改任何文件（不从备份恢复，
+    /// 不截掉日志末尾不完整的记录）。JSON 存储总是原子地替换文件；日志存储只追加，
+    /// 读取在第一条不完整的记录处停止。因此读到的总是某一次保存完成后的元数据。
+    ///
+    /// ## 返回值
+    /// * `Ok(Some(ChainMetadata))` - 最近一次保存的元数据
+    /// * `Ok(None)` - 存储中还没有数据（或是内存存储）
+    /// * `Err(BlockchainError)` - 文件无法读取或已损坏
+    pub fn read_metadata<P: AsRef<Path>>(&self, data_dir: P) -> Result<Option<ChainMetadata>, BlockchainError> {
+        let data_dir = data_dir.as_ref();
+        match self {
+            StoreKind::Json => match Blockchain::read_from_file(data_dir.join(JSON_FILE_NAME)) {
+                Ok(blockchain) => Ok(Some(blockchain.metadata())),
+                Err(BlockchainError::IoError(e)) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e),
+            },
+            StoreKind::Log => {
+                let file = match File::open(data_dir.join(LOG_FILE_NAME)) {
+                    Ok(file) => file,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+                    Err(e) => return Err(e.into()),
+                };
+                let file_len = file.metadata()?.len();
+                let mut reader = BufReader::new(file);
+                let (mut offset, mut metadata) = (0, None);
+                while let Some((kind, payload)) = read_record(&mut reader, offset, file_len)? {
+                    if kind == RECORD_METADATA {
+                        metadata = Some(serde_json::from_slice(&payload)?);
+                    }
+                    offset += RECORD_HEADER_LEN + payload.len() as u64;
+                }
+                Ok(metadata)
+            }
+            StoreKind::Memory => Ok(None),
+        }
+    }
+}
+
+impl FromStr for StoreKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(StoreKind::Json),
+            "log" => Ok(StoreKind::Log),
+            "memory" => Ok(StoreKind::Memory),
+            other => Err(format!("未知的存储类型 '{}'，可选: json, log, memory", other)),
+        }
+    }
+}
+
+/// 检查写入高度是否连续，不允许在链尾之后留下空洞
+fn check_height(block: &Block, count: u64) -> Result<(), BlockchainError> {
+    if block.index > count {
+        return Err(BlockchainError::StorageError(format!(
+            "区块高度 {} 不连续，当前存储 {} 个区块",
+            block.index, count
+        )));
+    }
+    Ok(())
+}
+
+// ==================== 原子写入与备份轮换 ====================
+
+/// # 在文件名后追加后缀
+///
+/// 例如 `data/blockchain.json` 加上 `tmp` 得到 `data/blockchain.json.tmp`。
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// 第 `n` 个备份文件的路径，`n` 从1开始，数字越小越新
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    with_suffix(path, &n.to_string())
+}
+
+/// 写入过程中使用的临时文件路径
+pub fn temp_path(path: &Path) -> PathBuf {
+    with_suffix(path, "tmp")
+}
+
+/// 恢复时损坏的主文件被移动到的路径
+pub fn corrupt_path(path: &Path) -> PathBuf {
+    with_suffix(path, "corrupt")
+}
+
+/// # 原子写入文件
+///
+/// 直接对目标文件调用 `fs::write` 时，如果进程在写入过程中崩溃，
+/// 目标文件会只剩下一半内容。这里改为：
+///
+/// 1. 把内容写入同目录下的临时文件（`.tmp`）
+/// 2. 调用 `sync_all()` 确保数据真正落盘
+/// 3. 用 `rename` 把临时文件替换为目标文件——同一文件系统内的重命名是原子的
+/// 4. 同步父目录，确保重命名本身也被持久化
+///
+/// 因此任何时刻目标文件要么是旧的完整内容，要么是新的完整内容。
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp = temp_path(path);
+    {
+        let mut file = File::create(&temp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    fs::rename(&temp, path)?;
+    sync_parent_dir(path);
+    Ok(())
+}
+
+/// 同步父目录的元数据；不支持打开目录的平台上静默跳过
+fn sync_parent_dir(path: &Path) {
+    if let Some(parent) = path.parent()
+        && let Ok(dir) = File::open(parent)
+    {
+        let _ = dir.sync_all();
+    }
+}
+
+/// # 轮换备份文件
+///
+/// 在覆盖目标文件之前调用：删除最旧的备份，其余备份编号依次加一，
+/// 再把当前的目标文件保存为 `.1`。目标文件不存在时什么也不做。
+///
+/// 当前文件优先通过硬链接保存为备份，这样目标文件在整个过程中始终存在；
+/// 文件系统不支持硬链接时退化为复制。
+pub fn rotate_backups(path: &Path, keep: usize) -> io::Result<()> {
+    if keep == 0 || !path.exists() {
+        return Ok(());
+    }
+
+    let oldest = backup_path(path, keep);
+    if oldest.exists() {
+        fs::remove_file(&oldest)?;
+    }
+    for n in (1..keep).rev() {
+        let from = backup_path(path, n);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, n + 1))?;
+        }
+    }
+
+    let newest = backup_path(path, 1);
+    if fs::hard_link(path, &newest).is_err() {
+        fs::copy(path, &newest)?;
+    }
+    Ok(())
+}
+
+// ==================== 内存存储 ====================
+
+/// # 内存存储 (MemoryStore)
+///
+/// 所有数据都保存在进程内存中，不做任何持久化。
+/// 适合单元测试和临时演示。
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    blocks: Vec<Block>,
+    metadata: Option<ChainMetadata>,
+}
+
+impl MemoryStore {
+    /// 创建空的内存存储
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ChainStore for MemoryStore {
+    fn put_block(&mut self, block: &Block) -> Result<(), BlockchainError> {
+        check_height(block, self.blocks.len() as u64)?;
+        self.blocks.truncate(block.index as usize);
+        self.blocks.push(block.clone());
+        Ok(())
+    }
+
+    fn get_block(&self, height: u64) -> Result<Option<Block>, BlockchainError> {
+        Ok(self.blocks.get(height as usize).cloned())
+    }
+
+    fn get_block_by_hash(&self, hash: &str) -> Result<Option<Block>, BlockchainError> {
+        Ok(self.blocks.iter().find(|block| block.hash == hash).cloned())
+    }
+
+    fn block_count(&self) -> u64 {
+        self.blocks.len() as u64
+    }
+
+    fn iter_range<'a>(
+        &'a self,
+        from: u64,
+        to: u64,
+    ) -> Box<dyn Iterator<Item = Result<Block, BlockchainError>> + 'a> {
+        let to = to.min(self.block_count()) as usize;
+        let from = (from as usize).min(to);
+        Box::new(self.blocks[from..to].iter().cloned().map(Ok))
+    }
+
+    fn truncate(&mut self, len: u64) -> Result<(), BlockchainError> {
+        self.blocks.truncate(len as usize);
+        Ok(())
+    }
+
+    fn metadata(&self) -> Result<Option<ChainMetadata>, BlockchainError> {
+        Ok(self.metadata.clone())
+    }
+
+    fn put_metadata(&mut self, metadata: &ChainMetadata) -> Result<(), BlockchainError> {
+        self.metadata = Some(metadata.clone());
+        Ok(())
+    }
+
+    fn location(&self) -> String {
+        "内存".to_string()
+    }
+}
+
+// ==================== JSON 文件存储 ====================
+
+/// # JSON 文件存储 (JsonFileStore)
+///
+/// 沿用 `Blockchain::save_to_file` 的格式化JSON文件。整个链在内存中
+/// 维护一份副本，写操作只标记为"脏"，在 `flush` 时整体写回文件。
+#[derive(Debug)]
+pub struct JsonFileStore {
+    path: PathBuf,
+    inner: MemoryStore,
+    dirty: bool,
+}
+
+impl JsonFileStore {
+    /// 打开JSON文件存储，文件不存在时视为空存储
+    ///
+    /// 文件存在时通过 `Blockchain::recover_from_file` 按链规范读取：主文件损坏时会回退到
+    /// 最新的有效备份，而不是被当作空存储后被新链覆盖。没有任何可用备份时报错。
+    pub fn open<P: AsRef<Path>>(path: P, spec: &ChainSpec) -> Result<Self, BlockchainError> {
+        let path = path.as_ref().to_path_buf();
+        let mut inner = MemoryStore::new();
+
+        if path.exists() || backup_path(&path, 1).exists() {
+            let (blockchain, recovered_from) = Blockchain::recover_from_file(&path, spec)?;
+            if let Some(backup) = recovered_from {
+                println!(
+                    "{}",
+                    format!("⚠️  {} 已损坏，已从备份 {} 恢复", path.display(), backup.display()).yellow()
+                );
+            }
+            inner.metadata = Some(blockchain.metadata());
+            inner.blocks = blockchain.chain;
+        }
+
+        Ok(JsonFileStore { path, inner, dirty: false })
+    }
+}
+
+impl ChainStore for JsonFileStore {
+    fn put_block(&mut self, block: &Block) -> Result<(), BlockchainError> {
+        self.inner.put_block(block)?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn get_block(&self, height: u64) -> Result<Option<Block>, BlockchainError> {
+        self.inner.get_block(height)
+    }
+
+    fn get_block_by_hash(&self, hash: &str) -> Result<Option<Block>, BlockchainError> {
+        self.inner.get_block_by_hash(hash)
+    }
+
+    fn block_count(&self) -> u64 {
+        self.inner.block_count()
+    }
+
+    fn iter_range<'a>(
+        &'a self,
+        from: u64,
+        to: u64,
+    ) -> Box<dyn Iterator<Item = Result<Block, BlockchainError>> + 'a> {
+        self.inner.iter_range(from, to)
+    }
+
+    fn truncate(&mut self, len: u64) -> Result<(), BlockchainError> {
+        if len < self.inner.block_count() {
+            self.inner.truncate(len)?;
+            self.dirty = true;
+        }
+        Ok(())
+    }
+
+    fn metadata(&self) -> Result<Option<ChainMetadata>, BlockchainError> {
+        self.inner.metadata()
+    }
+
+    fn put_metadata(&mut self, metadata: &ChainMetadata) -> Result<(), BlockchainError> {
+        self.inner.put_metadata(metadata)?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BlockchainError> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let metadata = self.inner.metadata.clone().unwrap_or_default();
+        let blockchain = Blockchain::from_parts(self.inner.blocks.clone(), metadata);
+        blockchain.save_to_file(&self.path)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+// ==================== 日志结构文件存储 ====================
+
+/// 日志记录类型：一个区块
+const RECORD_BLOCK: u8 = 1;
+/// 日志记录类型：链元数据
+const RECORD_METADATA: u8 = 2;
+/// 日志记录类型：截断到指定长度
+const RECORD_TRUNCATE: u8 = 3;
+
+/// 每条日志记录的头部长度：类型(1) + 长度(4) + 校验和(4)
+const RECORD_HEADER_LEN: u64 = 9;
+
+/// 日志中一个区块记录的位置信息
+#[derive(Debug, Clone)]
+struct LogEntry {
+    /// 记录负载在文件中的偏移量
+    offset: u64,
+    /// 负载长度
+    len: u32,
+    /// 区块哈希，用于截断时维护哈希索引
+    hash: String,
+}
+
+/// # 日志结构文件存储 (LogStore)
+///
+/// 一个只追加写入的紧凑存储。每次保存只把新区块和最新元数据追加到
+/// 文件末尾，而不是像JSON存储那样重写整个文件。
+///
+/// ## 记录格式
+/// ```text
+/// [类型 u8][负载长度 u32 LE][校验和 4字节][负载]
+/// ```
+/// - 区块记录的负载是紧凑JSON格式的区块
+/// - 元数据记录的负载是紧凑JSON格式的 `ChainMetadata`，以最后一条为准
+/// - 截断记录的负载是新的链长度（u64 LE），用于链被替换的情况
+/// - 校验和取负载SHA-256的前4个字节
+///
+/// ## 崩溃处理
+/// 打开时顺序重放整个日志并在内存中建立高度和哈希索引。
+/// 如果文件末尾有写了一半的记录（进程在追加过程中崩溃），
+/// 这条不完整的记录在下一次写入之前被截掉，之前的数据保持完好；只读取不写入的命令不会修改文件。
+/// 不在文件末尾的损坏记录（校验和不符）说明文件本身已损坏，打开时报错，不会截掉它之后的有效记录。
+#[derive(Debug)]
+pub struct LogStore {
+    path: PathBuf,
+    file: File,
+    /// 文件当前有效长度，即下一条记录的写入位置
+    end: u64,
+    /// 文件末尾是否有需要在下一次写入前截掉的不完整记录
+    torn_tail: bool,
+    entries: Vec<LogEntry>,
+    by_hash: HashMap<String, u64>,
+    metadata: Option<ChainMetadata>,
+}
+
+impl LogStore {
+    /// 打开（或创建）日志文件并重放其中的记录
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, BlockchainError> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().read(true).append(true).create(true).open(&path)?;
+        let file_len = file.metadata()?.len();
+
+        let mut store = LogStore {
+            path,
+            file,
+            end: 0,
+            torn_tail: false,
+            entries: Vec::new(),
+            by_hash: HashMap::new(),
+            metadata: None,
+        };
+        store.replay(file_len)?;
+        // 末尾不完整的记录留到下一次写入前再截掉
+        store.torn_tail = store.end < file_len;
+
+        Ok(store)
+    }
+
+    /// 从头读取日志，重建内存索引
+    fn replay(&mut self, file_len: u64) -> Result<(), BlockchainError> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let mut offset = 0u64;
+
+        while let Some((kind, payload)) = read_record(&mut reader, offset, file_len)? {
+            let payload_offset = offset + RECORD_HEADER_LEN;
+            match kind {
+                RECORD_BLOCK => {
+                    let block: Block = serde_json::from_slice(&payload)?;
+                    check_height(&block, self.entries.len() as u64)?;
+                    self.truncate_index(block.index);
+                    self.push_entry(&block, payload_offset, payload.len() as u32);
+                }
+                RECORD_METADATA => {
+                    self.metadata = Some(serde_json::from_slice(&payload)?);
+                }
+                RECORD_TRUNCATE => {
+                    let len = u64::from_le_bytes(payload_array(&payload)?);
+                    self.truncate_index(len);
+                }
+                other => {
+                    return Err(BlockchainError::StorageError(format!("未知的日志记录类型 {}", other)));
+                }
+            }
+            offset = payload_offset + payload.len() as u64;
+        }
+
+        self.end = offset;
+        Ok(())
+    }
+
+    /// 在文件末尾追加一条记录，返回负载的偏移量
+    fn append_record(&mut self, kind: u8, payload: &[u8]) -> Result<u64, BlockchainError> {
+        if self.torn_tail {
+            // 丢弃末尾不完整的记录，新记录接在最后一条完整的记录之后
+            self.file.set_len(self.end)?;
+            self.torn_tail = false;
+        }
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN as usize + payload.len());
+        Self::encode_record(&mut record, kind, payload);
+
+        self.file.write_all(&record)?;
+        let payload_offset = self.end + RECORD_HEADER_LEN;
+        self.end += record.len() as u64;
+        Ok(payload_offset)
+    }
+
+    fn push_entry(&mut self, block: &Block, offset: u64, len: u32) {
+        self.by_hash.insert(block.hash.clone(), block.index);
+        self.entries.push(LogEntry { offset, len, hash: block.hash.clone() });
+    }
+
+    fn truncate_index(&mut self, len: u64) {
+        for entry in self.entries.drain((len as usize).min(self.entries.len())..) {
+            self.by_hash.remove(&entry.hash);
+        }
+    }
+
+    /// 把一条记录编码到缓冲区
+    fn encode_record(buf: &mut Vec<u8>, kind: u8, payload: &[u8]) {
+        buf.push(kind);
+        buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&checksum(payload));
+        buf.extend_from_slice(payload);
+    }
+
+    /// 读取指定位置的区块负载
+    fn read_entry(file: &mut File, entry: &LogEntry) -> Result<Block, BlockchainError> {
+        file.seek(SeekFrom::Start(entry.offset))?;
+        let mut payload = vec![0u8; entry.len as usize];
+        file.read_exact(&mut payload)?;
+        Ok(serde_json::from_slice(&payload)?)
+    }
+}
+
+impl ChainStore for LogStore {
+    fn put_block(&mut self, block: &Block) -> Result<(), BlockchainError> {
+        check_height(block, self.entries.len() as u64)?;
+        self.truncate_index(block.index);
+
+        let payload = serde_json::to_vec(block)?;
+        let offset = self.append_record(RECORD_BLOCK, &payload)?;
+        self.push_entry(block, offset, payload.len() as u32);
+        Ok(())
+    }
+
+    fn get_block(&self, height: u64) -> Result<Option<Block>, BlockchainError> {
+        match self.entries.get(height as usize) {
+            Some(entry) => Ok(Some(Self::read_entry(&mut File::open(&self.path)?, entry)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_block_by_hash(&self, hash: &str) -> Result<Option<Block>, BlockchainError> {
+        match self.by_hash.get(hash) {
+            Some(&height) => self.get_block(height),
+            None => Ok(None),
+        }
+    }
+
+    fn block_count(&self) -> u64 {
+        self.entries.len() as u64
+    }
+
+    fn iter_range<'a>(
+        &'a self,
+        from: u64,
+        to: u64,
+    ) -> Box<dyn Iterator<Item = Result<Block, BlockchainError>> + 'a> {
+        let to = to.min(self.block_count()) as usize;
+        let from = (from as usize).min(to);
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) => return Box::new(std::iter::once(Err(e.into()))),
+        };
+        Box::new(self.entries[from..to].iter().map(move |entry| Self::read_entry(&mut file, entry)))
+    }
+
+    fn truncate(&mut self, len: u64) -> Result<(), BlockchainError> {
+        if len < self.entries.len() as u64 {
+            self.append_record(RECORD_TRUNCATE, &len.to_le_bytes())?;
+            self.truncate_index(len);
+        }
+        Ok(())
+    }
+
+    fn metadata(&self) -> Result<Option<ChainMetadata>, BlockchainError> {
+        Ok(self.metadata.clone())
+    }
+
+    fn put_metadata(&mut self, metadata: &ChainMetadata) -> Result<(), BlockchainError> {
+        if self.metadata.as_ref() == Some(metadata) {
+            return Ok(());
+        }
+        let payload = serde_json::to_vec(metadata)?;
+        self.append_record(RECORD_METADATA, &payload)?;
+        self.metadata = Some(metadata.clone());
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BlockchainError> {
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    /// 重写整个日志文件（同时丢弃历史记录，相当于压缩日志），然后重新打开
+    fn rewrite(&mut self, chain: &[Block], metadata: &ChainMetadata) -> Result<(), BlockchainError> {
+        let mut buf = Vec::new();
+        for block in chain {
+            Self::encode_record(&mut buf, RECORD_BLOCK, &serde_json::to_vec(block)?);
+        }
+        Self::encode_record(&mut buf, RECORD_METADATA, &serde_json::to_vec(metadata)?);
+        write_atomic(&self.path, &buf)?;
+        *self = LogStore::open(&self.path)?;
+        Ok(())
+    }
+
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+/// 计算日志记录的校验和
+fn checksum(payload: &[u8]) -> [u8; 4] {
+    let digest = Sha256::digest(payload);
+    [digest[0], digest[1], digest[2], digest[3]]
+}
+
+/// 把负载转换为定长数组，长度不符时视为损坏
+fn payload_array<const N: usize>(payload: &[u8]) -> Result<[u8; N], BlockchainError> {
+    payload
+        .try_into()
+        .map_err(|_| BlockchainError::StorageError("日志记录长度错误".to_string()))
+}
+
+/// # 读取一条日志记录
+///
+/// ## 参数
+/// * `reader` - 位于 `offset` 处的读取器
+/// * `offset` - 这条记录在文件中的偏移量
+/// * `file_len` - 文件长度，记录头中的长度超出文件时不会按它分配缓冲区
+///
+/// ## 返回值
+/// * `Ok(Some((类型, 负载)))` - 一条完整的记录
+/// * `Ok(None)` - 日志到此结束：要么正好读到文件末尾，要么文件的最后一条记录不完整或校验失败（崩溃时写了一半）
+/// * `Err(BlockchainError::StorageError)` - 文件中间的记录校验失败，文件已损坏
+fn read_record<R: Read>(reader: &mut R, offset: u64, file_len: u64) -> Result<Option<(u8, Vec<u8>)>, BlockchainError> {
+    let mut header = [0u8; RECORD_HEADER_LEN as usize];
+    if let Err(e) = reader.read_exact(&mut header) {
+        return match e.kind() {
+            io::ErrorKind::UnexpectedEof => Ok(None),
+            _ => Err(e.into()),
+        };
+    }
+
+    let kind = header[0];
+    let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as u64;
+    let record_end = offset + RECORD_HEADER_LEN + len;
+    if record_end > file_len {
+        // 负载没有写完
+        return Ok(None);
+    }
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload)?;
+
+    if checksum(&payload) != header[5..9] {
+        if record_end == file_len {
+            return Ok(None);
+        }
+        return Err(BlockchainError::StorageError(format!(
+            "日志偏移 {} 处的记录校验失败，之后还有 {} 字节：文件已损坏",
+            offset,
+            file_len - record_end
+        )));
+    }
+    Ok(Some((kind, payload)))
+}
+
+// ==================== 单元测试 ====================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::sample_chain;
+    use tempfile::TempDir;
+
+    /// 对任意存储后端执行相同的读写检查
+    fn exercise_store(store: &mut dyn ChainStore) {
+        let blockchain = sample_chain(3);
+        blockchain.save_to_store(store).unwrap();
+
+        assert_eq!(store.block_count(), 4);
+        assert_eq!(store.get_block(2).unwrap().unwrap(), blockchain.chain[2]);
+        assert_eq!(
+            store.get_block_by_hash(&blockchain.chain[3].hash).unwrap().unwrap().index,
+            3
+        );
+        assert_eq!(store.tip().unwrap().unwrap(), blockchain.chain[3]);
+        assert!(store.get_block(9).unwrap().is_none());
+
+        let range: Vec<Block> = store.iter_range(1, 3).collect::<Result<_, _>>().unwrap();
+        assert_eq!(range, blockchain.chain[1..3].to_vec());
+
+        let metadata = store.metadata().unwrap().unwrap();
+        assert_eq!(metadata.difficulty, 1);
+        assert_eq!(metadata.mining_reward, blockchain.mining_reward);
+
+        // 覆盖已有高度时，后续区块应被丢弃
+        store.put_block(&blockchain.chain[2]).unwrap();
+        assert_eq!(store.block_count(), 3);
+        assert!(store.get_block_by_hash(&blockchain.chain[3].hash).unwrap().is_none());
+
+        // 不允许跳过高度写入
+        let mut gap = blockchain.chain[3].clone();
+        gap.index = 7;
+        assert!(store.put_block(&gap).is_err());
+    }
+
+    #[test]
+    fn test_memory_store() {
+        exercise_store(&mut MemoryStore::new());
+    }
+
+    #[test]
+    fn test_json_store() {
+        let dir = TempDir::new().unwrap();
+        exercise_store(&mut JsonFileStore::open(dir.path().join(JSON_FILE_NAME), &ChainSpec::default()).unwrap());
+    }
+
+    #[test]
+    fn test_log_store() {
+        let dir = TempDir::new().unwrap();
+        exercise_store(&mut LogStore::open(dir.path().join(LOG_FILE_NAME)).unwrap());
+    }
+
+    /// # 测试各文件存储重新打开后数据仍然完整
+    #[test]
+    fn test_store_roundtrip_after_reopen() {
+        for kind in [StoreKind::Json, StoreKind::Log] {
+            let dir = TempDir::new().unwrap();
+            let mut blockchain = sample_chain(2);
+            blockchain.save_to_store(kind.open(dir.path(), &ChainSpec::default()).unwrap().as_mut()).unwrap();
+
+            // 第二次保存只需追加新区块
+            blockchain.add_block("追加区块".to_string()).unwrap();
+            blockchain.save_to_store(kind.open(dir.path(), &ChainSpec::default()).unwrap().as_mut()).unwrap();
+
+            let loaded = Blockchain::load_from_store(kind.open(dir.path(), &ChainSpec::default()).unwrap().as_ref(), ChainSpec::default()).unwrap();
+            assert_eq!(loaded.chain, blockchain.chain);
+            assert_eq!(loaded.difficulty, blockchain.difficulty);
+        }
+    }
+
+    /// # 测试保存较短的链时存储会被截断
+    #[test]
+    fn test_save_shorter_chain_truncates_store() {
+        let dir = TempDir::new().unwrap();
+        let mut store = LogStore::open(dir.path().join(LOG_FILE_NAME)).unwrap();
+        let mut blockchain = sample_chain(3);
+        blockchain.save_to_store(&mut store).unwrap();
+
+        blockchain.chain.truncate(2);
+        blockchain.save_to_store(&mut store).unwrap();
+        drop(store);
+
+        let reopened = LogStore::open(dir.path().join(LOG_FILE_NAME)).unwrap();
+        assert_eq!(reopened.block_count(), 2);
+    }
+
+    /// # 测试重写存储后裁剪的数据从日志文件中删除
+    #[test]
+    fn test_rewrite_compacts_log() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(LOG_FILE_NAME);
+        let mut store = LogStore::open(&path).unwrap();
+        let mut blockchain = sample_chain(2);
+        blockchain.add_block("大块数据".repeat(1000)).unwrap();
+        blockchain.save_to_store(&mut store).unwrap();
+        let full_len = fs::metadata(&path).unwrap().len();
+
+        blockchain.prune_below(4).unwrap();
+        blockchain.rewrite_store(&mut store).unwrap();
+        assert!(fs::metadata(&path).unwrap().len() < full_len);
+        assert!(!String::from_utf8_lossy(&fs::read(&path).unwrap()).contains("测试区块 #0"));
+
+        let loaded = Blockchain::load_from_store(&LogStore::open(&path).unwrap(), ChainSpec::default()).unwrap();
+        assert_eq!(loaded.chain, blockchain.chain);
+    }
+
+    /// # 测试日志末尾的不完整记录会被丢弃
+    ///
+    /// 模拟进程在追加记录的过程中崩溃：文件末尾只写入了半条记录（记录头中的长度远超文件）。
+    /// 打开时不修改文件，下一次写入前才截掉这条记录。
+    #[test]
+    fn test_log_store_discards_torn_tail() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(LOG_FILE_NAME);
+        let blockchain = sample_chain(2);
+        blockchain.save_to_store(&mut LogStore::open(&path).unwrap()).unwrap();
+
+        let intact_len = fs::metadata(&path).unwrap().len();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[RECORD_BLOCK, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 1, 2]).unwrap();
+        drop(file);
+
+        let mut store = LogStore::open(&path).unwrap();
+        assert_eq!(store.block_count(), 3);
+        assert_eq!(fs::metadata(&path).unwrap().len(), intact_len + 11);
+
+        let metadata = ChainMetadata { difficulty: 3, ..store.metadata().unwrap().unwrap() };
+        store.put_metadata(&metadata).unwrap();
+        let reopened = LogStore::open(&path).unwrap();
+        assert_eq!(reopened.metadata().unwrap().unwrap().difficulty, 3);
+        assert_eq!((reopened.block_count(), reopened.end), (3, fs::metadata(&path).unwrap().len()));
+    }
+
+    /// # 测试文件中间的损坏记录：打开失败，不截掉之后的有效记录
+    #[test]
+    fn test_log_store_rejects_corrupt_record_in_middle() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(LOG_FILE_NAME);
+        sample_chain(2).save_to_store(&mut LogStore::open(&path).unwrap()).unwrap();
+
+        // 翻转第一条记录负载中的一个字节
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[RECORD_HEADER_LEN as usize + 1] ^= 0x01;
+        fs::write(&path, &bytes).unwrap();
+
+        assert!(matches!(LogStore::open(&path), Err(BlockchainError::StorageError(_))));
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+    }
+
+    /// # 测试JSON存储在主文件损坏时从备份恢复
+    #[test]
+    fn test_json_store_recovers_from_backup() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(JSON_FILE_NAME);
+        let mut blockchain = sample_chain(1);
+        blockchain.save_to_file(&path).unwrap();
+        blockchain.add_block("第二次保存".to_string()).unwrap();
+        blockchain.save_to_file(&path).unwrap();
+
+        fs::write(&path, "{\"chain\": [").unwrap();
+
+        let store = JsonFileStore::open(&path, &ChainSpec::default()).unwrap();
+        assert_eq!(store.block_count(), 2);
+    }
+
+    /// # 测试只读地读取元数据：存储仍然打开、日志末尾有不完整的记录时也不修改文件
+    #[test]
+    fn test_read_metadata_without_opening() {
+        let dir = TempDir::new().unwrap();
+        let mut blockchain = sample_chain(2);
+        blockchain.submit_transaction("待打包".to_string()).unwrap();
+
+        for kind in [StoreKind::Json, StoreKind::Log] {
+            assert!(kind.read_metadata(dir.path()).unwrap().is_none());
+            // 模拟运行中的节点：存储一直保持打开
+            let mut store = kind.open(dir.path(), &ChainSpec::default()).unwrap();
+            blockchain.save_to_store(store.as_mut()).unwrap();
+            let metadata = kind.read_metadata(dir.path()).unwrap().unwrap();
+            assert_eq!(metadata.pending_transactions, vec!["待打包".to_string()]);
+        }
+
+        let path = dir.path().join(LOG_FILE_NAME);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[RECORD_METADATA, 200, 0, 0, 0, 1, 2]).unwrap();
+        drop(file);
+        let len = fs::metadata(&path).unwrap().len();
+        assert!(StoreKind::Log.read_metadata(dir.path()).unwrap().is_some());
+        assert_eq!(fs::metadata(&path).unwrap().len(), len);
+        assert!(StoreKind::Memory.read_metadata(dir.path()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_store_kind_parsing() {
+        assert_eq!("json".parse::<StoreKind>().unwrap(), StoreKind::Json);
+        assert_eq!("log".parse::<StoreKind>().unwrap(), StoreKind::Log);
+        assert_eq!("memory".parse::<StoreKind>().unwrap(), StoreKind::Memory);
+        assert!("sqlite".parse::<StoreKind>().is_err());
+    }
+}
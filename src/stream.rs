@@ -0,0 +1,401 @@
+// ==================== 依赖库导入 ====================
+// 导入区块、区块链和错误类型
+use crate::block::Block;
+use crate::blockchain::{Blockchain, BlockchainError};
+// 导入验证使用的链规范
+use crate::chainspec::ChainSpec;
+// 导入二进制格式的逐块解码
+use crate::codec::{self, FileFormat};
+// 导入当前的存储格式版本
+use crate::migration::CURRENT_FORMAT_VERSION;
+// serde: 自定义反序列化，边解析边验证
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+// std::cell / std::rc: 在读取器和验证器之间共享已读字节数
+use std::cell::Cell;
+use std::rc::Rc;
+// std::fmt: Visitor 的错误描述
+use std::fmt;
+// std::fs / std::io: 带缓冲地读取文件
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+// std::ops: 控制是否继续处理下一个区块
+use std::ops::ControlFlow;
+// std::path: 路径处理
+use std::path::Path;
+
+/// 每验证多少个区块报告一次进度
+pub const PROGRESS_INTERVAL: u64 = 1000;
+
+/// 提前停止解析时使用的内部错误信息，不会展示给用户
+const STOP_MARKER: &str = "stream stopped at requested height";
+
+/// # 流式验证进度 (Progress)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// 已验证的区块数
+    pub blocks: u64,
+    /// 完成百分比（0~100），无法估计时为 None
+    pub percent: Option<f64>,
+}
+
+/// # 流式验证结果 (StreamReport)
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamReport {
+    /// 通过验证的区块数
+    pub blocks_checked: u64,
+    /// 最后一个通过验证的区块
+    pub tip: Block,
+    /// 是否因为到达 `until` 高度而提前停止
+    pub stopped_early: bool,
+}
+
+/// # 流式验证器 (StreamValidator)
+///
+/// 逐个接收区块，只保留最近的区块用于检查连接关系和难度调整
+/// （`ChainSpec::difficulty_window` 个，最多两倍），因此内存占用与链的长度无关。
+struct StreamValidator<F> {
+    spec: ChainSpec,
+    recent: Vec<Block>,
+    blocks_checked: u64,
+    until: Option<u64>,
+    progress: F,
+    /// 估算完成百分比的方式：(已处理量, 总量)
+    position: Rc<Cell<u64>>,
+    total: u64,
+    /// 解析过程中遇到的验证错误（serde 的错误类型无法携带它）
+    failure: Option<BlockchainError>,
+    stopped_early: bool,
+}
+
+impl<F: FnMut(Progress)> StreamValidator<F> {
+    fn new(spec: &ChainSpec, until: Option<u64>, progress: F, position: Rc<Cell<u64>>, total: u64) -> Self {
+        StreamValidator {
+            spec: spec.clone(),
+            recent: Vec::new(),
+            blocks_checked: 0,
+            until,
+            progress,
+            position,
+            total,
+            failure: None,
+            stopped_early: false,
+        }
+    }
+
+    /// 验证下一个区块，返回是否继续
+    fn push(&mut self, block: Block) -> Result<ControlFlow<()>, BlockchainError> {
+        if self.recent.is_empty() {
+            Blockchain::check_genesis_in(&self.spec, &block)?;
+        } else {
+            Blockchain::check_successor_in(&self.spec, &self.recent, &block)?;
+        }
+
+        self.blocks_checked += 1;
+        if self.blocks_checked.is_multiple_of(PROGRESS_INTERVAL) {
+            self.report();
+        }
+
+        let reached = self.until.is_some_and(|height| block.index >= height);
+        self.recent.push(block);
+        let window = self.spec.difficulty_window();
+        if self.recent.len() >= window * 2 {
+            self.recent.drain(..self.recent.len() - window);
+        }
+        if reached {
+            self.stopped_early = true;
+            return Ok(ControlFlow::Break(()));
+        }
+        Ok(ControlFlow::Continue(()))
+    }
+
+    fn report(&mut self) {
+        let percent = (self.total > 0).then(|| self.position.get() as f64 * 100.0 / self.total as f64);
+        (self.progress)(Progress { blocks: self.blocks_checked, percent });
+    }
+
+    /// 结束验证，生成报告
+    fn finish(mut self) -> Result<StreamReport, BlockchainError> {
+        if let Some(e) = self.failure.take() {
+            return Err(e);
+        }
+        let Some(tip) = self.recent.pop() else {
+            return Err(BlockchainError::InvalidChain("区块链为空".to_string()));
+        };
+        self.report();
+        Ok(StreamReport {
+            blocks_checked: self.blocks_checked,
+            tip,
+            stopped_early: self.stopped_early,
+        })
+    }
+}
+
+/// 统计已读取字节数的读取器，用于估算进度
+struct CountingReader<R> {
+    inner: R,
+    count: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n as u64);
+        Ok(n)
+    }
+}
+
+/// # 流式验证区块链文件
+///
+/// 与 `Blockchain::load_from_file` 把整个文件读入内存不同，这里边读边解析，
+/// 每解析出一个区块就立即验证并丢弃，适合验证非常大的区块链文件。
+///
+/// ## 支持的格式
+/// - 二进制格式（`SBCB` 魔数开头）
+/// - 当前版本的JSON版本信封
+/// - 没有版本信息的旧JSON文件（版本0）
+///
+/// ## 参数
+/// * `path` - 区块链文件路径
+/// * `spec` - 验证使用的链规范
+/// * `until` - 验证到该高度（含）后停止，None 表示验证整条链
+/// * `progress` - 每验证 `PROGRESS_INTERVAL` 个区块调用一次，结束时再调用一次
+///
+/// ## 返回值
+/// * `Ok(StreamReport)` - 所有已读取的区块都通过验证
+/// * `Err(BlockchainError::InvalidBlock)` - 指出第一个无效区块的高度和原因
+/// * `Err(...)` - 文件无法读取或格式错误
+pub fn validate_file<P, F>(
+    path: P,
+    spec: &ChainSpec,
+    until: Option<u64>,
+    progress: F,
+) -> Result<StreamReport, BlockchainError>
+where
+    P: AsRef<Path>,
+    F: FnMut(Progress),
+{
+    let file = File::open(path)?;
+    let total = file.metadata()?.len();
+    let position = Rc::new(Cell::new(0));
+    let mut reader = BufReader::new(CountingReader { inner: file, count: Rc::clone(&position) });
+
+    let mut validator = StreamValidator::new(spec, until, progress, position, total);
+    let format = FileFormat::detect(io::BufRead::fill_buf(&mut reader)?);
+    match format {
+        FileFormat::Binary => validate_binary(&mut reader, &mut validator)?,
+        FileFormat::Json => validate_json(reader, &mut validator)?,
+    }
+    validator.finish()
+}
+
+/// # 流式验证任意区块序列
+///
+/// 用于存储后端（例如日志存储的 `iter_range`）等已经能逐块读取的来源。
+///
+/// ## 参数
+/// * `blocks` - 从创世区块开始的区块序列
+/// * `total` - 区块总数，用于计算进度百分比（未知时传0）
+/// * `spec` / `until` / `progress` - 同 `validate_file`
+pub fn validate_blocks<I, F>(
+    blocks: I,
+    total: u64,
+    spec: &ChainSpec,
+    until: Option<u64>,
+    progress: F,
+) -> Result<StreamReport, BlockchainError>
+where
+    I: IntoIterator<Item = Result<Block, BlockchainError>>,
+    F: FnMut(Progress),
+{
+    let position = Rc::new(Cell::new(0));
+    let mut validator = StreamValidator::new(spec, until, progress, Rc::clone(&position), total);
+    for block in blocks {
+        position.set(position.get() + 1);
+        if validator.push(block?)?.is_break() {
+            break;
+        }
+    }
+    validator.finish()
+}
+
+/// 逐块读取二进制格式
+fn validate_binary<R: Read, F: FnMut(Progress)>(
+    reader: &mut R,
+    validator: &mut StreamValidator<F>,
+) -> Result<(), BlockchainError> {
+    let header = codec::read_header(reader)?;
+    for _ in 0..header.block_count {
+        if validator.push(codec::read_block(reader, header.version)?)?.is_break() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// 逐块解析JSON格式
+fn validate_json<R: Read, F: FnMut(Progress)>(
+    reader: R,
+    validator: &mut StreamValidator<F>,
+) -> Result<(), BlockchainError> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    match ChainFileSeed(validator).deserialize(&mut deserializer) {
+        Ok(()) => Ok(deserializer.end()?),
+        // 验证失败或提前停止时，解析会被中止，真正的结果记录在验证器中
+        Err(_) if validator.failure.is_some() || validator.stopped_early => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// # 区块链文件对象的解析种子
+///
+/// 同时处理版本信封 `{"format_version": 1, "blockchain": {...}}`
+/// 和区块链对象本身 `{"chain": [...], ...}`：遇到 `blockchain` 字段时递归进入，
+/// 遇到 `chain` 字段时逐块验证，其余字段直接跳过而不分配内存。
+struct ChainFileSeed<'a, F>(&'a mut StreamValidator<F>);
+
+impl<'de, F: FnMut(Progress)> DeserializeSeed<'de> for ChainFileSeed<'_, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, F: FnMut(Progress)> Visitor<'de> for ChainFileSeed<'_, F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "区块链文件对象")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "format_version" => {
+                    let version: u32 = map.next_value()?;
+                    if version > CURRENT_FORMAT_VERSION {
+                        return Err(de::Error::custom(format!(
+                            "文件格式版本 {} 高于当前程序支持的版本 {}",
+                            version, CURRENT_FORMAT_VERSION
+                        )));
+                    }
+                }
+                "blockchain" => map.next_value_seed(ChainFileSeed(&mut *self.0))?,
+                "chain" => map.next_value_seed(BlocksSeed(&mut *self.0))?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `chain` 数组的解析种子：每解析出一个区块立即交给验证器
+struct BlocksSeed<'a, F>(&'a mut StreamValidator<F>);
+
+impl<'de, F: FnMut(Progress)> DeserializeSeed<'de> for BlocksSeed<'_, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F: FnMut(Progress)> Visitor<'de> for BlocksSeed<'_, F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "区块数组")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(block) = seq.next_element::<Block>()? {
+            match self.0.push(block) {
+                Ok(ControlFlow::Continue(())) => {}
+                Ok(ControlFlow::Break(())) => return Err(de::Error::custom(STOP_MARKER)),
+                Err(e) => {
+                    let message = e.to_string();
+                    self.0.failure = Some(e);
+                    return Err(de::Error::custom(message));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// ==================== 单元测试 ====================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::sample_chain;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// # 测试三种文件格式都能流式验证
+    #[test]
+    fn test_validate_all_formats() {
+        let dir = TempDir::new().unwrap();
+        let blockchain = sample_chain(4);
+
+        let json = dir.path().join("chain.json");
+        blockchain.save_to_file(&json).unwrap();
+        let binary = dir.path().join("chain.bin");
+        blockchain.save_to_file_as(&binary, FileFormat::Binary).unwrap();
+        let legacy = dir.path().join("legacy.json");
+        fs::write(&legacy, serde_json::to_string(&blockchain).unwrap()).unwrap();
+
+        for path in [&json, &binary, &legacy] {
+            let report = validate_file(path, &ChainSpec::default(), None, |_| {}).unwrap();
+            assert_eq!(report.blocks_checked, 5);
+            assert_eq!(&report.tip, blockchain.get_latest_block());
+            assert!(!report.stopped_early);
+        }
+    }
+
+    /// # 测试在指定高度停止
+    #[test]
+    fn test_stop_at_height() {
+        let dir = TempDir::new().unwrap();
+        let blockchain = sample_chain(4);
+        let path = dir.path().join("chain.json");
+        blockchain.save_to_file(&path).unwrap();
+
+        let report = validate_file(&path, &ChainSpec::default(), Some(2), |_| {}).unwrap();
+        assert_eq!(report.blocks_checked, 3);
+        assert_eq!(report.tip.index, 2);
+        assert!(report.stopped_early);
+    }
+
+    /// # 测试报告第一个被篡改的区块
+    #[test]
+    fn test_reports_tampered_block() {
+        let dir = TempDir::new().unwrap();
+        let mut blockchain = sample_chain(4);
+        blockchain.chain[3].data = "篡改".to_string();
+
+        let path = dir.path().join("chain.bin");
+        blockchain.save_to_file_as(&path, FileFormat::Binary).unwrap();
+        let err = validate_file(&path, &ChainSpec::default(), None, |_| {}).unwrap_err();
+        assert!(err.to_string().contains("#3"));
+
+        // 篡改点之前停止时验证通过
+        assert!(validate_file(&path, &ChainSpec::default(), Some(2), |_| {}).is_ok());
+
+        let path = dir.path().join("chain.json");
+        blockchain.save_to_file(&path).unwrap();
+        assert!(matches!(validate_file(&path, &ChainSpec::default(), None, |_| {}), Err(BlockchainError::InvalidBlock(_))));
+    }
+
+    /// # 测试进度回调
+    #[test]
+    fn test_progress_reported() {
+        let blockchain = sample_chain(3);
+        let mut updates = Vec::new();
+        let blocks = blockchain.chain.iter().cloned().map(Ok);
+        validate_blocks(blocks, 4, &ChainSpec::default(), None, |p| updates.push(p)).unwrap();
+        assert_eq!(updates.last(), Some(&Progress { blocks: 4, percent: Some(100.0) }));
+    }
+}
@@ -0,0 +1,631 @@
+// ==================== 依赖库导入 ====================
+// 导入区块和区块链类型
+use crate::block::Block;
+use crate::blockchain::{Blockchain, BlockchainError};
+// 导入链规范，区块头按链规范的共识参数检查
+use crate::chainspec::ChainSpec;
+// std::collections: 已下载的区块按高度排序，下载请求按节点记录
+use std::collections::{BTreeMap, HashMap, HashSet};
+// std::net: 用地址区分节点
+use std::net::SocketAddr;
+// std::time: 下载请求的超时
+use std::time::{Duration, Instant};
+
+/// 一条 `Headers` 消息最多携带的区块头数，收到满额的回复说明对方还有更多
+pub const MAX_HEADERS_PER_MESSAGE: usize = 2000;
+
+/// 一次向一个节点请求的区块数
+pub const BODY_BATCH: u64 = 100;
+
+/// 最多领先链尖下载多少个区块。等待某个较慢节点的区块时，其他节点下载的区块不会无限堆积
+const MAX_DOWNLOAD_AHEAD: u64 = 2000;
+
+/// 下载请求的超时时间，超时的区块改由其他节点下载
+pub const BODY_TIMEOUT: Duration = Duration::from_secs(30);
+
+// ==================== 区块头 ====================
+//
+// 区块头就是裁剪后的区块（见 `Block::prune`）：数据被替换为 `data_hash`，其余字段不变。
+// 区块哈希是根据原始数据计算的，因此区块头阶段无法重新计算哈希，只能检查：
+// - 哈希的格式和工作量证明
+// - 与前一个区块头的哈希链接和索引连续性
+// - 链规范的共识参数（链ID、难度范围和难度调整规则）和检查点
+//
+// 下载到完整区块后，`check_body` 再确认它与区块头一致并重新计算哈希，
+// 伪造的区块头最晚在这一步被发现，对应的区块不会进入区块链。
+//
+// 比较两条链时使用累计工作量（见 `Block::work`）。
+
+/// 区块的区块头：裁剪后的副本
+pub fn header(block: &Block) -> Block {
+    let mut header = block.clone();
+    header.prune();
+    header
+}
+
+/// # 检查一串区块头
+///
+/// ## 参数
+/// * `spec` - 链规范
+/// * `ancestors` - 第一个区块头之前连续的区块（或区块头），最后一个是它的前一个区块；
+///   至少包含 `ChainSpec::difficulty_window` 个，或者从创世区块开始
+/// * `headers` - 按高度排列的区块头
+///
+/// ## 返回值
+/// * `Err(BlockchainError::InvalidBlock)` - 第一个无效区块头的高度和原因
+pub fn check_headers(spec: &ChainSpec, ancestors: &[Block], headers: &[Block]) -> Result<(), BlockchainError> {
+    let mut recent = ancestors.to_vec();
+    for header in headers {
+        let reason = if !header.is_pruned() || !header.data.is_empty() {
+            Some("区块头不应包含数据")
+        } else if header.hash.len() != 64 || !header.hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+            Some("哈希格式无效")
+        } else if !header.has_valid_proof_of_work() {
+            Some("不满足工作量证明难度")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            return Err(BlockchainError::InvalidBlock(format!("区块头 #{}: {}", header.index, reason)));
+        }
+        Blockchain::check_link(recent.last().expect("至少包含前一个区块"), header)?;
+        spec.check_consensus(&recent, header)?;
+        ChainSpec::check_not_future(header)?;
+        spec.check_block(header)?;
+        recent.push(header.clone());
+    }
+    Ok(())
+}
+
+/// # 检查下载到的区块与区块头一致
+///
+/// 区块的裁剪副本必须与区块头完全相同（数据的哈希也相同），
+/// 并且根据完整数据重新计算的哈希必须等于区块头中的哈希。
+pub fn check_body(header_block: &Block, block: &Block) -> Result<(), BlockchainError> {
+    if block.is_pruned() || header(block) != *header_block {
+        return Err(BlockchainError::InvalidBlock(format!("区块 #{} 与区块头不一致", block.index)));
+    }
+    if !block.is_valid() {
+        return Err(BlockchainError::InvalidBlock(format!("区块 #{}: 哈希值与区块内容不符", block.index)));
+    }
+    Ok(())
+}
+
+/// # 生成区块定位器
+///
+/// 定位器是一组区块哈希：从链尖开始的10个连续区块，之后间隔逐次加倍，最后是创世区块。
+/// 对方在自己的链上找到第一个认识的哈希，就知道两条链从哪里开始不同，
+/// 只需几十个哈希就能在很长的链上找到分叉点。
+///
+/// ## 参数
+/// * `blockchain` - 本地区块链
+/// * `tip` - 额外放在最前面的哈希，例如已下载区块头的最后一个
+pub fn locator(blockchain: &Blockchain, tip: Option<&Block>) -> Vec<String> {
+    let mut locator: Vec<String> = tip.map(|block| block.hash.clone()).into_iter().collect();
+    let mut height = blockchain.get_latest_block().index;
+    let mut step = 1;
+    loop {
+        locator.push(blockchain.chain[height as usize].hash.clone());
+        if height == 0 {
+            return locator;
+        }
+        if locator.len() >= 10 {
+            step *= 2;
+        }
+        height = height.saturating_sub(step);
+    }
+}
+
+/// # 在本地区块链上查找定位器
+///
+/// ## 返回值
+/// 定位器中第一个出现在本地区块链上的区块的下一个高度，即对方缺少的第一个区块；
+/// 一个都不认识时为0
+pub fn locate(blockchain: &Blockchain, locator: &[String]) -> u64 {
+    locator
+        .iter()
+        .find_map(|hash| blockchain.get_block_by_hash(hash))
+        .map_or(0, |block| block.index + 1)
+}
+
+// ==================== 下载状态 ====================
+
+/// 发给一个节点、尚未收到回复的下载请求
+#[derive(Debug, Clone, Copy)]
+struct Request {
+    from: u64,
+    to: u64,
+    sent: Instant,
+}
+
+/// `HeaderSync::add_headers` 的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeadersOutcome {
+    /// 区块头延长或替换了待下载的链
+    Extended,
+    /// 区块头都已经知道，或所在的链工作量不比当前的多
+    Ignored,
+    /// 区块头接不上本地区块链，也接不上已有的区块头
+    Gap,
+}
+
+/// # 区块头优先同步 (HeaderSync)
+///
+/// 记录已知工作量最大的一条区块头链，以及它的区块的下载进度：
+///
+/// 1. 用区块定位器向链更高的节点请求区块头（`add_headers`），检查后与本地区块链和已有的区块头
+///    比较累计工作量，工作量更大的链替换原来的链
+/// 2. 把区块头之下还没有区块的高度分成若干段（`next_request`），分别向不同的节点下载
+/// 3. 收到的区块与区块头核对（`receive_bodies`），不一致的节点不再参与这次同步
+/// 4. 从分叉点开始连续的区块交给调用者（`take_ready`），区块头随之移出
+///
+/// 区块头链从本地区块链上的某个区块（分叉点）开始。分叉点就是链尖时，区块下载好就可以直接追加；
+/// 分叉点低于链尖时（对方的链与本地链分叉了），要等下载好的部分比本地链在分叉点之后的部分更重，
+/// 才一起交给调用者，由 `Blockchain::consider_chain` 切换到新链。
+///
+/// 节点断开（`peer_gone`）或请求超时（`expire`）后，没有完成的段重新分配给其他节点。
+/// 已经追加的区块会立即保存，进程重启后从保存的链尖重新请求区块头，已下载的部分不需要重来。
+#[derive(Debug, Default)]
+pub struct HeaderSync {
+    /// 分叉点：区块头链前面最后一个在本地区块链上的区块的高度
+    base: u64,
+    /// 分叉点之后的区块头，`headers[i]` 的高度为 `base + 1 + i`
+    headers: Vec<Block>,
+    /// 区块头链是否比本地区块链更重。区块头分多次收到时，较长的分叉一开始可能还不够重，
+    /// 这时只继续请求区块头，不下载区块
+    heavier: bool,
+    /// 已下载、尚未交给调用者的区块和提供它们的节点
+    downloaded: BTreeMap<u64, (Block, SocketAddr)>,
+    /// 每个节点正在进行的下载请求
+    requests: HashMap<SocketAddr, Request>,
+    /// 在这次同步中提供了不一致区块、超时或没有区块可提供的节点
+    excluded: HashSet<SocketAddr>,
+    /// 同步开始时的链尖高度
+    start: u64,
+}
+
+impl HeaderSync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 是否还有区块头没有下载完
+    pub fn is_active(&self) -> bool {
+        !self.headers.is_empty()
+    }
+
+    /// 同步的目标高度：最后一个区块头的高度，没有区块头时为链尖高度
+    pub fn target(&self, tip: u64) -> u64 {
+        match self.is_active() {
+            true => self.base + self.headers.len() as u64,
+            false => tip,
+        }
+    }
+
+    /// 同步开始时的链尖高度
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// 请求区块头时使用的定位器：已有区块头的最后一个，再加上本地区块链的定位器
+    pub fn locator(&self, blockchain: &Blockchain) -> Vec<String> {
+        locator(blockchain, self.headers.last())
+    }
+
+    /// 高度 `height` 上的区块哈希，分叉点及之前取自本地区块链，之后取自区块头
+    fn hash_at<'a>(&'a self, blockchain: &'a Blockchain, height: u64) -> Option<&'a str> {
+        let block = match height.checked_sub(self.base + 1) {
+            None => blockchain.get_block(height),
+            Some(offset) => self.headers.get(offset as usize),
+        };
+        block.map(|block| block.hash.as_str())
+    }
+
+    /// # 加入从节点收到的区块头
+    ///
+    /// 本地区块链上已有的区块头被跳过，其余的区块头按 `previous_hash` 接在已有的区块头上，
+    /// 或者接在本地区块链的某个区块上（新的分叉点）。得到的链的累计工作量比本地区块链
+    /// 和已有的区块头链都大时，替换已有的区块头，被替换部分已下载的区块一并丢弃。
+    ///
+    /// 对方还有更多区块头（`more`）时，还不够重的链也会先记下来，继续请求后面的区块头。
+    ///
+    /// ## 返回值
+    /// * `Err(BlockchainError::InvalidBlock)` - 区块头无效，发送它们的节点不可信
+    pub fn add_headers(
+        &mut self,
+        blockchain: &Blockchain,
+        headers: Vec<Block>,
+        more: bool,
+    ) -> Result<HeadersOutcome, BlockchainError> {
+        let known = headers
+            .iter()
+            .take_while(|h| blockchain.get_block(h.index).is_some_and(|block| block.hash == h.hash))
+            .count();
+        let headers = &headers[known..];
+        let Some(first) = headers.first() else {
+            return Ok(HeadersOutcome::Ignored);
+        };
+        let Some(parent_height) = first.index.checked_sub(1) else {
+            return Err(BlockchainError::InvalidBlock("区块头 #0 与本地的创世区块不同".to_string()));
+        };
+
+        // 先尝试接在已有区块头上，再尝试接在本地区块链上
+        let active = self.is_active();
+        let (base, mut chain) = if active
+            && parent_height >= self.base
+            && self.hash_at(blockchain, parent_height) == Some(first.previous_hash.as_str())
+        {
+            (self.base, self.headers[..(parent_height - self.base) as usize].to_vec())
+        } else if blockchain
+            .get_block(parent_height)
+            .is_some_and(|block| block.hash == first.previous_hash)
+        {
+            (parent_height, Vec::new())
+        } else {
+            return Ok(HeadersOutcome::Gap);
+        };
+
+        // 分叉点之前本地的一个调整周期，接上已有的区块头，作为检查难度调整的前序区块
+        let local = &blockchain.chain[..=base as usize];
+        let mut ancestors = local[local.len().saturating_sub(blockchain.spec().difficulty_window())..].to_vec();
+        ancestors.extend_from_slice(&chain);
+        check_headers(blockchain.spec(), &ancestors, headers)?;
+        let extends = active && base == self.base && chain.len() == self.headers.len();
+        chain.extend_from_slice(headers);
+        if active
+            && base == self.base
+            && chain.len() <= self.headers.len()
+            && chain.iter().zip(&self.headers).all(|(ours, theirs)| ours.hash == theirs.hash)
+        {
+            return Ok(HeadersOutcome::Ignored);
+        }
+
+        let prefix_work = |base: u64| Blockchain::work_of(&blockchain.chain[..=base as usize]);
+        let candidate = prefix_work(base).saturating_add(Blockchain::work_of(&chain));
+        let ours = blockchain.total_work();
+        let accept = if extends {
+            more || candidate > ours
+        } else if active {
+            let current = prefix_work(self.base).saturating_add(Blockchain::work_of(&self.headers));
+            candidate > ours.max(current)
+        } else {
+            more || candidate > ours
+        };
+        if !accept {
+            if extends {
+                // 对方的区块头已经发完，这条链还是不如本地区块链重
+                self.reset();
+            }
+            return Ok(HeadersOutcome::Ignored);
+        }
+
+        if !active {
+            self.start = blockchain.get_latest_block().index;
+        }
+        self.base = base;
+        self.headers = chain;
+        self.heavier = candidate > ours;
+        let headers = &self.headers;
+        self.downloaded.retain(|height, (block, _)| {
+            height
+                .checked_sub(base + 1)
+                .and_then(|offset| headers.get(offset as usize))
+                .is_some_and(|header_block| header_block.hash == block.hash)
+        });
+        Ok(HeadersOutcome::Extended)
+    }
+
+    /// # 为节点分配下一段要下载的区块
+    ///
+    /// 找到从分叉点开始第一段既没有下载、也没有分配给其他节点的高度，最多 `BODY_BATCH` 个，
+    /// 不超过节点已知的高度，也不超过本地链尖之上 `MAX_DOWNLOAD_AHEAD` 个区块。
+    /// 区块头链还不比本地区块链重时不下载。
+    ///
+    /// ## 返回值
+    /// 分配的高度范围（包含两端）；节点已有请求、被排除或没有可分配的区块时返回 None
+    pub fn next_request(&mut self, peer: SocketAddr, peer_height: u64, tip: u64, now: Instant) -> Option<(u64, u64)> {
+        if !self.heavier || self.requests.contains_key(&peer) || self.excluded.contains(&peer) {
+            return None;
+        }
+        let limit = self.target(tip).min(peer_height).min(tip + MAX_DOWNLOAD_AHEAD);
+        let is_free = |height: u64| {
+            !self.downloaded.contains_key(&height)
+                && !self.requests.values().any(|r| (r.from..=r.to).contains(&height))
+        };
+
+        let from = (self.base + 1..=limit).find(|height| is_free(*height))?;
+        let to = (from..=limit.min(from + BODY_BATCH - 1))
+            .take_while(|height| is_free(*height))
+            .last()?;
+        self.requests.insert(peer, Request { from, to, sent: now });
+        Some((from, to))
+    }
+
+    /// # 处理节点回复的区块
+    ///
+    /// 请求范围内与区块头一致的区块被保存，等待交给调用者。回复少于请求的区块时，
+    /// 剩下的部分会重新分配。回复中没有任何可用区块的节点不再参与这次同步。
+    ///
+    /// ## 返回值
+    /// * `Err(BlockchainError::InvalidBlock)` - 区块与区块头不一致
+    pub fn receive_bodies(&mut self, peer: SocketAddr, blocks: Vec<Block>) -> Result<(), BlockchainError> {
+        let Some(request) = self.requests.remove(&peer) else {
+            return Ok(());
+        };
+        if blocks.is_empty() {
+            self.excluded.insert(peer);
+            return Ok(());
+        }
+
+        let mut accepted = 0;
+        for block in blocks {
+            if !(request.from..=request.to).contains(&block.index) || block.index <= self.base {
+                continue;
+            }
+            // 区块头在请求发出后可能被更重的链替换，此时不再需要这个区块
+            let Some(header_block) = self.headers.get((block.index - self.base - 1) as usize) else {
+                continue;
+            };
+            if header_block.hash != block.hash {
+                continue;
+            }
+            if let Err(e) = check_body(header_block, &block) {
+                self.excluded.insert(peer);
+                return Err(e);
+            }
+            self.downloaded.insert(block.index, (block, peer));
+            accepted += 1;
+        }
+        if accepted == 0 {
+            // 对方提供的是另一条链上的区块
+            self.excluded.insert(peer);
+        }
+        Ok(())
+    }
+
+    /// # 取出可以使用的区块
+    ///
+    /// 返回从分叉点的下一个高度开始连续下载好的区块和提供它们的节点，它们的区块头随之移出。
+    /// 分叉点低于本地链尖时，只有这些区块比本地链在分叉点之后的部分更重才会返回。
+    ///
+    /// 返回的第一个区块正好接在本地链尖上时，调用者按顺序追加；否则调用者用分叉点之前的本地区块
+    /// 加上这些区块组成候选链，交给 `Blockchain::consider_chain`。失败时调用 `reset`。
+    pub fn take_ready(&mut self, blockchain: &Blockchain) -> Vec<(Block, SocketAddr)> {
+        let count = (self.base + 1..)
+            .take_while(|height| self.downloaded.contains_key(height))
+            .count();
+        if count == 0 {
+            return Vec::new();
+        }
+        if self.base < blockchain.get_latest_block().index {
+            let ours = Blockchain::work_of(&blockchain.chain[self.base as usize + 1..]);
+            if Blockchain::work_of(&self.headers[..count]) <= ours {
+                return Vec::new();
+            }
+        }
+
+        let base = self.base;
+        let ready = (1..=count as u64)
+            .filter_map(|offset| self.downloaded.remove(&(base + offset)))
+            .collect();
+        self.headers.drain(..count);
+        self.base += count as u64;
+        if self.headers.is_empty() {
+            self.finish();
+        }
+        ready
+    }
+
+    /// 节点断开，它未完成的请求重新分配
+    pub fn peer_gone(&mut self, peer: SocketAddr) {
+        self.requests.remove(&peer);
+        self.excluded.remove(&peer);
+    }
+
+    /// # 取消超时的请求
+    ///
+    /// 超时的节点不再参与这次同步，它负责的区块重新分配给其他节点。
+    ///
+    /// ## 返回值
+    /// 超时的节点
+    pub fn expire(&mut self, now: Instant) -> Vec<SocketAddr> {
+        let expired: Vec<SocketAddr> = self
+            .requests
+            .iter()
+            .filter(|(_, request)| now.duration_since(request.sent) > BODY_TIMEOUT)
+            .map(|(peer, _)| *peer)
+            .collect();
+        for peer in &expired {
+            self.requests.remove(peer);
+            self.excluded.insert(*peer);
+        }
+        expired
+    }
+
+    /// 放弃当前的区块头和下载进度，例如本地链尖已经被其他区块占据
+    pub fn reset(&mut self) {
+        self.headers.clear();
+        self.finish();
+    }
+
+    fn finish(&mut self) {
+        self.heavier = false;
+        self.downloaded.clear();
+        self.requests.clear();
+        self.excluded.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Network;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    /// 在 `base` 之上挖出 `count` 个区块，`tag` 区分不同的分支
+    fn extend(base: &Blockchain, count: u64, tag: &str) -> Blockchain {
+        let mut blockchain = base.clone();
+        for i in 0..count {
+            blockchain.add_block_silent(format!("{} #{}", tag, i)).unwrap();
+        }
+        blockchain
+    }
+
+    fn headers_of(blockchain: &Blockchain, from: u64) -> Vec<Block> {
+        blockchain.chain[from as usize..].iter().map(header).collect()
+    }
+
+    /// # 测试区块头的检查和区块与区块头的核对
+    #[test]
+    fn test_check_headers_and_bodies() {
+        let base = Blockchain::with_spec(Network::Regtest.spec());
+        let remote = extend(&base, 3, "远端");
+        let headers = headers_of(&remote, 1);
+        check_headers(base.spec(), &base.chain, &headers).unwrap();
+
+        // 不连续、工作量不足或带有数据的区块头被拒绝
+        assert!(check_headers(base.spec(), &base.chain, &headers[1..]).is_err());
+        let mut forged = headers.clone();
+        forged[1].hash = "f".repeat(64);
+        assert!(check_headers(base.spec(), &base.chain, &forged).is_err());
+        assert!(check_headers(base.spec(), &base.chain, &remote.chain[1..]).is_err());
+
+        check_body(&headers[0], &remote.chain[1]).unwrap();
+        let mut tampered = remote.chain[1].clone();
+        tampered.data = "篡改".to_string();
+        assert!(check_body(&headers[0], &tampered).is_err());
+        assert!(check_body(&headers[0], &headers[0]).is_err());
+    }
+
+    /// # 测试定位器能找到两条链的分叉点
+    #[test]
+    fn test_locator() {
+        let base = extend(&Blockchain::with_spec(Network::Regtest.spec()), 30, "共同");
+        let ours = extend(&base, 5, "本地");
+        let theirs = extend(&base, 40, "远端");
+
+        let locator = locator(&ours, None);
+        assert_eq!(locator.first(), Some(&ours.get_latest_block().hash));
+        assert_eq!(locator.last(), Some(&ours.chain[0].hash));
+        assert!(locator.len() < 20);
+        // 对方从分叉点之后开始回复
+        assert_eq!(locate(&theirs, &locator), 31);
+        assert_eq!(locate(&ours, &locator), 36);
+    }
+
+    /// # 测试选择累计工作量最大的区块头链
+    #[test]
+    fn test_heaviest_headers_win() {
+        let base = Blockchain::with_spec(Network::Regtest.spec());
+        let short = extend(&base, 3, "短链");
+        let long = extend(&base, 5, "长链");
+        let mut sync = HeaderSync::new();
+
+        assert_eq!(sync.add_headers(&base, headers_of(&short, 1), false).unwrap(), HeadersOutcome::Extended);
+        assert_eq!(sync.target(0), 3);
+        assert_eq!(sync.add_headers(&base, headers_of(&long, 1), false).unwrap(), HeadersOutcome::Extended);
+        assert_eq!(sync.target(0), 5);
+        // 更轻的链和已知的区块头不会替换当前的链
+        assert_eq!(sync.add_headers(&base, headers_of(&short, 1), false).unwrap(), HeadersOutcome::Ignored);
+        assert_eq!(sync.add_headers(&base, headers_of(&long, 2), false).unwrap(), HeadersOutcome::Ignored);
+        assert_eq!(sync.target(0), 5);
+
+        // 接不上的区块头
+        let longer = extend(&long, 3, "更长");
+        assert_eq!(sync.add_headers(&base, headers_of(&longer, 7), false).unwrap(), HeadersOutcome::Gap);
+        assert_eq!(sync.add_headers(&base, headers_of(&longer, 6), false).unwrap(), HeadersOutcome::Extended);
+        assert_eq!(sync.target(0), 8);
+    }
+
+    /// # 测试与本地区块链分叉的区块头：足够重之后才交给调用者切换
+    #[test]
+    fn test_fork_headers() {
+        let base = extend(&Blockchain::with_spec(Network::Regtest.spec()), 2, "共同");
+        let local = extend(&base, 3, "本地");
+        let remote = extend(&base, 5, "远端");
+        let mut sync = HeaderSync::new();
+
+        // 分两次收到的分叉：前两个区块头还不如本地链重，只记下来，不下载
+        let headers = headers_of(&remote, 1);
+        assert_eq!(sync.add_headers(&local, headers[..3].to_vec(), true).unwrap(), HeadersOutcome::Extended);
+        assert_eq!(sync.next_request(addr(1), 7, 5, Instant::now()), None);
+        assert_eq!(sync.add_headers(&local, headers[3..].to_vec(), false).unwrap(), HeadersOutcome::Extended);
+        assert_eq!(sync.target(5), 7);
+        assert_eq!(sync.next_request(addr(1), 7, 5, Instant::now()), Some((3, 7)));
+
+        // 下载好的部分不比本地链重时先不交出
+        sync.receive_bodies(addr(1), remote.chain[3..6].to_vec()).unwrap();
+        assert!(sync.take_ready(&local).is_empty());
+        assert_eq!(sync.next_request(addr(2), 7, 5, Instant::now()), Some((6, 7)));
+        sync.receive_bodies(addr(2), remote.chain[6..].to_vec()).unwrap();
+
+        let ready: Vec<Block> = sync.take_ready(&local).into_iter().map(|(block, _)| block).collect();
+        let mut candidate = local.chain[..3].to_vec();
+        candidate.extend(ready);
+        let mut local = local;
+        let replacement = local.consider_chain(candidate).unwrap().unwrap();
+        assert_eq!(replacement.fork_height, 2);
+        assert_eq!(local.chain, remote.chain);
+        assert!(!sync.is_active());
+
+        // 发完之后仍然不如本地链重的分叉被放弃
+        let short = extend(&base, 1, "短分叉");
+        assert_eq!(sync.add_headers(&local, headers_of(&short, 3), true).unwrap(), HeadersOutcome::Extended);
+        let more = extend(&short, 1, "短分叉续");
+        assert_eq!(sync.add_headers(&local, headers_of(&more, 4), false).unwrap(), HeadersOutcome::Ignored);
+        assert!(!sync.is_active());
+    }
+
+    /// # 测试把区块分配给多个节点下载，节点断开后由其他节点接手
+    #[test]
+    fn test_parallel_download_and_resume() {
+        let base = Blockchain::with_spec(Network::Regtest.spec());
+        let remote = extend(&base, 2 * BODY_BATCH + 10, "远端");
+        let target = remote.get_latest_block().index;
+        let mut local = base.clone();
+        let mut sync = HeaderSync::new();
+        sync.add_headers(&local, headers_of(&remote, 1), false).unwrap();
+        let now = Instant::now();
+
+        let (a, b, c) = (addr(1), addr(2), addr(3));
+        assert_eq!(sync.next_request(a, target, 0, now), Some((1, BODY_BATCH)));
+        assert_eq!(sync.next_request(a, target, 0, now), None);
+        assert_eq!(sync.next_request(b, target, 0, now), Some((BODY_BATCH + 1, 2 * BODY_BATCH)));
+
+        // b 先完成，但它的区块要等 a 的区块追加之后才能追加
+        let bodies = |from: u64, to: u64| remote.chain[from as usize..=to as usize].to_vec();
+        sync.receive_bodies(b, bodies(BODY_BATCH + 1, 2 * BODY_BATCH)).unwrap();
+        assert!(sync.take_ready(&local).is_empty());
+
+        // a 断开，它的区块交给 c
+        sync.peer_gone(a);
+        assert_eq!(sync.next_request(c, target, 0, now), Some((1, BODY_BATCH)));
+        sync.receive_bodies(c, bodies(1, BODY_BATCH)).unwrap();
+        for (block, _) in sync.take_ready(&local) {
+            local.append_block(block).unwrap();
+        }
+        assert_eq!(local.get_latest_block().index, 2 * BODY_BATCH);
+
+        // 提供不一致区块的节点被排除
+        let tip = local.get_latest_block().index;
+        assert_eq!(sync.next_request(b, target, tip, now), Some((tip + 1, target)));
+        let mut wrong = bodies(tip + 1, target);
+        wrong[0].data = "篡改".to_string();
+        assert!(sync.receive_bodies(b, wrong).is_err());
+        assert_eq!(sync.next_request(b, target, tip, now), None);
+
+        // 超时的请求重新分配
+        assert!(sync.next_request(c, target, tip, now).is_some());
+        assert_eq!(sync.expire(now + BODY_TIMEOUT * 2), vec![c]);
+        assert_eq!(sync.next_request(addr(4), target, tip, now), Some((tip + 1, target)));
+        sync.receive_bodies(addr(4), bodies(tip + 1, target)).unwrap();
+        for (block, _) in sync.take_ready(&local) {
+            local.append_block(block).unwrap();
+        }
+        assert_eq!(local.get_latest_block(), remote.get_latest_block());
+        assert!(!sync.is_active());
+    }
+}
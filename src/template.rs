@@ -0,0 +1,215 @@
+// ==================== 依赖库导入 ====================
+// 导入区块结构，模板最终还原为区块
+use crate::block::Block;
+// 导入错误类型
+use crate::blockchain::BlockchainError;
+// 导入共识允许的时间戳范围
+use crate::chainspec::MAX_FUTURE_BLOCK_SECS;
+// 导入区块数据的打包格式，额外随机数改变第一条记录后重新打包
+use crate::ingest;
+// chrono: 时间戳的转换和检查
+use chrono::{DateTime, Utc};
+// serde: 模板序列化为 JSON 交给外部矿工
+use serde::Serialize;
+
+/// 额外随机数追加到第一条记录后最多增加的字节数：分隔符 `/` 加上 u64 最多20位数字。
+/// 生成模板时为它预留区块数据的空间，使用额外随机数的区块不会超过链规范的数据上限
+pub const EXTRA_NONCE_BYTES: usize = 21;
+
+/// 模板ID取模板内容哈希的前多少个十六进制字符
+const TEMPLATE_ID_LEN: usize = 16;
+
+// ==================== 区块模板 ====================
+
+/// # 区块模板 (BlockTemplate)
+///
+/// 交给外部矿工的下一个区块：除了 nonce 之外的全部字段，以及需要满足的目标。
+/// 矿工自己寻找 nonce，找到后只需提交模板ID和 nonce（见 `Work`），
+/// 节点用模板还原出完整的区块，验证后追加到链上。
+///
+/// ## 区块哈希的计算方法
+/// 与 `Block::calculate_hash` 相同：把以下字段直接连接成字符串，取 SHA-256 的十六进制：
+///
+/// `index` `timestamp` `data` `previous_hash` `nonce` `difficulty`，
+/// 有 `chain_id` 时再接上 `/` 和 `chain_id`
+///
+/// 其中 `timestamp` 是Unix时间戳（秒），数字都是十进制。哈希以 `target`
+/// （`difficulty` 个 `0`）开头即满足工作量证明。
+///
+/// ## 扩大搜索空间
+/// - 时间戳：可以使用 `min_timestamp` 之后、不超过本机时间两小时的任意时间戳
+/// - 额外随机数：第一条记录 `coinbase` 变为 `coinbase/额外随机数`，区块数据随之改变，
+///   按 `ingest::pack_data` 的格式重新打包：只有一条记录时就是这条记录本身，
+///   多条记录（`coinbase` 之后是 `transactions`）时是JSON字符串数组
+///
+/// 模板只在链尖不变时有效，链尖改变后提交会被拒绝，矿工需要重新获取模板。
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BlockTemplate {
+    /// 模板ID，提交时用它指明基于哪个模板
+    pub id: String,
+    /// 新区块的高度
+    pub index: u64,
+    /// 链尖的哈希，新区块接在它后面
+    pub previous_hash: String,
+    /// 建议的时间戳（生成模板的时间，Unix秒）
+    pub timestamp: i64,
+    /// 允许的最早时间戳：链尖区块的时间
+    pub min_timestamp: i64,
+    /// 新区块的难度
+    pub difficulty: u32,
+    /// 哈希需要的前缀
+    pub target: String,
+    /// 链规范要求的链ID，参与哈希计算
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<String>,
+    /// 区块数据的第一条记录，额外随机数追加在它后面
+    pub coinbase: String,
+    /// 打包进区块的交易池交易
+    pub transactions: Vec<String>,
+    /// 不使用额外随机数时的区块数据
+    pub data: String,
+}
+
+/// # 外部矿工提交的工作 (Work)
+///
+/// 没有给出的时间戳使用模板建议的时间戳；没有额外随机数时区块数据就是模板的 `data`。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Work {
+    pub nonce: u64,
+    pub timestamp: Option<i64>,
+    pub extra_nonce: Option<u64>,
+}
+
+impl BlockTemplate {
+    /// # 由尚未挖矿的区块生成模板
+    ///
+    /// ## 参数
+    /// * `candidate` - 下一个区块（见 `Blockchain::block_template`），它的数据由 `coinbase` 和 `transactions` 打包而成
+    /// * `coinbase` - 区块数据的第一条记录
+    /// * `transactions` - 之后的交易池交易
+    /// * `min_timestamp` - 链尖区块的时间
+    pub fn new(candidate: &Block, coinbase: String, transactions: Vec<String>, min_timestamp: DateTime<Utc>) -> Self {
+        let timestamp = candidate.timestamp.timestamp();
+        let mut id = Block::hash_data(&format!("{}{}{}", candidate.previous_hash, timestamp, candidate.data));
+        id.truncate(TEMPLATE_ID_LEN);
+        BlockTemplate {
+            id,
+            index: candidate.index,
+            previous_hash: candidate.previous_hash.clone(),
+            timestamp,
+            min_timestamp: min_timestamp.timestamp(),
+            difficulty: candidate.difficulty,
+            target: "0".repeat(candidate.difficulty as usize),
+            chain_id: candidate.chain_id.clone(),
+            coinbase,
+            transactions,
+            data: candidate.data.clone(),
+        }
+    }
+
+    /// 使用额外随机数时的区块数据
+    fn data_with_extra_nonce(&self, extra_nonce: u64) -> String {
+        let mut records = vec![format!("{}/{}", self.coinbase, extra_nonce)];
+        records.extend(self.transactions.iter().cloned());
+        ingest::pack_data(&records)
+    }
+
+    /// # 用提交的工作还原区块
+    ///
+    /// 先检查时间戳的范围（见 `ChainSpec::check_consensus` 和 `ChainSpec::check_not_future`），不必等到矿工的哈希被验证才发现；
+    /// 再用 `Block::has_valid_proof_of_work` 检查工作量证明。
+    /// 不检查区块与链尖的关系，追加前仍需 `Blockchain::append_block` 完整验证。
+    ///
+    /// ## 返回值
+    /// * `Ok(Block)` - 满足工作量证明的区块
+    /// * `Err(BlockchainError::InvalidBlock)` - 时间戳超出范围或哈希不满足目标
+    pub fn block(&self, work: &Work) -> Result<Block, BlockchainError> {
+        let timestamp = work.timestamp.unwrap_or(self.timestamp);
+        let latest = Utc::now().timestamp() + MAX_FUTURE_BLOCK_SECS;
+        if timestamp < self.min_timestamp || timestamp > latest {
+            return Err(BlockchainError::InvalidBlock(format!(
+                "区块 #{}: 时间戳 {} 超出允许范围 {}-{}",
+                self.index, timestamp, self.min_timestamp, latest
+            )));
+        }
+        let data = match work.extra_nonce {
+            Some(extra_nonce) => self.data_with_extra_nonce(extra_nonce),
+            None => self.data.clone(),
+        };
+
+        let mut block = Block::new(self.index, data, self.previous_hash.clone(), self.difficulty)
+            .with_chain_id(self.chain_id.clone());
+        block.timestamp = DateTime::from_timestamp(timestamp, 0).expect("时间戳已检查范围");
+        block.nonce = work.nonce;
+        block.hash = block.calculate_hash();
+        if !block.has_valid_proof_of_work() {
+            return Err(BlockchainError::InvalidBlock(format!(
+                "区块 #{}: 哈希 {} 不满足目标 {}",
+                self.index, block.hash, self.target
+            )));
+        }
+        Ok(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 像外部矿工一样，只用模板中的字段寻找满足目标的 nonce
+    fn solve(template: &BlockTemplate, data: &str, timestamp: i64) -> u64 {
+        let chain_id = template.chain_id.as_ref().map(|id| format!("/{}", id)).unwrap_or_default();
+        (0..)
+            .find(|nonce| {
+                let header = format!(
+                    "{}{}{}{}{}{}{}",
+                    template.index, timestamp, data, template.previous_hash, nonce, template.difficulty, chain_id
+                );
+                Block::hash_data(&header).starts_with(&template.target)
+            })
+            .unwrap()
+    }
+
+    fn template() -> BlockTemplate {
+        let previous = Block::genesis("创世".to_string(), Utc::now(), 1);
+        let records = vec!["矿工".to_string(), "转账 5".to_string()];
+        let candidate = Block::new(1, ingest::pack_data(&records), previous.hash.clone(), 2)
+            .with_chain_id(Some("testnet".to_string()));
+        BlockTemplate::new(&candidate, records[0].clone(), records[1..].to_vec(), previous.timestamp)
+    }
+
+    /// # 测试按模板说明的方法在外部挖矿，提交后还原出有效区块
+    #[test]
+    fn test_solve_template() {
+        let template = template();
+        assert_eq!((template.target.as_str(), template.id.len()), ("00", TEMPLATE_ID_LEN));
+
+        let nonce = solve(&template, &template.data, template.timestamp);
+        let block = template.block(&Work { nonce, ..Work::default() }).unwrap();
+        assert!(block.is_valid() && block.has_valid_proof_of_work());
+        assert_eq!((block.nonce, block.data.as_str()), (nonce, template.data.as_str()));
+
+        // 额外随机数改变第一条记录，时间戳可以在允许范围内调整
+        let timestamp = template.timestamp + 1;
+        let data = ingest::pack_data(&["矿工/7".to_string(), "转账 5".to_string()]);
+        let nonce = solve(&template, &data, timestamp);
+        let work = Work { nonce, timestamp: Some(timestamp), extra_nonce: Some(7) };
+        let block = template.block(&work).unwrap();
+        assert_eq!((block.timestamp.timestamp(), ingest::unpack_data(&block.data)[0].as_str()), (timestamp, "矿工/7"));
+        assert!(block.is_valid());
+    }
+
+    /// # 测试拒绝不满足目标的 nonce 和超出范围的时间戳
+    #[test]
+    fn test_rejects_invalid_work() {
+        let template = template();
+        let nonce = solve(&template, &template.data, template.timestamp);
+        let wrong = (0..).find(|&other| other != nonce && template.block(&Work { nonce: other, ..Work::default() }).is_err());
+        assert!(wrong.is_some());
+
+        for timestamp in [template.min_timestamp - 1, template.timestamp + MAX_FUTURE_BLOCK_SECS + 60] {
+            let work = Work { nonce, timestamp: Some(timestamp), extra_nonce: None };
+            assert!(matches!(template.block(&work), Err(BlockchainError::InvalidBlock(reason)) if reason.contains("时间戳")));
+        }
+    }
+}
@@ -0,0 +1,21 @@
+// ==================== 依赖库导入 ====================
+// 导入区块链类型
+use crate::blockchain::Blockchain;
+
+/// # 由给定数据挖出的测试区块链
+///
+/// 使用内置链规范和难度1，依次挖出数据为 `records` 的区块，挖矿很快。
+pub fn chain_of(records: &[&str]) -> Blockchain {
+    let mut blockchain = Blockchain::new();
+    blockchain.set_difficulty(1);
+    for record in records {
+        blockchain.add_block_silent(record.to_string()).unwrap();
+    }
+    blockchain
+}
+
+/// 有 `blocks` 个区块（不含创世区块）的测试区块链，第 i 个区块的数据为 `测试区块 #i`
+pub fn sample_chain(blocks: usize) -> Blockchain {
+    let records: Vec<String> = (0..blocks).map(|i| format!("测试区块 #{}", i)).collect();
+    chain_of(&records.iter().map(String::as_str).collect::<Vec<_>>())
+}
@@ -1,8 +1,9 @@
 use crate::blockchain::Blockchain;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use colored::*;
 use std::io::{self, Write};
 
-/// 工具函数模块，提供各种辅助功能
+// 工具函数模块，提供各种辅助功能
 
 /// 显示程序横幅
 pub fn display_banner() {
@@ -18,9 +19,9 @@ pub fn display_banner() {
     ".bright_cyan());
 }
 
-/// 显示主菜单
-pub fn display_main_menu() {
-    println!("\n{}", "📋 ===== 主菜单 =====".bright_yellow());
+/// 显示主菜单，标题中注明当前使用的区块链
+pub fn display_main_menu(chain: &str) {
+    println!("\n{}", format!("📋 ===== 主菜单（区块链: {}）=====", chain).bright_yellow());
     println!("1. 📦 挖掘新区块");
     println!("2. 📊 显示区块链");
     println!("3. ✅ 验证区块链");
@@ -30,8 +31,9 @@ pub fn display_main_menu() {
     println!("7. 📈 显示统计信息");
     println!("8. 🚀 批量挖矿");
     println!("9. 🔍 查看区块详情");
+    println!("c. 🔀 切换区块链");
     println!("0. 👋 退出程序");
-    print!("\n请选择操作 (0-9): ");
+    print!("\n请选择操作 (0-9, c): ");
     //stdout()刷新缓冲区 flush()确保输出立即显示 unwrap()处理可能的错误
     io::stdout().flush().unwrap();
 }
@@ -120,6 +122,43 @@ pub fn format_hash(hash: &str, max_length: usize) -> String {
     }
 }
 
+/// 截断过长的文本，按字符而不是字节计数，避免切断多字节字符
+pub fn truncate_text(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        let truncated: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+        format!("{}…", truncated)
+    }
+}
+
+/// 分页：返回第 `page` 页（从1开始）的内容和总页数，页码超出范围时返回空切片
+pub fn paginate<T>(items: &[T], page: usize, per_page: usize) -> (&[T], usize) {
+    let per_page = per_page.max(1);
+    let total_pages = items.len().div_ceil(per_page).max(1);
+    let start = page.saturating_sub(1).saturating_mul(per_page).min(items.len());
+    let end = (start + per_page).min(items.len());
+    (&items[start..end], total_pages)
+}
+
+/// 解析命令行中的时间（按UTC）
+///
+/// 支持 RFC 3339（`2024-01-01T10:00:00Z`）、`2024-01-01 10:00:00` 和 `2024-01-01`。
+/// 只有日期时，`end_of_day` 为 true 表示当天最后一秒，否则表示当天开始。
+pub fn parse_time(text: &str, end_of_day: bool) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S") {
+        return Ok(time.and_utc());
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        let time = if end_of_day { date.and_hms_opt(23, 59, 59) } else { date.and_hms_opt(0, 0, 0) };
+        return Ok(time.expect("有效的时刻").and_utc());
+    }
+    Err(format!("无法识别的时间 '{}'，请使用 2024-01-01、2024-01-01 10:00:00 或 RFC 3339 格式", text))
+}
+
 /// 格式化文件大小
 pub fn format_file_size(bytes: usize) -> String {
     const UNITS: &[&str] = &["B", "KB", "MB", "GB"];
@@ -169,6 +208,7 @@ pub fn display_pretty_stats(blockchain: &Blockchain) {
         let genesis_block = &blockchain.chain[0];
         let chain_duration = latest_block.timestamp - genesis_block.timestamp;
         
+        println!("🔗 最新哈希: {}", format_hash(&latest_block.hash, 24).bright_cyan());
         println!("📅 链时长: {}", format_duration(chain_duration.num_seconds() as f64).bright_cyan());
         println!("🚀 平均哈希率: {} H/s", stats.average_hash_rate.to_string().bright_cyan());
         println!("💎 总尝试次数: {}", stats.total_attempts.to_string().bright_cyan());